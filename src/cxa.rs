@@ -51,22 +51,33 @@ pub unsafe extern "C" fn __cxa_atexit(
     0
 }
 
+/// Runs the handlers whose `dso` matches `matches`, latest first.
+///
+/// The lock is released while a handler runs, as it may register other
+/// handlers.
+fn run_handlers(matches: impl Fn(usize) -> bool) {
+    loop {
+        let entry = CXA_ATEXIT_FUNCS
+            .lock()
+            .iter_mut()
+            .rev()
+            .find(|slot| slot.is_some_and(|entry| matches(entry.dso)))
+            .and_then(Option::take);
+
+        match entry {
+            Some(entry) => (entry.func)(entry.arg as *mut c_void),
+            None => break,
+        }
+    }
+}
+
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn __cxa_finalize(dso: *mut c_void) {
-    let mut funcs = CXA_ATEXIT_FUNCS.lock();
-
     let dso_usize = dso as usize;
-
-    for slot in funcs.iter_mut().rev() {
-        if let Some(entry) = slot.as_ref()
-            && (dso.is_null() || entry.dso == dso_usize)
-            && let Some(entry_to_run) = slot.take()
-        {
-            (entry_to_run.func)(entry_to_run.arg as *mut c_void);
-        }
-    }
+    run_handlers(|entry_dso| dso.is_null() || entry_dso == dso_usize);
 
     // clean up remaining list
+    let mut funcs = CXA_ATEXIT_FUNCS.lock();
     if dso.is_null() {
         funcs.clear();
     } else {
@@ -74,6 +85,13 @@ pub unsafe extern "C" fn __cxa_finalize(dso: *mut c_void) {
     }
 }
 
+/// Runs the handlers registered by an object that is being unloaded, that is
+/// those whose `dso` argument is an address for which `contains` returns `true`.
+pub(crate) fn finalize_object(contains: impl Fn(usize) -> bool) {
+    run_handlers(|entry_dso| entry_dso != 0 && contains(entry_dso));
+    CXA_ATEXIT_FUNCS.lock().retain(|opt| opt.is_some());
+}
+
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn __cxa_thread_atexit_impl(
     func: extern "C" fn(*mut c_void),
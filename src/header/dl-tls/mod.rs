@@ -7,8 +7,7 @@
 #[cfg(target_arch = "x86")]
 use core::arch::global_asm;
 
-use alloc::alloc::alloc;
-use core::{alloc::Layout, ptr};
+use core::ptr;
 
 use crate::{ld_so::tcb::Tcb, platform::types::c_void};
 
@@ -23,7 +22,6 @@ pub struct dl_tls_index {
 pub unsafe extern "C" fn __tls_get_addr(ti: *mut dl_tls_index) -> *mut c_void {
     let tcb = unsafe { Tcb::current().unwrap() };
     let ti = unsafe { &*ti };
-    let masters = tcb.masters().unwrap();
 
     #[cfg(feature = "trace_tls")]
     log::trace!(
@@ -31,59 +29,28 @@ pub unsafe extern "C" fn __tls_get_addr(ti: *mut dl_tls_index) -> *mut c_void {
         ti,
         ti.ti_module,
         ti.ti_offset,
-        masters.len(),
+        tcb.masters().map_or(0, |masters| masters.len()),
         tcb.dtv_mut().len()
     );
 
-    if tcb.dtv_mut().len() < masters.len() {
-        // Reallocate DTV.
-        tcb.setup_dtv(masters.len());
-    }
+    // Module IDs start at 1.
+    let Some(dtv_index) = ti.ti_module.checked_sub(1) else {
+        return ptr::null_mut();
+    };
 
-    let dtv_index = ti.ti_module - 1;
+    // Objects loaded by other threads since the last call are picked up here.
+    let mut ptr = tcb.tls_region(dtv_index);
 
-    if dtv_index >= tcb.dtv_mut().len() {
-        // TODO: handle this?
+    if ptr.is_null() {
+        // The module is unknown or has been unloaded.
         log::warn!(
-            "dl-tls: __tls_get_addr requested out-of-bounds module {} (DTV len {})",
+            "dl-tls: __tls_get_addr requested invalid module {} (DTV len {})",
             ti.ti_module,
             tcb.dtv_mut().len()
         );
         return ptr::null_mut();
     }
 
-    if tcb.dtv_mut()[dtv_index].is_null() {
-        // Allocate TLS for module.
-        let master = &masters[dtv_index];
-
-        let module_tls = unsafe {
-            // FIXME(andypython): master.align
-            let layout = Layout::from_size_align_unchecked(master.segment_size, 16);
-            let ptr = alloc(layout);
-
-            ptr::copy_nonoverlapping(master.ptr, ptr, master.image_size);
-            ptr::write_bytes(
-                ptr.add(master.image_size),
-                0,
-                master.segment_size - master.image_size,
-            );
-
-            ptr
-        };
-
-        // Set the DTV entry.
-        tcb.dtv_mut()[dtv_index] = module_tls;
-    }
-
-    let mut ptr = tcb.dtv_mut()[dtv_index];
-
-    if ptr.is_null() {
-        panic!(
-            "__tls_get_addr({ti:p}: {:#x}, {:#x})",
-            ti.ti_module, ti.ti_offset
-        );
-    }
-
     if cfg!(target_arch = "riscv64") {
         ptr = unsafe { ptr.add(0x800 + ti.ti_offset) }; // dynamic offsets are 0x800-based on risc-v
     } else {
@@ -7,20 +7,26 @@
 #![warn(warnings, unused_variables)]
 
 use core::{
-    ptr, str,
+    mem, ptr, str,
     sync::atomic::{AtomicUsize, Ordering},
 };
 
 use alloc::sync::Arc;
+use object::{NativeEndian, elf, read::elf::ProgramHeader as _};
 
 use crate::{
     c_str::CStr,
+    header::link::{self, link_map},
     ld_so::{
-        dso::DSO,
-        linker::{DlError, Resolve, ScopeKind},
+        self,
+        dso::{DSO, ProgramHeader, SymbolVersion},
+        linker::{DlError, OpenFlags, Resolve, ScopeKind},
         tcb::Tcb,
     },
-    platform::types::{c_char, c_int, c_void},
+    platform::{
+        self, Pal, Sys,
+        types::{c_char, c_int, c_long, c_uint, c_void, size_t},
+    },
 };
 
 /// Relocations are performed at an implementation-defined time.
@@ -30,6 +36,12 @@ pub const RTLD_NOW: c_int = 1 << 1;
 /// Non-POSIX, see <https://www.man7.org/linux/man-pages/man3/dlopen.3.html>.
 /// Don't load the shared object.
 pub const RTLD_NOLOAD: c_int = 1 << 2;
+/// Non-POSIX, see <https://www.man7.org/linux/man-pages/man3/dlopen.3.html>.
+/// Search the symbols of the shared object before the global scope.
+pub const RTLD_DEEPBIND: c_int = 1 << 3;
+/// Non-POSIX, see <https://www.man7.org/linux/man-pages/man3/dlopen.3.html>.
+/// Don't unload the shared object during `dlclose()`.
+pub const RTLD_NODELETE: c_int = 1 << 12;
 /// All symbols are available for relocation processing of other modules.
 pub const RTLD_GLOBAL: c_int = 1 << 8;
 /// All symbold are not made available for relocation processing by other
@@ -46,6 +58,48 @@ pub const RTLD_LOCAL: c_int = 0x0000;
     reason = "cbindgen issue: https://github.com/mozilla/cbindgen/issues/948"
 )]
 pub const RTLD_DEFAULT: *mut c_void = 0 as *mut c_void; // XXX: cbindgen doesn't like ptr::null_mut() for publically exported constants
+/// Special purpose value for `handle` for `dlsym()` reserved by POSIX for
+/// future use.
+/// The identifier lookup finds the next definition after the object
+/// containing the caller, in the scope that object belongs to.
+pub const RTLD_NEXT: *mut c_void = -1isize as *mut c_void;
+
+/// Non-POSIX, see <https://www.man7.org/linux/man-pages/man3/dlmopen.3.html>.
+/// Link map namespace identifier.
+pub type Lmid_t = c_long;
+
+/// Non-POSIX, see <https://www.man7.org/linux/man-pages/man3/dlmopen.3.html>.
+/// The initial namespace, containing the program and its dependencies.
+pub const LM_ID_BASE: Lmid_t = 0;
+/// Non-POSIX, see <https://www.man7.org/linux/man-pages/man3/dlmopen.3.html>.
+/// Load the object into a new namespace.
+pub const LM_ID_NEWLM: Lmid_t = -1;
+
+/// Non-POSIX, see <https://www.man7.org/linux/man-pages/man3/dlinfo.3.html>.
+/// Obtain the ID of the link map namespace of the object.
+pub const RTLD_DI_LMID: c_int = 1;
+/// Non-POSIX, see <https://www.man7.org/linux/man-pages/man3/dlinfo.3.html>.
+/// Obtain a pointer to the link map entry of the object.
+pub const RTLD_DI_LINKMAP: c_int = 2;
+/// Non-POSIX, see <https://www.man7.org/linux/man-pages/man3/dlinfo.3.html>.
+/// Obtain the library search paths of the object.
+pub const RTLD_DI_SERINFO: c_int = 4;
+/// Non-POSIX, see <https://www.man7.org/linux/man-pages/man3/dlinfo.3.html>.
+/// Obtain the size of the buffer needed by `RTLD_DI_SERINFO`.
+pub const RTLD_DI_SERINFOSIZE: c_int = 5;
+/// Non-POSIX, see <https://www.man7.org/linux/man-pages/man3/dlinfo.3.html>.
+/// Copy the directory the object was loaded from into a buffer.
+pub const RTLD_DI_ORIGIN: c_int = 6;
+/// Non-POSIX, see <https://www.man7.org/linux/man-pages/man3/dlinfo.3.html>.
+/// Obtain the TLS module ID of the object.
+pub const RTLD_DI_TLS_MODID: c_int = 9;
+/// Non-POSIX, see <https://www.man7.org/linux/man-pages/man3/dlinfo.3.html>.
+/// Obtain a pointer to the TLS block of the object for the calling thread, or
+/// a null pointer if it has not been allocated yet.
+pub const RTLD_DI_TLS_DATA: c_int = 10;
+/// Non-POSIX, see <https://www.man7.org/linux/man-pages/man3/dlinfo.3.html>.
+/// Obtain a pointer to the program headers of the object.
+pub const RTLD_DI_PHDR: c_int = 11;
 
 static ERROR_NOT_SUPPORTED: &core::ffi::CStr = c"dlfcn not supported";
 
@@ -61,35 +115,209 @@ fn set_last_error(error: DlError) {
 #[allow(non_camel_case_types)]
 pub struct Dl_info_t {
     /// Pathname of mapped object file.
-    dli_fname: *const c_char,
+    pub(crate) dli_fname: *const c_char,
     /// Base of mapped address range.
-    dli_fbase: *mut c_void,
+    pub(crate) dli_fbase: *mut c_void,
     /// Symbol name or null pointer.
-    dli_sname: *const c_char,
+    pub(crate) dli_sname: *const c_char,
     /// Symbol address of null pointer.
-    dli_saddr: *mut c_void,
+    pub(crate) dli_saddr: *mut c_void,
 }
 
 /// alias as per spec update: <https://www.austingroupbugs.net/view.php?id=1847>
 pub type Dl_info = Dl_info_t;
 
+/// Non-POSIX, see <https://www.man7.org/linux/man-pages/man3/dlinfo.3.html>.
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct Dl_serpath {
+    /// Directory searched for libraries.
+    pub dls_name: *mut c_char,
+    /// `LA_SER_*` flag telling where the directory comes from.
+    pub dls_flags: c_uint,
+}
+
+/// Non-POSIX, see <https://www.man7.org/linux/man-pages/man3/dlinfo.3.html>.
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct Dl_serinfo {
+    /// Size of the buffer, including the directory names.
+    pub dls_size: size_t,
+    /// Number of entries in `dls_serpath`.
+    pub dls_cnt: c_uint,
+    /// The search directories, followed by their names.
+    pub dls_serpath: [Dl_serpath; 1],
+}
+
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/dladdr.html>.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn dladdr(_addr: *const c_void, info: *mut Dl_info_t) -> c_int {
-    //TODO
-    unsafe {
-        (*info).dli_fname = ptr::null();
-        (*info).dli_fbase = ptr::null_mut();
-        (*info).dli_sname = ptr::null();
-        (*info).dli_saddr = ptr::null_mut();
+pub unsafe extern "C" fn dladdr(addr: *const c_void, info: *mut Dl_info_t) -> c_int {
+    c_int::from(unsafe { find_addr(addr as usize, &mut *info, true) })
+}
+
+/// Same as `dladdr()`, but gives up instead of waiting if the linker is in
+/// use, so that it can be called while allocating memory.
+pub(crate) unsafe fn try_dladdr(addr: *const c_void, info: &mut Dl_info_t) -> bool {
+    unsafe { find_addr(addr as usize, info, false) }
+}
+
+unsafe fn find_addr(addr: usize, info: &mut Dl_info_t, wait: bool) -> bool {
+    if let Some(tcb) = unsafe { Tcb::current() }
+        && !tcb.linker_ptr.is_null()
+    {
+        let linker = unsafe { &*tcb.linker_ptr };
+        let linker = if wait {
+            linker.lock()
+        } else {
+            match linker.try_lock() {
+                Some(linker) => linker,
+                None => return false,
+            }
+        };
+        let Some(obj) = linker.find_object(addr) else {
+            return false;
+        };
+
+        info.dli_fname = unsafe { (*obj.link_map).l_name };
+        info.dli_fbase = obj.base.cast::<c_void>().cast_mut();
+        (info.dli_sname, info.dli_saddr) = match obj.symbol_at(addr) {
+            Some((name, value)) => (name.as_ptr().cast(), value as *mut c_void),
+            None => (ptr::null(), ptr::null_mut()),
+        };
+        return true;
     }
+
+    // Statically linked programs have no dynamic symbol table to search.
+    let phdrs = ld_so::static_phdrs();
+    let load_bias = ld_so::static_load_bias();
+    let mut segments = phdrs
+        .iter()
+        .filter(|ph| ph.p_type(NativeEndian) == elf::PT_LOAD)
+        .map(|ph| {
+            let start = load_bias + ph.p_vaddr(NativeEndian) as usize;
+            start..start + ph.p_memsz(NativeEndian) as usize
+        });
+    let Some(first) = segments.next() else {
+        return false;
+    };
+    if !first.contains(&addr) && !segments.any(|segment| segment.contains(&addr)) {
+        return false;
+    }
+
+    info.dli_fname = unsafe { platform::program_invocation_name };
+    info.dli_fbase = (first.start & !(Sys::getpagesize() - 1)) as *mut c_void;
+    info.dli_sname = ptr::null();
+    info.dli_saddr = ptr::null_mut();
+    true
+}
+
+/// Non-POSIX, see <https://www.man7.org/linux/man-pages/man3/dlinfo.3.html>.
+///
+/// Obtains the information selected by `request` about the object `handle`
+/// into `arg`.
+///
+/// For `RTLD_DI_SERINFO`, `arg` must point to a buffer whose `dls_size` and
+/// `dls_cnt` were filled by a `RTLD_DI_SERINFOSIZE` request.
+///
+/// Returns `0` on success, or the number of program headers for
+/// `RTLD_DI_PHDR`. Upon failure, returns `-1` and sets the error returned by
+/// `dlerror()`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dlinfo(handle: *mut c_void, request: c_int, arg: *mut c_void) -> c_int {
+    let Some(obj) = (unsafe { handle.cast::<DSO>().as_ref() }) else {
+        set_last_error(DlError::InvalidHandle);
+        return -1;
+    };
+
+    match request {
+        RTLD_DI_LMID => unsafe { arg.cast::<Lmid_t>().write(LM_ID_BASE) },
+        RTLD_DI_LINKMAP => unsafe { arg.cast::<*mut link_map>().write(obj.link_map) },
+        RTLD_DI_SERINFO | RTLD_DI_SERINFOSIZE => {
+            let Some(tcb) = (unsafe { Tcb::current() }) else {
+                ERROR.store(ERROR_NOT_SUPPORTED.as_ptr() as usize, Ordering::SeqCst);
+                return -1;
+            };
+            if tcb.linker_ptr.is_null() {
+                ERROR.store(ERROR_NOT_SUPPORTED.as_ptr() as usize, Ordering::SeqCst);
+                return -1;
+            }
+            let dirs = unsafe { (*tcb.linker_ptr).lock() }.search_dirs(obj);
+
+            let info = arg.cast::<Dl_serinfo>();
+            let names =
+                mem::offset_of!(Dl_serinfo, dls_serpath) + dirs.len() * size_of::<Dl_serpath>();
+            let size = names + dirs.iter().map(|(dir, _)| dir.len() + 1).sum::<usize>();
+            if request == RTLD_DI_SERINFOSIZE {
+                unsafe {
+                    (*info).dls_size = size;
+                    (*info).dls_cnt = dirs.len() as c_uint;
+                }
+                return 0;
+            }
+
+            // The buffer must have been sized by RTLD_DI_SERINFOSIZE.
+            if unsafe { (*info).dls_size } < size
+                || unsafe { (*info).dls_cnt } as usize != dirs.len()
+            {
+                set_last_error(DlError::InvalidRequest);
+                return -1;
+            }
+            let paths = unsafe { (&raw mut (*info).dls_serpath).cast::<Dl_serpath>() };
+            let mut name = unsafe { arg.cast::<c_char>().add(names) };
+            for (i, (dir, flags)) in dirs.iter().enumerate() {
+                unsafe {
+                    ptr::copy_nonoverlapping(dir.as_ptr().cast::<c_char>(), name, dir.len());
+                    name.add(dir.len()).write(0);
+                    paths.add(i).write(Dl_serpath {
+                        dls_name: name,
+                        dls_flags: *flags,
+                    });
+                    name = name.add(dir.len() + 1);
+                }
+            }
+        }
+        RTLD_DI_ORIGIN => {
+            let name = unsafe { CStr::from_ptr((*obj.link_map).l_name) }.to_bytes();
+            let origin = match name.iter().rposition(|&c| c == b'/') {
+                Some(0) => &b"/"[..],
+                Some(i) => &name[..i],
+                None => &b"."[..],
+            };
+            let arg = arg.cast::<u8>();
+            unsafe {
+                ptr::copy_nonoverlapping(origin.as_ptr(), arg, origin.len());
+                arg.add(origin.len()).write(0);
+            }
+        }
+        RTLD_DI_TLS_MODID => unsafe { arg.cast::<size_t>().write(obj.tls_module_id) },
+        RTLD_DI_TLS_DATA => unsafe {
+            arg.cast::<*mut c_void>()
+                .write(link::tls_data(obj.tls_module_id))
+        },
+        RTLD_DI_PHDR => {
+            unsafe { arg.cast::<*const ProgramHeader>().write(obj.phdrs.as_ptr()) };
+            return obj.phdrs.len() as c_int;
+        }
+        _ => {
+            set_last_error(DlError::InvalidRequest);
+            return -1;
+        }
+    }
+
     0
 }
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/dlopen.html>.
 #[unsafe(no_mangle)]
+#[inline(never)]
 pub unsafe extern "C" fn dlopen(cfilename: *const c_char, flags: c_int) -> *mut c_void {
-    //TODO support all sort of flags
+    let caller = core::intrinsics::return_address() as usize;
+    unsafe { open(cfilename, flags, caller) }
+}
+
+/// Opens `cfilename` on behalf of the code at the address `caller`, whose
+/// object provides the search paths.
+unsafe fn open(cfilename: *const c_char, flags: c_int, caller: usize) -> *mut c_void {
     let resolve = if flags & RTLD_NOW == RTLD_NOW {
         Resolve::Now
     } else {
@@ -102,7 +330,10 @@ pub unsafe extern "C" fn dlopen(cfilename: *const c_char, flags: c_int) -> *mut
         ScopeKind::Local
     };
 
-    let noload = flags & RTLD_NOLOAD == RTLD_NOLOAD;
+    let mut open_flags = OpenFlags::empty();
+    open_flags.set(OpenFlags::NOLOAD, flags & RTLD_NOLOAD == RTLD_NOLOAD);
+    open_flags.set(OpenFlags::NODELETE, flags & RTLD_NODELETE == RTLD_NODELETE);
+    open_flags.set(OpenFlags::DEEPBIND, flags & RTLD_DEEPBIND == RTLD_DEEPBIND);
 
     let filename = if cfilename.is_null() {
         None
@@ -126,7 +357,7 @@ pub unsafe extern "C" fn dlopen(cfilename: *const c_char, flags: c_int) -> *mut
 
     let mut linker = unsafe { (*tcb.linker_ptr).lock() };
 
-    match linker.load_library(filename, resolve, scope, noload) {
+    match linker.load_library(filename, resolve, scope, open_flags, caller) {
         Ok(handle) => Arc::into_raw(handle).cast::<c_void>().cast_mut(),
         Err(error) => {
             set_last_error(error);
@@ -135,16 +366,35 @@ pub unsafe extern "C" fn dlopen(cfilename: *const c_char, flags: c_int) -> *mut
     }
 }
 
-/// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/dlsym.html>.
+/// Non-POSIX, see <https://www.man7.org/linux/man-pages/man3/dlmopen.3.html>.
+///
+/// Equivalent to `dlopen()`, except that the object is loaded into the link
+/// map namespace `lmid`. Only the initial namespace, `LM_ID_BASE`, is
+/// supported.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void {
-    let handle = unsafe { handle.cast::<DSO>().as_ref() };
-
-    if symbol.is_null() {
-        ERROR.store(ERROR_NOT_SUPPORTED.as_ptr() as usize, Ordering::SeqCst);
+#[inline(never)]
+pub unsafe extern "C" fn dlmopen(
+    lmid: Lmid_t,
+    cfilename: *const c_char,
+    flags: c_int,
+) -> *mut c_void {
+    if lmid != LM_ID_BASE {
+        set_last_error(DlError::InvalidRequest);
         return ptr::null_mut();
     }
 
+    let caller = core::intrinsics::return_address() as usize;
+    unsafe { open(cfilename, flags, caller) }
+}
+
+/// Looks up `symbol`, with the given `version`, from `handle` on behalf of
+/// the code at the address `caller`.
+unsafe fn lookup_sym(
+    handle: *mut c_void,
+    symbol: *const c_char,
+    version: Option<&SymbolVersion<'_>>,
+    caller: usize,
+) -> *mut c_void {
     let symbol_str = unsafe { str::from_utf8_unchecked(CStr::from_ptr(symbol).to_bytes()) };
 
     // FIXME(andypython): just call obj.scope.get_sym() directly or search the
@@ -162,7 +412,14 @@ pub unsafe extern "C" fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *m
 
     let linker = unsafe { (*tcb.linker_ptr).lock() };
 
-    match linker.get_sym(handle, symbol_str) {
+    let sym = if handle == RTLD_NEXT {
+        linker.get_next_sym(caller, symbol_str, version)
+    } else {
+        let handle = unsafe { handle.cast::<DSO>().as_ref() };
+        linker.get_sym(handle, symbol_str, version)
+    };
+
+    match sym {
         Some(sym) => sym,
         _ => {
             ERROR.store(ERROR_NOT_SUPPORTED.as_ptr() as usize, Ordering::SeqCst);
@@ -171,6 +428,47 @@ pub unsafe extern "C" fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *m
     }
 }
 
+/// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/dlsym.html>.
+#[unsafe(no_mangle)]
+#[inline(never)]
+pub unsafe extern "C" fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void {
+    if symbol.is_null() {
+        ERROR.store(ERROR_NOT_SUPPORTED.as_ptr() as usize, Ordering::SeqCst);
+        return ptr::null_mut();
+    }
+
+    let caller = core::intrinsics::return_address() as usize;
+    unsafe { lookup_sym(handle, symbol, None, caller) }
+}
+
+/// Non-POSIX, see <https://www.man7.org/linux/man-pages/man3/dlvsym.3.html>.
+///
+/// Equivalent to `dlsym()`, except that the symbol must be defined with the
+/// version `version`.
+#[unsafe(no_mangle)]
+#[inline(never)]
+pub unsafe extern "C" fn dlvsym(
+    handle: *mut c_void,
+    symbol: *const c_char,
+    version: *const c_char,
+) -> *mut c_void {
+    if symbol.is_null() || version.is_null() {
+        ERROR.store(ERROR_NOT_SUPPORTED.as_ptr() as usize, Ordering::SeqCst);
+        return ptr::null_mut();
+    }
+
+    let version_str = unsafe { str::from_utf8_unchecked(CStr::from_ptr(version).to_bytes()) };
+    let caller = core::intrinsics::return_address() as usize;
+    unsafe {
+        lookup_sym(
+            handle,
+            symbol,
+            Some(&SymbolVersion::new(version_str)),
+            caller,
+        )
+    }
+}
+
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/dlclose.html>.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn dlclose(handle: *mut c_void) -> c_int {
@@ -189,10 +487,19 @@ pub unsafe extern "C" fn dlclose(handle: *mut c_void) -> c_int {
         return -1;
     }
 
-    let mut linker = unsafe { (*tcb.linker_ptr).lock() };
     let obj = unsafe { Arc::from_raw(handle.cast::<DSO>()) };
-    linker.unload(obj);
-    0
+    let unloaded = unsafe { (*tcb.linker_ptr).lock() }.unload(obj);
+    match unloaded {
+        // Finalizers calling dlclose or dlsym would deadlock under the lock.
+        Ok(unloaded) => {
+            unloaded.finalize();
+            0
+        }
+        Err(err) => {
+            set_last_error(err);
+            -1
+        }
+    }
 }
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/dlerror.html>.
@@ -11,6 +11,7 @@ use crate::{
         Pal, Sys,
         types::{c_char, c_int, c_short, c_ulonglong, mode_t, off_t, pid_t},
     },
+    pthread,
 };
 
 pub use self::sys::*;
@@ -120,7 +121,12 @@ pub unsafe extern "C" fn fcntl(fildes: c_int, cmd: c_int, mut __valist: ...) ->
         _ => 0,
     };
 
-    Sys::fcntl(fildes, cmd, arg).or_minus_one_errno()
+    match cmd {
+        // Only the commands that may block are cancellation points.
+        F_SETLKW | F_OFD_SETLKW => pthread::cancellation_point(|| Sys::fcntl(fildes, cmd, arg)),
+        _ => Sys::fcntl(fildes, cmd, arg),
+    }
+    .or_minus_one_errno()
 }
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/open.html>.
@@ -142,7 +148,7 @@ pub unsafe extern "C" fn open(path: *const c_char, oflag: c_int, mut __valist: .
     };
 
     let path = unsafe { CStr::from_ptr(path) };
-    Sys::openat(AT_FDCWD, path, oflag, mode).or_minus_one_errno()
+    pthread::cancellation_point(|| Sys::openat(AT_FDCWD, path, oflag, mode)).or_minus_one_errno()
 }
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/openat.html>.
@@ -172,7 +178,7 @@ pub unsafe extern "C" fn openat(
     };
 
     let path = unsafe { CStr::from_ptr(path) };
-    Sys::openat(fd, path, oflag, mode).or_minus_one_errno()
+    pthread::cancellation_point(|| Sys::openat(fd, path, oflag, mode)).or_minus_one_errno()
 }
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/posix_fallocate.html>.
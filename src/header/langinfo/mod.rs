@@ -2,10 +2,14 @@
 //!
 //! See <https://pubs.opengroup.org/onlinepubs/9799919799/basedefs/langinfo.h.html>.
 
-// TODO : involve loading locale data. Currently, the implementation only supports the "C" locale.
-
-use crate::{header::bits_locale_t::locale_t, platform::types::size_t};
-use core::ffi::c_char;
+use crate::{
+    header::{
+        bits_locale_t::locale_t,
+        locale::{locale_data, uselocale},
+    },
+    platform::types::size_t,
+};
+use core::{ffi::c_char, ptr};
 
 // TODO move `nl_item` to nl_types.h (not yet present in relibc) or bits header
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/basedefs/langinfo.h.html>.
@@ -194,7 +198,7 @@ pub const ABALTMON_12: nl_item = 80;
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/nl_langinfo.html>.
 ///
-/// Get a string from the langinfo table
+/// Get a string from the langinfo table of the current locale
 ///
 /// # Safety
 /// - Caller must ensure `item` is a valid `nl_item` index.
@@ -202,26 +206,28 @@ pub const ABALTMON_12: nl_item = 80;
 /// - Compatibility requires mutable pointer to be returned, but it should not be mutated!
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn nl_langinfo(item: nl_item) -> *mut c_char {
-    // Validate the item and perform the lookup
-    let ptr = if item < STRING_TABLE.len() {
-        STRING_TABLE[item].as_ptr().cast::<c_char>()
-    } else {
-        // Return a pointer to an empty string if the item is invalid
-        c"".as_ptr().cast::<c_char>()
-    };
-    // Mutable pointer is required (unsafe!)
-    ptr.cast_mut()
+    unsafe { nl_langinfo_l(item, uselocale(ptr::null_mut())) }
 }
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/nl_langinfo_l.html>.
 ///
-/// Get a string from the langinfo table
+/// Get a string from the langinfo table of `loc`
 ///
 /// # Safety
 /// - Caller must ensure `item` is a valid `nl_item` index.
 /// - Returns a pointer to a null-terminated string, or an empty string if the item is invalid.
 /// - Compatibility requires mutable pointer to be returned, but it should not be mutated!
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn nl_langinfo_l(item: nl_item, _loc: locale_t) -> *mut c_char {
-    unsafe { nl_langinfo(item) }
+pub unsafe extern "C" fn nl_langinfo_l(item: nl_item, loc: locale_t) -> *mut c_char {
+    // Items the locale does not define are the same as in the "C" locale
+    let ptr = if let Some(s) = unsafe { locale_data(loc) }.and_then(|data| data.langinfo(item)) {
+        s.as_ptr()
+    } else if item < STRING_TABLE.len() {
+        STRING_TABLE[item].as_ptr().cast::<c_char>()
+    } else {
+        // Return a pointer to an empty string if the item is invalid
+        c"".as_ptr().cast::<c_char>()
+    };
+    // Mutable pointer is required (unsafe!)
+    ptr.cast_mut()
 }
@@ -24,6 +24,10 @@ pub const NGROUPS_MAX: usize = 65536;
 /// Maximum number of `iovec` structures that one process has available for
 /// use with `readv()` or `writev()`.
 pub const IOV_MAX: c_int = 1024;
+/// Maximum number of message priorities supported by message queues.
+pub const MQ_PRIO_MAX: c_long = 32768;
+/// Maximum value a semaphore may have.
+pub const SEM_VALUE_MAX: c_int = c_int::MAX;
 
 /// Minimum required value for `IOV_MAX`.
 pub const _XOPEN_IOV_MAX: c_int = 16;
@@ -95,6 +99,10 @@ pub const _POSIX_LOGIN_NAME_MAX: c_long = 9;
 pub const _POSIX_MAX_CANON: c_long = 255;
 /// Minimum required value for `MAX_INPUT`.
 pub const _POSIX_MAX_INPUT: c_long = 255;
+/// Minimum required value for `MQ_OPEN_MAX`.
+pub const _POSIX_MQ_OPEN_MAX: c_long = 8;
+/// Minimum required value for `MQ_PRIO_MAX`.
+pub const _POSIX_MQ_PRIO_MAX: c_long = 32;
 /// Minimum required value for `NAME_MAX`.
 pub const _POSIX_NAME_MAX: c_long = 14;
 pub const _POSIX_NGROUPS_MAX: c_long = 8;
@@ -0,0 +1,183 @@
+//! `link.h` implementation.
+//!
+//! Non-POSIX, see <https://www.man7.org/linux/man-pages/man7/rtld-audit.7.html> and
+//! <https://www.man7.org/linux/man-pages/man3/dl_iterate_phdr.3.html>.
+
+use alloc::vec::Vec;
+use core::{mem, ptr};
+
+use object::{NativeEndian, elf, read::elf::ProgramHeader as _};
+
+#[cfg(target_pointer_width = "32")]
+use crate::header::elf::Elf32_Phdr;
+#[cfg(target_pointer_width = "64")]
+use crate::header::elf::Elf64_Phdr;
+use crate::{
+    header::elf::Elf64_Half,
+    ld_so::{self, tcb::Tcb},
+    platform::types::{c_char, c_int, c_uint, c_ulonglong, c_void, size_t},
+};
+
+/// Version of the auditing interface, returned by `la_version()`.
+pub const LAV_CURRENT: c_uint = 1;
+
+/// Audit symbol bindings to the object, returned by `la_objopen()`.
+pub const LA_FLG_BINDTO: c_uint = 0x01;
+/// Audit symbol bindings from the object, returned by `la_objopen()`.
+pub const LA_FLG_BINDFROM: c_uint = 0x02;
+
+/// `la_pltenter()` will not be called for the symbol.
+pub const LA_SYMB_NOPLTENTER: c_uint = 0x01;
+/// `la_pltexit()` will not be called for the symbol.
+pub const LA_SYMB_NOPLTEXIT: c_uint = 0x02;
+/// `la_pltexit()` is called with a pointer to the returned structure.
+pub const LA_SYMB_STRUCTCALL: c_uint = 0x04;
+/// The binding resulted from `dlsym()`.
+pub const LA_SYMB_DLSYM: c_uint = 0x08;
+/// A previous `la_symbind()` call returned an alternate value.
+pub const LA_SYMB_ALTVALUE: c_uint = 0x10;
+
+/// The search directory is the original name of the object.
+pub const LA_SER_ORIG: c_uint = 0x01;
+/// The search directory is from `LD_LIBRARY_PATH`.
+pub const LA_SER_LIBPATH: c_uint = 0x02;
+/// The search directory is from `DT_RPATH` or `DT_RUNPATH`.
+pub const LA_SER_RUNPATH: c_uint = 0x04;
+/// The search directory is from the library cache.
+pub const LA_SER_CONFIG: c_uint = 0x08;
+/// The search directory is a default system directory.
+pub const LA_SER_DEFAULT: c_uint = 0x40;
+/// The search directory is restricted to secure objects.
+pub const LA_SER_SECURE: c_uint = 0x80;
+
+/// Entry of the list of loaded objects, shared with debuggers and auditing
+/// libraries.
+#[repr(C)]
+pub struct link_map {
+    /// Difference between the address in the ELF file and the addresses in
+    /// memory.
+    pub l_addr: usize,
+    /// Absolute file name the object was found in.
+    pub l_name: *const c_char,
+    /// Dynamic section of the object.
+    pub l_ld: usize,
+    pub l_next: *mut link_map,
+    pub l_prev: *mut link_map,
+}
+
+/// Information about a loaded object, passed to the callback of
+/// [`dl_iterate_phdr()`].
+#[repr(C)]
+pub struct dl_phdr_info {
+    /// Base address of the object.
+    pub dlpi_addr: usize,
+    /// Null-terminated name of the object.
+    pub dlpi_name: *const c_char,
+    /// Program headers of the object.
+    #[cfg(target_pointer_width = "32")]
+    pub dlpi_phdr: *const Elf32_Phdr,
+    /// Program headers of the object.
+    #[cfg(target_pointer_width = "64")]
+    pub dlpi_phdr: *const Elf64_Phdr,
+    /// Number of program headers of the object.
+    pub dlpi_phnum: Elf64_Half,
+    /// Number of objects loaded so far.
+    pub dlpi_adds: c_ulonglong,
+    /// Number of objects unloaded so far.
+    pub dlpi_subs: c_ulonglong,
+    /// TLS module ID of the object, or `0` if it has no TLS segment.
+    pub dlpi_tls_modid: size_t,
+    /// Address of the TLS block of the object for the calling thread, or a
+    /// null pointer if it has not been allocated yet.
+    pub dlpi_tls_data: *mut c_void,
+}
+
+/// Returns the address of the TLS block of the module `tls_modid` for the
+/// calling thread, or a null pointer if `tls_modid` is `0` or the block has
+/// not been allocated yet. Unlike `__tls_get_addr()`, this never allocates
+/// the block.
+pub(crate) fn tls_data(tls_modid: usize) -> *mut c_void {
+    let Some(dtv_index) = tls_modid.checked_sub(1) else {
+        return ptr::null_mut();
+    };
+    match unsafe { Tcb::current() } {
+        Some(tcb) => tcb.allocated_tls_region(dtv_index).cast(),
+        None => ptr::null_mut(),
+    }
+}
+
+/// Collects the information passed to [`dl_iterate_phdr()`] about each loaded
+/// object, with the program first.
+fn phdr_infos() -> Vec<dl_phdr_info> {
+    if let Some(tcb) = unsafe { Tcb::current() }
+        && !tcb.linker_ptr.is_null()
+    {
+        let linker = unsafe { (*tcb.linker_ptr).lock() };
+        let (adds, subs) = linker.load_counts();
+
+        return linker
+            .objects()
+            .map(|obj| dl_phdr_info {
+                dlpi_addr: obj.load_bias(),
+                dlpi_name: unsafe { (*obj.link_map).l_name },
+                dlpi_phdr: obj.phdrs.as_ptr().cast(),
+                dlpi_phnum: obj.phdrs.len() as Elf64_Half,
+                dlpi_adds: adds,
+                dlpi_subs: subs,
+                dlpi_tls_modid: obj.tls_module_id,
+                dlpi_tls_data: tls_data(obj.tls_module_id),
+            })
+            .collect();
+    }
+
+    // Statically linked programs consist of a single object.
+    let phdrs = ld_so::static_phdrs();
+    if phdrs.is_empty() {
+        return Vec::new();
+    }
+
+    let tls_modid = phdrs
+        .iter()
+        .any(|ph| ph.p_type(NativeEndian) == elf::PT_TLS) as usize;
+
+    alloc::vec![dl_phdr_info {
+        dlpi_addr: ld_so::static_load_bias(),
+        dlpi_name: c"".as_ptr(),
+        dlpi_phdr: phdrs.as_ptr().cast(),
+        dlpi_phnum: phdrs.len() as Elf64_Half,
+        dlpi_adds: 1,
+        dlpi_subs: 0,
+        dlpi_tls_modid: tls_modid,
+        dlpi_tls_data: tls_data(tls_modid),
+    }]
+}
+
+/// Non-POSIX, see <https://www.man7.org/linux/man-pages/man3/dl_iterate_phdr.3.html>.
+///
+/// Calls `callback` with information about each loaded object, starting with
+/// the program, until it returns a nonzero value.
+///
+/// Returns the value returned by the last call to `callback`, or `0` if there
+/// were no calls.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dl_iterate_phdr(
+    callback: Option<
+        unsafe extern "C" fn(info: *mut dl_phdr_info, size: size_t, data: *mut c_void) -> c_int,
+    >,
+    data: *mut c_void,
+) -> c_int {
+    let Some(callback) = callback else {
+        return 0;
+    };
+
+    // The linker is not locked while calling back, so that the callback may
+    // itself load objects.
+    for mut info in phdr_infos() {
+        let ret = unsafe { callback(&raw mut info, mem::size_of::<dl_phdr_info>(), data) };
+        if ret != 0 {
+            return ret;
+        }
+    }
+
+    0
+}
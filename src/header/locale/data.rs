@@ -3,7 +3,10 @@ use core::str::FromStr;
 use alloc::{boxed::Box, ffi::CString, string::String, vec::Vec};
 
 use super::constants::*;
-use crate::platform::types::{c_char, c_int};
+use crate::{
+    header::langinfo::*,
+    platform::types::{c_char, c_int},
+};
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/basedefs/locale.h.html>.
 /// this struct is not ordered like in the posix spec for readability
@@ -86,9 +89,66 @@ pub(crate) struct LocaleData {
     pub mon_grouping: Vec<c_char>,
     pub positive_sign: CString,
     pub negative_sign: CString,
+    pub time: LcTime,
 }
 unsafe impl Sync for LocaleData {}
 
+/// Names and formats of the `LC_TIME` category. Those that are empty are
+/// taken from the "C" locale.
+#[derive(Clone, Default)]
+pub(crate) struct LcTime {
+    pub abday: Vec<CString>,
+    pub day: Vec<CString>,
+    pub abmon: Vec<CString>,
+    pub mon: Vec<CString>,
+    pub ab_alt_mon: Vec<CString>,
+    pub alt_mon: Vec<CString>,
+    pub am_pm: Vec<CString>,
+    pub d_t_fmt: Option<CString>,
+    pub d_fmt: Option<CString>,
+    pub t_fmt: Option<CString>,
+    pub t_fmt_ampm: Option<CString>,
+    /// Era segments, separated by `;`.
+    pub era: Option<CString>,
+    pub era_d_fmt: Option<CString>,
+    pub era_t_fmt: Option<CString>,
+    pub era_d_t_fmt: Option<CString>,
+    /// Alternative digits, separated by `;`.
+    pub alt_digits: Option<CString>,
+}
+
+impl LcTime {
+    fn langinfo(&self, item: nl_item) -> Option<&CString> {
+        match item {
+            ABDAY_1..=ABDAY_7 => self.abday.get(item - ABDAY_1),
+            DAY_1..=DAY_7 => self.day.get(item - DAY_1),
+            ABMON_1..=ABMON_12 => self.abmon.get(item - ABMON_1),
+            MON_1..=MON_12 => self.mon.get(item - MON_1),
+            // Locales without alternative names use the usual ones.
+            ABALTMON_1..=ABALTMON_12 => {
+                let i = item - ABALTMON_1;
+                self.ab_alt_mon.get(i).or_else(|| self.abmon.get(i))
+            }
+            ALTMON_1..=ALTMON_12 => {
+                let i = item - ALTMON_1;
+                self.alt_mon.get(i).or_else(|| self.mon.get(i))
+            }
+            AM_STR => self.am_pm.first(),
+            PM_STR => self.am_pm.get(1),
+            D_T_FMT => self.d_t_fmt.as_ref(),
+            D_FMT => self.d_fmt.as_ref(),
+            T_FMT => self.t_fmt.as_ref(),
+            T_FMT_AMPM => self.t_fmt_ampm.as_ref(),
+            ERA => self.era.as_ref(),
+            ERA_D_FMT => self.era_d_fmt.as_ref(),
+            ERA_T_FMT => self.era_t_fmt.as_ref(),
+            ERA_D_T_FMT => self.era_d_t_fmt.as_ref(),
+            ALT_DIGITS => self.alt_digits.as_ref(),
+            _ => None,
+        }
+    }
+}
+
 impl LocaleData {
     pub fn new(name: CString, defs: PosixLocaleDef) -> Box<Self> {
         let mut data = Box::new(LocaleData {
@@ -103,6 +163,7 @@ impl LocaleData {
             mon_grouping: Self::to_grouping_char(defs.mon_grouping),
             positive_sign: Self::to_cstring(defs.positive_sign),
             negative_sign: Self::to_cstring(defs.negative_sign),
+            time: defs.time,
             lconv: unsafe { core::mem::zeroed() },
         });
 
@@ -129,6 +190,18 @@ impl LocaleData {
         LocaleData::new(CString::from_str("C").unwrap(), PosixLocaleDef::default())
     }
 
+    /// Returns the value of the `nl_langinfo()` item `item` in the locale, or
+    /// `None` if it is the same as in the "C" locale.
+    pub fn langinfo(&self, item: nl_item) -> Option<&core::ffi::CStr> {
+        match item {
+            // Locales without LC_NUMERIC leave the decimal point empty
+            RADIXCHAR => Some(&self.decimal_point).filter(|s| !s.is_empty()),
+            THOUSEP => Some(&self.thousands_sep),
+            _ => self.time.langinfo(item),
+        }
+        .map(CString::as_c_str)
+    }
+
     fn update_lconv_pointers(&mut self) {
         self.lconv.decimal_point = self.decimal_point.as_ptr().cast_mut();
         self.lconv.thousands_sep = self.thousands_sep.as_ptr().cast_mut();
@@ -172,6 +245,9 @@ impl LocaleData {
                 self.lconv.int_p_sign_posn = other.lconv.int_p_sign_posn;
                 self.lconv.int_n_sign_posn = other.lconv.int_n_sign_posn;
             }
+            LC_TIME => {
+                self.time = other.time.clone();
+            }
             LC_ALL => {
                 *self = other.clone();
             }
@@ -211,6 +287,7 @@ impl Clone for LocaleData {
             mon_grouping: self.mon_grouping.clone(),
             positive_sign: self.positive_sign.clone(),
             negative_sign: self.negative_sign.clone(),
+            time: self.time.clone(),
         };
         data.update_lconv_pointers();
         data
@@ -281,66 +358,83 @@ pub(crate) struct PosixLocaleDef {
     pub int_n_sep_by_space: Option<c_char>,
     pub int_p_sign_posn: Option<c_char>,
     pub int_n_sign_posn: Option<c_char>,
+    pub time: LcTime,
 }
 impl PosixLocaleDef {
     //! See <https://pubs.opengroup.org/onlinepubs/9799919799/basedefs/V1_chap07.html>
     pub fn parse(content: &str) -> Self {
         let mut locale = PosixLocaleDef::default();
 
+        let mut escape_char = '\\';
+        let mut comment_char = '#';
+
         let mut lines = content.lines();
         while let Some(line) = lines.next() {
-            let trimmed = line.trim();
-            if trimmed.is_empty() || trimmed.starts_with('#') {
+            let mut line = String::from(line.trim());
+            // multiline values
+            while line.ends_with(escape_char)
+                && let Some(next_line) = lines.next()
+            {
+                line.pop();
+                line.push_str(next_line.trim());
+            }
+            if line.is_empty() || line.starts_with(comment_char) {
                 continue;
             }
 
-            let mut parts = trimmed.split_ascii_whitespace();
-            let Some(key) = parts.next() else {
+            let Some((key, val)) = line.split_once(|c: char| c.is_ascii_whitespace()) else {
                 continue;
             };
-            let mut val: String = String::new();
-            while let Some(chunk) = parts.next() {
-                if !chunk.ends_with('\\') {
-                    val.push_str(chunk);
-                    break;
-                }
-                // multiline values
-                val.push_str(&chunk[0..chunk.len() - 1]);
-                let Some(next_line) = lines.next() else {
-                    break;
-                };
-                parts = next_line.split_ascii_whitespace();
-            }
-
+            let val = val.trim();
             if key.is_empty() || val.is_empty() {
                 continue;
             }
 
+            let parse_str = move |val| Self::parse_str(val, escape_char);
+            let parse_str_list = move |val| Self::parse_str_list(val, escape_char);
             match key {
-                "decimal_point" => locale.decimal_point = Self::parse_str(&val),
-                "thousands_sep" => locale.thousands_sep = Self::parse_str(&val),
-                "int_curr_symbol" => locale.int_curr_symbol = Self::parse_str(&val),
-                "currency_symbol" => locale.currency_symbol = Self::parse_str(&val),
-                "mon_decimal_point" => locale.mon_decimal_point = Self::parse_str(&val),
-                "mon_thousands_sep" => locale.mon_thousands_sep = Self::parse_str(&val),
-                "positive_sign" => locale.positive_sign = Self::parse_str(&val),
-                "negative_sign" => locale.negative_sign = Self::parse_str(&val),
-                "grouping" => locale.grouping = Self::parse_int_group(&val),
-                "mon_grouping" => locale.mon_grouping = Self::parse_int_group(&val),
-                "int_frac_digits" => locale.int_frac_digits = Self::parse_int(&val),
-                "frac_digits" => locale.frac_digits = Self::parse_int(&val),
-                "p_cs_precedes" => locale.p_cs_precedes = Self::parse_int(&val),
-                "p_sep_by_space" => locale.p_sep_by_space = Self::parse_int(&val),
-                "n_cs_precedes" => locale.n_cs_precedes = Self::parse_int(&val),
-                "n_sep_by_space" => locale.n_sep_by_space = Self::parse_int(&val),
-                "p_sign_posn" => locale.p_sign_posn = Self::parse_int(&val),
-                "n_sign_posn" => locale.n_sign_posn = Self::parse_int(&val),
-                "int_p_cs_precedes" => locale.int_p_cs_precedes = Self::parse_int(&val),
-                "int_p_sep_by_space" => locale.int_p_sep_by_space = Self::parse_int(&val),
-                "int_n_cs_precedes" => locale.int_n_cs_precedes = Self::parse_int(&val),
-                "int_n_sep_by_space" => locale.int_n_sep_by_space = Self::parse_int(&val),
-                "int_p_sign_posn" => locale.int_p_sign_posn = Self::parse_int(&val),
-                "int_n_sign_posn" => locale.int_n_sign_posn = Self::parse_int(&val),
+                "decimal_point" => locale.decimal_point = parse_str(val),
+                "thousands_sep" => locale.thousands_sep = parse_str(val),
+                "int_curr_symbol" => locale.int_curr_symbol = parse_str(val),
+                "currency_symbol" => locale.currency_symbol = parse_str(val),
+                "mon_decimal_point" => locale.mon_decimal_point = parse_str(val),
+                "mon_thousands_sep" => locale.mon_thousands_sep = parse_str(val),
+                "positive_sign" => locale.positive_sign = parse_str(val),
+                "negative_sign" => locale.negative_sign = parse_str(val),
+                "grouping" => locale.grouping = Self::parse_int_group(val),
+                "mon_grouping" => locale.mon_grouping = Self::parse_int_group(val),
+                "int_frac_digits" => locale.int_frac_digits = Self::parse_int(val),
+                "frac_digits" => locale.frac_digits = Self::parse_int(val),
+                "p_cs_precedes" => locale.p_cs_precedes = Self::parse_int(val),
+                "p_sep_by_space" => locale.p_sep_by_space = Self::parse_int(val),
+                "n_cs_precedes" => locale.n_cs_precedes = Self::parse_int(val),
+                "n_sep_by_space" => locale.n_sep_by_space = Self::parse_int(val),
+                "p_sign_posn" => locale.p_sign_posn = Self::parse_int(val),
+                "n_sign_posn" => locale.n_sign_posn = Self::parse_int(val),
+                "int_p_cs_precedes" => locale.int_p_cs_precedes = Self::parse_int(val),
+                "int_p_sep_by_space" => locale.int_p_sep_by_space = Self::parse_int(val),
+                "int_n_cs_precedes" => locale.int_n_cs_precedes = Self::parse_int(val),
+                "int_n_sep_by_space" => locale.int_n_sep_by_space = Self::parse_int(val),
+                "int_p_sign_posn" => locale.int_p_sign_posn = Self::parse_int(val),
+                "int_n_sign_posn" => locale.int_n_sign_posn = Self::parse_int(val),
+                "escape_char" => escape_char = val.chars().next().unwrap_or(escape_char),
+                "comment_char" => comment_char = val.chars().next().unwrap_or(comment_char),
+                "abday" => locale.time.abday = parse_str_list(val),
+                "day" => locale.time.day = parse_str_list(val),
+                "abmon" => locale.time.abmon = parse_str_list(val),
+                "mon" => locale.time.mon = parse_str_list(val),
+                "ab_alt_mon" => locale.time.ab_alt_mon = parse_str_list(val),
+                "alt_mon" => locale.time.alt_mon = parse_str_list(val),
+                "am_pm" => locale.time.am_pm = parse_str_list(val),
+                "d_t_fmt" => locale.time.d_t_fmt = parse_str(val),
+                "d_fmt" => locale.time.d_fmt = parse_str(val),
+                "t_fmt" => locale.time.t_fmt = parse_str(val),
+                "t_fmt_ampm" => locale.time.t_fmt_ampm = parse_str(val),
+                "era" => locale.time.era = Self::join(parse_str_list(val)),
+                "era_d_fmt" => locale.time.era_d_fmt = parse_str(val),
+                "era_t_fmt" => locale.time.era_t_fmt = parse_str(val),
+                "era_d_t_fmt" => locale.time.era_d_t_fmt = parse_str(val),
+                "alt_digits" => locale.time.alt_digits = Self::join(parse_str_list(val)),
                 _ => {}
             }
         }
@@ -364,22 +458,63 @@ impl PosixLocaleDef {
     }
 
     /// parse e.g. `""`
-    fn parse_str(val: &str) -> Option<CString> {
-        let mut r = String::new();
-        let mut v = val.chars();
-        if v.next() != Some('"') {
+    fn parse_str(val: &str, escape_char: char) -> Option<CString> {
+        let mut list = Self::parse_str_list(val, escape_char);
+        if list.len() != 1 {
             return None;
         }
-        while let Some(c) = v.next() {
-            if c == '"' {
-                if v.next().is_some() {
-                    return None;
+        list.pop()
+    }
+
+    /// parse e.g. `"Sun";"Mon"` -> [ "Sun", "Mon" ], where `escape_char`
+    /// quotes the next character and `<Uxxxx>` names a character by its code
+    fn parse_str_list(val: &str, escape_char: char) -> Vec<CString> {
+        let mut list = Vec::new();
+        let mut v = val.chars();
+        loop {
+            if v.next() != Some('"') {
+                return Vec::new();
+            }
+            let mut r = String::new();
+            loop {
+                match v.next() {
+                    Some('"') => break,
+                    Some(c) if c == escape_char => match v.next() {
+                        Some(c) => r.push(c),
+                        None => return Vec::new(),
+                    },
+                    Some('<') => {
+                        let name: String = v.by_ref().take_while(|&c| c != '>').collect();
+                        let c = name
+                            .strip_prefix('U')
+                            .and_then(|code| u32::from_str_radix(code, 16).ok())
+                            .and_then(char::from_u32);
+                        match c {
+                            Some(c) => r.push(c),
+                            // Other symbolic names are not supported
+                            None => return Vec::new(),
+                        }
+                    }
+                    Some(c) => r.push(c),
+                    None => return Vec::new(),
                 }
-                break;
             }
-            // TODO: Parse <..>
-            r.push(c);
+            let Ok(r) = CString::new(r) else {
+                return Vec::new();
+            };
+            list.push(r);
+
+            match v.next() {
+                Some(';') => {}
+                None => return list,
+                Some(_) => return Vec::new(),
+            }
         }
-        CString::new(r).ok()
+    }
+
+    /// join e.g. [ "a", "b" ] -> `a;b`
+    fn join(list: Vec<CString>) -> Option<CString> {
+        let bytes: Vec<&[u8]> = list.iter().map(|s| s.as_bytes()).collect();
+        (!list.is_empty()).then(|| CString::new(bytes.join(&b';')).unwrap())
     }
 }
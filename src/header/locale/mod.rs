@@ -10,7 +10,7 @@ use crate::{
     casting::ByteLiteral,
     error::{Errno, ResultExtPtrMut},
     fs::File,
-    header::{errno, fcntl},
+    header::{errno, fcntl, stdlib::secure_getenv},
     io::Read,
     platform::types::{c_char, c_int},
 };
@@ -51,6 +51,17 @@ pub unsafe extern "C" fn localeconv() -> *mut lconv {
     }
 }
 
+/// Returns the data of `loc`, which may be [`LC_GLOBAL_LOCALE`], or `None` if
+/// it is the "C" locale.
+pub(crate) unsafe fn locale_data(loc: locale_t) -> Option<&'static LocaleData> {
+    if loc == LC_GLOBAL_LOCALE || loc.is_null() {
+        // safety: GLOBAL_LOCALE is never set to null again
+        unsafe { GLOBAL_LOCALE.as_ref() }.map(|global| &global.data)
+    } else {
+        unsafe { loc.cast::<LocaleData>().as_ref() }
+    }
+}
+
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/setlocale.html>.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn setlocale(category: c_int, locale: *const c_char) -> *mut c_char {
@@ -138,6 +149,9 @@ pub unsafe extern "C" fn newlocale(mask: c_int, locale: *const c_char, base: loc
             if (mask & LC_MONETARY_MASK) == 0 {
                 new_locale.copy_category(base, LC_MONETARY);
             }
+            if (mask & LC_TIME_MASK) == 0 {
+                new_locale.copy_category(base, LC_TIME);
+            }
             // TODO: other categories?
         }
     }
@@ -167,8 +181,31 @@ pub unsafe extern "C" fn duplocale(loc: locale_t) -> locale_t {
     }
 }
 
+/// Loads the locale definition `name`, from the directories listed in
+/// `LOCPATH` first, then from the system's. `LOCPATH` is ignored in
+/// secure-execution mode.
 pub(crate) fn load_locale_file(name: &str) -> Result<Box<LocaleData>, Errno> {
-    let mut path = String::from("/usr/share/i18n/locales/");
+    let locpath = unsafe { CStr::from_nullable_ptr(secure_getenv(c"LOCPATH".as_ptr())) }
+        .and_then(|locpath| locpath.to_str().ok())
+        .unwrap_or("");
+
+    let mut result = Err(Errno(errno::ENOENT));
+    for dir in locpath
+        .split(':')
+        .filter(|dir| !dir.is_empty())
+        .chain(["/usr/share/i18n/locales"])
+    {
+        result = load_locale_file_in(dir, name);
+        if result.is_ok() {
+            break;
+        }
+    }
+    result
+}
+
+fn load_locale_file_in(dir: &str, name: &str) -> Result<Box<LocaleData>, Errno> {
+    let mut path = String::from(dir);
+    path.push('/');
     path.push_str(name);
 
     let path_c = CString::new(path).map_err(|_| Errno(errno::EINVAL))?;
@@ -3,17 +3,181 @@
 //! Non-POSIX, see <https://man7.org/linux/man-pages/man3/posix_memalign.3.html>.
 
 use crate::{
-    header::errno::ENOMEM,
+    header::{
+        errno::{EINVAL, ENOMEM},
+        stdio::FILE,
+    },
     platform::{
-        self, Pal, Sys,
-        types::{c_void, size_t},
+        self, AllocStats, FileWriter, Pal, Sys,
+        types::{c_int, c_void, size_t},
     },
 };
-use core::ptr;
+use core::{
+    fmt::{self, Write},
+    ptr,
+};
+
+/// Parameter of `mallopt()`: free bytes at the top of the heap that cause it
+/// to be trimmed.
+pub const M_TRIM_THRESHOLD: c_int = -1;
+/// Parameter of `mallopt()`: bytes kept at the top of the heap when it is
+/// trimmed.
+pub const M_TOP_PAD: c_int = -2;
+/// Parameter of `mallopt()`: size from which blocks are allocated with
+/// `mmap()` on their own, and returned to the system when freed.
+pub const M_MMAP_THRESHOLD: c_int = -3;
+/// Parameter of `mallopt()`: maximum number of blocks allocated with
+/// `mmap()` on their own at once. `0` disables it.
+pub const M_MMAP_MAX: c_int = -4;
+/// Parameter of `mallopt()`: byte written over freed blocks, and its
+/// complement over allocated blocks.
+pub const M_PERTURB: c_int = -6;
+/// Parameter of `mallopt()`: maximum number of arenas.
+pub const M_ARENA_MAX: c_int = -8;
+
+/// See <https://man7.org/linux/man-pages/man3/mallinfo.3.html>.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct mallinfo {
+    pub arena: c_int,
+    pub ordblks: c_int,
+    pub smblks: c_int,
+    pub hblks: c_int,
+    pub hblkhd: c_int,
+    pub usmblks: c_int,
+    pub fsmblks: c_int,
+    pub uordblks: c_int,
+    pub fordblks: c_int,
+    pub keepcost: c_int,
+}
+
+/// See <https://man7.org/linux/man-pages/man3/mallinfo.3.html>.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct mallinfo2 {
+    pub arena: size_t,
+    pub ordblks: size_t,
+    pub smblks: size_t,
+    pub hblks: size_t,
+    pub hblkhd: size_t,
+    pub usmblks: size_t,
+    pub fsmblks: size_t,
+    pub uordblks: size_t,
+    pub fordblks: size_t,
+    pub keepcost: size_t,
+}
+
+/// See <https://man7.org/linux/man-pages/man3/mallinfo.3.html>.
+///
+/// The fields are truncated to `int`, use `mallinfo2()` instead.
+#[deprecated]
+#[unsafe(no_mangle)]
+pub extern "C" fn mallinfo() -> mallinfo {
+    let info = mallinfo2();
+    mallinfo {
+        arena: info.arena as c_int,
+        hblks: info.hblks as c_int,
+        hblkhd: info.hblkhd as c_int,
+        usmblks: info.usmblks as c_int,
+        uordblks: info.uordblks as c_int,
+        fordblks: info.fordblks as c_int,
+        ..mallinfo::default()
+    }
+}
+
+/// See <https://man7.org/linux/man-pages/man3/mallinfo.3.html>.
+///
+/// `arena` (bytes obtained from the system for the heap), `hblks` and
+/// `hblkhd` (blocks mapped on their own and their bytes), `usmblks` (largest
+/// value of `arena` so far), `uordblks` (bytes allocated from the heap) and
+/// `fordblks` (bytes free in the heap) are tracked. Blocks freed by a thread
+/// but kept in its cache count as allocated.
+///
+/// dlmalloc does not expose its free lists nor the size of its top chunk, so
+/// `ordblks`, `smblks`, `fsmblks` and `keepcost` are zero.
+#[unsafe(no_mangle)]
+pub extern "C" fn mallinfo2() -> mallinfo2 {
+    let stats = platform::alloc_stats();
+    mallinfo2 {
+        arena: stats.system,
+        hblks: stats.mapped_blocks,
+        hblkhd: stats.mapped,
+        usmblks: stats.max_system,
+        uordblks: stats.in_use,
+        fordblks: stats.free(),
+        ..mallinfo2::default()
+    }
+}
+
+/// See <https://man7.org/linux/man-pages/man3/malloc_info.3.html>.
+///
+/// Like glibc, returns `EINVAL` rather than setting `errno` if `options` is
+/// not `0`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn malloc_info(options: c_int, stream: *mut FILE) -> c_int {
+    if options != 0 {
+        return EINVAL;
+    }
+
+    let mut stream = unsafe { (*stream).lock() };
+    match write_info(&mut *stream, &platform::alloc_stats()) {
+        Ok(()) => 0,
+        Err(_) => -1,
+    }
+}
+
+fn write_info(stream: &mut impl Write, stats: &AllocStats) -> fmt::Result {
+    stream.write_str("<malloc version=\"1\">\n<heap nr=\"0\">\n<sizes>\n</sizes>\n")?;
+    write_totals(stream, stats)?;
+    stream.write_str("</heap>\n")?;
+    write_totals(stream, stats)?;
+    stream.write_str("</malloc>\n")
+}
+
+fn write_totals(stream: &mut impl Write, stats: &AllocStats) -> fmt::Result {
+    write!(
+        stream,
+        "<total type=\"rest\" size=\"{}\"/>\n\
+         <total type=\"mmap\" count=\"{}\" size=\"{}\"/>\n\
+         <system type=\"current\" size=\"{}\"/>\n\
+         <system type=\"max\" size=\"{}\"/>\n",
+        stats.free(),
+        stats.mapped_blocks,
+        stats.mapped,
+        stats.system,
+        stats.max_system
+    )
+}
+
+/// See <https://man7.org/linux/man-pages/man3/malloc_stats.3.html>.
+#[unsafe(no_mangle)]
+pub extern "C" fn malloc_stats() {
+    let stats = platform::alloc_stats();
+    let _ = FileWriter::new(2).write_fmt(format_args!(
+        "max system bytes = {:>10}\nsystem bytes     = {:>10}\nin use bytes     = {:>10}\n",
+        stats.max_system, stats.system, stats.in_use
+    ));
+}
+
+/// See <https://man7.org/linux/man-pages/man3/malloc_trim.3.html>.
+#[unsafe(no_mangle)]
+pub extern "C" fn malloc_trim(pad: size_t) -> c_int {
+    c_int::from(platform::alloc_trim(pad))
+}
+
+/// See <https://man7.org/linux/man-pages/man3/mallopt.3.html>.
+///
+/// Returns `1` on success, and `0` for unsupported parameters or invalid
+/// values.
+#[unsafe(no_mangle)]
+pub extern "C" fn mallopt(param: c_int, value: c_int) -> c_int {
+    c_int::from(platform::set_malloc_option(param, value as isize))
+}
 
 /// See <https://man7.org/linux/man-pages/man3/posix_memalign.3.html>.
 #[deprecated]
 #[unsafe(no_mangle)]
+#[inline(never)]
 pub unsafe extern "C" fn pvalloc(size: size_t) -> *mut c_void {
     let page_size = Sys::getpagesize();
     // Find the smallest multiple of the page size in which the requested size
@@ -32,6 +196,8 @@ pub unsafe extern "C" fn pvalloc(size: size_t) -> *mut c_void {
             if ptr.is_null() {
                 platform::ERRNO.set(ENOMEM);
             }
+            let caller = core::intrinsics::return_address();
+            unsafe { platform::trace_alloc(caller.cast(), ptr, alloc_size) };
             ptr
         }
         None => {
@@ -0,0 +1,25 @@
+//! `mcheck.h` implementation.
+//!
+//! Non-POSIX, see <https://man7.org/linux/man-pages/man3/mtrace.3.html>.
+
+use crate::platform;
+
+// TODO: mcheck(), mcheck_pedantic(), mcheck_check_all(), mprobe()
+
+/// Non-POSIX, see <https://man7.org/linux/man-pages/man3/mtrace.3.html>.
+///
+/// Starts tracing the allocations to the file named by the `MALLOC_TRACE`
+/// environment variable. Does nothing if it is not set, or if allocations are
+/// already traced.
+#[unsafe(no_mangle)]
+pub extern "C" fn mtrace() {
+    platform::start_alloc_trace();
+}
+
+/// Non-POSIX, see <https://man7.org/linux/man-pages/man3/mtrace.3.html>.
+///
+/// Stops tracing the allocations.
+#[unsafe(no_mangle)]
+pub extern "C" fn muntrace() {
+    platform::stop_alloc_trace();
+}
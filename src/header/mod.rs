@@ -110,13 +110,15 @@ pub mod langinfo;
 // TODO: libintl.h
 pub mod libgen;
 pub mod limits;
+pub mod link;
 pub mod locale;
 pub mod malloc;
+pub mod mcheck;
 // TODO unfinished, unguard feature when ready
 #[cfg(feature = "math_libm")]
 pub mod math;
 pub mod monetary;
-// TODO: mqueue.h
+pub mod mqueue;
 // TODO: ndbm.h
 pub mod net_if;
 pub mod netdb;
@@ -159,11 +161,11 @@ pub mod sys_file;
 pub mod sys_ioctl;
 pub mod sys_ipc;
 pub mod sys_mman;
-// TODO: sys/msg.h
+pub mod sys_msg;
 pub mod sys_ptrace;
 pub mod sys_resource;
 pub mod sys_select;
-// TODO: sys/sem.h
+pub mod sys_sem;
 pub mod sys_shm;
 pub mod sys_socket;
 pub mod sys_stat;
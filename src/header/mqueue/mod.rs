@@ -0,0 +1,216 @@
+//! `mqueue.h` implementation.
+//!
+//! See <https://pubs.opengroup.org/onlinepubs/9799919799/basedefs/mqueue.h.html>.
+
+use core::slice;
+
+use crate::{
+    c_str::CStr,
+    error::ResultExt,
+    header::{fcntl::O_CREAT, signal::sigevent, time::timespec},
+    out::Out,
+    platform::{
+        PalMqueue, Sys,
+        types::{c_char, c_int, c_long, c_uint, mode_t, size_t, ssize_t},
+    },
+    pthread,
+};
+
+/// Message queue descriptor.
+pub type mqd_t = c_int;
+
+/// See <https://pubs.opengroup.org/onlinepubs/9799919799/basedefs/mqueue.h.html>.
+///
+/// Message queue attributes.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct mq_attr {
+    /// Message queue flags, either `0` or `O_NONBLOCK`.
+    pub mq_flags: c_long,
+    /// Maximum number of messages.
+    pub mq_maxmsg: c_long,
+    /// Maximum message size.
+    pub mq_msgsize: c_long,
+    /// Number of messages currently queued.
+    pub mq_curmsgs: c_long,
+    __reserved: [c_long; 4],
+}
+
+/// Default maximum number of messages for queues created without attributes.
+pub(crate) const MQ_DEFAULT_MAXMSG: c_long = 10;
+/// Default maximum message size for queues created without attributes.
+pub(crate) const MQ_DEFAULT_MSGSIZE: c_long = 8192;
+
+/// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/mq_close.html>.
+///
+/// Removes the association between the message queue descriptor `mqdes` and
+/// its message queue.
+///
+/// Upon success, returns `0`. Upon failure, returns `-1` and sets errno to
+/// indicate the error.
+#[unsafe(no_mangle)]
+pub extern "C" fn mq_close(mqdes: mqd_t) -> c_int {
+    Sys::mq_close(mqdes).map(|()| 0).or_minus_one_errno()
+}
+
+/// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/mq_getattr.html>.
+///
+/// Obtains the attributes and status of the message queue `mqdes`.
+///
+/// Upon success, returns `0` and writes the attributes to `mqstat`. Upon
+/// failure, returns `-1` and sets errno to indicate the error.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mq_getattr(mqdes: mqd_t, mqstat: *mut mq_attr) -> c_int {
+    Sys::mq_getsetattr(mqdes, None, Some(unsafe { Out::nonnull(mqstat) }))
+        .map(|()| 0)
+        .or_minus_one_errno()
+}
+
+/// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/mq_notify.html>.
+///
+/// Registers the calling process to be notified when a message arrives at the
+/// empty message queue `mqdes`, or removes the registration if
+/// `notification` is a null pointer.
+///
+/// Upon success, returns `0`. Upon failure, returns `-1` and sets errno to
+/// indicate the error.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mq_notify(mqdes: mqd_t, notification: *const sigevent) -> c_int {
+    Sys::mq_notify(mqdes, unsafe { notification.as_ref() })
+        .map(|()| 0)
+        .or_minus_one_errno()
+}
+
+/// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/mq_open.html>.
+///
+/// Establishes a connection between a process and the message queue `name`.
+///
+/// If `O_CREAT` is set in `oflag`, two further arguments are expected: the
+/// `mode_t` permissions of a newly created queue and a pointer to its
+/// `mq_attr` attributes, which may be null to use the defaults.
+///
+/// Upon success, returns a message queue descriptor. Upon failure, returns
+/// `-1` and sets errno to indicate the error.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mq_open(name: *const c_char, oflag: c_int, mut __valist: ...) -> mqd_t {
+    let (mode, attr) = if oflag & O_CREAT == O_CREAT {
+        let mode = unsafe { __valist.next_arg::<mode_t>() };
+        let attr = unsafe { __valist.next_arg::<*const mq_attr>() };
+        (mode, unsafe { attr.as_ref() })
+    } else {
+        (0, None)
+    };
+
+    let name = unsafe { CStr::from_ptr(name) };
+    Sys::mq_open(name, oflag, mode, attr).or_minus_one_errno()
+}
+
+/// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/mq_receive.html>.
+///
+/// Receives the oldest of the highest priority messages from the message
+/// queue `mqdes`.
+///
+/// Upon success, returns the length of the message in bytes, and stores its
+/// priority in `msg_prio` if it is not a null pointer. Upon failure, returns
+/// `-1` and sets errno to indicate the error.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mq_receive(
+    mqdes: mqd_t,
+    msg_ptr: *mut c_char,
+    msg_len: size_t,
+    msg_prio: *mut c_uint,
+) -> ssize_t {
+    unsafe { mq_timedreceive(mqdes, msg_ptr, msg_len, msg_prio, core::ptr::null()) }
+}
+
+/// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/mq_send.html>.
+///
+/// Adds the message pointed to by `msg_ptr` to the message queue `mqdes`.
+///
+/// Upon success, returns `0`. Upon failure, returns `-1` and sets errno to
+/// indicate the error.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mq_send(
+    mqdes: mqd_t,
+    msg_ptr: *const c_char,
+    msg_len: size_t,
+    msg_prio: c_uint,
+) -> c_int {
+    unsafe { mq_timedsend(mqdes, msg_ptr, msg_len, msg_prio, core::ptr::null()) }
+}
+
+/// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/mq_setattr.html>.
+///
+/// Sets the `mq_flags` attribute of the message queue description associated
+/// with `mqdes`. The other members of `mqstat` are ignored.
+///
+/// Upon success, returns `0` and, if `omqstat` is not a null pointer, stores
+/// the previous attributes and status in it. Upon failure, returns `-1` and
+/// sets errno to indicate the error.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mq_setattr(
+    mqdes: mqd_t,
+    mqstat: *const mq_attr,
+    omqstat: *mut mq_attr,
+) -> c_int {
+    Sys::mq_getsetattr(mqdes, Some(unsafe { &*mqstat }), unsafe {
+        Out::nullable(omqstat)
+    })
+    .map(|()| 0)
+    .or_minus_one_errno()
+}
+
+/// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/mq_timedreceive.html>.
+///
+/// Equivalent to `mq_receive()`, except that if the message queue is empty
+/// and `O_NONBLOCK` is not set, the wait is terminated when the absolute
+/// `CLOCK_REALTIME` timeout `abstime` expires.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mq_timedreceive(
+    mqdes: mqd_t,
+    msg_ptr: *mut c_char,
+    msg_len: size_t,
+    msg_prio: *mut c_uint,
+    abstime: *const timespec,
+) -> ssize_t {
+    let msg = unsafe { Out::from_raw_parts(msg_ptr.cast::<u8>(), msg_len) };
+    let abstime = unsafe { abstime.as_ref() };
+    pthread::cancellation_point(|| {
+        Sys::mq_timedreceive(mqdes, msg, unsafe { Out::nullable(msg_prio) }, abstime)
+    })
+    .map(|len| len as ssize_t)
+    .or_minus_one_errno()
+}
+
+/// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/mq_timedsend.html>.
+///
+/// Equivalent to `mq_send()`, except that if the message queue is full and
+/// `O_NONBLOCK` is not set, the wait is terminated when the absolute
+/// `CLOCK_REALTIME` timeout `abstime` expires.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mq_timedsend(
+    mqdes: mqd_t,
+    msg_ptr: *const c_char,
+    msg_len: size_t,
+    msg_prio: c_uint,
+    abstime: *const timespec,
+) -> c_int {
+    let msg = unsafe { slice::from_raw_parts(msg_ptr.cast::<u8>(), msg_len) };
+    let abstime = unsafe { abstime.as_ref() };
+    pthread::cancellation_point(|| Sys::mq_timedsend(mqdes, msg, msg_prio, abstime))
+        .map(|()| 0)
+        .or_minus_one_errno()
+}
+
+/// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/mq_unlink.html>.
+///
+/// Removes the message queue `name`. Descriptors that are still open keep
+/// referring to the queue until they are closed.
+///
+/// Upon success, returns `0`. Upon failure, returns `-1` and sets errno to
+/// indicate the error.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mq_unlink(name: *const c_char) -> c_int {
+    let name = unsafe { CStr::from_ptr(name) };
+    Sys::mq_unlink(name).map(|()| 0).or_minus_one_errno()
+}
@@ -10,6 +10,10 @@ use crate::{
     header::{
         bits_sigset_t::sigset_t,
         errno::{EBADF, EINTR},
+        pthread::{
+            __relibc_internal_pthread_cleanup_pop, __relibc_internal_pthread_cleanup_push,
+            CleanupLinkedListEntry,
+        },
         sys_epoll::{
             EPOLL_CLOEXEC, EPOLL_CTL_ADD, EPOLLERR, EPOLLHUP, EPOLLIN, EPOLLNVAL, EPOLLOUT,
             EPOLLPRI, EPOLLRDBAND, EPOLLRDNORM, EPOLLWRBAND, EPOLLWRNORM, epoll_data, epoll_event,
@@ -17,9 +21,10 @@ use crate::{
         time::timespec,
     },
     platform::{
-        ERRNO, PalEpoll, Sys,
-        types::{c_int, c_short, c_ulong},
+        ERRNO, Pal, PalEpoll, Sys,
+        types::{c_int, c_short, c_ulong, c_void},
     },
+    pthread,
 };
 
 /// Data other than high-priority data may be read without blocking.
@@ -123,8 +128,16 @@ pub unsafe fn poll_epoll(fds: &mut [pollfd], timeout: c_int, sigmask: *const sig
         return closed as i32;
     }
 
+    // The wait may act upon a cancellation request of poll or ppoll, which must not leak the
+    // epoll instance.
+    extern "C" fn close_epoll(epfd: *mut c_void) {
+        let _ = Sys::close(epfd as usize as c_int);
+    }
+    let mut entry = CleanupLinkedListEntry::new(close_epoll, *ep as usize as *mut c_void);
+
     let mut events: [epoll_event; 32] = unsafe { mem::zeroed() };
-    match unsafe {
+    unsafe { __relibc_internal_pthread_cleanup_push(ptr::from_mut(&mut entry).cast()) };
+    let result = unsafe {
         Sys::epoll_pwait(
             *ep,
             events.as_mut_ptr(),
@@ -132,7 +145,9 @@ pub unsafe fn poll_epoll(fds: &mut [pollfd], timeout: c_int, sigmask: *const sig
             timeout,
             sigmask,
         )
-    } {
+    };
+    unsafe { __relibc_internal_pthread_cleanup_pop(0) };
+    match result {
         Ok(res) => {
             for event in events.iter().take(res) {
                 let pi = unsafe { event.data.u64 as usize };
@@ -178,13 +193,13 @@ pub unsafe fn poll_epoll(fds: &mut [pollfd], timeout: c_int, sigmask: *const sig
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn poll(fds: *mut pollfd, nfds: nfds_t, timeout: c_int) -> c_int {
     trace_expr!(
-        unsafe {
+        pthread::cancellation_point(|| unsafe {
             poll_epoll(
                 slice::from_raw_parts_mut(fds, nfds as usize),
                 timeout,
                 ptr::null_mut(),
             )
-        },
+        }),
         "poll({:p}, {}, {})",
         fds,
         nfds,
@@ -219,13 +234,13 @@ pub unsafe extern "C" fn ppoll(
         }
     };
     trace_expr!(
-        unsafe {
+        pthread::cancellation_point(|| unsafe {
             poll_epoll(
                 slice::from_raw_parts_mut(fds, nfds as usize),
                 timeout,
                 sigmask,
             )
-        },
+        }),
         "ppoll({:p}, {}, {:p}, {:p})",
         fds,
         nfds,
@@ -8,7 +8,7 @@ use crate::{
             PTHREAD_CREATE_DETACHED, PTHREAD_CREATE_JOINABLE, PTHREAD_EXPLICIT_SCHED,
             PTHREAD_INHERIT_SCHED, PTHREAD_SCOPE_PROCESS, PTHREAD_SCOPE_SYSTEM, RlctAttr,
         },
-        sched::{SCHED_FIFO, SCHED_OTHER, SCHED_RR, sched_param},
+        sched::{self, SCHED_OTHER, sched_param},
     },
     platform::{
         Pal, Sys,
@@ -24,9 +24,7 @@ impl Default for RlctAttr {
             detachstate: PTHREAD_CREATE_JOINABLE as _,
             // Default according to POSIX.
             inheritsched: PTHREAD_INHERIT_SCHED as _,
-            // TODO: Linux
-            // Redox uses a round-robin scheduler
-            schedpolicy: SCHED_RR as _,
+            schedpolicy: SCHED_OTHER as _,
             // TODO: Linux uses this one.
             scope: PTHREAD_SCOPE_SYSTEM as _,
             guardsize: Sys::getpagesize(),
@@ -34,10 +32,7 @@ impl Default for RlctAttr {
             stack: 0,
             // TODO
             stacksize: 1024 * 1024,
-            param: sched_param {
-                // TODO
-                sched_priority: 0,
-            },
+            param: sched_param { sched_priority: 0 },
             #[cfg(target_pointer_width = "32")]
             _pad: [0; 12],
         }
@@ -376,19 +371,17 @@ pub unsafe extern "C" fn pthread_attr_setschedpolicy(
     attr: *mut pthread_attr_t,
     policy: c_int,
 ) -> c_int {
-    match policy {
-        SCHED_FIFO | SCHED_OTHER | SCHED_RR => {
-            // infallible, value of constants fit into `c_uchar`
-            if let Ok(pol) = policy.try_into() {
-                // SAFTEY: guaranteed to fit
-                unsafe {
-                    (*attr.cast::<RlctAttr>()).schedpolicy = pol;
-                }
-            }
-            0
+    if !sched::is_valid_policy(policy) {
+        return crate::header::errno::ENOTSUP;
+    }
+    // infallible, value of constants fit into `c_uchar`
+    if let Ok(pol) = policy.try_into() {
+        // SAFTEY: guaranteed to fit
+        unsafe {
+            (*attr.cast::<RlctAttr>()).schedpolicy = pol;
         }
-        _ => crate::header::errno::ENOTSUP,
     }
+    0
 }
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/pthread_attr_setscope.html>.
@@ -495,6 +488,9 @@ pub unsafe extern "C" fn pthread_getattr_np(thread: pthread_t, attr: *mut pthrea
     }
     attr.stack = thread.stack_base as usize;
     attr.stacksize = thread.stack_size;
+    attr.inheritsched = PTHREAD_EXPLICIT_SCHED as _;
+    attr.schedpolicy = thread.sched_policy.load(Ordering::Acquire) as _;
+    attr.param.sched_priority = thread.sched_priority.load(Ordering::Acquire);
     //TODO: more values?
     0
 }
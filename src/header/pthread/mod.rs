@@ -79,6 +79,17 @@ pub const PTHREAD_PRIO_PROTECT: c_int = 0;
 pub const PTHREAD_PROCESS_SHARED: c_int = 0;
 pub const PTHREAD_PROCESS_PRIVATE: c_int = 1;
 
+/// Non-POSIX. Read-write lock kind where readers may acquire the lock while
+/// writers are waiting. This is the default.
+pub const PTHREAD_RWLOCK_PREFER_READER_NP: c_int = 0;
+/// Non-POSIX. Read-write lock kind where waiting writers block new readers.
+pub const PTHREAD_RWLOCK_PREFER_WRITER_NP: c_int = 1;
+/// Non-POSIX. Same as `PTHREAD_RWLOCK_PREFER_WRITER_NP`, which in relibc
+/// already does not support a thread recursively acquiring read locks while a
+/// writer is waiting.
+pub const PTHREAD_RWLOCK_PREFER_WRITER_NONRECURSIVE_NP: c_int = 2;
+pub const PTHREAD_RWLOCK_DEFAULT_NP: c_int = PTHREAD_RWLOCK_PREFER_READER_NP;
+
 /// Signifies process scheduling contention scope.
 pub const PTHREAD_SCOPE_PROCESS: c_int = 0;
 /// Signifies system scheduling contention scope.
@@ -216,7 +227,7 @@ pub use tls::*;
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/pthread_join.html>.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn pthread_join(thread: pthread_t, retval: *mut *mut c_void) -> c_int {
-    match unsafe { pthread::join(&*thread.cast()) } {
+    match pthread::cancellation_point(|| unsafe { pthread::join(&*thread.cast()) }) {
         Ok(pthread::Retval(ret)) => {
             if !retval.is_null() {
                 unsafe { core::ptr::write(retval, ret) };
@@ -322,6 +333,16 @@ pub(crate) struct CleanupLinkedListEntry {
     prev: *const c_void,
 }
 
+impl CleanupLinkedListEntry {
+    pub(crate) const fn new(routine: extern "C" fn(*mut c_void), arg: *mut c_void) -> Self {
+        Self {
+            routine,
+            arg,
+            prev: core::ptr::null(),
+        }
+    }
+}
+
 #[thread_local]
 pub(crate) static CLEANUP_LL_HEAD: Cell<*const CleanupLinkedListEntry> =
     Cell::new(core::ptr::null());
@@ -346,9 +367,9 @@ pub unsafe extern "C" fn __relibc_internal_pthread_cleanup_pop(execute: c_int) {
 }
 
 pub(crate) unsafe fn run_destructor_stack() {
-    unsafe { crate::cxa::__cxa_thread_finalize() };
-
+    // Cancellation cleanup handlers run before thread-local destructors.
     let mut ptr = CLEANUP_LL_HEAD.get();
+    CLEANUP_LL_HEAD.set(core::ptr::null());
 
     while !ptr.is_null() {
         let entry = unsafe { ptr.read() };
@@ -356,4 +377,6 @@ pub(crate) unsafe fn run_destructor_stack() {
 
         (entry.routine)(entry.arg);
     }
+
+    unsafe { crate::cxa::__cxa_thread_finalize() };
 }
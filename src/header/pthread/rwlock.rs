@@ -2,7 +2,7 @@ use super::*;
 
 use crate::header::errno::{EBUSY, EINVAL};
 
-use crate::{header::time::CLOCK_REALTIME, pthread::Pshared};
+use crate::{header::time::CLOCK_REALTIME, pthread::Pshared, sync::rwlock::Preference};
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/pthread_rwlock_init.html>.
 #[unsafe(no_mangle)]
@@ -17,7 +17,7 @@ pub unsafe extern "C" fn pthread_rwlock_init(
     unsafe {
         rwlock
             .cast::<RlctRwlock>()
-            .write(RlctRwlock::new(attr.pshared))
+            .write(RlctRwlock::new(attr.pshared(), attr.preference()))
     };
 
     0
@@ -125,7 +125,12 @@ pub unsafe extern "C" fn pthread_rwlockattr_getpshared(
     attr: *const pthread_rwlockattr_t,
     pshared_out: *mut c_int,
 ) -> c_int {
-    unsafe { core::ptr::write(pshared_out, (*attr.cast::<RlctRwlockAttr>()).pshared.raw()) };
+    unsafe {
+        core::ptr::write(
+            pshared_out,
+            (*attr.cast::<RlctRwlockAttr>()).pshared().raw(),
+        )
+    };
 
     0
 }
@@ -140,7 +145,55 @@ pub unsafe extern "C" fn pthread_rwlockattr_setpshared(
         return EINVAL;
     };
 
-    (unsafe { *attr.cast::<RlctRwlockAttr>() }).pshared = pshared;
+    unsafe { (*attr.cast::<RlctRwlockAttr>()).set_pshared(pshared) };
+    0
+}
+
+/// Non-POSIX, see <https://www.man7.org/linux/man-pages/man3/pthread_rwlockattr_getkind_np.3.html>.
+///
+/// Obtains the value of the kind attribute, i.e. whether readers or writers
+/// are preferred, from the attributes object referenced by `attr`.
+///
+/// # Implementation
+/// Always succeeds, so will never return an error number.
+/// `PTHREAD_RWLOCK_PREFER_WRITER_NONRECURSIVE_NP` is reported as
+/// `PTHREAD_RWLOCK_PREFER_WRITER_NP`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_rwlockattr_getkind_np(
+    attr: *const pthread_rwlockattr_t,
+    pref: *mut c_int,
+) -> c_int {
+    let kind = match unsafe { (*attr.cast::<RlctRwlockAttr>()).preference() } {
+        Preference::Reader => PTHREAD_RWLOCK_PREFER_READER_NP,
+        Preference::Writer => PTHREAD_RWLOCK_PREFER_WRITER_NP,
+    };
+    unsafe { core::ptr::write(pref, kind) };
+
+    0
+}
+
+/// Non-POSIX, see <https://www.man7.org/linux/man-pages/man3/pthread_rwlockattr_setkind_np.3.html>.
+///
+/// Sets the kind attribute in the attributes object referenced by `attr`,
+/// selecting whether read-write locks initialized with it prefer readers or
+/// writers.
+///
+/// Upon success, returns `0`. Upon failure, returns `EINVAL` if `pref` is not
+/// a valid kind.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_rwlockattr_setkind_np(
+    attr: *mut pthread_rwlockattr_t,
+    pref: c_int,
+) -> c_int {
+    let preference = match pref {
+        PTHREAD_RWLOCK_PREFER_READER_NP => Preference::Reader,
+        PTHREAD_RWLOCK_PREFER_WRITER_NP | PTHREAD_RWLOCK_PREFER_WRITER_NONRECURSIVE_NP => {
+            Preference::Writer
+        }
+        _ => return EINVAL,
+    };
+
+    unsafe { (*attr.cast::<RlctRwlockAttr>()).set_preference(preference) };
     0
 }
 
@@ -161,9 +214,42 @@ pub unsafe extern "C" fn pthread_rwlock_destroy(rwlock: *mut pthread_rwlock_t) -
 
 pub(crate) type RlctRwlock = crate::sync::rwlock::InnerRwLock;
 
+// Both attributes are packed into a single byte, so that the size of pthread_rwlockattr_t stays
+// the same.
 #[derive(Clone, Copy, Default)]
 pub(crate) struct RlctRwlockAttr {
-    pshared: Pshared,
+    flags: u8,
+}
+impl RlctRwlockAttr {
+    const SHARED: u8 = 1 << 0;
+    const PREFER_WRITER: u8 = 1 << 1;
+
+    fn pshared(self) -> Pshared {
+        if self.flags & Self::SHARED != 0 {
+            Pshared::Shared
+        } else {
+            Pshared::Private
+        }
+    }
+    fn set_pshared(&mut self, pshared: Pshared) {
+        match pshared {
+            Pshared::Private => self.flags &= !Self::SHARED,
+            Pshared::Shared => self.flags |= Self::SHARED,
+        }
+    }
+    fn preference(self) -> Preference {
+        if self.flags & Self::PREFER_WRITER != 0 {
+            Preference::Writer
+        } else {
+            Preference::Reader
+        }
+    }
+    fn set_preference(&mut self, preference: Preference) {
+        match preference {
+            Preference::Reader => self.flags &= !Self::PREFER_WRITER,
+            Preference::Writer => self.flags |= Self::PREFER_WRITER,
+        }
+    }
 }
 #[inline]
 unsafe fn get<'a>(ptr: *mut pthread_rwlock_t) -> &'a RlctRwlock {
@@ -3,10 +3,11 @@
 //! See <https://pubs.opengroup.org/onlinepubs/9799919799/basedefs/sched.h.html>.
 
 use crate::{
-    error::ResultExt,
-    header::time::timespec,
+    error::{Errno, ResultExt},
+    header::{errno::EINVAL, time::timespec},
+    out::Out,
     platform::{
-        Pal, Sys,
+        ERRNO, Pal, Sys,
         types::{c_int, pid_t},
     },
 };
@@ -26,60 +27,157 @@ pub struct sched_param {
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/basedefs/sched.h.html>.
 ///
 /// First in first out (FIFO) scheduling policy.
+#[cfg(target_os = "redox")]
 pub const SCHED_FIFO: c_int = 0;
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/basedefs/sched.h.html>.
 ///
 /// Round robin scheduling policy.
+#[cfg(target_os = "redox")]
 pub const SCHED_RR: c_int = 1;
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/basedefs/sched.h.html>.
 ///
 /// Another scheduling policy.
+#[cfg(target_os = "redox")]
 pub const SCHED_OTHER: c_int = 2;
 
+/// See <https://pubs.opengroup.org/onlinepubs/9799919799/basedefs/sched.h.html>.
+///
+/// Another scheduling policy.
+#[cfg(target_os = "linux")]
+pub const SCHED_OTHER: c_int = 0;
+/// See <https://pubs.opengroup.org/onlinepubs/9799919799/basedefs/sched.h.html>.
+///
+/// First in first out (FIFO) scheduling policy.
+#[cfg(target_os = "linux")]
+pub const SCHED_FIFO: c_int = 1;
+/// See <https://pubs.opengroup.org/onlinepubs/9799919799/basedefs/sched.h.html>.
+///
+/// Round robin scheduling policy.
+#[cfg(target_os = "linux")]
+pub const SCHED_RR: c_int = 2;
+/// Non-POSIX, see <https://man7.org/linux/man-pages/man7/sched.7.html>.
+///
+/// Scheduling policy for batch-style, CPU-intensive processes.
+#[cfg(target_os = "linux")]
+pub const SCHED_BATCH: c_int = 3;
+/// Non-POSIX, see <https://man7.org/linux/man-pages/man7/sched.7.html>.
+///
+/// Scheduling policy for very low priority background jobs.
+#[cfg(target_os = "linux")]
+pub const SCHED_IDLE: c_int = 5;
+
+/// Returns whether `policy` is a scheduling policy supported on this platform.
+pub(crate) fn is_valid_policy(policy: c_int) -> bool {
+    match policy {
+        SCHED_FIFO | SCHED_RR | SCHED_OTHER => true,
+        #[cfg(target_os = "linux")]
+        SCHED_BATCH | SCHED_IDLE => true,
+        _ => false,
+    }
+}
+
+/// Checks `param` against the priority range of `policy`.
+pub(crate) fn check_param(policy: c_int, param: &sched_param) -> Result<(), Errno> {
+    let min = Sys::sched_get_priority_min(policy)?;
+    let max = Sys::sched_get_priority_max(policy)?;
+    if (min..=max).contains(&param.sched_priority) {
+        Ok(())
+    } else {
+        Err(Errno(EINVAL))
+    }
+}
+
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/sched_get_priority_max.html>.
-// #[unsafe(no_mangle)]
-#[expect(unused_variables, reason = "function not yet implemented")]
+///
+/// Returns the maximum priority value for the scheduling policy `policy`.
+#[unsafe(no_mangle)]
 pub extern "C" fn sched_get_priority_max(policy: c_int) -> c_int {
-    todo!()
+    Sys::sched_get_priority_max(policy).or_minus_one_errno()
 }
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/sched_get_priority_max.html>.
-// #[unsafe(no_mangle)]
-#[expect(unused_variables, reason = "function not yet implemented")]
+///
+/// Returns the minimum priority value for the scheduling policy `policy`.
+#[unsafe(no_mangle)]
 pub extern "C" fn sched_get_priority_min(policy: c_int) -> c_int {
-    todo!()
+    Sys::sched_get_priority_min(policy).or_minus_one_errno()
 }
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/sched_getparam.html>.
-// #[unsafe(no_mangle)]
-#[expect(unused_variables, reason = "function not yet implemented")]
+///
+/// Stores the scheduling parameters of the process `pid` (or of the calling
+/// process if `pid` is `0`) into `param`.
+#[unsafe(no_mangle)]
 pub unsafe extern "C" fn sched_getparam(pid: pid_t, param: *mut sched_param) -> c_int {
-    todo!()
+    if param.is_null() {
+        ERRNO.set(EINVAL);
+        return -1;
+    }
+    Sys::sched_getparam(pid, unsafe { Out::nonnull(param) })
+        .map(|()| 0)
+        .or_minus_one_errno()
+}
+
+/// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/sched_getscheduler.html>.
+///
+/// Returns the scheduling policy of the process `pid` (or of the calling
+/// process if `pid` is `0`).
+#[unsafe(no_mangle)]
+pub extern "C" fn sched_getscheduler(pid: pid_t) -> c_int {
+    Sys::sched_getscheduler(pid).or_minus_one_errno()
 }
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/sched_rr_get_interval.html>.
-// #[unsafe(no_mangle)]
-#[expect(unused_variables, reason = "function not yet implemented")]
-pub extern "C" fn sched_rr_get_interval(pid: pid_t, time: *const timespec) -> c_int {
-    todo!()
+///
+/// Stores the round robin time quantum of the process `pid` (or of the
+/// calling process if `pid` is `0`) into `time`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sched_rr_get_interval(pid: pid_t, time: *mut timespec) -> c_int {
+    if time.is_null() {
+        ERRNO.set(EINVAL);
+        return -1;
+    }
+    Sys::sched_rr_get_interval(pid, unsafe { Out::nonnull(time) })
+        .map(|()| 0)
+        .or_minus_one_errno()
 }
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/sched_setparam.html>.
-// #[unsafe(no_mangle)]
-#[expect(unused_variables, reason = "function not yet implemented")]
+///
+/// Sets the scheduling parameters of the process `pid` (or of the calling
+/// process if `pid` is `0`) to `param`, keeping its scheduling policy.
+#[unsafe(no_mangle)]
 pub unsafe extern "C" fn sched_setparam(pid: pid_t, param: *const sched_param) -> c_int {
-    todo!()
+    let Some(param) = (unsafe { param.as_ref() }) else {
+        ERRNO.set(EINVAL);
+        return -1;
+    };
+    Sys::sched_setparam(pid, param)
+        .map(|()| 0)
+        .or_minus_one_errno()
 }
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/sched_setscheduler.html>.
-// #[unsafe(no_mangle)]
-#[expect(unused_variables, reason = "function not yet implemented")]
-pub extern "C" fn sched_setscheduler(
+///
+/// Sets the scheduling policy and parameters of the process `pid` (or of the
+/// calling process if `pid` is `0`).
+///
+/// Upon success, returns the former scheduling policy of the process.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sched_setscheduler(
     pid: pid_t,
     policy: c_int,
     param: *const sched_param,
 ) -> c_int {
-    todo!()
+    let Some(param) = (unsafe { param.as_ref() }) else {
+        ERRNO.set(EINVAL);
+        return -1;
+    };
+    if !is_valid_policy(policy) {
+        ERRNO.set(EINVAL);
+        return -1;
+    }
+    Sys::sched_setscheduler(pid, policy, param).or_minus_one_errno()
 }
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/sched_yield.html>.
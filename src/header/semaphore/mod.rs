@@ -2,16 +2,28 @@
 //!
 //! See <https://pubs.opengroup.org/onlinepubs/9799919799/basedefs/semaphore.h.html>.
 
+use alloc::vec::Vec;
+use core::{mem::size_of, ptr, slice};
+
 use crate::{
-    error::ResultExt,
+    c_str::{CStr, CString},
+    error::{Errno, Result, ResultExt},
     header::{
         errno,
+        fcntl::{O_CLOEXEC, O_CREAT, O_EXCL, O_NOFOLLOW, O_RDWR},
+        limits::{NAME_MAX, SEM_VALUE_MAX},
+        stdlib,
+        sys_mman::{MAP_SHARED, PROT_READ, PROT_WRITE, shm_path},
+        sys_stat::stat,
         time::{self, timespec},
     },
+    out::Out,
     platform::{
-        self,
-        types::{c_char, c_int, c_long, c_uint, clockid_t},
+        self, Pal, Sys,
+        types::{c_char, c_int, c_long, c_uint, clockid_t, dev_t, ino_t, mode_t},
     },
+    pthread,
+    sync::Mutex,
 };
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/basedefs/semaphore.h.html>.
@@ -24,11 +36,170 @@ pub union sem_t {
 }
 pub type RlctSempahore = crate::sync::Semaphore;
 
+/// Return value of `sem_open()` on failure.
+#[expect(
+    clippy::zero_ptr,
+    reason = "cbindgen issue: https://github.com/mozilla/cbindgen/issues/948"
+)]
+pub const SEM_FAILED: *mut sem_t = 0 as *mut sem_t; // XXX: cbindgen doesn't like ptr::null_mut() for publically exported constants
+
+/// A named semaphore mapped into this process.
+///
+/// Opening the same semaphore several times must return the same address, so
+/// the mappings are shared and counted.
+struct Named {
+    dev: dev_t,
+    ino: ino_t,
+    sem: usize,
+    refs: usize,
+}
+
+static NAMED: Mutex<Vec<Named>> = Mutex::new(Vec::new());
+
+const FLAGS: c_int = O_RDWR | O_CLOEXEC | O_NOFOLLOW;
+
+/// Returns the path of the shared memory object backing the semaphore `name`.
+fn named_path(name: CStr) -> Result<CString> {
+    let name = name.to_bytes();
+    let name = &name[name.iter().take_while(|&&b| b == b'/').count()..];
+    if name.is_empty() || name.contains(&b'/') {
+        return Err(Errno(errno::EINVAL));
+    }
+    if name.len() + 4 > NAME_MAX {
+        return Err(Errno(errno::ENAMETOOLONG));
+    }
+
+    let mut sem_name = Vec::with_capacity(4 + name.len());
+    sem_name.extend_from_slice(b"sem.");
+    sem_name.extend_from_slice(name);
+    let sem_name = CString::new(sem_name).unwrap();
+    Ok(unsafe { shm_path(sem_name.as_ptr()) })
+}
+
+fn open_named(name: CStr, oflag: c_int, mode: mode_t, value: c_uint) -> Result<*mut sem_t> {
+    let path = named_path(name)?;
+    let path = CStr::borrow(&path);
+
+    let create = oflag & O_CREAT == O_CREAT;
+    let exclusive = create && oflag & O_EXCL == O_EXCL;
+    if create && value > SEM_VALUE_MAX as c_uint {
+        return Err(Errno(errno::EINVAL));
+    }
+
+    loop {
+        if !exclusive {
+            match Sys::open(path, FLAGS, 0) {
+                Ok(fd) => {
+                    let res = map_named(fd);
+                    let _ = Sys::close(fd);
+                    return res;
+                }
+                Err(Errno(errno::ENOENT)) if create => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        match create_named(path, mode, value) {
+            // Another process created it in the meantime.
+            Err(Errno(errno::EEXIST)) if !exclusive => continue,
+            res => return res,
+        }
+    }
+}
+
+/// Creates the semaphore at `path`, failing with `EEXIST` if it already exists.
+///
+/// As in glibc, the semaphore is initialized in a temporary file which is then linked into place,
+/// so that other processes never open a partially initialized semaphore.
+fn create_named(path: CStr, mode: mode_t, value: c_uint) -> Result<*mut sem_t> {
+    let mut tmp = unsafe { shm_path(c"sem.XXXXXX".as_ptr()) }.into_bytes_with_nul();
+    let tmp_ptr = tmp.as_mut_ptr().cast::<c_char>();
+    let mut err = Errno(errno::EEXIST);
+    let fd = stdlib::inner_mktemp(tmp_ptr, 0, || {
+        Sys::open(
+            unsafe { CStr::from_ptr(tmp_ptr) },
+            FLAGS | O_CREAT | O_EXCL,
+            mode,
+        )
+        .map_err(|e| err = e)
+        .ok()
+    })
+    .ok_or(err)?;
+    let tmp = unsafe { CStr::from_ptr(tmp_ptr) };
+
+    let res = init_named(fd, value)
+        .and_then(|()| Sys::link(tmp, path))
+        .and_then(|()| map_named(fd));
+    let _ = Sys::unlink(tmp);
+    let _ = Sys::close(fd);
+    res
+}
+
+fn init_named(fd: c_int, value: c_uint) -> Result<()> {
+    let mut sem = sem_t { align: 0 };
+    unsafe {
+        ptr::from_mut(&mut sem)
+            .cast::<RlctSempahore>()
+            .write(RlctSempahore::new(value))
+    };
+    let bytes =
+        unsafe { slice::from_raw_parts(ptr::from_ref(&sem).cast::<u8>(), size_of::<sem_t>()) };
+    if Sys::write(fd, bytes)? != bytes.len() {
+        return Err(Errno(errno::ENOSPC));
+    }
+    Ok(())
+}
+
+fn map_named(fd: c_int) -> Result<*mut sem_t> {
+    let mut st = stat::default();
+    Sys::fstat(fd, Out::from_mut(&mut st))?;
+    if st.st_size < size_of::<sem_t>() as _ {
+        return Err(Errno(errno::EINVAL));
+    }
+
+    let mut named = NAMED.lock();
+    if let Some(entry) = named
+        .iter_mut()
+        .find(|entry| entry.dev == st.st_dev && entry.ino == st.st_ino)
+    {
+        entry.refs += 1;
+        return Ok(entry.sem as *mut sem_t);
+    }
+
+    let sem = unsafe {
+        Sys::mmap(
+            ptr::null_mut(),
+            size_of::<sem_t>(),
+            PROT_READ | PROT_WRITE,
+            MAP_SHARED,
+            fd,
+            0,
+        )?
+    };
+    named.push(Named {
+        dev: st.st_dev,
+        ino: st.st_ino,
+        sem: sem as usize,
+        refs: 1,
+    });
+    Ok(sem.cast())
+}
+
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/sem_close.html>.
-// #[unsafe(no_mangle)]
-#[expect(unused_variables, reason = "function not yet implemented")]
+#[unsafe(no_mangle)]
 pub unsafe extern "C" fn sem_close(sem: *mut sem_t) -> c_int {
-    todo!("named semaphores")
+    let mut named = NAMED.lock();
+    let Some(i) = named.iter().position(|entry| entry.sem == sem as usize) else {
+        platform::ERRNO.set(errno::EINVAL);
+        return -1;
+    };
+
+    named[i].refs -= 1;
+    if named[i].refs == 0 {
+        named.swap_remove(i);
+        let _ = unsafe { Sys::munmap(sem.cast(), size_of::<sem_t>()) };
+    }
+    0
 }
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/sem_destroy.html>.
@@ -55,14 +226,23 @@ pub unsafe extern "C" fn sem_init(sem: *mut sem_t, _pshared: c_int, value: c_uin
 }
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/sem_open.html>.
-// TODO: va_list
-// #[unsafe(no_mangle)]
-#[expect(unused_variables, reason = "function not yet implemented")]
+#[unsafe(no_mangle)]
 pub unsafe extern "C" fn sem_open(
     name: *const c_char,
-    oflag: c_int, /* (va_list) value: c_uint */
+    oflag: c_int,
+    mut __valist: ...
 ) -> *mut sem_t {
-    todo!("named semaphores")
+    let (mode, value) = if oflag & O_CREAT == O_CREAT {
+        unsafe { (__valist.next_arg::<mode_t>(), __valist.next_arg::<c_uint>()) }
+    } else {
+        (0, 0)
+    };
+
+    let name = unsafe { CStr::from_ptr(name) };
+    open_named(name, oflag, mode, value).unwrap_or_else(|Errno(errno)| {
+        platform::ERRNO.set(errno);
+        SEM_FAILED
+    })
 }
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/sem_post.html>.
@@ -85,17 +265,19 @@ pub unsafe extern "C" fn sem_trywait(sem: *mut sem_t) -> c_int {
 }
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/sem_unlink.html>.
-// #[unsafe(no_mangle)]
-#[expect(unused_variables, reason = "function not yet implemented")]
+#[unsafe(no_mangle)]
 pub unsafe extern "C" fn sem_unlink(name: *const c_char) -> c_int {
-    todo!("named semaphores")
+    let name = unsafe { CStr::from_ptr(name) };
+    named_path(name)
+        .and_then(|path| Sys::unlink(CStr::borrow(&path)))
+        .map(|()| 0)
+        .or_minus_one_errno()
 }
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/sem_trywait.html>.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn sem_wait(sem: *mut sem_t) -> c_int {
-    unsafe { get(sem) }
-        .wait(None, time::CLOCK_MONOTONIC)
+    pthread::cancellation_point(|| unsafe { get(sem) }.wait(None, time::CLOCK_MONOTONIC))
         .map(|()| 0)
         .or_minus_one_errno()
 }
@@ -107,19 +289,21 @@ pub unsafe extern "C" fn sem_clockwait(
     clock_id: clockid_t,
     abstime: *const timespec,
 ) -> c_int {
-    unsafe { get(sem) }
-        .wait(Some(&unsafe { (*abstime).clone() }), clock_id)
-        .map(|()| 0)
-        .or_minus_one_errno()
+    pthread::cancellation_point(|| {
+        unsafe { get(sem) }.wait(Some(&unsafe { (*abstime).clone() }), clock_id)
+    })
+    .map(|()| 0)
+    .or_minus_one_errno()
 }
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/sem_timedwait.html>.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn sem_timedwait(sem: *mut sem_t, abstime: *const timespec) -> c_int {
-    unsafe { get(sem) }
-        .wait(Some(&unsafe { (*abstime).clone() }), time::CLOCK_REALTIME)
-        .map(|()| 0)
-        .or_minus_one_errno()
+    pthread::cancellation_point(|| {
+        unsafe { get(sem) }.wait(Some(&unsafe { (*abstime).clone() }), time::CLOCK_REALTIME)
+    })
+    .map(|()| 0)
+    .or_minus_one_errno()
 }
 
 unsafe fn get<'any>(sem: *mut sem_t) -> &'any RlctSempahore {
@@ -1,5 +1,5 @@
 use super::{sigset_t, stack_t};
-use crate::platform::types::{c_longlong, c_uchar, c_uint, c_ulong, c_ulonglong, c_ushort};
+use crate::platform::types::{c_int, c_longlong, c_uchar, c_uint, c_ulong, c_ulonglong, c_ushort};
 use core::arch::global_asm;
 
 // Needs to be defined in assembly because it can't have a function prologue
@@ -49,6 +49,12 @@ pub const SIGPOLL: usize = super::constants::SIGIO;
 /// Synonymous with `SIGSYS`.
 pub const SIGUNUSED: usize = super::constants::SIGSYS;
 
+/// Non-POSIX, see <https://www.man7.org/linux/man-pages/man2/timer_create.2.html>.
+///
+/// A signal is sent to the thread given by the kernel's
+/// `sigev_notify_thread_id`, which relibc only uses internally.
+pub const SIGEV_THREAD_ID: c_int = 4;
+
 // Below SA_* constants cannot share the same values as Redox for implementation reasons.
 /// Do not generate `SIGCHLD` when children stop or stopped children continue.
 pub const SA_NOCLDSTOP: usize = 1;
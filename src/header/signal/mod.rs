@@ -6,15 +6,16 @@ use core::{mem, ptr};
 
 use cbitset::BitSet;
 
-#[cfg(target_os = "redox")]
-use crate::platform::types::pthread_attr_t;
 use crate::{
     error::{Errno, ResultExt},
     header::{bits_sigset_t::sigset_t, errno, time::timespec},
     platform::{
         self, ERRNO, Pal, PalSignal, Sys,
-        types::{c_char, c_int, c_ulonglong, c_void, pid_t, pthread_t, size_t, uid_t},
+        types::{
+            c_char, c_int, c_ulonglong, c_void, pid_t, pthread_attr_t, pthread_t, size_t, uid_t,
+        },
     },
+    pthread,
 };
 
 pub mod constants;
@@ -137,13 +138,16 @@ pub struct sigevent {
     pub sigev_signo: c_int,
     /// Notification type.
     pub sigev_notify: c_int,
-    // Actually a union.  We only expose sigev_notify_thread_id because it's
-    // the most useful member
-    pub sigev_notify_thread_id: c_int,
+    // Actually a union with the thread ID for `SIGEV_THREAD_ID`, which only
+    // relibc uses internally.
+    /// Notification function.
+    pub sigev_notify_function: Option<extern "C" fn(sigval)>,
+    /// Notification attributes.
+    pub sigev_notify_attributes: *mut pthread_attr_t,
     #[cfg(target_pointer_width = "64")]
-    __unused1: [c_int; 11],
+    __unused1: [c_int; 8],
     #[cfg(target_pointer_width = "32")]
-    __unused1: [c_int; 12],
+    __unused1: [c_int; 11],
 }
 
 // FIXME: This struct is wrong on Linux
@@ -745,7 +749,10 @@ pub unsafe extern "C" fn sigset(
 /// the error.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn sigsuspend(sigmask: *const sigset_t) -> c_int {
-    Err(Sys::sigsuspend(unsafe { &*sigmask })).or_minus_one_errno()
+    Err(pthread::cancellation_point(|| {
+        Sys::sigsuspend(unsafe { &*sigmask })
+    }))
+    .or_minus_one_errno()
 }
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/sigwait.html>.
@@ -790,8 +797,10 @@ pub unsafe extern "C" fn sigtimedwait(
     // to differentiate between sigtimedwait and sigwaitinfo internally
     tp: *const timespec,
 ) -> c_int {
-    Sys::sigtimedwait(unsafe { &*set }, unsafe { sig.as_mut() }, unsafe {
-        tp.as_ref()
+    pthread::cancellation_point(|| {
+        Sys::sigtimedwait(unsafe { &*set }, unsafe { sig.as_mut() }, unsafe {
+            tp.as_ref()
+        })
     })
     .or_minus_one_errno()
 }
@@ -9,7 +9,7 @@ use crate::{
     header::{
         bits_sigset_t::sigset_t,
         errno::EINVAL,
-        sched::{SCHED_FIFO, SCHED_OTHER, SCHED_RR, sched_param},
+        sched::{self, SCHED_OTHER, sched_param},
     },
     platform::types::pid_t,
 };
@@ -41,7 +41,7 @@ pub struct posix_spawnattr_t {
     pub param: sched_param,
     pub flags: c_short,
     pub pgroup: c_int,
-    policy: c_int,
+    pub policy: c_int,
     pub sigdefault: sigset_t,
     pub sigmask: sigset_t,
 }
@@ -60,6 +60,7 @@ pub extern "C" fn posix_spawnattr_init(attr: *mut posix_spawnattr_t) -> c_int {
     unsafe {
         let attr = attr.as_mut().expect("posix_spawnattr_t cannot be NULL");
         *attr = zeroed();
+        attr.policy = SCHED_OTHER;
     }
     0
 }
@@ -154,10 +155,10 @@ pub unsafe extern "C" fn posix_spawnattr_setschedpolicy(
     schedpolicy: c_int,
 ) -> c_int {
     let attr = unsafe { attr.as_mut().expect("posix_spawnattr_t cannot be NULL") };
-    match schedpolicy {
-        SCHED_FIFO | SCHED_RR | SCHED_OTHER => attr.policy = schedpolicy,
-        _ => return EINVAL,
+    if !sched::is_valid_policy(schedpolicy) {
+        return EINVAL;
     }
+    attr.policy = schedpolicy;
 
     0
 }
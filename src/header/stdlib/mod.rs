@@ -37,6 +37,7 @@ use crate::{
             c_ushort, c_void, size_t, ssize_t, uintptr_t, wchar_t,
         },
     },
+    pthread,
     raw_cell::RawCell,
     sync::Once,
 };
@@ -270,16 +271,19 @@ pub unsafe extern "C" fn bsearch(
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/calloc.html>.
 #[unsafe(no_mangle)]
+#[inline(never)]
 pub unsafe extern "C" fn calloc(nelem: size_t, elsize: size_t) -> *mut c_void {
     //Handle possible integer overflow in size calculation
     match nelem.checked_mul(elsize) {
         Some(size) => {
-            /* If allocation fails here, errno setting will be handled
-             * by malloc() */
-            let ptr = unsafe { malloc(size) };
-            if !ptr.is_null() {
+            let ptr = unsafe { platform::alloc(size) };
+            if ptr.is_null() {
+                platform::ERRNO.set(ENOMEM);
+            } else {
                 unsafe { ptr.write_bytes(0, size) };
             }
+            let caller = core::intrinsics::return_address();
+            unsafe { platform::trace_alloc(caller.cast(), ptr, size) };
             ptr
         }
         None => {
@@ -384,6 +388,8 @@ pub unsafe extern "C" fn exit(status: c_int) -> ! {
 
     unsafe { flush_io_streams() };
 
+    platform::stop_alloc_trace();
+
     Sys::exit(status);
 }
 
@@ -406,7 +412,10 @@ pub extern "C" fn fcvt(
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/free.html>.
 #[unsafe(no_mangle)]
+#[inline(never)]
 pub unsafe extern "C" fn free(ptr: *mut c_void) {
+    let caller = core::intrinsics::return_address();
+    unsafe { platform::trace_free(caller.cast(), ptr) };
     unsafe { platform::free(ptr) };
 }
 
@@ -700,23 +709,29 @@ pub extern "C" fn lrand48() -> c_long {
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/malloc.html>.
 #[unsafe(no_mangle)]
+#[inline(never)]
 pub unsafe extern "C" fn malloc(size: size_t) -> *mut c_void {
     let ptr = unsafe { platform::alloc(size) };
     if ptr.is_null() {
         platform::ERRNO.set(ENOMEM);
     }
+    let caller = core::intrinsics::return_address();
+    unsafe { platform::trace_alloc(caller.cast(), ptr, size) };
     ptr
 }
 
 /// Non-POSIX, see <https://www.man7.org/linux/man-pages/man3/posix_memalign.3.html>.
 #[deprecated]
 #[unsafe(no_mangle)]
+#[inline(never)]
 pub unsafe extern "C" fn memalign(alignment: size_t, size: size_t) -> *mut c_void {
     if alignment.is_power_of_two() {
         let ptr = unsafe { platform::alloc_align(size, alignment) };
         if ptr.is_null() {
             platform::ERRNO.set(ENOMEM);
         }
+        let caller = core::intrinsics::return_address();
+        unsafe { platform::trace_alloc(caller.cast(), ptr, size) };
         ptr
     } else {
         platform::ERRNO.set(EINVAL);
@@ -755,7 +770,7 @@ pub unsafe extern "C" fn mbtowc(pwc: *mut wchar_t, s: *const c_char, n: size_t)
     (unsafe { mbrtowc(pwc, s, n, &raw mut state) }) as c_int
 }
 
-fn inner_mktemp<T, F>(name: *mut c_char, suffix_len: c_int, mut attempt: F) -> Option<T>
+pub(crate) fn inner_mktemp<T, F>(name: *mut c_char, suffix_len: c_int, mut attempt: F) -> Option<T>
 where
     F: FnMut() -> Option<T>,
 {
@@ -916,6 +931,7 @@ pub unsafe extern "C" fn nrand48(xsubi: *mut c_ushort) -> c_long {
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/posix_memalign.html>.
 #[unsafe(no_mangle)]
+#[inline(never)]
 pub unsafe extern "C" fn posix_memalign(
     memptr: *mut *mut c_void,
     alignment: size_t,
@@ -925,6 +941,8 @@ pub unsafe extern "C" fn posix_memalign(
 
     if alignment.is_multiple_of(VOID_PTR_SIZE) && alignment.is_power_of_two() {
         let ptr = unsafe { platform::alloc_align(size, alignment) };
+        let caller = core::intrinsics::return_address();
+        unsafe { platform::trace_alloc(caller.cast(), ptr, size) };
         unsafe { *memptr = ptr };
         if ptr.is_null() { ENOMEM } else { 0 }
     } else {
@@ -1196,11 +1214,15 @@ pub unsafe extern "C" fn random() -> c_long {
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/realloc.html>.
 #[unsafe(no_mangle)]
+#[inline(never)]
 pub unsafe extern "C" fn realloc(ptr: *mut c_void, size: size_t) -> *mut c_void {
+    let old_size = unsafe { platform::traced_size(ptr) };
     let new_ptr = unsafe { platform::realloc(ptr, size) };
     if new_ptr.is_null() {
         platform::ERRNO.set(ENOMEM);
     }
+    let caller = core::intrinsics::return_address();
+    unsafe { platform::trace_realloc(caller.cast(), ptr, old_size, new_ptr, size) };
     new_ptr
 }
 
@@ -1644,7 +1666,11 @@ pub unsafe extern "C" fn system(command: *const c_char) -> c_int {
         unreachable!();
     } else if child_pid > 0 {
         let mut wstatus = 0;
-        if Sys::waitpid(child_pid, Some(Out::from_mut(&mut wstatus)), 0).or_minus_one_errno() == -1
+        if pthread::cancellation_point(|| {
+            Sys::waitpid(child_pid, Some(Out::from_mut(&mut wstatus)), 0)
+        })
+        .or_minus_one_errno()
+            == -1
         {
             return -1;
         }
@@ -1729,6 +1755,7 @@ pub unsafe extern "C" fn unsetenv(key: *const c_char) -> c_int {
 /// Specifications Issue 5, and the function was removed in Issue 6.
 #[deprecated]
 #[unsafe(no_mangle)]
+#[inline(never)]
 pub unsafe extern "C" fn valloc(size: size_t) -> *mut c_void {
     /* sysconf(_SC_PAGESIZE) is a c_long and may in principle not
      * convert correctly to a size_t. */
@@ -1740,6 +1767,8 @@ pub unsafe extern "C" fn valloc(size: size_t) -> *mut c_void {
             if ptr.is_null() {
                 platform::ERRNO.set(ENOMEM);
             }
+            let caller = core::intrinsics::return_address();
+            unsafe { platform::trace_alloc(caller.cast(), ptr, size) };
             ptr
         }
         Err(_) => {
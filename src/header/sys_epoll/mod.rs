@@ -233,9 +233,11 @@ pub unsafe extern "C" fn epoll_pwait(
     sigmask: *const sigset_t,
 ) -> c_int {
     trace_expr!(
-        unsafe { Sys::epoll_pwait(epfd, events, maxevents, timeout, sigmask) }
-            .map(|e| e as c_int)
-            .or_minus_one_errno(),
+        crate::pthread::cancellation_point(|| unsafe {
+            Sys::epoll_pwait(epfd, events, maxevents, timeout, sigmask)
+        })
+        .map(|e| e as c_int)
+        .or_minus_one_errno(),
         "epoll_pwait({}, {:p}, {}, {}, {:p})",
         epfd,
         events,
@@ -10,6 +10,7 @@ use crate::{
         ERRNO, Pal, Sys,
         types::{c_char, c_int, c_void, mode_t, off_t, size_t},
     },
+    pthread,
 };
 
 pub use self::sys::*;
@@ -125,7 +126,7 @@ pub unsafe extern "C" fn mremap(
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/msync.html>.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn msync(addr: *mut c_void, len: size_t, flags: c_int) -> c_int {
-    unsafe { Sys::msync(addr, len, flags) }
+    pthread::cancellation_point(|| unsafe { Sys::msync(addr, len, flags) })
         .map(|()| 0)
         .or_minus_one_errno()
 }
@@ -155,12 +156,12 @@ pub unsafe extern "C" fn munmap(addr: *mut c_void, len: size_t) -> c_int {
 }
 
 #[cfg(target_os = "linux")]
-static SHM_PATH: &[u8] = b"/dev/shm/";
+pub(crate) static SHM_PATH: &[u8] = b"/dev/shm/";
 
 #[cfg(target_os = "redox")]
-static SHM_PATH: &[u8] = b"/scheme/shm/";
+pub(crate) static SHM_PATH: &[u8] = b"/scheme/shm/";
 
-unsafe fn shm_path(name: *const c_char) -> CString {
+pub(crate) unsafe fn shm_path(name: *const c_char) -> CString {
     let name_c = unsafe { CStr::from_ptr(name) };
 
     let mut path = SHM_PATH.to_vec();
@@ -0,0 +1,114 @@
+//! `sys/msg.h` implementation.
+//!
+//! See <https://pubs.opengroup.org/onlinepubs/9799919799/basedefs/sys_msg.h.html>.
+
+use crate::{
+    error::ResultExt,
+    header::sys_ipc::ipc_perm,
+    platform::{
+        PalIpc, Sys,
+        types::{c_int, c_long, c_ulong, c_void, key_t, pid_t, size_t, ssize_t, time_t},
+    },
+    pthread,
+};
+
+/// Type used for the number of messages in the message queue.
+pub type msgqnum_t = c_ulong;
+
+/// Type used for the number of bytes allowed in a message queue.
+pub type msglen_t = c_ulong;
+
+/// No error if big message.
+pub const MSG_NOERROR: c_int = 0o10000;
+/// Non-POSIX. Receive any message except of the specified type.
+pub const MSG_EXCEPT: c_int = 0o20000;
+
+/// See <https://pubs.opengroup.org/onlinepubs/9799919799/basedefs/sys_msg.h.html>.
+///
+/// Message queue data structure.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct msqid_ds {
+    /// Operation permission structure.
+    pub msg_perm: ipc_perm,
+    /// Number of messages currently on queue.
+    pub msg_qnum: msgqnum_t,
+    /// Maximum number of bytes allowed on queue.
+    pub msg_qbytes: msglen_t,
+    /// Process ID of last `msgsnd()`.
+    pub msg_lspid: pid_t,
+    /// Process ID of last `msgrcv()`.
+    pub msg_lrpid: pid_t,
+    /// Time of last `msgsnd()`.
+    pub msg_stime: time_t,
+    /// Time of last `msgrcv()`.
+    pub msg_rtime: time_t,
+    /// Time of last change.
+    pub msg_ctime: time_t,
+}
+
+/// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/msgctl.html>.
+///
+/// Performs the control operation `cmd` on the message queue `msqid`.
+///
+/// Upon success, returns `0`. Upon failure, returns `-1` and sets errno to
+/// indicate the error.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn msgctl(msqid: c_int, cmd: c_int, buf: *mut msqid_ds) -> c_int {
+    unsafe { Sys::msgctl(msqid, cmd, buf) }
+        .map(|()| 0)
+        .or_minus_one_errno()
+}
+
+/// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/msgget.html>.
+///
+/// Returns the identifier of the message queue associated with `key`,
+/// creating it if requested by `msgflg`.
+///
+/// Upon success, returns a message queue identifier. Upon failure, returns
+/// `-1` and sets errno to indicate the error.
+#[unsafe(no_mangle)]
+pub extern "C" fn msgget(key: key_t, msgflg: c_int) -> c_int {
+    Sys::msgget(key, msgflg).or_minus_one_errno()
+}
+
+/// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/msgrcv.html>.
+///
+/// Receives a message of up to `msgsz` bytes from the message queue `msqid`
+/// into the buffer `msgp`, which starts with the `long` message type. Which
+/// message is received depends on `msgtyp`.
+///
+/// Upon success, returns the number of bytes received, not including the
+/// message type. Upon failure, returns `-1` and sets errno to indicate the
+/// error.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn msgrcv(
+    msqid: c_int,
+    msgp: *mut c_void,
+    msgsz: size_t,
+    msgtyp: c_long,
+    msgflg: c_int,
+) -> ssize_t {
+    pthread::cancellation_point(|| unsafe { Sys::msgrcv(msqid, msgp, msgsz, msgtyp, msgflg) })
+        .map(|len| len as ssize_t)
+        .or_minus_one_errno()
+}
+
+/// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/msgsnd.html>.
+///
+/// Sends the message `msgp`, consisting of the `long` message type followed by
+/// `msgsz` bytes of data, to the message queue `msqid`.
+///
+/// Upon success, returns `0`. Upon failure, returns `-1` and sets errno to
+/// indicate the error.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn msgsnd(
+    msqid: c_int,
+    msgp: *const c_void,
+    msgsz: size_t,
+    msgflg: c_int,
+) -> c_int {
+    pthread::cancellation_point(|| unsafe { Sys::msgsnd(msqid, msgp, msgsz, msgflg) })
+        .map(|()| 0)
+        .or_minus_one_errno()
+}
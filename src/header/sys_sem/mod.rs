@@ -0,0 +1,157 @@
+//! `sys/sem.h` implementation.
+//!
+//! See <https://pubs.opengroup.org/onlinepubs/9799919799/basedefs/sys_sem.h.html>.
+
+use core::slice;
+
+use crate::{
+    error::ResultExt,
+    header::{
+        errno::EINVAL,
+        sys_ipc::{IPC_SET, IPC_STAT, ipc_perm},
+        time::timespec,
+    },
+    platform::{
+        ERRNO, PalIpc, Sys,
+        types::{c_int, c_short, c_ushort, key_t, size_t, time_t},
+    },
+};
+
+/// Set up adjust on exit entry.
+pub const SEM_UNDO: c_short = 0x1000;
+
+/// Get `sempid`.
+pub const GETPID: c_int = 11;
+/// Get `semval`.
+pub const GETVAL: c_int = 12;
+/// Get all cases of `semval`.
+pub const GETALL: c_int = 13;
+/// Get `semncnt`.
+pub const GETNCNT: c_int = 14;
+/// Get `semzcnt`.
+pub const GETZCNT: c_int = 15;
+/// Set `semval`.
+pub const SETVAL: c_int = 16;
+/// Set all cases of `semval`.
+pub const SETALL: c_int = 17;
+
+/// See <https://pubs.opengroup.org/onlinepubs/9799919799/basedefs/sys_sem.h.html>.
+///
+/// Semaphore set data structure.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct semid_ds {
+    /// Operation permission structure.
+    pub sem_perm: ipc_perm,
+    /// Number of semaphores in set.
+    pub sem_nsems: c_ushort,
+    /// Last `semop()` time.
+    pub sem_otime: time_t,
+    /// Last time changed by `semctl()`.
+    pub sem_ctime: time_t,
+}
+
+/// See <https://pubs.opengroup.org/onlinepubs/9799919799/basedefs/sys_sem.h.html>.
+///
+/// Semaphore operation.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct sembuf {
+    /// Semaphore number.
+    pub sem_num: c_ushort,
+    /// Semaphore operation.
+    pub sem_op: c_short,
+    /// Operation flags.
+    pub sem_flg: c_short,
+}
+
+/// The optional fourth argument of [`semctl()`].
+///
+/// Applications declare this union themselves, it is not provided by `sys/sem.h`.
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub union semun {
+    pub val: c_int,
+    pub buf: *mut semid_ds,
+    pub array: *mut c_ushort,
+}
+
+/// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/semctl.html>.
+///
+/// Performs the control operation `cmd` on the semaphore set `semid`, or on
+/// its semaphore `semnum`.
+///
+/// Depending on `cmd`, a fourth argument of type `union semun` is expected.
+///
+/// Upon success, returns the value requested by `GETVAL`, `GETPID`, `GETNCNT`
+/// or `GETZCNT`, and `0` for other commands. Upon failure, returns `-1` and
+/// sets errno to indicate the error.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn semctl(
+    semid: c_int,
+    semnum: c_int,
+    cmd: c_int,
+    mut __valist: ...
+) -> c_int {
+    // Only read the argument if it is actually passed.
+    let arg = match cmd {
+        SETVAL => semun {
+            val: unsafe { __valist.next_arg::<c_int>() },
+        },
+        GETALL | SETALL => semun {
+            array: unsafe { __valist.next_arg::<*mut c_ushort>() },
+        },
+        IPC_STAT | IPC_SET => semun {
+            buf: unsafe { __valist.next_arg::<*mut semid_ds>() },
+        },
+        _ => semun { val: 0 },
+    };
+
+    unsafe { Sys::semctl(semid, semnum, cmd, arg) }.or_minus_one_errno()
+}
+
+/// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/semget.html>.
+///
+/// Returns the identifier of the semaphore set associated with `key`, creating
+/// a set of `nsems` semaphores if requested by `semflg`.
+///
+/// Upon success, returns a semaphore identifier. Upon failure, returns `-1`
+/// and sets errno to indicate the error.
+#[unsafe(no_mangle)]
+pub extern "C" fn semget(key: key_t, nsems: c_int, semflg: c_int) -> c_int {
+    Sys::semget(key, nsems, semflg).or_minus_one_errno()
+}
+
+/// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/semop.html>.
+///
+/// Atomically performs the `nsops` operations in `sops` on the semaphore set
+/// `semid`.
+///
+/// Upon success, returns `0`. Upon failure, returns `-1` and sets errno to
+/// indicate the error.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn semop(semid: c_int, sops: *mut sembuf, nsops: size_t) -> c_int {
+    unsafe { semtimedop(semid, sops, nsops, core::ptr::null()) }
+}
+
+/// Non-POSIX, see <https://www.man7.org/linux/man-pages/man2/semop.2.html>.
+///
+/// Equivalent to `semop()`, except that the wait is terminated after the
+/// relative timeout `timeout`, unless it is a null pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn semtimedop(
+    semid: c_int,
+    sops: *mut sembuf,
+    nsops: size_t,
+    timeout: *const timespec,
+) -> c_int {
+    if nsops == 0 {
+        ERRNO.set(EINVAL);
+        return -1;
+    }
+
+    let sops = unsafe { slice::from_raw_parts(sops, nsops) };
+    Sys::semtimedop(semid, sops, unsafe { timeout.as_ref() })
+        .map(|()| 0)
+        .or_minus_one_errno()
+}
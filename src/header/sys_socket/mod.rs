@@ -11,6 +11,7 @@ use crate::{
         PalSocket, Sys,
         types::{c_char, c_int, c_long, c_uchar, c_uint, c_void, size_t, ssize_t},
     },
+    pthread,
 };
 
 pub use crate::header::bits_socklen_t::socklen_t;
@@ -177,7 +178,8 @@ pub unsafe extern "C" fn accept(
     address_len: *mut socklen_t,
 ) -> c_int {
     trace_expr!(
-        unsafe { Sys::accept(socket, address, address_len) }.or_minus_one_errno(),
+        pthread::cancellation_point(|| unsafe { Sys::accept(socket, address, address_len) })
+            .or_minus_one_errno(),
         "accept({}, {:p}, {:p})",
         socket,
         address,
@@ -211,7 +213,8 @@ pub unsafe extern "C" fn connect(
     address_len: socklen_t,
 ) -> c_int {
     trace_expr!(
-        unsafe { Sys::connect(socket, address, address_len) }.or_minus_one_errno(),
+        pthread::cancellation_point(|| unsafe { Sys::connect(socket, address, address_len) })
+            .or_minus_one_errno(),
         "connect({}, {:p}, {})",
         socket,
         address,
@@ -316,9 +319,11 @@ pub unsafe extern "C" fn recvfrom(
     address_len: *mut socklen_t,
 ) -> ssize_t {
     trace_expr!(
-        unsafe { Sys::recvfrom(socket, buffer, length, flags, address, address_len) }
-            .map(|r| r as ssize_t)
-            .or_minus_one_errno(),
+        pthread::cancellation_point(|| unsafe {
+            Sys::recvfrom(socket, buffer, length, flags, address, address_len)
+        })
+        .map(|r| r as ssize_t)
+        .or_minus_one_errno(),
         "recvfrom({}, {:p}, {}, {:#x}, {:p}, {:p})",
         socket,
         buffer,
@@ -332,7 +337,7 @@ pub unsafe extern "C" fn recvfrom(
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/recvmsg.html>.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn recvmsg(socket: c_int, msg: *mut msghdr, flags: c_int) -> ssize_t {
-    unsafe { Sys::recvmsg(socket, msg, flags) }
+    pthread::cancellation_point(|| unsafe { Sys::recvmsg(socket, msg, flags) })
         .map(|r| r as ssize_t)
         .or_minus_one_errno()
 }
@@ -351,7 +356,7 @@ pub unsafe extern "C" fn send(
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/sendmsg.html>.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn sendmsg(socket: c_int, msg: *const msghdr, flags: c_int) -> ssize_t {
-    unsafe { Sys::sendmsg(socket, msg, flags) }
+    pthread::cancellation_point(|| unsafe { Sys::sendmsg(socket, msg, flags) })
         .map(|w| w as ssize_t)
         .or_minus_one_errno()
 }
@@ -367,9 +372,11 @@ pub unsafe extern "C" fn sendto(
     dest_len: socklen_t,
 ) -> ssize_t {
     trace_expr!(
-        unsafe { Sys::sendto(socket, message, length, flags, dest_addr, dest_len) }
-            .map(|w| w as ssize_t)
-            .or_minus_one_errno(),
+        pthread::cancellation_point(|| unsafe {
+            Sys::sendto(socket, message, length, flags, dest_addr, dest_len)
+        })
+        .map(|w| w as ssize_t)
+        .or_minus_one_errno(),
         "sendto({}, {:p}, {}, {:#x}, {:p}, {})",
         socket,
         message,
@@ -9,6 +9,7 @@ use crate::{
         Pal, Sys,
         types::{c_int, pid_t},
     },
+    pthread,
 };
 
 /// Do not hang if no status is available; return immediately.
@@ -83,5 +84,6 @@ pub unsafe extern "C" fn wait(stat_loc: *mut c_int) -> pid_t {
 /// sets errno.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn waitpid(pid: pid_t, stat_loc: *mut c_int, options: c_int) -> pid_t {
-    Sys::waitpid(pid, unsafe { Out::nullable(stat_loc) }, options).or_minus_one_errno()
+    pthread::cancellation_point(|| Sys::waitpid(pid, unsafe { Out::nullable(stat_loc) }, options))
+        .or_minus_one_errno()
 }
@@ -8,6 +8,7 @@ use crate::{
         self,
         types::{c_int, c_uchar, c_uint, c_ulong, c_void, pid_t},
     },
+    pthread,
 };
 
 pub use crate::header::bits_winsize::winsize;
@@ -295,7 +296,9 @@ pub unsafe extern "C" fn tcflush(fildes: c_int, queue_selector: c_int) -> c_int
 /// indicate the error.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn tcdrain(fildes: c_int) -> c_int {
-    unsafe { sys_ioctl::ioctl(fildes, sys_ioctl::TCSBRK, core::ptr::dangling_mut()) }
+    pthread::cancellation_point(|| unsafe {
+        sys_ioctl::ioctl(fildes, sys_ioctl::TCSBRK, core::ptr::dangling_mut())
+    })
 }
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/tcsendbreak.html>.
@@ -0,0 +1,241 @@
+//! `getdate` implementation.
+//!
+//! See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/getdate.html>.
+
+use alloc::vec::Vec;
+use core::ptr;
+
+use crate::{
+    c_str::CStr,
+    fs::File,
+    header::{
+        fcntl::{O_CLOEXEC, O_RDONLY},
+        locale::uselocale,
+        stdlib::getenv,
+        sys_stat::{S_IFMT, S_IFREG, stat},
+        unistd::R_OK,
+    },
+    io::{BufRead, BufReader},
+    out::Out,
+    platform::{
+        Pal, Sys,
+        types::{c_char, c_int},
+    },
+    raw_cell::RawCell,
+};
+
+use super::{blank_tm, getdate_err, localtime_r, mktime, strptime, time, tm};
+
+/// cbindgen:ignore
+static GETDATE_RETURN_TM: RawCell<tm> = RawCell::new(blank_tm());
+
+/// cbindgen:ignore
+/// Marks the fields that the matching template did not set.
+const UNSET: c_int = c_int::MIN;
+
+/// The values of [`getdate_err`].
+///
+/// POSIX also reserves `6` for memory allocation failures, which abort the
+/// process in relibc.
+#[derive(Clone, Copy)]
+enum GetdateError {
+    /// `DATEMSK` is undefined or empty.
+    NoTemplates = 1,
+    /// The template file cannot be opened for reading.
+    Open = 2,
+    /// The status of the template file cannot be read.
+    Stat = 3,
+    /// The template file is not a regular file.
+    NotRegular = 4,
+    /// An I/O error occurred while reading the template file.
+    Read = 5,
+    /// No template matches the input.
+    NoMatch = 7,
+    /// The input does not name a valid date, such as February 31.
+    Invalid = 8,
+}
+
+/// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/getdate.html>.
+///
+/// # Safety
+/// The caller is required to ensure that the function has exclusive access to
+/// the static `tm` structure it returns.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn getdate(string: *const c_char) -> *mut tm {
+    let result = GETDATE_RETURN_TM.as_mut_ptr();
+    match unsafe { getdate_r(string, result) } {
+        0 => result,
+        err => {
+            unsafe { getdate_err = err };
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Non-POSIX, see <https://www.man7.org/linux/man-pages/man3/getdate.3.html>.
+///
+/// Reentrant version of [`getdate()`], which stores the result in `resbufp`
+/// and returns `0`, or the value [`getdate()`] would set `getdate_err` to.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn getdate_r(string: *const c_char, resbufp: *mut tm) -> c_int {
+    let string = unsafe { CStr::from_ptr(string) };
+    let result = unsafe { &mut *resbufp };
+    match parse_date(string.to_bytes(), result) {
+        Ok(()) => 0,
+        Err(err) => err as c_int,
+    }
+}
+
+/// Open the template file named by `DATEMSK`.
+fn open_templates() -> Result<BufReader<File>, GetdateError> {
+    let path = unsafe { CStr::from_nullable_ptr(getenv(c"DATEMSK".as_ptr())) }
+        .filter(|path| !path.is_empty())
+        .ok_or(GetdateError::NoTemplates)?;
+
+    let mut st = stat::default();
+    Sys::stat(path, Out::from_mut(&mut st)).map_err(|_| GetdateError::Stat)?;
+    if st.st_mode & S_IFMT != S_IFREG {
+        return Err(GetdateError::NotRegular);
+    }
+    Sys::access(path, R_OK).map_err(|_| GetdateError::Open)?;
+
+    File::open(path, O_RDONLY | O_CLOEXEC)
+        .map(BufReader::new)
+        .map_err(|_| GetdateError::Open)
+}
+
+/// Parse `input` with the first template matching all of it, leaving the
+/// fields it does not set to [`UNSET`].
+fn match_template(
+    templates: &mut BufReader<File>,
+    input: &[u8],
+    result: &mut tm,
+) -> Result<(), GetdateError> {
+    // Surrounding blanks are ignored.
+    let input = input.trim_ascii();
+    let locale = unsafe { uselocale(ptr::null_mut()) };
+
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if templates
+            .read_until(b'\n', &mut line)
+            .map_err(|_| GetdateError::Read)?
+            == 0
+        {
+            return Err(GetdateError::NoMatch);
+        }
+        let template = line.strip_suffix(b"\n").unwrap_or(&line);
+
+        *result = tm {
+            tm_sec: UNSET,
+            tm_min: UNSET,
+            tm_hour: UNSET,
+            tm_mday: UNSET,
+            tm_mon: UNSET,
+            tm_year: UNSET,
+            tm_wday: UNSET,
+            ..blank_tm()
+        };
+        if strptime::parse(input, template, result, locale) == Some(input.len()) {
+            return Ok(());
+        }
+    }
+}
+
+fn parse_date(input: &[u8], result: &mut tm) -> Result<(), GetdateError> {
+    let mut templates = open_templates()?;
+    match_template(&mut templates, input, result)?;
+
+    let mut now = blank_tm();
+    unsafe { localtime_r(&time(ptr::null_mut()), &mut now) };
+
+    // Days computed below may go past the end of the month, which mktime()
+    // normalizes.
+    let mut computed_mday = false;
+
+    // Only a weekday means today, or that day next week if it has passed.
+    if (0..=6).contains(&result.tm_wday)
+        && result.tm_year == UNSET
+        && result.tm_mon == UNSET
+        && result.tm_mday == UNSET
+    {
+        result.tm_year = now.tm_year;
+        result.tm_mon = now.tm_mon;
+        result.tm_mday = now.tm_mday + (result.tm_wday - now.tm_wday + 7) % 7;
+        computed_mday = true;
+    }
+
+    // A month without a day means its first day, or its first given weekday,
+    // in the current year, or the next one if the month has passed.
+    if (0..=11).contains(&result.tm_mon) && result.tm_mday == UNSET {
+        if result.tm_year == UNSET {
+            result.tm_year = now.tm_year + c_int::from(result.tm_mon < now.tm_mon);
+        }
+        result.tm_mday = first_weekday(result.tm_year, result.tm_mon, result.tm_wday);
+        computed_mday = true;
+    }
+
+    // Without any time, the current time is assumed. Otherwise the missing
+    // parts are zero.
+    if result.tm_hour == UNSET && result.tm_min == UNSET && result.tm_sec == UNSET {
+        result.tm_hour = now.tm_hour;
+        result.tm_min = now.tm_min;
+        result.tm_sec = now.tm_sec;
+    }
+    for field in [&mut result.tm_hour, &mut result.tm_min, &mut result.tm_sec] {
+        if *field == UNSET {
+            *field = 0;
+        }
+    }
+
+    // Only a time means today, or tomorrow if the hour has passed.
+    if result.tm_mon == UNSET && result.tm_mday == UNSET && result.tm_wday == UNSET {
+        result.tm_year = now.tm_year;
+        result.tm_mon = now.tm_mon;
+        result.tm_mday = now.tm_mday + c_int::from(result.tm_hour < now.tm_hour);
+        computed_mday = true;
+    }
+
+    if result.tm_year == UNSET {
+        result.tm_year = now.tm_year;
+    }
+    if result.tm_mon == UNSET {
+        result.tm_mon = now.tm_mon;
+    }
+
+    if !computed_mday
+        && !(1..=days_in_month(result.tm_year, result.tm_mon)).contains(&result.tm_mday)
+    {
+        return Err(GetdateError::Invalid);
+    }
+    if unsafe { mktime(result) } == -1 {
+        return Err(GetdateError::Invalid);
+    }
+    Ok(())
+}
+
+/// The day of the first `wday` of a month, or `1` if `wday` is unset.
+fn first_weekday(year: c_int, mon: c_int, wday: c_int) -> c_int {
+    if wday == UNSET {
+        return 1;
+    }
+    let mut first = tm {
+        tm_year: year,
+        tm_mon: mon,
+        tm_mday: 1,
+        ..blank_tm()
+    };
+    unsafe { mktime(&mut first) };
+    1 + (wday - first.tm_wday + 7) % 7
+}
+
+fn days_in_month(year: c_int, mon: c_int) -> c_int {
+    let year = i64::from(year) + 1900;
+    match mon {
+        1 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        1 => 28,
+        3 | 5 | 8 | 10 => 30,
+        _ => 31,
+    }
+}
@@ -3,14 +3,13 @@
 //! See <https://pubs.opengroup.org/onlinepubs/9799919799/basedefs/time.h.html>.
 
 use crate::{
-    c_str::{CStr, CString},
+    c_str::CString,
     error::{Errno, ResultExt},
     header::{
+        bits_locale_t::locale_t,
         errno::{EINVAL, ENOMEM, EOVERFLOW, ETIMEDOUT},
+        locale::uselocale,
         signal::sigevent,
-        stdlib::getenv,
-        time::posix_tz::PosixTz,
-        unistd::readlink,
     },
     out::Out,
     platform::{
@@ -19,15 +18,12 @@ use crate::{
             c_char, c_double, c_int, c_long, clock_t, clockid_t, pid_t, size_t, time_t, timer_t,
         },
     },
+    pthread,
     raw_cell::RawCell,
     sync::{Mutex, MutexGuard},
 };
 use alloc::collections::BTreeSet;
-use chrono::{
-    DateTime, Datelike, NaiveDate, NaiveDateTime, Offset, TimeZone, Timelike, Utc,
-    offset::MappedLocalTime,
-};
-use chrono_tz::{OffsetComponents, OffsetName, Tz};
+use chrono::{DateTime, Datelike, NaiveDate, TimeZone, Timelike, Utc, offset::MappedLocalTime};
 use core::{cell::OnceCell, convert::TryFrom, mem, ptr};
 
 pub use crate::header::bits_timespec::timespec;
@@ -36,10 +32,14 @@ pub use self::constants::*;
 
 pub mod constants;
 
+mod getdate;
 mod posix_tz;
-mod strftime;
+pub(crate) mod strftime;
 mod strptime;
-pub use strptime::strptime;
+mod tzif;
+mod zone;
+pub use getdate::{getdate, getdate_r};
+pub use strptime::{strptime, strptime_l};
 
 /// cbindgen:ignore
 const YEARS_PER_ERA: time_t = 400;
@@ -109,6 +109,7 @@ pub static mut timezone: c_long = 0;
 #[unsafe(no_mangle)]
 pub static mut tzname: TzName = TzName([ptr::null_mut(); 2]);
 
+/// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/getdate.html>.
 #[unsafe(no_mangle)]
 pub static mut getdate_err: c_int = 0;
 
@@ -258,15 +259,21 @@ pub unsafe extern "C" fn clock_gettime(clock_id: clockid_t, tp: *mut timespec) -
 }
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/clock_nanosleep.html>.
-// #[unsafe(no_mangle)]
-#[expect(unused_variables, reason = "function not yet implemented")]
-pub extern "C" fn clock_nanosleep(
+///
+/// Unlike [`nanosleep()`], returns the error number instead of setting errno.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn clock_nanosleep(
     clock_id: clockid_t,
     flags: c_int,
     rqtp: *const timespec,
     rmtp: *mut timespec,
 ) -> c_int {
-    unimplemented!();
+    match pthread::cancellation_point(|| unsafe {
+        Sys::clock_nanosleep(clock_id, flags, rqtp, rmtp)
+    }) {
+        Ok(()) => 0,
+        Err(Errno(errno)) => errno,
+    }
 }
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/clock_getres.html>.
@@ -314,13 +321,6 @@ pub unsafe extern "C" fn difftime(time1: time_t, time0: time_t) -> c_double {
     (time1 - time0) as _
 }
 
-/// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/getdate.html>.
-// #[unsafe(no_mangle)]
-#[expect(unused_variables, reason = "function not yet implemented")]
-pub unsafe extern "C" fn getdate(string: *const c_char) -> *const tm {
-    unimplemented!();
-}
-
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/gmtime.html>.
 ///
 /// # Safety
@@ -350,9 +350,15 @@ pub unsafe extern "C" fn gmtime_r(timer: *const time_t, result: *mut tm) -> *mut
 
     // SAFETY: the caller is required to ensure that `result` is convertible
     // to an `Out<tm>`.
-    let result_out = unsafe { Out::nonnull(result) };
+    let mut result_out = unsafe { Out::nonnull(result) };
 
-    let _ = get_localtime(timer_val, result_out);
+    let Some(mut t) = seconds_to_tm(timer_val) else {
+        platform::ERRNO.set(EOVERFLOW);
+        return ptr::null_mut();
+    };
+    t.tm_isdst = 0;
+    t.tm_zone = UTC_STR.as_ptr().cast::<c_char>();
+    result_out.write(t);
     result
 }
 
@@ -389,74 +395,45 @@ pub unsafe extern "C" fn localtime_r(timer: *const time_t, result: *mut tm) -> *
 
     // SAFETY: the caller is required to ensure that `result` is convertible
     // to an `Out<tm>`.
-    let result_out = unsafe { Out::nonnull(result) };
+    let mut result_out = unsafe { Out::nonnull(result) };
 
     let mut lock = TIMEZONE_LOCK.lock();
-
-    // SAFETY: the caller is required to ensure that `daylight`, `timezone`
-    // and `tzname` are not accessed by user code.
-    unsafe { clear_timezone(&mut lock) };
-    if let (Some(std_time), dst_time) = get_localtime(timer_val, result_out) {
+    zone::with_current(|zone| {
         // SAFETY: the caller is required to ensure that `daylight`,
         // `timezone` and `tzname` are not accessed by user code.
-        unsafe { set_timezone(&mut lock, &std_time, dst_time) };
-    }
-    result
+        unsafe { set_timezone(&mut lock, zone) };
+
+        let Some(t) = local_tm(zone, timer_val) else {
+            platform::ERRNO.set(EOVERFLOW);
+            return ptr::null_mut();
+        };
+        result_out.write(t);
+        result
+    })
 }
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/mktime.html>.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn mktime(timeptr: *mut tm) -> time_t {
     let mut lock = TIMEZONE_LOCK.lock();
-    unsafe { clear_timezone(&mut lock) };
-
-    let year = unsafe { (*timeptr).tm_year } + 1900;
-    let month = (unsafe { (*timeptr).tm_mon } + 1) as _;
-    let day = unsafe { (*timeptr).tm_mday } as _;
-    let hour = unsafe { (*timeptr).tm_hour } as _;
-    let minute = unsafe { (*timeptr).tm_min } as _;
-    let second = unsafe { (*timeptr).tm_sec } as _;
-
-    let Some(naive_local) = NaiveDate::from_ymd_opt(year, month, day)
-        .and_then(|date| date.and_hms_opt(hour, minute, second))
-    else {
+    let timeptr = unsafe { &mut *timeptr };
+
+    let Some(local) = tm_to_seconds(timeptr) else {
         platform::ERRNO.set(EOVERFLOW);
         return -1;
     };
 
-    // POSIX TODO
-    let tz = time_zone().unwrap_or(Tz::UTC);
-    let isdst = unsafe { (*timeptr).tm_isdst };
-    let tz_datetime = match tz.from_local_datetime(&naive_local) {
-        MappedLocalTime::Single(datetime) => datetime,
-        MappedLocalTime::Ambiguous(early, late) => {
-            if isdst > 0 {
-                early
-            } else {
-                late
-            }
-        }
-        MappedLocalTime::None => {
+    zone::with_current(|zone| {
+        unsafe { set_timezone(&mut lock, zone) };
+
+        let timestamp = zone.local_to_utc(local, timeptr.tm_isdst);
+        let Some(t) = local_tm(zone, timestamp) else {
             platform::ERRNO.set(EOVERFLOW);
             return -1;
-        }
-    };
-    let timestamp = tz_datetime.timestamp();
-
-    unsafe { ptr::write(timeptr, datetime_to_tm(&tz_datetime)) };
-
-    // Convert UTC time to local time
-    let (std_time, dst_time) = match tz.timestamp_opt(timestamp, 0) {
-        MappedLocalTime::Single(t) => (t, None),
-        // This variant contains the two possible results, in the order (earliest, latest).
-        MappedLocalTime::Ambiguous(t1, t2) => (t2, Some(t1)),
-        MappedLocalTime::None => return timestamp,
-    };
-    {
-        unsafe { set_timezone(&mut lock, &std_time, dst_time) };
-    }
-
-    timestamp
+        };
+        *timeptr = t;
+        timestamp
+    })
 }
 
 // FIXME seems redox-rt sys posix_nanosleep calls wrapper which disables signals
@@ -476,7 +453,7 @@ pub unsafe extern "C" fn mktime(timeptr: *mut tm) -> time_t {
 /// not returned.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn nanosleep(rqtp: *const timespec, rmtp: *mut timespec) -> c_int {
-    unsafe { Sys::nanosleep(rqtp, rmtp) }
+    pthread::cancellation_point(|| unsafe { Sys::nanosleep(rqtp, rmtp) })
         .map(|()| 0)
         .or_minus_one_errno()
 }
@@ -489,17 +466,28 @@ pub unsafe extern "C" fn strftime(
     format: *const c_char,
     timeptr: *const tm,
 ) -> size_t {
-    let mut w = platform::StringWriter(s, maxsize);
-    let ret = unsafe { strftime::strftime(&mut w, format, timeptr) };
-    if ret < maxsize { ret } else { 0 }
+    unsafe { strftime_l(s, maxsize, format, timeptr, uselocale(ptr::null_mut())) }
 }
 
-// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/strftime.html>.
-// TODO: needs locale_t
-// #[unsafe(no_mangle)]
-/*pub extern "C" fn strftime_l(s: *mut char, maxsize: size_t, format: *const c_char, timeptr: *const tm, locale: locale_t) -> size_t {
-    unimplemented!();
-}*/
+/// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/strftime.html>.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn strftime_l(
+    s: *mut c_char,
+    maxsize: size_t,
+    format: *const c_char,
+    timeptr: *const tm,
+    locale: locale_t,
+) -> size_t {
+    let format = unsafe { core::ffi::CStr::from_ptr(format) }.to_bytes();
+    let Some(out) = strftime::strftime(format, unsafe { &*timeptr }, locale, maxsize) else {
+        return 0;
+    };
+    unsafe {
+        ptr::copy_nonoverlapping(out.as_ptr(), s.cast::<u8>(), out.len());
+        *s.add(out.len()) = 0;
+    }
+    out.len()
+}
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/time.html>.
 #[unsafe(no_mangle)]
@@ -535,27 +523,7 @@ pub unsafe extern "C" fn timegm(tm: *mut tm) -> time_t {
 #[deprecated]
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn timelocal(tm: *mut tm) -> time_t {
-    let tm_val = unsafe { &mut *tm };
-    // POSIX TODO
-    let tz = time_zone().unwrap_or(Tz::UTC);
-    let Some(dt) = convert_tm_generic(&tz, tm_val) else {
-        return -1;
-    };
-
-    let tz_name = CString::new(tz.name()).unwrap();
-    #[cfg(target_pointer_width = "64")]
-    let tm_gmtoff = dt.offset().fix().local_minus_utc().into();
-    #[cfg(target_pointer_width = "32")]
-    let tm_gmtoff = dt.offset().fix().local_minus_utc();
-    unsafe {
-        (*tm).tm_wday = dt.weekday().num_days_from_sunday() as _;
-        (*tm).tm_yday = dt.ordinal0() as _; // day of year starting at 0
-        (*tm).tm_isdst = dt.offset().dst_offset().num_hours() as _;
-        (*tm).tm_gmtoff = tm_gmtoff;
-        (*tm).tm_zone = tz_name.into_raw().cast();
-    }
-
-    dt.timestamp()
+    unsafe { mktime(tm) }
 }
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/timer_create.html>.
@@ -583,10 +551,9 @@ pub unsafe extern "C" fn timer_delete(timerid: timer_t) -> c_int {
 }
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/timer_getoverrun.html>.
-// #[unsafe(no_mangle)]
-#[expect(unused_variables, reason = "function not yet implemented")]
-pub extern "C" fn timer_getoverrun(timerid: timer_t) -> c_int {
-    unimplemented!();
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn timer_getoverrun(timerid: timer_t) -> c_int {
+    Sys::timer_getoverrun(timerid).or_minus_one_errno()
 }
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/timer_gettime.html>.
@@ -653,42 +620,7 @@ pub unsafe extern "C" fn tzset() {
     let mut lock = TIMEZONE_LOCK.lock();
     // SAFETY: the caller is required to ensure that `daylight`, `timezone`
     // and `tzname` are not accessed by user code.
-    unsafe { clear_timezone(&mut lock) };
-
-    let tz_option = time_zone();
-
-    match tz_option {
-        Some(tz) => {
-            // IANA
-            let datetime = now();
-            let (std_time, dst_time) = match tz.from_local_datetime(&datetime) {
-                MappedLocalTime::Single(t) => (t, None),
-                // This variant contains the two possible results, in the order (earliest, latest).
-                MappedLocalTime::Ambiguous(t1, t2) => (t2, Some(t1)),
-                MappedLocalTime::None => return,
-            };
-
-            // SAFETY: the caller is required to ensure that `daylight`, `timezone`
-            // and `tzname` are not accessed by user code.
-            unsafe { set_timezone(&mut lock, &std_time, dst_time) }
-        }
-        None => {
-            // POSIX
-            let tz_posix = get_current_time_zone();
-            let tz = PosixTz::parse(tz_posix);
-            unsafe {
-                // SAFETY: the caller is required to ensure access exclusively for the
-                // holder of `TIMEZONE_LOCK`.
-                lock.0 = Some(CString::new(tz.std).unwrap());
-                lock.1 = Some(CString::new(tz.dst).unwrap());
-
-                tzname.0[0] = lock.0.as_ref().unwrap().as_ptr().cast_mut();
-                tzname.0[1] = lock.1.as_ref().unwrap().as_ptr().cast_mut();
-                daylight = i32::from(tz.daylight);
-                timezone = tz.timezone.unwrap_or(0);
-            }
-        }
-    }
+    zone::with_current(|zone| unsafe { set_timezone(&mut lock, zone) });
 }
 
 fn convert_tm_generic<Tz: TimeZone>(tz: &Tz, tm_val: &tm) -> Option<DateTime<Tz>> {
@@ -714,173 +646,81 @@ fn convert_tm_generic<Tz: TimeZone>(tz: &Tz, tm_val: &tm) -> Option<DateTime<Tz>
     }
 }
 
-/// # Safety
-/// The caller must ensure that `daylight`, `timezone` and `tzname` are not
-/// accessed by user code for the duration of the call (relibc functions are
-/// required to hold `TIMEZONE_LOCK` when accessing these).
-unsafe fn clear_timezone(guard: &mut MutexGuard<'_, (Option<CString>, Option<CString>)>) {
-    guard.0 = None;
-    guard.1 = None;
-
-    // SAFETY: the caller is required to ensure access exclusively for the
-    // holder of `TIMEZONE_LOCK`.
-    unsafe {
-        tzname.0[0] = ptr::null_mut();
-        tzname.0[1] = ptr::null_mut();
-        timezone = 0;
-        daylight = 0;
-    }
-}
-
-#[inline(always)]
-fn get_system_time_zone<'a>() -> Option<&'a str> {
-    // Resolve the symlink for localtime
-    const BSIZE: size_t = 100;
-    let mut buffer: [u8; BSIZE] = [0; BSIZE];
-
-    #[cfg(not(target_os = "redox"))]
-    let (localtime, prefix) = (c"/etc/localtime", "/usr/share/zoneinfo/");
-
-    #[cfg(target_os = "redox")]
-    let (localtime, prefix) = (c"/etc/localtime", "/usr/share/zoneinfo/");
-
-    if unsafe { readlink(localtime.as_ptr().cast(), buffer.as_mut_ptr().cast(), BSIZE) } == -1 {
-        return None;
-    }
-
-    let path = unsafe { CStr::from_ptr(buffer.as_mut_ptr().cast()) };
-
-    if let Ok(tz_name) = path.to_str()
-        && let Some(stripped) = tz_name.strip_prefix(prefix)
-    {
-        return Some(stripped);
-    }
-
-    None
-}
-
-fn get_current_time_zone<'a>() -> &'a str {
-    // Check the `TZ` environment variable
-    let tz_env = unsafe { getenv(c"TZ".as_ptr().cast()) };
-    if !tz_env.is_null()
-        && let Ok(tz) = unsafe { CStr::from_ptr(tz_env) }.to_str()
-    {
-        return tz;
-    }
-
-    // Fallback to the system's default time zone
-    if let Some(tz) = get_system_time_zone() {
-        return tz;
-    }
-
-    // If all else fails, use UTC
-    "UTC"
-}
-
-#[inline(always)]
-fn time_zone() -> Option<Tz> {
-    get_current_time_zone().parse().ok() //.unwrap_or(Tz::UTC)
-}
-
-#[inline(always)]
-fn now() -> NaiveDateTime {
-    let mut now = timespec::default();
-    if Sys::clock_gettime(CLOCK_REALTIME, Out::from_mut(&mut now)).is_ok() {}; // TODO what to do if Err?
-    DateTime::from_timestamp(now.tv_sec, now.tv_nsec as _)
-        .unwrap_or_default()
-        .naive_local()
-}
-
-#[inline(always)]
-fn get_localtime(
-    timer: time_t,
-    mut result: Out<tm>,
-) -> (Option<DateTime<Tz>>, Option<DateTime<Tz>>) {
-    // POSIX TODO
-    let tz = time_zone().unwrap_or(Tz::UTC);
-
-    // Convert UTC time to local time
-    let (std_time, dst_time) = match tz.timestamp_opt(timer, 0) {
-        MappedLocalTime::Single(t) => (Some(t), None),
-        // This variant contains the two possible results, in the order (earliest, latest).
-        MappedLocalTime::Ambiguous(t1, t2) => (Some(t2), Some(t1)),
-        MappedLocalTime::None => return (None, None),
-    };
-
-    let localtime = datetime_to_tm(&std_time.unwrap());
-    result.write(localtime);
-    (std_time, dst_time)
-}
-
-fn datetime_to_tm(local_time: &DateTime<Tz>) -> tm {
-    let tz = local_time.timezone().name();
-    let tz = tz.strip_prefix("Etc/").unwrap_or(tz);
+/// Returns the broken-down time of `local` seconds since the Epoch, as if it
+/// were UTC.
+fn seconds_to_tm(local: i64) -> Option<tm> {
+    let local = DateTime::from_timestamp(local, 0)?;
 
     let mut t = blank_tm();
-    // Populate the `tm` structure
-    t.tm_sec = local_time.second() as _;
-    t.tm_min = local_time.minute() as _;
-    t.tm_hour = local_time.hour() as _;
-    t.tm_mday = local_time.day() as _;
-    t.tm_mon = local_time.month0() as _; // 0-based month
-    t.tm_year = (local_time.year() - 1900) as _; // Years since 1900
-    t.tm_wday = local_time.weekday().num_days_from_sunday() as _;
-    t.tm_yday = local_time.ordinal0() as _; // 0-based day of year
-
-    let offset = local_time.offset();
-    t.tm_isdst = offset.dst_offset().num_hours() as _;
-    // Get the UTC offset in seconds
-    #[cfg(target_pointer_width = "64")]
-    let tm_gtoff = offset.fix().local_minus_utc().into();
-    #[cfg(target_pointer_width = "32")]
-    let tm_gtoff = offset.fix().local_minus_utc();
-    t.tm_gmtoff = tm_gtoff;
-
-    let tm_zone = {
-        let mut timezone_names = TIMEZONE_NAMES.lock();
-        timezone_names.get_or_init(BTreeSet::new);
-        let cstr = CString::new(tz).unwrap();
-        timezone_names.get_mut().unwrap().insert(cstr.clone());
-        timezone_names.get().unwrap().get(&cstr).unwrap().as_ptr()
-    };
-
-    t.tm_zone = tm_zone.cast();
-    t
-}
-
+    t.tm_sec = local.second() as _;
+    t.tm_min = local.minute() as _;
+    t.tm_hour = local.hour() as _;
+    t.tm_mday = local.day() as _;
+    t.tm_mon = local.month0() as _; // 0-based month
+    t.tm_year = (local.year() - 1900) as _; // Years since 1900
+    t.tm_wday = local.weekday().num_days_from_sunday() as _;
+    t.tm_yday = local.ordinal0() as _; // 0-based day of year
+    Some(t)
+}
+
+/// Returns the time in `t` in seconds since the Epoch, as if it were UTC.
+/// Fields outside of their usual range are normalized.
+fn tm_to_seconds(t: &tm) -> Option<i64> {
+    let month = i64::from(t.tm_mon);
+    let year = i32::try_from(i64::from(t.tm_year) + 1900 + month.div_euclid(12)).ok()?;
+    let first = NaiveDate::from_ymd_opt(year, month.rem_euclid(12) as u32 + 1, 1)?;
+    let days =
+        first.and_hms_opt(0, 0, 0)?.and_utc().timestamp() / SECS_PER_DAY + i64::from(t.tm_mday) - 1;
+    Some(
+        days * SECS_PER_DAY
+            + i64::from(t.tm_hour) * 60 * 60
+            + i64::from(t.tm_min) * 60
+            + i64::from(t.tm_sec),
+    )
+}
+
+/// Returns the broken-down local time in `zone` of `time` seconds since the
+/// Epoch.
+fn local_tm(zone: &zone::Zone, time: time_t) -> Option<tm> {
+    let local_time_type = zone.local_time_type(time);
+    let mut t = seconds_to_tm(time.checked_add(local_time_type.utoff.into())?)?;
+    t.tm_isdst = local_time_type.isdst.into();
+    t.tm_gmtoff = local_time_type.utoff.into();
+    t.tm_zone = zone_name(&local_time_type.name);
+    Some(t)
+}
+
+/// Returns a pointer to `name` for the `tm_zone` field, valid until the end
+/// of the program.
+fn zone_name(name: &str) -> *const c_char {
+    let mut timezone_names = TIMEZONE_NAMES.lock();
+    timezone_names.get_or_init(BTreeSet::new);
+    let cstr = CString::new(name).unwrap();
+    timezone_names.get_mut().unwrap().insert(cstr.clone());
+    timezone_names.get().unwrap().get(&cstr).unwrap().as_ptr()
+}
+
+/// Sets `daylight`, `timezone` and `tzname` for `zone`.
+///
 /// # Safety
 /// The caller must ensure that `daylight`, `timezone` and `tzname` are not
 /// accessed by user code for the duration of the call (relibc functions are
 /// required to hold `TIMEZONE_LOCK` when accessing these).
 unsafe fn set_timezone(
     guard: &mut MutexGuard<'_, (Option<CString>, Option<CString>)>,
-    std: &DateTime<Tz>,
-    dst: Option<DateTime<Tz>>,
+    zone: &zone::Zone,
 ) {
+    let globals = zone.globals();
+    guard.0 = Some(CString::new(&*globals.std).unwrap());
+    guard.1 = Some(CString::new(&*globals.dst).unwrap());
+
     // SAFETY: the caller is required to ensure access exclusively for the
     // holder of `TIMEZONE_LOCK`.
     unsafe {
-        let ut_offset = std.offset();
-
-        guard.0 = Some(CString::new(ut_offset.abbreviation().expect("Wrong timezone")).unwrap());
         tzname.0[0] = guard.0.as_ref().unwrap().as_ptr().cast_mut();
-
-        match dst {
-            Some(dst) => {
-                guard.1 = Some(
-                    CString::new(dst.offset().abbreviation().expect("Wrong timezone")).unwrap(),
-                );
-                tzname.0[1] = guard.1.as_ref().unwrap().as_ptr().cast_mut();
-                daylight = 1;
-            }
-            None => {
-                guard.1 = None;
-                tzname.0[1] = guard.0.as_ref().unwrap().as_ptr().cast_mut();
-                daylight = 0;
-            }
-        }
-
-        timezone = -c_long::from(ut_offset.fix().local_minus_utc());
+        tzname.0[1] = guard.1.as_ref().unwrap().as_ptr().cast_mut();
+        daylight = c_int::from(globals.daylight);
+        timezone = globals.timezone;
     }
 }
 
@@ -1,7 +1,13 @@
 use core::num::ParseIntError;
 
+use chrono::{Datelike, NaiveDate};
+
 use crate::platform::types::c_long;
 
+/// Rules of `EST5EDT`, which POSIX leaves to the implementation when a
+/// daylight saving time zone has none.
+const DEFAULT_RULES: &str = "M3.2.0,M11.1.0";
+
 #[derive(Debug)]
 pub struct PosixTz<'a> {
     pub std: &'a str,
@@ -17,10 +23,70 @@ pub struct PosixTz<'a> {
 
 #[derive(Debug)]
 struct TransitionTime {
-    month: u8,         // 1-12
-    week_of_month: u8, // 1-5
-    day_of_week: u8,   // 0-6, 0 = Sun
-    time: Option<u32>, // time of transition in seconds
+    date: TransitionDate,
+    time: i32, // time of transition in seconds, in the local time before it
+}
+
+#[derive(Debug)]
+enum TransitionDate {
+    /// `Jn`: day of the year, 1-365, without February 29.
+    Julian(u16),
+    /// `n`: day of the year, 0-365.
+    Day(u16),
+    /// `Mm.w.d`: day `d` (0-6, 0 = Sun) of week `w` (1-5, 5 = last) of month
+    /// `m` (1-12).
+    MonthWeekDay { month: u8, week: u8, day: u8 },
+}
+
+impl TransitionTime {
+    fn parse(input: &str) -> Option<Self> {
+        let (date, time) = match input.split_once('/') {
+            Some((date, time)) => (date, PosixTz::time_to_seconds(time).ok()?),
+            None => (input, 2 * 60 * 60),
+        };
+
+        let date = if let Some(day) = date.strip_prefix('J') {
+            TransitionDate::Julian(day.parse().ok().filter(|day| (1..=365).contains(day))?)
+        } else if let Some(date) = date.strip_prefix('M') {
+            let mut fields = date.splitn(3, '.').map(|field| field.parse::<u8>().ok());
+            let (month, week, day) = (fields.next()??, fields.next()??, fields.next()??);
+            if !(1..=12).contains(&month) || !(1..=5).contains(&week) || day > 6 {
+                return None;
+            }
+            TransitionDate::MonthWeekDay { month, week, day }
+        } else {
+            TransitionDate::Day(date.parse().ok().filter(|day| *day <= 365)?)
+        };
+        Some(Self { date, time })
+    }
+
+    /// Returns the local time of the transition in `year`, in seconds since
+    /// the Epoch.
+    fn local_time(&self, year: i32) -> Option<i64> {
+        let date = match self.date {
+            TransitionDate::Julian(day) => {
+                let date = NaiveDate::from_yo_opt(year, u32::from(day))?;
+                // February 29 is never counted.
+                if date.leap_year() && day >= 60 {
+                    date.succ_opt()?
+                } else {
+                    date
+                }
+            }
+            TransitionDate::Day(day) => NaiveDate::from_yo_opt(year, u32::from(day) + 1)?,
+            TransitionDate::MonthWeekDay { month, week, day } => {
+                let first = NaiveDate::from_ymd_opt(year, u32::from(month), 1)?;
+                let first_day = first.weekday().num_days_from_sunday() as u8;
+                let mut mday = 1 + (day + 7 - first_day) % 7 + (week - 1) * 7;
+                // Week 5 is the last week, which may be the 4th.
+                while NaiveDate::from_ymd_opt(year, u32::from(month), u32::from(mday)).is_none() {
+                    mday -= 7;
+                }
+                first.with_day(u32::from(mday))?
+            }
+        };
+        Some(date.and_hms_opt(0, 0, 0)?.and_utc().timestamp() + i64::from(self.time))
+    }
 }
 
 impl<'a> PosixTz<'a> {
@@ -58,27 +124,32 @@ impl<'a> PosixTz<'a> {
             result.timezone = Some(c_long::from(std_offset.unwrap_or(0)).clamp(-86400, 86400));
 
             // dst rules: same as std
-            let (dst, _, _) = PosixTz::collect_tz_and_offset(remaining);
+            let (dst, dst_offset, _) = PosixTz::collect_tz_and_offset(remaining);
             result.dst = dst;
 
             if !dst.is_empty() {
                 result.daylight = true;
             }
 
-            // Likely defaults to std_offset - 3600. Will need to test in another method when needed
-            result.dst_offset = std_offset; // Possibly keep as None if 0. Need to study other functions
+            // One hour ahead of standard time by default
+            result.dst_offset = dst_offset.or(std_offset.map(|offset| offset - 60 * 60));
         }
 
-        // UNIMPLEMENTED: start/end aren't required by tzset, will implement after testing functions
-        // that require it
         let mut has_transition_rules = false;
-        if let Some(_start) = input_split.next() {
+        if let Some(start) = input_split.next() {
             has_transition_rules = true;
             result.daylight = true;
+            result.start = TransitionTime::parse(start);
         }
-        if let Some(_end) = input_split.next() {
+        if let Some(end) = input_split.next() {
             has_transition_rules = true;
             result.daylight = true;
+            result.end = TransitionTime::parse(end);
+        }
+        if !has_transition_rules && result.daylight {
+            let (start, end) = DEFAULT_RULES.split_once(',').unwrap();
+            result.start = TransitionTime::parse(start);
+            result.end = TransitionTime::parse(end);
         }
 
         // Set dst to std if:
@@ -95,19 +166,26 @@ impl<'a> PosixTz<'a> {
     fn collect_tz_and_offset(input: &str) -> (&str, Option<i32>, &str) {
         let mut result = ("", None, input);
 
-        // TZ rules: 3+ ascii characters
-        let std_end = input
-            .find(|c: char| !c.is_ascii_alphabetic())
-            .unwrap_or(input.len());
-        let std = &input[..std_end];
+        // TZ rules: 3+ ascii characters, or any characters between < and >
+        let (std, remaining) = match input
+            .strip_prefix('<')
+            .and_then(|quoted| quoted.split_once('>'))
+        {
+            Some((std, remaining)) => (std, remaining),
+            None => {
+                let std_end = input
+                    .find(|c: char| !c.is_ascii_alphabetic())
+                    .unwrap_or(input.len());
+                input.split_at(std_end)
+            }
+        };
         if std.len() >= 3 {
             result.0 = std;
         }
-        let remaining = &input[std_end..];
 
         // offset rules: +-hh (optional :mm (optional :ss))
         let offset_end = remaining
-            .find(|c: char| c.is_ascii_alphabetic())
+            .find(|c: char| c.is_ascii_alphabetic() || c == '<')
             .unwrap_or(remaining.len());
         let offset_secs = PosixTz::time_to_seconds(&remaining[..offset_end]).ok();
         result.1 = offset_secs;
@@ -116,6 +194,46 @@ impl<'a> PosixTz<'a> {
         result
     }
 
+    /// Returns the offset from UTC in seconds, whether daylight saving time is
+    /// in effect and the abbreviation of the time zone, at `time` seconds since
+    /// the Epoch.
+    pub fn local_time_type(&self, time: i64) -> (i32, bool, &'a str) {
+        // Offsets are in seconds west of UTC.
+        let std_offset = self.std_offset.unwrap_or(0);
+        let std = (-std_offset, false, self.std);
+        if !self.daylight || self.dst.is_empty() {
+            return std;
+        }
+        let (Some(start), Some(end)) = (&self.start, &self.end) else {
+            return std;
+        };
+        let dst_offset = self.dst_offset.unwrap_or(std_offset - 60 * 60);
+
+        let Some(year) = chrono::DateTime::from_timestamp(time - i64::from(std_offset), 0)
+            .map(|local| local.year())
+        else {
+            return std;
+        };
+        let (Some(start), Some(end)) = (start.local_time(year), end.local_time(year)) else {
+            return std;
+        };
+        // Transitions happen in the local time in effect before them.
+        let start = start + i64::from(std_offset);
+        let end = end + i64::from(dst_offset);
+
+        let is_dst = if start < end {
+            start <= time && time < end
+        } else {
+            // Southern hemisphere
+            !(end <= time && time < start)
+        };
+        if is_dst {
+            (-dst_offset, true, self.dst)
+        } else {
+            std
+        }
+    }
+
     fn time_to_seconds(time_str: &str) -> Result<i32, ParseIntError> {
         let mut result = 0;
         let mut time_split = time_str.split(':');
@@ -2,312 +2,529 @@
 //
 // See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/strftime.html>.
 
-use alloc::string::String;
+use alloc::{string::String, vec::Vec};
+use core::{ffi::CStr, fmt::Write};
 
 use super::tm;
-use crate::{
-    c_str::CStr,
-    casting::{CCharPtrToU8, U8PtrToCCharPtr},
-    platform::{
-        self, WriteByte,
-        types::{c_char, c_int, size_t},
+use crate::header::{
+    bits_locale_t::locale_t,
+    langinfo::{
+        ABALTMON_1, ABDAY_1, ABMON_1, ALT_DIGITS, ALTMON_1, AM_STR, D_FMT, D_T_FMT, DAY_1, ERA,
+        ERA_D_FMT, ERA_D_T_FMT, ERA_T_FMT, MON_1, PM_STR, T_FMT, T_FMT_AMPM, nl_item,
+        nl_langinfo_l,
     },
 };
 
-// We use the langinfo constants
-use crate::header::langinfo::{
-    ABDAY_1,
-    ABMON_1,
-    AM_STR,
-    DAY_1,
-    MON_1,
-    PM_STR,
-    // TODO : other constants if needed
-    nl_item,
-    nl_langinfo,
-};
-
-/// A helper that calls `nl_langinfo(item)` and converts the returned pointer
-/// into a `&str`. If it fails or is null, returns an empty string "".
-unsafe fn langinfo_to_str(item: nl_item) -> &'static str {
-    use core::ffi::CStr;
+/// The output would not fit in the buffer.
+struct Full;
+
+/// Flags, width and modifier of a conversion specification, such as `%_10E`.
+#[derive(Default)]
+struct Spec {
+    /// `_` to pad with spaces, `-` not to pad numbers, or `0` to pad with
+    /// zeros.
+    pad: Option<u8>,
+    width: usize,
+    /// `^`: convert to uppercase.
+    upcase: bool,
+    /// `#`: swap the case of names.
+    swapcase: bool,
+    /// `E` or `O`.
+    modifier: Option<u8>,
+}
 
-    let ptr = unsafe { nl_langinfo(item) };
-    if ptr.is_null() {
-        return "";
-    }
-    unsafe { CStr::from_ptr(ptr) }.to_str().unwrap_or_default()
+/// How names are written.
+#[derive(Clone, Copy, PartialEq)]
+enum Case {
+    Keep,
+    Upper,
+    Lower,
 }
 
-/// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/strftime.html>.
-///
-/// Formats time data according to the given `format` string.
-///
-/// Use `langinfo` for locale-based day/month names,
-/// but still hard-codes other aspects of the "C" locale (like numeric/date
-/// formats) and ignores `%E` / `%O` variations.
-pub unsafe fn strftime<W: WriteByte>(w: &mut W, format: *const c_char, t: *const tm) -> size_t {
-    /// Helper that actually parses the format string and writes output.
-    pub unsafe fn inner_strftime<W: WriteByte>(
-        w: &mut W,
-        mut format: *const c_char,
-        t: *const tm,
-    ) -> bool {
-        macro_rules! w {
-            (byte $b:expr) => {{
-                if w.write_u8($b).is_err() {
-                    return false;
-                }
-            }};
-            (char $chr:expr) => {{
-                if w.write_char($chr).is_err() {
-                    return false;
-                }
-            }};
-            (recurse $fmt:expr) => {{
-                let mut tmp = String::with_capacity($fmt.len() + 1);
-                tmp.push_str($fmt);
-                tmp.push('\0');
-
-                if unsafe { !inner_strftime(w, U8PtrToCCharPtr::cast_mut(tmp.as_ptr()), t) } {
-                    return false;
-                }
-            }};
-            ($str:expr) => {{
-                if w.write_str($str).is_err() {
-                    return false;
-                }
-            }};
-            ($fmt:expr, $($args:expr),+) => {{
-                // Could use write!() if we didn't need the exact count
-                if write!(w, $fmt, $($args),+).is_err() {
-                    return false;
-                }
-            }};
-        }
+/// An era of the `ERA` item of the locale, containing the formatted time.
+struct Era<'a> {
+    name: &'a [u8],
+    format: &'a [u8],
+    year: i64,
+}
 
-        while unsafe { *format } != 0 {
-            // If the character isn't '%', just copy it out.
-            if unsafe { CCharPtrToU8::from_const(format) } != b'%' {
-                w!(byte unsafe { CCharPtrToU8::from_const(format) });
-                format = unsafe { format.add(1) };
-                continue;
-            }
+struct Formatter<'a> {
+    t: &'a tm,
+    loc: locale_t,
+    out: Vec<u8>,
+    /// Length at which the output no longer fits in the buffer.
+    limit: usize,
+}
 
-            // Skip '%'
-            format = unsafe { format.add(1) };
+/// Formats the time `t` according to `format` and the `LC_TIME` category of
+/// `loc`, as glibc does, including its flags `_`, `-`, `0`, `^` and `#`, and
+/// field widths.
+///
+/// Returns `None` if the output is `maxsize` bytes or longer.
+pub fn strftime(format: &[u8], t: &tm, loc: locale_t, maxsize: usize) -> Option<Vec<u8>> {
+    let mut formatter = Formatter {
+        t,
+        loc,
+        out: Vec::new(),
+        limit: maxsize,
+    };
+    formatter.format(format).ok()?;
+    (formatter.out.len() < maxsize).then_some(formatter.out)
+}
 
-            // POSIX says '%E' and '%O' can modify numeric formats for locales,
-            // but we ignore them in this minimal "C" locale approach.
-            if unsafe { CCharPtrToU8::from_const(format) } == b'E'
-                || unsafe { CCharPtrToU8::from_const(format) } == b'O'
-            {
-                format = unsafe { format.add(1) };
-            }
+impl Formatter<'_> {
+    fn push(&mut self, bytes: &[u8]) -> Result<(), Full> {
+        if self.out.len() + bytes.len() >= self.limit {
+            return Err(Full);
+        }
+        self.out.extend_from_slice(bytes);
+        Ok(())
+    }
 
-            match unsafe { CCharPtrToU8::from_const(format) } {
-                // Literal '%'
-                b'%' => w!(byte b'%'),
+    fn fill(&mut self, byte: u8, count: usize) -> Result<(), Full> {
+        if self.out.len().saturating_add(count) >= self.limit {
+            return Err(Full);
+        }
+        self.out.resize(self.out.len() + count, byte);
+        Ok(())
+    }
 
-                // Newline and tab expansions
-                b'n' => w!(byte b'\n'),
-                b't' => w!(byte b'\t'),
+    /// Pads `len` bytes to the width of `spec`.
+    fn pad(&mut self, spec: &Spec, width: usize, len: usize) -> Result<(), Full> {
+        let byte = if spec.pad == Some(b'0') { b'0' } else { b' ' };
+        self.fill(byte, width.saturating_sub(len))
+    }
 
-                // Abbreviated weekday name: %a
-                b'a' => {
-                    // `ABDAY_1 + tm_wday` is the correct langinfo ID for abbreviated weekdays
-                    let s = unsafe { langinfo_to_str(ABDAY_1 + (*t).tm_wday as usize) };
-                    w!(s);
-                }
+    fn string(&mut self, spec: &Spec, s: &[u8], case: Case) -> Result<(), Full> {
+        self.pad(spec, spec.width, s.len())?;
+        let start = self.out.len();
+        self.push(s)?;
+        match case {
+            Case::Keep => {}
+            Case::Upper => self.out[start..].make_ascii_uppercase(),
+            Case::Lower => self.out[start..].make_ascii_lowercase(),
+        }
+        Ok(())
+    }
 
-                // Full weekday name: %A
-                b'A' => {
-                    // `DAY_1 + tm_wday` is the correct langinfo ID for full weekdays
-                    let s = unsafe { langinfo_to_str(DAY_1 + (*t).tm_wday as usize) };
-                    w!(s);
-                }
+    /// Writes a name, which `#` converts to uppercase.
+    fn name(&mut self, spec: &Spec, item: nl_item) -> Result<(), Full> {
+        let case = if spec.upcase || spec.swapcase {
+            Case::Upper
+        } else {
+            Case::Keep
+        };
+        self.string(spec, self.langinfo(item), case)
+    }
 
-                // Abbreviated month name: %b or %h
-                b'b' | b'h' => {
-                    let s = unsafe { langinfo_to_str(ABMON_1 + (*t).tm_mon as usize) };
-                    w!(s);
-                }
+    /// Writes `value` with at least `digits` digits, padded with zeros unless
+    /// `spacepad` is set.
+    fn number(
+        &mut self,
+        spec: &Spec,
+        digits: usize,
+        value: i64,
+        spacepad: bool,
+    ) -> Result<(), Full> {
+        let mut pad = spec.pad;
+        if spacepad && pad != Some(b'0') && pad != Some(b'-') {
+            pad = Some(b'_');
+        }
 
-                // Full month name: %B
-                b'B' => {
-                    let s = unsafe { langinfo_to_str(MON_1 + (*t).tm_mon as usize) };
-                    w!(s);
-                }
+        if spec.modifier == Some(b'O')
+            && value >= 0
+            && let Some(digit) = self.alt_digit(value)
+        {
+            let spec = Spec { pad, ..*spec };
+            return self.string(&spec, digit, Case::Keep);
+        }
 
-                // Century: %C
-                b'C' => {
-                    let mut year = unsafe { (*t).tm_year } / 100;
-                    if unsafe { (*t).tm_year } % 100 != 0 {
-                        year += 1;
-                    }
-                    w!("{:02}", year + 19);
+        let mut buf = String::new();
+        let _ = write!(buf, "{}", value.unsigned_abs());
+        let len = buf.len() + usize::from(value < 0);
+        let digits = digits.max(spec.width);
+        let mut width = spec.width;
+        let mut sign = value < 0;
+
+        if pad != Some(b'-') && digits > len {
+            let padding = digits - len;
+            if pad == Some(b'_') {
+                self.fill(b' ', padding)?;
+            } else {
+                if sign {
+                    self.push(b"-")?;
+                    sign = false;
+                    width = width.saturating_sub(1);
                 }
+                self.fill(b'0', padding)?;
+            }
+            width = width.saturating_sub(padding);
+        }
 
-                // Day of month: %d
-                b'd' => w!("{:02}", unsafe { (*t).tm_mday }),
-
-                // %D => same as %m/%d/%y
-                b'D' => w!(recurse "%m/%d/%y"),
-
-                // Day of month, space-padded: %e
-                b'e' => w!("{:2}", unsafe { (*t).tm_mday }),
-
-                // ISO 8601 date: %F => %Y-%m-%d
-                b'F' => w!(recurse "%Y-%m-%d"),
+        let len = buf.len() + usize::from(sign);
+        self.pad(&Spec { pad, ..*spec }, width, len)?;
+        if sign {
+            self.push(b"-")?;
+        }
+        self.push(buf.as_bytes())
+    }
 
-                // Hour (00-23): %H
-                b'H' => w!("{:02}", unsafe { (*t).tm_hour }),
+    /// Writes the time formatted by `format`, padded as a whole.
+    fn subformat(&mut self, spec: &Spec, format: &[u8]) -> Result<(), Full> {
+        let mut sub = Formatter {
+            t: self.t,
+            loc: self.loc,
+            out: Vec::new(),
+            limit: self.limit - self.out.len(),
+        };
+        sub.format(format)?;
+        let case = if spec.upcase { Case::Upper } else { Case::Keep };
+        self.string(spec, &sub.out, case)
+    }
 
-                // Hour (01-12): %I
-                b'I' => w!("{:02}", (unsafe { (*t).tm_hour } + 12 - 1) % 12 + 1),
+    /// Returns the item `item` of the locale, which stays valid while the
+    /// locale is in use.
+    fn langinfo(&self, item: nl_item) -> &'static [u8] {
+        unsafe { CStr::from_ptr(nl_langinfo_l(item, self.loc)) }.to_bytes()
+    }
 
-                // Day of year: %j
-                b'j' => w!("{:03}", unsafe { (*t).tm_yday } + 1),
+    /// Returns the `E` variant of the format `item`, or `fallback` if there
+    /// is none.
+    fn era_format(&self, spec: &Spec, item: nl_item, fallback: nl_item) -> &'static [u8] {
+        match self.langinfo(item) {
+            format if spec.modifier == Some(b'E') && !format.is_empty() => format,
+            _ => self.langinfo(fallback),
+        }
+    }
 
-                // etc.
-                b'k' => w!("{:2}", unsafe { (*t).tm_hour }),
-                b'l' => w!("{:2}", (unsafe { (*t).tm_hour } + 12 - 1) % 12 + 1),
-                b'm' => w!("{:02}", unsafe { (*t).tm_mon } + 1),
-                b'M' => w!("{:02}", unsafe { (*t).tm_min }),
+    fn alt_digit(&self, value: i64) -> Option<&'static [u8]> {
+        let digits = self.langinfo(ALT_DIGITS);
+        if digits.is_empty() {
+            return None;
+        }
+        let digit = digits.split(|&b| b == b';').nth(value.try_into().ok()?)?;
+        (!digit.is_empty()).then_some(digit)
+    }
 
-                // AM/PM (uppercase): %p
-                b'p' => {
-                    // Get "AM" / "PM" from langinfo
-                    if unsafe { (*t).tm_hour } < 12 {
-                        w!(unsafe { langinfo_to_str(AM_STR) });
-                    } else {
-                        w!(unsafe { langinfo_to_str(PM_STR) });
-                    }
-                }
+    /// Returns the era of the locale that contains the time, if the
+    /// conversion has the `E` modifier.
+    fn era(&self, spec: &Spec) -> Option<Era<'static>> {
+        if spec.modifier != Some(b'E') {
+            return None;
+        }
+        let date = (
+            i64::from(self.t.tm_year) + 1900,
+            self.t.tm_mon + 1,
+            self.t.tm_mday,
+        );
+
+        eras(self.langinfo(ERA)).find_map(|era| {
+            let contains = if era.start <= era.end {
+                (era.start..=era.end).contains(&date)
+            } else {
+                (era.end..=era.start).contains(&date)
+            };
+            contains.then(|| Era {
+                name: era.name,
+                format: era.format,
+                year: era.offset + (date.0 - era.start.0) * era.direction,
+            })
+        })
+    }
 
-                // am/pm (lowercase): %P
-                b'P' => {
-                    // Convert the AM_STR / PM_STR to lowercase
-                    if unsafe { (*t).tm_hour } < 12 {
-                        let am = unsafe { langinfo_to_str(AM_STR) }.to_ascii_lowercase();
-                        w!(&am);
-                    } else {
-                        let pm = unsafe { langinfo_to_str(PM_STR) }.to_ascii_lowercase();
-                        w!(&pm);
-                    }
+    fn format(&mut self, format: &[u8]) -> Result<(), Full> {
+        let mut i = 0;
+        while i < format.len() {
+            if format[i] != b'%' {
+                self.push(&format[i..=i])?;
+                i += 1;
+                continue;
+            }
+            let start = i;
+            i += 1;
+
+            let mut spec = Spec::default();
+            while let Some(&c) = format.get(i) {
+                match c {
+                    b'_' | b'-' | b'0' => spec.pad = Some(c),
+                    b'^' => spec.upcase = true,
+                    b'#' => spec.swapcase = true,
+                    _ => break,
                 }
+                i += 1;
+            }
+            while let Some(&c) = format.get(i).filter(|c| c.is_ascii_digit()) {
+                spec.width = spec
+                    .width
+                    .saturating_mul(10)
+                    .saturating_add(usize::from(c - b'0'));
+                i += 1;
+            }
+            if let Some(&c @ (b'E' | b'O')) = format.get(i) {
+                spec.modifier = Some(c);
+                i += 1;
+            }
 
-                // 12-hour clock with seconds + AM/PM: %r => %I:%M:%S %p
-                b'r' => w!(recurse "%I:%M:%S %p"),
-
-                // 24-hour clock without seconds: %R => %H:%M
-                b'R' => w!(recurse "%H:%M"),
-
-                // Seconds since the Epoch: %s => calls mktime() to convert tm to time_t
-                b's' => w!("{}", unsafe { super::mktime(t.cast_mut()) }),
-
-                // Seconds (00-60): %S (unchanged)
-                b'S' => w!("{:02}", unsafe { (*t).tm_sec }),
-
-                // 24-hour clock with seconds: %T => %H:%M:%S
-                b'T' => w!(recurse "%H:%M:%S"),
-
-                // Weekday (1-7, Monday=1): %u
-                b'u' => w!("{}", (unsafe { (*t).tm_wday } + 7 - 1) % 7 + 1),
-
-                // Sunday-based week of year: %U
-                b'U' => w!(
-                    "{}",
-                    (unsafe { (*t).tm_yday } + 7 - unsafe { (*t).tm_wday }) / 7
-                ),
+            let end = (i + 1).min(format.len());
+            let conversion = format.get(i).copied().unwrap_or(0);
+            if !self.conversion(&spec, conversion)? {
+                // Invalid conversions are copied as they are.
+                let case = if spec.upcase { Case::Upper } else { Case::Keep };
+                self.string(&spec, &format[start..end], case)?;
+            }
+            i = end;
+        }
+        Ok(())
+    }
 
-                // ISO-8601 week of year
-                b'V' => w!("{}", week_of_year(unsafe { &*t })),
+    /// Writes one conversion, returning `false` if it is not valid.
+    fn conversion(&mut self, spec: &Spec, conversion: u8) -> Result<bool, Full> {
+        let t = self.t;
+        let modifier = spec.modifier;
+        let hour12 = match t.tm_hour {
+            0 => 12,
+            hour if hour > 12 => hour - 12,
+            hour => hour,
+        };
+
+        match (conversion, modifier) {
+            (b'a' | b'A' | b'D' | b'F' | b'+', Some(_))
+            | (b'c' | b'x' | b'X' | b'Y', Some(b'O'))
+            | (
+                b'b' | b'B' | b'h' | b'd' | b'e' | b'g' | b'G' | b'H' | b'I' | b'j' | b'k' | b'l'
+                | b'm' | b'M' | b'S' | b'U' | b'V' | b'w' | b'W',
+                Some(b'E'),
+            ) => return Ok(false),
+            _ => {}
+        }
 
-                // Weekday (0-6, Sunday=0): %w
-                b'w' => w!("{}", unsafe { (*t).tm_wday }),
+        match conversion {
+            b'%' => self.string(spec, b"%", Case::Keep)?,
+            b'n' => self.string(spec, b"\n", Case::Keep)?,
+            b't' => self.string(spec, b"\t", Case::Keep)?,
+
+            // Names of the weekday and month
+            b'a' => self.name(spec, ABDAY_1 + weekday(t))?,
+            b'A' => self.name(spec, DAY_1 + weekday(t))?,
+            b'b' | b'h' if modifier == Some(b'O') => self.name(spec, ABALTMON_1 + month(t))?,
+            b'b' | b'h' => self.name(spec, ABMON_1 + month(t))?,
+            b'B' if modifier == Some(b'O') => self.name(spec, ALTMON_1 + month(t))?,
+            b'B' => self.name(spec, MON_1 + month(t))?,
+
+            // AM or PM, lowercase with `%P`
+            b'p' | b'P' => {
+                let case = if conversion == b'P' || spec.swapcase {
+                    Case::Lower
+                } else if spec.upcase {
+                    Case::Upper
+                } else {
+                    Case::Keep
+                };
+                let item = if t.tm_hour > 11 { PM_STR } else { AM_STR };
+                self.string(spec, self.langinfo(item), case)?;
+            }
 
-                // Monday-based week of year: %W
-                b'W' => w!(
-                    "{}",
-                    (unsafe { (*t).tm_yday } + 7 - (unsafe { (*t).tm_wday } + 6) % 7) / 7
-                ),
+            // Formats of the locale
+            b'c' => self.subformat(spec, self.era_format(spec, ERA_D_T_FMT, D_T_FMT))?,
+            b'x' => self.subformat(spec, self.era_format(spec, ERA_D_FMT, D_FMT))?,
+            b'X' => self.subformat(spec, self.era_format(spec, ERA_T_FMT, T_FMT))?,
+            b'r' => match self.langinfo(T_FMT_AMPM) {
+                b"" => self.subformat(spec, b"%I:%M:%S %p")?,
+                format => self.subformat(spec, format)?,
+            },
+
+            b'D' => self.subformat(spec, b"%m/%d/%y")?,
+            b'F' => self.subformat(spec, b"%Y-%m-%d")?,
+            b'R' => self.subformat(spec, b"%H:%M")?,
+            b'T' => self.subformat(spec, b"%H:%M:%S")?,
+            // Extension from BSD, as the format of date(1)
+            b'+' => self.subformat(spec, b"%a %b %d %T %Z %Y")?,
+
+            // Year
+            b'C' => match self.era(spec) {
+                Some(era) => self.string(spec, era.name, Case::Keep)?,
+                None => self.number(spec, 1, year(t).div_euclid(100), false)?,
+            },
+            b'y' => match self.era(spec) {
+                Some(era) => self.number(spec, 1, era.year, false)?,
+                None => self.number(spec, 2, i64::from(t.tm_year).rem_euclid(100), false)?,
+            },
+            b'Y' => match self.era(spec) {
+                Some(era) => self.subformat(spec, era.format)?,
+                None => self.number(spec, 1, year(t), false)?,
+            },
+
+            // Week-based year and week of ISO 8601
+            b'G' => self.number(spec, 1, iso_week(t).0, false)?,
+            b'g' => self.number(spec, 2, iso_week(t).0.rem_euclid(100), false)?,
+            b'V' => self.number(spec, 2, iso_week(t).1, false)?,
+
+            // Other numbers
+            b'd' => self.number(spec, 2, t.tm_mday.into(), false)?,
+            b'e' => self.number(spec, 2, t.tm_mday.into(), true)?,
+            b'H' => self.number(spec, 2, t.tm_hour.into(), false)?,
+            b'I' => self.number(spec, 2, hour12.into(), false)?,
+            b'k' => self.number(spec, 2, t.tm_hour.into(), true)?,
+            b'l' => self.number(spec, 2, hour12.into(), true)?,
+            b'j' => self.number(spec, 3, i64::from(t.tm_yday) + 1, false)?,
+            b'm' => self.number(spec, 2, i64::from(t.tm_mon) + 1, false)?,
+            b'M' => self.number(spec, 2, t.tm_min.into(), false)?,
+            b'S' => self.number(spec, 2, t.tm_sec.into(), false)?,
+            b'u' => self.number(spec, 1, (i64::from(t.tm_wday) + 6) % 7 + 1, false)?,
+            b'w' => self.number(spec, 1, t.tm_wday.into(), false)?,
+            b'U' => {
+                let week = (i64::from(t.tm_yday) - i64::from(t.tm_wday) + 7) / 7;
+                self.number(spec, 2, week, false)?;
+            }
+            b'W' => {
+                let monday = (i64::from(t.tm_wday) + 6) % 7;
+                self.number(spec, 2, (i64::from(t.tm_yday) - monday + 7) / 7, false)?;
+            }
 
-                // US format date: %m/%d/%y.
-                b'x' => w!(recurse "%m/%d/%y"),
+            // Seconds since the Epoch
+            b's' => {
+                let mut copy = tm { ..*t };
+                let time = unsafe { super::mktime(&mut copy) };
+                self.number(
+                    &Spec {
+                        modifier: None,
+                        ..*spec
+                    },
+                    1,
+                    time,
+                    false,
+                )?;
+            }
 
-                // US format time: %H:%M:%S
-                b'X' => w!(recurse "%T"),
+            // Offset from UTC, as `+hhmm`, unknown if `tm_isdst` is negative
+            b'z' if t.tm_isdst < 0 => {}
+            b'z' => {
+                let offset = i64::from(t.tm_gmtoff);
+                let sign: &[u8] = if offset < 0 { b"-" } else { b"+" };
+                self.string(spec, sign, Case::Keep)?;
+                let minutes = offset.abs() / 60;
+                self.number(spec, 4, minutes / 60 * 100 + minutes % 60, false)?;
+            }
 
-                // Last two digits of year: %y
-                b'y' => w!("{:02}", unsafe { (*t).tm_year } % 100),
+            // Abbreviation of the time zone
+            b'Z' => {
+                let zone = if t.tm_zone.is_null() {
+                    &[]
+                } else {
+                    unsafe { CStr::from_ptr(t.tm_zone) }.to_bytes()
+                };
+                let case = if spec.swapcase {
+                    Case::Lower
+                } else if spec.upcase {
+                    Case::Upper
+                } else {
+                    Case::Keep
+                };
+                self.string(spec, zone, case)?;
+            }
 
-                // Full year: %Y
-                b'Y' => w!("{}", unsafe { (*t).tm_year } + 1900),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
 
-                // Timezone offset: %z
-                b'z' => {
-                    let offset = unsafe { (*t).tm_gmtoff };
-                    let (sign, offset) = if offset < 0 {
-                        ('-', -offset)
-                    } else {
-                        ('+', offset)
-                    };
-                    let mins = offset.div_euclid(60);
-                    let min = mins.rem_euclid(60);
-                    let hour = mins.div_euclid(60);
-                    w!("{}{:02}{:02}", sign, hour, min)
-                }
+fn year(t: &tm) -> i64 {
+    i64::from(t.tm_year) + 1900
+}
 
-                // Timezone name: %Z
-                b'Z' => w!(
-                    "{}",
-                    unsafe { CStr::from_ptr((*t).tm_zone) }.to_str().unwrap()
-                ),
+fn weekday(t: &tm) -> nl_item {
+    t.tm_wday.rem_euclid(7) as nl_item
+}
 
-                // Date+time+TZ: %+
-                b'+' => w!(recurse "%a %b %d %T %Z %Y"),
+fn month(t: &tm) -> nl_item {
+    t.tm_mon.rem_euclid(12) as nl_item
+}
 
-                // Unrecognized format specifier => fail
-                _ => return false,
-            }
+fn is_leap(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
 
-            // Move past the format specifier
-            format = unsafe { format.add(1) };
-        }
-        true
+/// Returns the week-based year and the week of the year of ISO 8601, in which
+/// weeks start on Monday, and the first week of a year is the one containing
+/// its first Thursday.
+fn iso_week(t: &tm) -> (i64, i64) {
+    // Number of days between the Monday of the first week of the year and
+    // the day `yday` of the year, which is a `wday`.
+    let days = |yday: i64, wday: i64| yday - (yday - wday + 4).rem_euclid(7) + 3;
+
+    let (yday, wday) = (i64::from(t.tm_yday), i64::from(t.tm_wday));
+    let year = year(t);
+    let days_this_year = days(yday, wday);
+    if days_this_year < 0 {
+        // The week belongs to the previous year.
+        let days = days(yday + 365 + i64::from(is_leap(year - 1)), wday);
+        return (year - 1, days / 7 + 1);
     }
-
-    // Wrap the writer in a CountingWriter to return how many bytes were written.
-    let mut cw = platform::CountingWriter::new(w);
-    if unsafe { !inner_strftime(&mut cw, format, t) } {
-        return 0;
+    let days_next_year = days(yday - 365 - i64::from(is_leap(year)), wday);
+    if days_next_year >= 0 {
+        (year + 1, days_next_year / 7 + 1)
+    } else {
+        (year, days_this_year / 7 + 1)
     }
-    cw.written
 }
 
-/// Calculate number of weeks in a year as defined by ISO 8601
-///
-/// ## Source
-/// https://en.wikipedia.org/wiki/ISO_week_date
-fn weeks_per_year(year: c_int) -> c_int {
-    let year = f64::from(year);
-    let p_y = (year + (year / 4.) - (year / 100.) + (year / 400.)) as c_int % 7;
-    if p_y == 4 { 53 } else { 52 }
+/// A segment of the `ERA` item of a locale.
+#[derive(Clone, Copy)]
+pub(super) struct EraEntry<'a> {
+    /// `1` if years count up from the start date, `-1` if they count down.
+    pub direction: i64,
+    /// The year of the era at the start date.
+    pub offset: i64,
+    pub start: (i64, i32, i32),
+    pub end: (i64, i32, i32),
+    pub name: &'a [u8],
+    pub format: &'a [u8],
 }
 
-/// Calculate the week of the year accounting for leap weeks (ISO 8601)
-///
-/// ## Source
-/// https://en.wikipedia.org/wiki/ISO_week_date
-fn week_of_year(time: &tm) -> c_int {
-    let week = (10 + time.tm_yday - time.tm_wday) / 7;
-
-    if week <= 1 {
-        weeks_per_year(time.tm_year - 1)
-    } else if week > weeks_per_year(time.tm_year) {
-        1
-    } else {
-        week
+/// Parses the `ERA` item of a locale, whose segments are
+/// `direction:offset:start_date:end_date:name:format`.
+pub(super) fn eras(era: &[u8]) -> impl Iterator<Item = EraEntry<'_>> {
+    era.split(|&b| b == b';').filter_map(|segment| {
+        let mut fields = segment.splitn(6, |&b| b == b':');
+        let direction = match fields.next()? {
+            b"+" => 1,
+            b"-" => -1,
+            _ => return None,
+        };
+        Some(EraEntry {
+            direction,
+            offset: parse_number(fields.next()?)?,
+            start: parse_era_date(fields.next()?)?,
+            end: parse_era_date(fields.next()?)?,
+            name: fields.next()?,
+            format: fields.next()?,
+        })
+    })
+}
+
+fn parse_number(s: &[u8]) -> Option<i64> {
+    core::str::from_utf8(s).ok()?.parse().ok()
+}
+
+/// Parses the start or end date of an era, `yyyy/mm/dd`, or `-*` and `+*`
+/// for the beginning and end of time.
+fn parse_era_date(s: &[u8]) -> Option<(i64, i32, i32)> {
+    match s {
+        b"-*" => Some((i64::MIN, 0, 0)),
+        b"+*" => Some((i64::MAX, 0, 0)),
+        _ => {
+            // The year may be negative.
+            let split = s.iter().skip(1).position(|&b| b == b'/')? + 1;
+            let (year, rest) = s.split_at(split);
+            let mut rest = rest[1..].split(|&b| b == b'/');
+            let month = parse_number(rest.next()?)?;
+            let day = parse_number(rest.next()?)?;
+            Some((
+                parse_number(year)?,
+                month.try_into().ok()?,
+                day.try_into().ok()?,
+            ))
+        }
     }
 }
@@ -2,47 +2,86 @@
 //
 // See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/strptime.html>.
 
-use crate::header::time::tm;
-use alloc::string::String;
-use core::{
-    ffi::{CStr, c_char, c_int, c_void},
-    ptr,
-    ptr::NonNull,
-    str,
+use alloc::{string::String, vec::Vec};
+use chrono::{Datelike, NaiveDate, Weekday};
+use core::{ffi::CStr, ptr};
+
+use super::{
+    localtime_r,
+    strftime::{EraEntry, eras},
+    tm, zone, zone_name,
 };
+use crate::{
+    header::{
+        bits_locale_t::locale_t,
+        langinfo::{
+            ABALTMON_1, ABDAY_1, ABMON_1, ALT_DIGITS, ALTMON_1, AM_STR, D_FMT, D_T_FMT, DAY_1, ERA,
+            ERA_D_FMT, ERA_D_T_FMT, ERA_T_FMT, MON_1, PM_STR, T_FMT, T_FMT_AMPM, nl_item,
+            nl_langinfo_l,
+        },
+        locale::uselocale,
+    },
+    platform::types::{c_char, c_int, c_long, time_t},
+};
+
+/// What the conversions parsed, to compute the fields they determine once the
+/// whole input is parsed.
+#[derive(Default)]
+struct State {
+    /// `%I`: `tm_hour` is on 12 hours.
+    hour12: bool,
+    /// `%p` matched the PM string.
+    pm: bool,
+    /// `%C`
+    century: Option<i64>,
+    /// `%y`: `tm_year` is only known within the century.
+    year_in_century: bool,
+    /// `%EC`, or the era whose format matched `%EY`.
+    era: Option<EraEntry<'static>>,
+    /// `%Ey`
+    era_year: Option<i64>,
+    /// `%G` or `%g`
+    iso_year: Option<i64>,
+    /// `%V`
+    iso_week: Option<i64>,
+    /// `%U` or `%W`, and whether weeks start on Monday.
+    week: Option<(i64, bool)>,
+    have_year: bool,
+    have_mon: bool,
+    have_mday: bool,
+    have_wday: bool,
+    have_yday: bool,
+}
+
+struct Parser<'a> {
+    input: &'a [u8],
+    pos: usize,
+    t: &'a mut tm,
+    loc: locale_t,
+    state: State,
+}
 
-/// cbindgen:ignore
-/// For convenience, we define some helper constants for the C-locale.
-const SHORT_DAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
-/// cbindgen:ignore
-const LONG_DAYS: [&str; 7] = [
-    "Sunday",
-    "Monday",
-    "Tuesday",
-    "Wednesday",
-    "Thursday",
-    "Friday",
-    "Saturday",
-];
-/// cbindgen:ignore
-const SHORT_MONTHS: [&str; 12] = [
-    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
-];
-/// cbindgen:ignore
-const LONG_MONTHS: [&str; 12] = [
-    "January",
-    "February",
-    "March",
-    "April",
-    "May",
-    "June",
-    "July",
-    "August",
-    "September",
-    "October",
-    "November",
-    "December",
-];
+/// Parses `input` according to `format` and the `LC_TIME` category of `loc`
+/// into `t`, as glibc does, including its conversions `%k`, `%l`, `%s`, `%z`
+/// and `%Z`.
+///
+/// `tm_wday` and `tm_yday` are computed when the date is known, and the date
+/// is computed from ISO 8601 week dates, week numbers with a weekday, and days
+/// of the year.
+///
+/// Returns the number of bytes parsed, or `None` if `input` does not match.
+pub fn parse(input: &[u8], format: &[u8], t: &mut tm, loc: locale_t) -> Option<usize> {
+    let mut parser = Parser {
+        input,
+        pos: 0,
+        t,
+        loc,
+        state: State::default(),
+    };
+    parser.parse(format)?;
+    parser.finish()?;
+    Some(parser.pos)
+}
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/strptime.html>.
 #[unsafe(no_mangle)]
@@ -51,530 +90,549 @@ pub unsafe extern "C" fn strptime(
     format: *const c_char,
     tm: *mut tm,
 ) -> *mut c_char {
-    // Validate inputs
-    if NonNull::new(buf.cast::<c_void>().cast_mut()).is_none() {
+    unsafe { strptime_l(buf, format, tm, uselocale(ptr::null_mut())) }
+}
+
+/// Non-POSIX, see <https://www.man7.org/linux/man-pages/man3/strptime.3.html>.
+///
+/// Like [`strptime()`], with the names and formats of `locale`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn strptime_l(
+    buf: *const c_char,
+    format: *const c_char,
+    tm: *mut tm,
+    locale: locale_t,
+) -> *mut c_char {
+    if buf.is_null() || format.is_null() || tm.is_null() {
         return ptr::null_mut();
     }
-    if NonNull::new(format.cast::<c_void>().cast_mut()).is_none() {
-        return ptr::null_mut();
+    let input = unsafe { CStr::from_ptr(buf) }.to_bytes();
+    let format = unsafe { CStr::from_ptr(format) }.to_bytes();
+    match parse(input, format, unsafe { &mut *tm }, locale) {
+        Some(len) => unsafe { buf.add(len) }.cast_mut(),
+        None => ptr::null_mut(),
     }
-    if NonNull::new(tm).is_none() {
-        return ptr::null_mut();
+}
+
+impl Parser<'_> {
+    /// Returns the item `item` of the locale, which stays valid while the
+    /// locale is in use.
+    fn langinfo(&self, item: nl_item) -> &'static [u8] {
+        unsafe { CStr::from_ptr(nl_langinfo_l(item, self.loc)) }.to_bytes()
     }
 
-    // Convert raw pointers into slices/strings.
-    let input_str = unsafe {
-        if buf.is_null() {
-            return ptr::null_mut();
-        }
-        match CStr::from_ptr(buf).to_str() {
-            Ok(s) => s,
-            Err(_) => return ptr::null_mut(), // Not a valid UTF-8
-        }
-    };
+    /// Returns the `count` items following each of `firsts`, in order.
+    fn items(&self, firsts: &[nl_item], count: nl_item) -> Vec<&'static [u8]> {
+        firsts
+            .iter()
+            .flat_map(|&first| (first..first + count).map(|item| self.langinfo(item)))
+            .collect()
+    }
 
-    let fmt_str = unsafe {
-        if format.is_null() {
-            return ptr::null_mut();
-        }
-        match CStr::from_ptr(format).to_str() {
-            Ok(s) => s,
-            Err(_) => return ptr::null_mut(), // Not a valid UTF-8
+    fn skip_spaces(&mut self) {
+        while self
+            .input
+            .get(self.pos)
+            .is_some_and(u8::is_ascii_whitespace)
+        {
+            self.pos += 1;
         }
-    };
+    }
 
-    // We parse the format specifiers in a loop
-    let mut fmt_chars = fmt_str.chars().peekable();
-    let mut index_in_input = 0;
+    fn literal(&mut self, c: u8) -> Option<()> {
+        (self.input.get(self.pos) == Some(&c)).then(|| self.pos += 1)
+    }
 
-    while let Some(fc) = fmt_chars.next() {
-        if fc != '%' {
-            // If it's a normal character, we expect it to match exactly in input
-            if input_str.len() <= index_in_input {
-                return ptr::null_mut(); // input ended too soon
-            }
-            let in_char = input_str.as_bytes()[index_in_input] as char;
-            if in_char != fc {
-                // mismatch
-                return ptr::null_mut();
-            }
-            index_in_input += 1;
-            continue;
-        }
+    fn digit(&self) -> Option<i64> {
+        let c = *self.input.get(self.pos)?;
+        c.is_ascii_digit().then(|| i64::from(c - b'0'))
+    }
 
-        // If we see '%', read the next character
-        let Some(spec) = fmt_chars.next() else {
-            // format string ended abruptly after '%'
-            return ptr::null_mut();
-        };
+    /// Parses the longest of `names`, ignoring case, and returns its index.
+    fn name<'n>(&mut self, names: impl IntoIterator<Item = &'n [u8]>) -> Option<usize> {
+        let rest = &self.input[self.pos..];
+        let (index, len) = names
+            .into_iter()
+            .enumerate()
+            .filter(|(_, name)| {
+                !name.is_empty()
+                    && rest
+                        .get(..name.len())
+                        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(name))
+            })
+            .map(|(i, name)| (i, name.len()))
+            .max_by_key(|&(_, len)| len)?;
+        self.pos += len;
+        Some(index)
+    }
 
-        // POSIX says `%E` or `%O` are modified specifiers for locale.
-        // We will skip them if they appear (like strftime does) and read the next char.
-        let final_spec = if spec == 'E' || spec == 'O' {
-            match fmt_chars.next() {
-                Some(ch) => ch,
-                None => return ptr::null_mut(),
-            }
-        } else {
-            spec
-        };
+    /// Parses a number between `min` and `max` of at most `digits` digits,
+    /// which stops before the value would exceed `max`. With `%O`, the
+    /// alternative digits of the locale are tried first.
+    fn number(&mut self, modifier: Option<u8>, min: i64, max: i64, digits: usize) -> Option<i64> {
+        let alt_digits = self.langinfo(ALT_DIGITS);
+        if modifier == Some(b'O')
+            && !alt_digits.is_empty()
+            && let Some(value) = self.name(alt_digits.split(|&b| b == b';'))
+        {
+            let value = i64::try_from(value).ok()?;
+            return (min..=max).contains(&value).then_some(value);
+        }
 
-        // Handle known specifiers
-        match final_spec {
-            // Whitespace: %n or %t
-            'n' | 't' => {
-                // Skip over any whitespace in the input
-                while index_in_input < input_str.len()
-                    && input_str.as_bytes()[index_in_input].is_ascii_whitespace()
-                {
-                    index_in_input += 1;
-                }
+        let mut value = self.digit()?;
+        self.pos += 1;
+        for _ in 1..digits {
+            match self.digit() {
+                Some(digit) if value * 10 <= max => value = value * 10 + digit,
+                _ => break,
             }
+            self.pos += 1;
+        }
+        (min..=max).contains(&value).then_some(value)
+    }
 
-            // Literal % => "%%"
-            '%' => {
-                if index_in_input >= input_str.len()
-                    || input_str.as_bytes()[index_in_input] as char != '%'
-                {
-                    return ptr::null_mut();
-                }
-                index_in_input += 1;
+    fn parse(&mut self, format: &[u8]) -> Option<()> {
+        let mut i = 0;
+        while i < format.len() {
+            let c = format[i];
+            i += 1;
+            if c.is_ascii_whitespace() {
+                // Whitespace matches any amount of it, including none.
+                self.skip_spaces();
+                continue;
             }
-
-            // Day of Month: %d / %e
-            'd' | 'e' => {
-                // parse a 2-digit day (with or without leading zero)
-                let Some((val, len)) = parse_int(&input_str[index_in_input..], 2, false) else {
-                    return ptr::null_mut();
-                };
-                unsafe {
-                    (*tm).tm_mday = val as c_int;
-                    // Day of month is limited to [1,31] according to the standard
-                    if (*tm).tm_mday < 1 || (*tm).tm_mday > 31 {
-                        return ptr::null_mut();
-                    }
-                }
-                index_in_input += len;
+            if c != b'%' {
+                self.literal(c)?;
+                continue;
             }
 
-            // Month: %m
-            'm' => {
-                // parse a 2-digit month
-                let Some((val, len)) = parse_int(&input_str[index_in_input..], 2, false) else {
-                    return ptr::null_mut();
-                };
-                // tm_mon is 0-based (0 = Jan, 1 = Feb,...)
-                unsafe {
-                    (*tm).tm_mon = (val as c_int) - 1;
-                    if (*tm).tm_mon < 0 || (*tm).tm_mon > 11 {
-                        return ptr::null_mut();
-                    }
-                }
-                index_in_input += len;
+            // The flags and field width of strftime() are accepted and
+            // ignored.
+            while format.get(i).is_some_and(|c| b"_-0^#".contains(c)) {
+                i += 1;
             }
-
-            // Year without century: %y
-            'y' => {
-                // parse a 2-digit year
-                let Some((val, len)) = parse_int(&input_str[index_in_input..], 2, false) else {
-                    return ptr::null_mut();
-                };
-                // According to POSIX, %y in strptime is [00,99], and the "year" is 1900..1999 for [00..99],
-                // but the standard says: "values in [69..99] refer to 1969..1999, [00..68] => 2000..2068"
-                let fullyear = if val >= 69 { val + 1900 } else { val + 2000 };
-                unsafe {
-                    (*tm).tm_year = (fullyear - 1900) as c_int;
-                }
-                index_in_input += len;
+            while format.get(i).is_some_and(u8::is_ascii_digit) {
+                i += 1;
             }
-
-            // Year with century: %Y
-            'Y' => {
-                // parse up to 4-digit (or more) year
-                // We allow more than 4 digits if needed
-                let Some((val, len)) = parse_int(&input_str[index_in_input..], 4, true) else {
-                    return ptr::null_mut();
-                };
-                unsafe {
-                    (*tm).tm_year = (val as c_int) - 1900;
+            let modifier = match format.get(i) {
+                Some(&c @ (b'E' | b'O')) => {
+                    i += 1;
+                    Some(c)
                 }
-                index_in_input += len;
-            }
+                _ => None,
+            };
+            let conversion = *format.get(i)?;
+            i += 1;
 
-            // Hour (00..23): %H
-            'H' => {
-                let Some((val, len)) = parse_int(&input_str[index_in_input..], 2, false) else {
-                    return ptr::null_mut();
-                };
-                if val > 23 {
-                    return ptr::null_mut();
-                }
-                unsafe {
-                    (*tm).tm_hour = val as c_int;
-                }
-                index_in_input += len;
+            if !matches!(conversion, b'%' | b'n' | b't') {
+                self.skip_spaces();
             }
+            self.conversion(modifier, conversion)?;
+        }
+        Some(())
+    }
 
-            // Hour (01..12): %I
-            'I' => {
-                let Some((val, len)) = parse_int(&input_str[index_in_input..], 2, false) else {
-                    return ptr::null_mut();
-                };
-                if !(1..=12).contains(&val) {
-                    return ptr::null_mut();
-                }
-                unsafe {
-                    (*tm).tm_hour = val as c_int;
-                }
-                // We’ll interpret AM/PM with %p if it appears later
-                index_in_input += len;
+    fn conversion(&mut self, modifier: Option<u8>, conversion: u8) -> Option<()> {
+        match (modifier, conversion) {
+            (None, _)
+            | (Some(b'E'), b'c' | b'C' | b'x' | b'X' | b'y' | b'Y')
+            | (
+                Some(b'O'),
+                b'b' | b'B' | b'h' | b'd' | b'e' | b'H' | b'I' | b'k' | b'l' | b'm' | b'M' | b'S'
+                | b'u' | b'U' | b'V' | b'w' | b'W' | b'y',
+            ) => {}
+            _ => return None,
+        }
+        let era = modifier == Some(b'E') && !self.langinfo(ERA).is_empty();
+
+        match conversion {
+            b'%' => self.literal(b'%')?,
+            b'n' | b't' => self.skip_spaces(),
+
+            // Names, abbreviated or not
+            b'a' | b'A' => {
+                let day = self.name(self.items(&[DAY_1, ABDAY_1], 7))?;
+                self.t.tm_wday = (day % 7) as c_int;
+                self.state.have_wday = true;
+            }
+            b'b' | b'B' | b'h' => {
+                let mon = self.name(self.items(&[MON_1, ABMON_1, ALTMON_1, ABALTMON_1], 12))?;
+                self.t.tm_mon = (mon % 12) as c_int;
+                self.state.have_mon = true;
+            }
+            b'p' => {
+                let am_pm = [self.langinfo(AM_STR), self.langinfo(PM_STR)];
+                self.state.pm = self.name(am_pm)? == 1;
             }
 
-            // Minute (00..59): %M
-            'M' => {
-                let Some((val, len)) = parse_int(&input_str[index_in_input..], 2, false) else {
-                    return ptr::null_mut();
-                };
-                if val > 59 {
-                    return ptr::null_mut();
-                }
-                unsafe {
-                    (*tm).tm_min = val as c_int;
+            // Formats of the locale
+            b'c' => self.locale_format(modifier, ERA_D_T_FMT, D_T_FMT)?,
+            b'x' => self.locale_format(modifier, ERA_D_FMT, D_FMT)?,
+            b'X' => self.locale_format(modifier, ERA_T_FMT, T_FMT)?,
+            b'r' => match self.langinfo(T_FMT_AMPM) {
+                b"" => self.parse(b"%I:%M:%S %p")?,
+                format => self.parse(format)?,
+            },
+            b'D' => self.parse(b"%m/%d/%y")?,
+            b'F' => self.parse(b"%Y-%m-%d")?,
+            b'R' => self.parse(b"%H:%M")?,
+            b'T' => self.parse(b"%H:%M:%S")?,
+
+            // Years
+            b'C' => {
+                if era {
+                    let entries: Vec<EraEntry> = eras(self.langinfo(ERA)).collect();
+                    let index = self.name(entries.iter().map(|era| era.name))?;
+                    self.state.era = Some(entries[index]);
+                } else {
+                    self.state.century = Some(self.number(modifier, 0, 99, 2)?);
                 }
-                index_in_input += len;
+                self.state.have_year = true;
             }
-
-            // Seconds (00..60): %S
-            'S' => {
-                let Some((val, len)) = parse_int(&input_str[index_in_input..], 2, false) else {
-                    return ptr::null_mut();
-                };
-                if val > 60 {
-                    return ptr::null_mut();
+            b'y' => {
+                if era {
+                    self.state.era_year = Some(self.number(modifier, 0, 9999, 4)?);
+                } else {
+                    let year = self.number(modifier, 0, 99, 2)?;
+                    // 69 to 99 are in the 20th century, 00 to 68 in the 21st.
+                    self.t.tm_year = (if year < 69 { year + 100 } else { year }) as c_int;
+                    self.state.year_in_century = true;
                 }
-                unsafe {
-                    (*tm).tm_sec = val as c_int;
+                self.state.have_year = true;
+            }
+            b'Y' => {
+                if era {
+                    self.era_year()?;
+                } else {
+                    self.t.tm_year = (self.number(modifier, 0, 9999, 4)? - 1900) as c_int;
+                    self.state.year_in_century = false;
                 }
-                index_in_input += len;
+                self.state.have_year = true;
             }
 
-            // AM/PM: %p
-            'p' => {
-                // Parse either "AM" or "PM" (no case-sensitive)
-                // We'll read up to 2 or 3 letters from input ("AM", "PM")
-                let leftover = &input_str[index_in_input..];
-                let parsed_len = match parse_am_pm(leftover) {
-                    Some((is_pm, used)) => {
-                        if unsafe { (*tm).tm_hour } == 12 {
-                            // 12 AM => 00:xx, 12 PM => 12:xx
-                            unsafe {
-                                (*tm).tm_hour = if is_pm { 12 } else { 0 };
-                            }
-                        } else {
-                            // 1..11 AM => 1..11, 1..11 PM => 13..23
-                            if is_pm {
-                                unsafe {
-                                    (*tm).tm_hour += 12;
-                                }
-                            }
-                        }
-                        used
-                    }
-                    None => return ptr::null_mut(),
-                };
-                index_in_input += parsed_len;
+            // Dates
+            b'd' | b'e' => {
+                self.t.tm_mday = self.number(modifier, 1, 31, 2)? as c_int;
+                self.state.have_mday = true;
             }
-
-            // Weekday Name: %a/%A
-            'a' => {
-                // Abbreviated day name (Sun..Sat)
-                let leftover = &input_str[index_in_input..];
-                let parsed_len = match parse_weekday(leftover, true) {
-                    Some((wday, used)) => {
-                        unsafe {
-                            (*tm).tm_wday = wday as c_int;
-                        }
-                        used
-                    }
-                    None => return ptr::null_mut(),
-                };
-                index_in_input += parsed_len;
+            b'j' => {
+                self.t.tm_yday = (self.number(modifier, 1, 366, 3)? - 1) as c_int;
+                self.state.have_yday = true;
             }
-            'A' => {
-                // Full day name (Sunday..Saturday)
-                let leftover = &input_str[index_in_input..];
-                let parsed_len = match parse_weekday(leftover, false) {
-                    Some((wday, used)) => {
-                        unsafe {
-                            (*tm).tm_wday = wday as c_int;
-                        }
-                        used
-                    }
-                    None => return ptr::null_mut(),
-                };
-                index_in_input += parsed_len;
+            b'm' => {
+                self.t.tm_mon = (self.number(modifier, 1, 12, 2)? - 1) as c_int;
+                self.state.have_mon = true;
             }
-
-            // Month Name: %b/%B/%h
-            'b' | 'h' => {
-                // Abbreviated month name
-                let leftover = &input_str[index_in_input..];
-                let parsed_len = match parse_month(leftover, true) {
-                    Some((mon, used)) => {
-                        unsafe {
-                            (*tm).tm_mon = mon as c_int;
-                        }
-                        used
-                    }
-                    None => return ptr::null_mut(),
-                };
-                index_in_input += parsed_len;
+            b'u' => {
+                self.t.tm_wday = (self.number(modifier, 1, 7, 1)? % 7) as c_int;
+                self.state.have_wday = true;
             }
-            'B' => {
-                // Full month name
-                let leftover = &input_str[index_in_input..];
-                let parsed_len = match parse_month(leftover, false) {
-                    Some((mon, used)) => {
-                        unsafe {
-                            (*tm).tm_mon = mon as c_int;
-                        }
-                        used
-                    }
-                    None => return ptr::null_mut(),
-                };
-                index_in_input += parsed_len;
+            b'w' => {
+                self.t.tm_wday = self.number(modifier, 0, 6, 1)? as c_int;
+                self.state.have_wday = true;
             }
-
-            // Day of year: %j
-            'j' => {
-                // parse 3-digit day of year [001..366]
-                let Some((val, len)) = parse_int(&input_str[index_in_input..], 3, false) else {
-                    return ptr::null_mut();
-                };
-                if !(1..=366).contains(&val) {
-                    return ptr::null_mut();
-                }
-                // store in tm_yday
-                unsafe {
-                    (*tm).tm_yday = (val - 1) as c_int;
-                }
-                index_in_input += len;
+            b'U' => self.state.week = Some((self.number(modifier, 0, 53, 2)?, false)),
+            b'W' => self.state.week = Some((self.number(modifier, 0, 53, 2)?, true)),
+            b'G' => self.state.iso_year = Some(self.number(modifier, 0, 9999, 4)?),
+            b'g' => {
+                let year = self.number(modifier, 0, 99, 2)?;
+                self.state.iso_year = Some(if year < 69 { 2000 + year } else { 1900 + year });
             }
+            b'V' => self.state.iso_week = Some(self.number(modifier, 1, 53, 2)?),
 
-            // Date shortcuts: %D, %F, etc.
-            'D' => {
-                // Equivalent to "%m/%d/%y"
-                // We can do a mini strptime recursion or manually parse
-                // For simplicity, we'll do it inline here
-                let subfmt = "%m/%d/%y";
-                let Some(used) =
-                    (unsafe { apply_subformat(&input_str[index_in_input..], subfmt, tm) })
-                else {
-                    return ptr::null_mut();
-                };
-                index_in_input += used;
-            }
-            'F' => {
-                // Equivalent to "%Y-%m-%d"
-                let subfmt = "%Y-%m-%d";
-                let Some(used) =
-                    (unsafe { apply_subformat(&input_str[index_in_input..], subfmt, tm) })
-                else {
-                    return ptr::null_mut();
-                };
-                index_in_input += used;
+            // Times
+            b'H' | b'k' => {
+                self.t.tm_hour = self.number(modifier, 0, 23, 2)? as c_int;
+                self.state.hour12 = false;
             }
-            'T' => {
-                // Equivalent to %H:%M:%S
-                let subfmt = "%H:%M:%S";
-                let Some(used) =
-                    (unsafe { apply_subformat(&input_str[index_in_input..], subfmt, tm) })
-                else {
-                    return ptr::null_mut();
-                };
-                index_in_input += used;
-            }
-
-            // TODO : not implemented: %x, %X, %c, %r, %R, etc.
-            // Hint : if you want to implement these, do similarly to %D / %F (or parse manually)
-            'x' | 'X' | 'c' | 'r' | 'R' => {
-                // Return NULL if we don’t want to accept them :
-                return ptr::null_mut();
+            b'I' | b'l' => {
+                self.t.tm_hour = (self.number(modifier, 1, 12, 2)? % 12) as c_int;
+                self.state.hour12 = true;
             }
+            b'M' => self.t.tm_min = self.number(modifier, 0, 59, 2)? as c_int,
+            b'S' => self.t.tm_sec = self.number(modifier, 0, 60, 2)? as c_int,
+            b's' => self.epoch_seconds()?,
+            b'z' => self.t.tm_gmtoff = self.utc_offset()?,
+            b'Z' => self.zone()?,
+            _ => return None,
+        }
+        Some(())
+    }
 
-            // Timezone: %Z or %z
-            'Z' | 'z' => {
-                // Full/abbrev time zone name or numeric offset
-                // Implementation omitted. Real support is quite complicated.
-                return ptr::null_mut();
-            }
+    /// Parses the `E` variant of the format `item`, or `fallback` if there is
+    /// none.
+    fn locale_format(
+        &mut self,
+        modifier: Option<u8>,
+        item: nl_item,
+        fallback: nl_item,
+    ) -> Option<()> {
+        let format = match self.langinfo(item) {
+            format if modifier == Some(b'E') && !format.is_empty() => format,
+            _ => self.langinfo(fallback),
+        };
+        self.parse(format)
+    }
 
-            _ => {
-                // We do not recognize this specifier
-                return ptr::null_mut();
+    /// `%EY`: parses the year with the format of the first era that matches.
+    fn era_year(&mut self) -> Option<()> {
+        let start = self.pos;
+        for era in eras(self.langinfo(ERA)).filter(|era| !era.format.is_empty()) {
+            if self.parse(era.format).is_some() {
+                self.state.era = Some(era);
+                return Some(());
             }
+            self.pos = start;
         }
+        None
     }
 
-    // If we got here, parsing was successful. Return pointer to the
-    // next unparsed character in `buf`.
-    let ret_ptr = unsafe { buf.add(index_in_input) };
-    ret_ptr.cast_mut()
-}
-
-// Helper / Parsing Logic
+    /// `%s`: parses the seconds since the Epoch, in local time.
+    fn epoch_seconds(&mut self) -> Option<()> {
+        let negative = self.literal(b'-').is_some();
+        let mut seconds = self.digit()?;
+        self.pos += 1;
+        while let Some(digit) = self.digit() {
+            seconds = seconds.checked_mul(10)?.checked_add(digit)?;
+            self.pos += 1;
+        }
+        if negative {
+            seconds = -seconds;
+        }
+        let seconds = time_t::try_from(seconds).ok()?;
 
-/// Parse an integer from the beginning of `input_str`.
-///
-/// - `width` is the maximum number of digits to parse
-/// - `allow_variable_width` indicates if we can parse fewer digits
-///   (e.g., `%Y` can have more than 4 digits, but also might parse "2023" or "12345").
-fn parse_int(input: &str, width: usize, allow_variable: bool) -> Option<(i32, usize)> {
-    let mut val = 0i32;
-    let chars = input.chars();
-    let mut count = 0;
-
-    for c in chars {
-        if !c.is_ascii_digit() {
-            break;
+        if unsafe { localtime_r(&seconds, &mut *self.t) }.is_null() {
+            return None;
         }
+        let state = &mut self.state;
+        state.have_year = true;
+        state.have_mon = true;
+        state.have_mday = true;
+        state.have_wday = true;
+        state.have_yday = true;
+        state.year_in_century = false;
+        Some(())
+    }
 
-        // Check for integer overflow
-        val = val
-            .checked_mul(10)?
-            .checked_add(i32::from(c as u8 - b'0'))?;
+    /// `%z`: parses an offset from UTC, `Z`, or a sign followed by `hh`,
+    /// `hhmm` or `hh:mm`.
+    fn utc_offset(&mut self) -> Option<c_long> {
+        if self.literal(b'Z').is_some() {
+            return Some(0);
+        }
+        let sign = match self.input.get(self.pos)? {
+            b'+' => 1,
+            b'-' => -1,
+            _ => return None,
+        };
+        self.pos += 1;
 
-        count += 1;
-        if count == width && !allow_variable {
-            break;
+        let mut value = 0;
+        let mut digits = 0;
+        while digits < 4
+            && let Some(digit) = self.digit()
+        {
+            value = value * 10 + digit;
+            digits += 1;
+            self.pos += 1;
+            if digits == 2
+                && self.input.get(self.pos) == Some(&b':')
+                && self.input.get(self.pos + 1).is_some_and(u8::is_ascii_digit)
+            {
+                self.pos += 1;
+            }
         }
+        let (hours, minutes) = match digits {
+            2 => (value, 0),
+            4 if value % 100 < 60 => (value / 100, value % 100),
+            _ => return None,
+        };
+        Some(sign * (hours * 60 * 60 + minutes * 60) as c_long)
     }
 
-    if count == 0 { None } else { Some((val, count)) }
-}
+    /// `%Z`: parses `UTC`, `GMT`, or the standard or daylight saving time
+    /// abbreviation of the local time zone, and sets `tm_isdst` and
+    /// `tm_zone` accordingly.
+    fn zone(&mut self) -> Option<()> {
+        let (std, dst, timezone) = zone::with_current(|zone| {
+            let globals = zone.globals();
+            let dst = if globals.daylight {
+                String::from(globals.dst)
+            } else {
+                String::new()
+            };
+            (String::from(globals.std), dst, globals.timezone)
+        });
+        let names = ["UTC", "GMT", std.as_str(), dst.as_str()];
+
+        let index = self.name(names.iter().map(|name| name.as_bytes()))?;
+        match index {
+            0 | 1 => {
+                self.t.tm_isdst = 0;
+                self.t.tm_gmtoff = 0;
+            }
+            2 => {
+                self.t.tm_isdst = 0;
+                self.t.tm_gmtoff = -timezone;
+            }
+            _ => self.t.tm_isdst = 1,
+        }
+        self.t.tm_zone = zone_name(names[index]);
+        Some(())
+    }
 
-/// Handle AM/PM. Returns (is_pm, length_consumed).
-/// Accepts "AM", "am", "PM", "pm" case-insensitively.
-fn parse_am_pm(s: &str) -> Option<(bool, usize)> {
-    let trimmed = s.trim_start();
-    // Amount of whitespace skipped; can be 0
-    let diff = s.len() - trimmed.len();
-    let s = trimmed.get(0..2)?;
+    /// Computes the fields that the parsed ones determine.
+    fn finish(&mut self) -> Option<()> {
+        let Self { t, state, .. } = self;
 
-    if s.eq_ignore_ascii_case("AM") {
-        return Some((false, diff + 2));
-    }
-    if s.eq_ignore_ascii_case("PM") {
-        return Some((true, diff + 2));
-    }
-    None
-}
+        if state.hour12 && state.pm {
+            t.tm_hour += 12;
+        }
 
-/// Parse a weekday name from `s`.
-/// - if `abbrev == true`, match short forms: "Mont".."Sun"
-/// - otherwise, match "Monday".."Sunday"
-///
-/// Return (weekday_index, length_consumed).
-fn parse_weekday(s: &str, abbrev: bool) -> Option<(usize, usize)> {
-    let list = if abbrev { &SHORT_DAYS } else { &LONG_DAYS };
-    for (i, name) in list.iter().enumerate() {
-        if s.len() >= name.len()
-            && s.get(0..name.len())
-                .is_some_and(|sub| sub.eq_ignore_ascii_case(name))
-        {
-            return Some((i, name.len()));
+        if let Some(century) = state.century {
+            let year_in_century = if state.year_in_century {
+                i64::from(t.tm_year).rem_euclid(100)
+            } else {
+                0
+            };
+            t.tm_year = c_int::try_from((century - 19) * 100 + year_in_century).ok()?;
+        }
+        if let Some(era) = state.era {
+            let year = match state.era_year {
+                Some(era_year) => era
+                    .start
+                    .0
+                    .checked_add((era_year - era.offset) * era.direction)?,
+                None => era.start.0,
+            };
+            t.tm_year = c_int::try_from(year.checked_sub(1900)?).ok()?;
+        } else if let Some(year) = state.era_year {
+            // Without an era, `%Ey` is `%y`.
+            t.tm_year = c_int::try_from(if year < 69 { year + 100 } else { year }).ok()?;
         }
-    }
-    None
-}
 
-/// Parse a month name from `s`.
-/// - If `abbrev == true`, match short forms: "Jan".."Dec"
-/// - Otherwise, match "January".."December"
-///
-/// Return (month_index, length_consumed).
-fn parse_month(s: &str, abbrev: bool) -> Option<(usize, usize)> {
-    let list = if abbrev { &SHORT_MONTHS } else { &LONG_MONTHS };
-    for (i, name) in list.iter().enumerate() {
-        if s.len() >= name.len()
-            && s.get(0..name.len())
-                .is_some_and(|sub| sub.eq_ignore_ascii_case(name))
-        {
-            return Some((i, name.len()));
+        // The date is left alone if it does not exist, such as February 30,
+        // for the caller to check.
+        if let Some((date, overwrite)) = date(t, state) {
+            t.tm_year = date.year() - 1900;
+            if overwrite || !state.have_mon {
+                t.tm_mon = date.month0() as c_int;
+            }
+            if overwrite || !state.have_mday {
+                t.tm_mday = date.day() as c_int;
+            }
+            if !state.have_wday {
+                t.tm_wday = date.weekday().num_days_from_sunday() as c_int;
+            }
+            if overwrite || !state.have_yday {
+                t.tm_yday = date.ordinal0() as c_int;
+            }
         }
+        Some(())
     }
-    None
 }
 
-/// Apply a small subformat (like "%m/%d/%y" or "%Y-%m-%d") to `input`.
-/// Return how many characters of `input` were consumed or None on error.
-unsafe fn apply_subformat(input: &str, subfmt: &str, tm: *mut tm) -> Option<usize> {
-    // We'll do a temporary strptime call on a substring.
-    // Then we see how many chars it consumed. If that call fails, we return None.
-    // Otherwise, we return the count.
-
-    // Convert `input` to a null-terminated buffer temporarily
-    let mut tmpbuf = String::with_capacity(input.len() + 1);
-    tmpbuf.push_str(input);
-    tmpbuf.push('\0');
-
-    let mut tmpfmt = String::with_capacity(subfmt.len() + 1);
-    tmpfmt.push_str(subfmt);
-    tmpfmt.push('\0');
-
-    // We need a copy of the tm, so if partial parse fails, we don't override.
-    let old_tm = unsafe { ptr::read(tm) }; // backup
-
-    let consumed_ptr = unsafe {
-        strptime(
-            tmpbuf.as_ptr().cast::<c_char>(),
-            tmpfmt.as_ptr().cast::<c_char>(),
-            tm,
-        )
-    };
+/// Returns the date that the parsed fields determine, and whether it replaces
+/// them, as ISO 8601 week dates do.
+fn date(t: &tm, state: &State) -> Option<(NaiveDate, bool)> {
+    let year = || i32::try_from(i64::from(t.tm_year) + 1900).ok();
 
-    if consumed_ptr.is_null() {
-        // revert
-        unsafe {
-            *tm = old_tm;
-        }
-        return None;
+    if let (Some(iso_year), Some(iso_week)) = (state.iso_year, state.iso_week) {
+        // Monday unless a weekday is given
+        let weekday = if state.have_wday {
+            Weekday::try_from(((t.tm_wday + 6) % 7) as u8).ok()?
+        } else {
+            Weekday::Mon
+        };
+        let date =
+            NaiveDate::from_isoywd_opt(i32::try_from(iso_year).ok()?, iso_week as u32, weekday)?;
+        Some((date, true))
+    } else if let Some((week, monday_first)) = state.week
+        && state.have_wday
+    {
+        // Week 1 starts on the first Sunday or Monday of the year, and the
+        // days before are in week 0.
+        let first = NaiveDate::from_ymd_opt(year()?, 1, 1)?;
+        let yday = if state.have_yday {
+            i64::from(t.tm_yday)
+        } else {
+            let offset = i64::from(monday_first);
+            let first_wday = i64::from(first.weekday().num_days_from_sunday());
+            (7 - (first_wday - offset)).rem_euclid(7)
+                + (week - 1) * 7
+                + (i64::from(t.tm_wday) - offset).rem_euclid(7)
+        };
+        let days = i64::from(first.num_days_from_ce()).checked_add(yday)?;
+        let date = NaiveDate::from_num_days_from_ce_opt(i32::try_from(days).ok()?)?;
+        Some((date, false))
+    } else if state.have_yday && !(state.have_mon && state.have_mday) {
+        let date = NaiveDate::from_yo_opt(year()?, u32::try_from(t.tm_yday + 1).ok()?)?;
+        Some((date, false))
+    } else if state.have_year && state.have_mon && state.have_mday {
+        let date = NaiveDate::from_ymd_opt(year()?, (t.tm_mon + 1) as u32, t.tm_mday as u32)?;
+        Some((date, false))
+    } else {
+        None
     }
-
-    // consumed_ptr - tmpbuf.as_ptr() => # of bytes consumed
-    let diff = (consumed_ptr as usize) - (tmpbuf.as_ptr() as usize);
-    Some(diff)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::parse_am_pm;
+    use super::parse;
+    use crate::header::{
+        locale::LC_GLOBAL_LOCALE,
+        time::{blank_tm, tm},
+    };
+
+    fn parse_tm(input: &str, format: &str) -> Option<(tm, usize)> {
+        let mut t = blank_tm();
+        let len = parse(
+            input.as_bytes(),
+            format.as_bytes(),
+            &mut t,
+            LC_GLOBAL_LOCALE,
+        )?;
+        Some((t, len))
+    }
 
     #[test]
     fn am_pm_parser_works() {
-        let am = "am";
-        let am_expected = Some((false, 2));
-        assert_eq!(am_expected, parse_am_pm(am));
-
-        let pm = "pm";
-        let pm_expected = Some((true, 2));
-        assert_eq!(pm_expected, parse_am_pm(pm));
-
-        let am_caps = "AM";
-        assert_eq!(am_expected, parse_am_pm(am_caps));
-
-        let pm_caps = "PM";
-        assert_eq!(pm_expected, parse_am_pm(pm_caps));
+        let hour = |input| parse_tm(input, "%I%p").map(|(t, len)| (t.tm_hour, len));
+
+        assert_eq!(hour("12am"), Some((0, 4)));
+        assert_eq!(hour("1pm"), Some((13, 3)));
+        assert_eq!(hour("12AM"), Some((0, 4)));
+        assert_eq!(hour("1PM"), Some((13, 3)));
+        assert_eq!(hour("12aM"), Some((0, 4)));
+        assert_eq!(hour("12        \tam"), Some((0, 13)));
+        assert_eq!(hour("1        pm        "), Some((13, 11)));
+        assert_eq!(hour("1 nm"), None);
+    }
 
-        let am_weird = "aM";
-        assert_eq!(am_expected, parse_am_pm(am_weird));
+    #[test]
+    fn utc_offsets() {
+        let offset = |input| parse_tm(input, "%z").map(|(t, len)| (t.tm_gmtoff, len));
+
+        assert_eq!(offset("Z"), Some((0, 1)));
+        assert_eq!(offset("+02"), Some((7200, 3)));
+        assert_eq!(offset("-0530"), Some((-19800, 5)));
+        assert_eq!(offset("+05:45"), Some((20700, 6)));
+        assert_eq!(offset("+0160"), None);
+        assert_eq!(offset("+1"), None);
+    }
 
-        let am_prefix = "        \tam";
-        let am_prefix_expected = Some((false, 11));
-        assert_eq!(am_prefix_expected, parse_am_pm(am_prefix));
+    #[test]
+    fn week_dates() {
+        let date = |input, format| {
+            parse_tm(input, format).map(|(t, _)| (t.tm_year, t.tm_mon, t.tm_mday, t.tm_yday))
+        };
 
-        let pm_spaces = "        pm        ";
-        let pm_spaces_expected = Some((true, 10));
-        assert_eq!(pm_spaces_expected, parse_am_pm(pm_spaces));
+        // 2021-01-03 is a Sunday, which ISO 8601 puts in the last week of
+        // 2020.
+        assert_eq!(date("2020-W53-7", "%G-W%V-%u"), Some((121, 0, 3, 2)));
+        assert_eq!(date("2021-W01", "%G-W%V"), Some((121, 0, 4, 3)));
+        assert_eq!(date("2021 00 5", "%Y %U %w"), Some((121, 0, 1, 0)));
+        assert_eq!(date("2021 01 1", "%Y %W %u"), Some((121, 0, 4, 3)));
+        assert_eq!(date("2021 060", "%Y %j"), Some((121, 2, 1, 59)));
     }
 }
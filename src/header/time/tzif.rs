@@ -0,0 +1,191 @@
+//! Parser of the TZif files of the time zone database.
+//!
+//! See <https://www.rfc-editor.org/rfc/rfc8536>.
+
+use alloc::{string::String, vec::Vec};
+use core::str;
+
+use super::posix_tz::PosixTz;
+
+const MAGIC: &[u8] = b"TZif";
+const HEADER_LEN: usize = 44;
+
+/// A local time type of a TZif file.
+#[derive(Clone, Copy, Debug)]
+struct LocalTimeType {
+    /// Offset from UTC in seconds.
+    utoff: i32,
+    isdst: bool,
+    /// Index of the abbreviation in the designations.
+    desigidx: u8,
+}
+
+#[derive(Debug)]
+pub struct TzIf {
+    transitions: Vec<i64>,
+    /// Index in `types` of the local time type after each transition.
+    transition_types: Vec<u8>,
+    types: Vec<LocalTimeType>,
+    /// NUL-terminated abbreviations of the local time types.
+    designations: Vec<u8>,
+    /// TZ string for the times after the last transition, empty if there is
+    /// none.
+    footer: String,
+}
+
+/// Counts from the header of a data block.
+struct Header {
+    isutcnt: usize,
+    isstdcnt: usize,
+    leapcnt: usize,
+    timecnt: usize,
+    typecnt: usize,
+    charcnt: usize,
+}
+
+impl Header {
+    fn parse(data: &[u8]) -> Option<(u8, Self)> {
+        if data.len() < HEADER_LEN || !data.starts_with(MAGIC) {
+            return None;
+        }
+        let count = |i: usize| {
+            let start = 20 + i * 4;
+            u32::from_be_bytes(data[start..start + 4].try_into().unwrap()) as usize
+        };
+        let header = Self {
+            isutcnt: count(0),
+            isstdcnt: count(1),
+            leapcnt: count(2),
+            timecnt: count(3),
+            typecnt: count(4),
+            charcnt: count(5),
+        };
+        Some((data[4], header))
+    }
+
+    /// Length of the data block, with times of `time_len` bytes, or `None` if
+    /// it overflows.
+    fn data_len(&self, time_len: usize) -> Option<usize> {
+        self.timecnt
+            .checked_mul(time_len + 1)?
+            .checked_add(self.typecnt.checked_mul(6)?)?
+            .checked_add(self.charcnt)?
+            .checked_add(self.leapcnt.checked_mul(time_len + 4)?)?
+            .checked_add(self.isstdcnt)?
+            .checked_add(self.isutcnt)
+    }
+}
+
+impl TzIf {
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let (version, header) = Header::parse(data)?;
+        let v1_len = HEADER_LEN.checked_add(header.data_len(4)?)?;
+        if version == 0 {
+            return Self::parse_block(&header, data.get(HEADER_LEN..v1_len)?, 4, "");
+        }
+
+        // Version 2 and later repeat the data with 64-bit times, followed by
+        // a footer.
+        let data = data.get(v1_len..)?;
+        let (_, header) = Header::parse(data)?;
+        let len = HEADER_LEN.checked_add(header.data_len(8)?)?;
+        let footer = data.get(len..)?.strip_prefix(b"\n")?;
+        let footer = &footer[..footer.iter().position(|&b| b == b'\n')?];
+        let footer = str::from_utf8(footer).ok()?;
+        Self::parse_block(&header, data.get(HEADER_LEN..len)?, 8, footer)
+    }
+
+    fn parse_block(header: &Header, data: &[u8], time_len: usize, footer: &str) -> Option<Self> {
+        if header.typecnt == 0 || header.charcnt == 0 {
+            return None;
+        }
+
+        let (times, data) = data.split_at(header.timecnt * time_len);
+        let transitions = times
+            .chunks_exact(time_len)
+            .map(|time| match time_len {
+                4 => i64::from(i32::from_be_bytes(time.try_into().unwrap())),
+                _ => i64::from_be_bytes(time.try_into().unwrap()),
+            })
+            .collect();
+
+        let (transition_types, data) = data.split_at(header.timecnt);
+        if transition_types
+            .iter()
+            .any(|&i| usize::from(i) >= header.typecnt)
+        {
+            return None;
+        }
+
+        let (types, data) = data.split_at(header.typecnt * 6);
+        let types: Vec<_> = types
+            .chunks_exact(6)
+            .map(|ty| LocalTimeType {
+                utoff: i32::from_be_bytes(ty[..4].try_into().unwrap()),
+                isdst: ty[4] != 0,
+                desigidx: ty[5],
+            })
+            .collect();
+
+        // Leap seconds and the indicators of how transition times were
+        // specified are not needed.
+        let designations = &data[..header.charcnt];
+        if types.iter().any(|ty| {
+            designations
+                .get(usize::from(ty.desigidx)..)
+                .is_none_or(|name| !name.contains(&0))
+        }) {
+            return None;
+        }
+
+        Some(Self {
+            transitions,
+            transition_types: transition_types.to_vec(),
+            types,
+            designations: designations.to_vec(),
+            footer: String::from(footer),
+        })
+    }
+
+    fn designation(&self, ty: &LocalTimeType) -> &str {
+        let name = &self.designations[usize::from(ty.desigidx)..];
+        let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+        str::from_utf8(name).unwrap_or("")
+    }
+
+    /// Returns the offset from UTC in seconds, whether daylight saving time is
+    /// in effect and the abbreviation of the time zone, at `time` seconds since
+    /// the Epoch.
+    pub fn local_time_type(&self, time: i64) -> (i32, bool, &str) {
+        let i = self
+            .transitions
+            .partition_point(|&transition| transition <= time);
+        if i == self.transitions.len() && !self.footer.is_empty() {
+            return PosixTz::parse(&self.footer).local_time_type(time);
+        }
+
+        let ty = match i {
+            0 => &self.types[0],
+            i => &self.types[usize::from(self.transition_types[i - 1])],
+        };
+        (ty.utoff, ty.isdst, self.designation(ty))
+    }
+
+    /// Returns the abbreviations of standard and daylight saving time, their
+    /// offset from UTC in seconds, in the most recent transitions to each.
+    pub fn rules(&self) -> ((&str, i32), (&str, i32)) {
+        let mut std = None;
+        let mut dst = None;
+        for &i in self.transition_types.iter().rev() {
+            let ty = &self.types[usize::from(i)];
+            let rule = if ty.isdst { &mut dst } else { &mut std };
+            rule.get_or_insert((self.designation(ty), ty.utoff));
+            if std.is_some() && dst.is_some() {
+                break;
+            }
+        }
+
+        let std = std.unwrap_or_else(|| (self.designation(&self.types[0]), self.types[0].utoff));
+        (std, dst.unwrap_or(std))
+    }
+}
@@ -0,0 +1,213 @@
+//! The local time zone, selected by the `TZ` environment variable.
+//!
+//! `TZ` may name a TZif file, either relative to [`ZONEINFO`] or as an
+//! absolute path, optionally preceded by `:`, or contain a POSIX TZ string.
+//! Without `TZ`, the zone is read from [`LOCALTIME`].
+
+use alloc::{borrow::Cow, string::String, vec::Vec};
+
+#[cfg(feature = "chrono-tz")]
+use chrono::{DateTime, Datelike, Offset, TimeZone};
+#[cfg(feature = "chrono-tz")]
+use chrono_tz::{OffsetComponents, OffsetName};
+
+use crate::{
+    c_str::{CStr, CString},
+    fs::File,
+    header::{
+        fcntl::{O_CLOEXEC, O_RDONLY},
+        stdlib::getenv,
+    },
+    io::Read,
+    platform::types::c_long,
+    sync::Mutex,
+};
+
+use super::{posix_tz::PosixTz, tzif::TzIf};
+
+const ZONEINFO: &[u8] = b"/usr/share/zoneinfo/";
+const LOCALTIME: &core::ffi::CStr = c"/etc/localtime";
+
+/// The zone, and the value of `TZ` it was loaded from.
+static CURRENT: Mutex<Option<(Option<Vec<u8>>, Zone)>> = Mutex::new(None);
+
+pub enum Zone {
+    TzIf(TzIf),
+    /// A POSIX TZ string.
+    Posix(String),
+    /// The database compiled in, for systems without TZif files.
+    #[cfg(feature = "chrono-tz")]
+    Chrono(chrono_tz::Tz),
+}
+
+pub struct LocalTimeType<'a> {
+    /// Offset from UTC in seconds.
+    pub utoff: i32,
+    pub isdst: bool,
+    pub name: Cow<'a, str>,
+}
+
+/// Values of `tzname`, `timezone` and `daylight` for a zone.
+pub struct Globals<'a> {
+    pub std: Cow<'a, str>,
+    pub dst: Cow<'a, str>,
+    pub timezone: c_long,
+    pub daylight: bool,
+}
+
+/// Calls `f` with the current time zone, which is loaded again if `TZ`
+/// changed since the last call.
+pub fn with_current<T>(f: impl FnOnce(&Zone) -> T) -> T {
+    let tz = unsafe { CStr::from_nullable_ptr(getenv(c"TZ".as_ptr())) }.map(CStr::to_bytes);
+
+    let mut current = CURRENT.lock();
+    if current
+        .as_ref()
+        .is_none_or(|(loaded, _)| loaded.as_deref() != tz)
+    {
+        *current = Some((tz.map(<[u8]>::to_vec), Zone::load(tz)));
+    }
+    f(&current.as_ref().unwrap().1)
+}
+
+fn read_tzif(path: CStr) -> Option<TzIf> {
+    let mut file = File::open(path, O_RDONLY | O_CLOEXEC).ok()?;
+    let mut data = Vec::new();
+    file.read_to_end(&mut data).ok()?;
+    TzIf::parse(&data)
+}
+
+impl Zone {
+    fn load(tz: Option<&[u8]>) -> Self {
+        let Some(tz) = tz else {
+            return read_tzif(LOCALTIME.into())
+                .map_or_else(|| Self::Posix(String::from("UTC0")), Self::TzIf);
+        };
+
+        let name = tz.strip_prefix(b":").unwrap_or(tz);
+        // Names must not escape the zoneinfo directory.
+        if !name.is_empty() && !name.windows(2).any(|w| w == b"..") {
+            let path = if name.starts_with(b"/") {
+                name.to_vec()
+            } else {
+                [ZONEINFO, name].concat()
+            };
+            if let Ok(path) = CString::new(path)
+                && let Some(tzif) = read_tzif(CStr::borrow(&path))
+            {
+                return Self::TzIf(tzif);
+            }
+
+            #[cfg(feature = "chrono-tz")]
+            if let Ok(name) = core::str::from_utf8(name)
+                && let Ok(tz) = name.parse()
+            {
+                return Self::Chrono(tz);
+            }
+        }
+        Self::Posix(String::from_utf8_lossy(tz).into_owned())
+    }
+
+    /// Returns the local time type at `time` seconds since the Epoch.
+    pub fn local_time_type(&self, time: i64) -> LocalTimeType<'_> {
+        let (utoff, isdst, name) = match self {
+            Self::TzIf(tzif) => tzif.local_time_type(time),
+            Self::Posix(tz) => PosixTz::parse(tz).local_time_type(time),
+            #[cfg(feature = "chrono-tz")]
+            Self::Chrono(tz) => {
+                let utc = DateTime::from_timestamp(time, 0).unwrap_or_default();
+                let offset = tz.offset_from_utc_datetime(&utc.naive_utc());
+                return LocalTimeType {
+                    utoff: offset.fix().local_minus_utc(),
+                    isdst: offset.dst_offset().num_seconds() != 0,
+                    name: Cow::Owned(String::from(offset.abbreviation().unwrap_or(""))),
+                };
+            }
+        };
+        LocalTimeType {
+            utoff,
+            isdst,
+            name: Cow::Borrowed(name),
+        }
+    }
+
+    /// Returns the number of seconds since the Epoch of the local time
+    /// `local`, in seconds since the Epoch as if it were UTC.
+    ///
+    /// A time repeated when clocks are turned back is taken as daylight
+    /// saving time if `isdst` is positive, and as standard time otherwise. A
+    /// time skipped when clocks are turned forward is taken with the offset
+    /// from before the change.
+    pub fn local_to_utc(&self, local: i64, isdst: i32) -> i64 {
+        const DAY: i64 = 24 * 60 * 60;
+
+        // Around a change, the offsets before and after it are the only
+        // candidates.
+        let before = self.local_time_type(local.saturating_sub(DAY)).utoff;
+        let after = self.local_time_type(local.saturating_add(DAY)).utoff;
+        let valid = |utoff: i32| {
+            let time = local.saturating_sub(i64::from(utoff));
+            (self.local_time_type(time).utoff == utoff).then_some(time)
+        };
+
+        match (valid(before), valid(after)) {
+            (Some(early), Some(late)) if isdst > 0 => early.min(late),
+            (Some(early), Some(late)) => early.max(late),
+            (Some(time), None) | (None, Some(time)) => time,
+            (None, None) => local.saturating_sub(i64::from(before)),
+        }
+    }
+
+    /// Returns the values of `tzname`, `timezone` and `daylight` for the
+    /// zone.
+    pub fn globals(&self) -> Globals<'_> {
+        match self {
+            Self::TzIf(tzif) => {
+                let ((std, std_utoff), (dst, dst_utoff)) = tzif.rules();
+                Globals {
+                    std: Cow::Borrowed(std),
+                    dst: Cow::Borrowed(dst),
+                    timezone: -c_long::from(std_utoff),
+                    daylight: std_utoff != dst_utoff,
+                }
+            }
+            Self::Posix(tz) => {
+                let tz = PosixTz::parse(tz);
+                Globals {
+                    std: Cow::Borrowed(tz.std),
+                    dst: Cow::Borrowed(tz.dst),
+                    timezone: tz.timezone.unwrap_or(0),
+                    daylight: tz.daylight,
+                }
+            }
+            #[cfg(feature = "chrono-tz")]
+            Self::Chrono(tz) => {
+                // Compare winter and summer of this year.
+                let now = unsafe { super::time(core::ptr::null_mut()) };
+                let year = DateTime::from_timestamp(now, 0).unwrap_or_default().year();
+                let [january, july] = [1, 7].map(|month| {
+                    let time = tz
+                        .with_ymd_and_hms(year, month, 1, 0, 0, 0)
+                        .earliest()
+                        .map_or(0, |time| time.timestamp());
+                    self.local_time_type(time)
+                });
+                let (std, dst) = if january.isdst {
+                    (july, january)
+                } else {
+                    (january, july)
+                };
+                Globals {
+                    timezone: -c_long::from(std.utoff),
+                    daylight: std.utoff != dst.utoff,
+                    dst: if dst.isdst {
+                        dst.name
+                    } else {
+                        std.name.clone()
+                    },
+                    std: std.name,
+                }
+            }
+        }
+    }
+}
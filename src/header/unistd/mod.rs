@@ -35,6 +35,7 @@ use crate::{
             size_t, ssize_t, suseconds_t, time_t, uid_t,
         },
     },
+    pthread,
 };
 
 pub use self::{brk::*, getopt::*, pathconf::*, sysconf::*};
@@ -211,7 +212,9 @@ pub unsafe extern "C" fn chroot(path: *const c_char) -> c_int {
 /// indicate the error.
 #[unsafe(no_mangle)]
 pub extern "C" fn close(fildes: c_int) -> c_int {
-    Sys::close(fildes).map(|()| 0).or_minus_one_errno()
+    pthread::cancellation_point(|| Sys::close(fildes))
+        .map(|()| 0)
+        .or_minus_one_errno()
 }
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/confstr.html>.
@@ -444,7 +447,9 @@ pub unsafe extern "C" fn fchownat(
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/fdatasync.html>.
 #[unsafe(no_mangle)]
 pub extern "C" fn fdatasync(fildes: c_int) -> c_int {
-    Sys::fdatasync(fildes).map(|()| 0).or_minus_one_errno()
+    pthread::cancellation_point(|| Sys::fdatasync(fildes))
+        .map(|()| 0)
+        .or_minus_one_errno()
 }
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/fork.html>.
@@ -467,8 +472,11 @@ pub unsafe extern "C" fn fork() -> pid_t {
     for prepare in unsafe { &fork_hooks[0] } {
         prepare();
     }
+    platform::fork_prepare();
     let pid = unsafe { Sys::fork() }.or_minus_one_errno();
+    platform::fork_release();
     if pid == 0 {
+        platform::fork_child();
         for child in unsafe { &fork_hooks[2] } {
             child();
         }
@@ -483,7 +491,9 @@ pub unsafe extern "C" fn fork() -> pid_t {
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/fsync.html>.
 #[unsafe(no_mangle)]
 pub extern "C" fn fsync(fildes: c_int) -> c_int {
-    Sys::fsync(fildes).map(|()| 0).or_minus_one_errno()
+    pthread::cancellation_point(|| Sys::fsync(fildes))
+        .map(|()| 0)
+        .or_minus_one_errno()
 }
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/ftruncate.html>.
@@ -967,13 +977,10 @@ pub unsafe extern "C" fn pread(
     nbyte: size_t,
     offset: off_t,
 ) -> ssize_t {
-    Sys::pread(
-        fildes,
-        unsafe { slice::from_raw_parts_mut(buf.cast::<u8>(), nbyte) },
-        offset,
-    )
-    .map(|read| read as ssize_t)
-    .or_minus_one_errno()
+    let buf = unsafe { slice::from_raw_parts_mut(buf.cast::<u8>(), nbyte) };
+    pthread::cancellation_point(|| Sys::pread(fildes, buf, offset))
+        .map(|read| read as ssize_t)
+        .or_minus_one_errno()
 }
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/write.html>.
@@ -991,13 +998,10 @@ pub unsafe extern "C" fn pwrite(
     nbyte: size_t,
     offset: off_t,
 ) -> ssize_t {
-    Sys::pwrite(
-        fildes,
-        unsafe { slice::from_raw_parts(buf.cast::<u8>(), nbyte) },
-        offset,
-    )
-    .map(|read| read as ssize_t)
-    .or_minus_one_errno()
+    let buf = unsafe { slice::from_raw_parts(buf.cast::<u8>(), nbyte) };
+    pthread::cancellation_point(|| Sys::pwrite(fildes, buf, offset))
+        .map(|read| read as ssize_t)
+        .or_minus_one_errno()
 }
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/read.html>.
@@ -1011,7 +1015,7 @@ pub unsafe extern "C" fn pwrite(
 pub unsafe extern "C" fn read(fildes: c_int, buf: *mut c_void, nbyte: size_t) -> ssize_t {
     let buf = unsafe { slice::from_raw_parts_mut(buf.cast::<u8>(), nbyte) };
     trace_expr!(
-        Sys::read(fildes, buf)
+        pthread::cancellation_point(|| Sys::read(fildes, buf))
             .map(|read| read as ssize_t)
             .or_minus_one_errno(),
         "read({}, {:p}, {})",
@@ -1189,7 +1193,8 @@ pub extern "C" fn sleep(seconds: c_uint) -> c_uint {
     // If sleep() returns because the requested time has elapsed, the value returned shall be 0.
     // If sleep() returns due to delivery of a signal, the return value shall be the "unslept" amount
     // (the requested time minus the time actually slept) in seconds.
-    match unsafe { Sys::nanosleep(&raw const rqtp, &raw mut rmtp) } {
+    match pthread::cancellation_point(|| unsafe { Sys::nanosleep(&raw const rqtp, &raw mut rmtp) })
+    {
         Err(Errno(_)) => rmtp.tv_sec as c_uint,
         _ => 0,
     }
@@ -1393,7 +1398,7 @@ pub extern "C" fn usleep(useconds: useconds_t) -> c_int {
         tv_nsec,
     };
     let rmtp = ptr::null_mut();
-    unsafe { Sys::nanosleep(&raw const rqtp, rmtp) }
+    pthread::cancellation_point(|| unsafe { Sys::nanosleep(&raw const rqtp, rmtp) })
         .map(|()| 0)
         .or_minus_one_errno()
 }
@@ -1467,7 +1472,7 @@ unsafe fn with_argv(
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn write(fildes: c_int, buf: *const c_void, nbyte: size_t) -> ssize_t {
     let buf = unsafe { slice::from_raw_parts(buf.cast::<u8>(), nbyte) };
-    Sys::write(fildes, buf)
+    pthread::cancellation_point(|| Sys::write(fildes, buf))
         .map(|bytes| bytes as ssize_t)
         .or_minus_one_errno()
 }
@@ -162,8 +162,7 @@ pub(super) fn sysconf_impl(name: c_int) -> c_long {
         // TODO: limits.h?
         _SC_DELAYTIMER_MAX => -1,
         _SC_MQ_OPEN_MAX => -1,
-        // TODO: limits.h?
-        _SC_MQ_PRIO_MAX => -1,
+        _SC_MQ_PRIO_MAX => MQ_PRIO_MAX,
         _SC_VERSION => _POSIX_VERSION,
         _SC_PAGE_SIZE => PAGE_SIZE.try_into().unwrap_or(-1),
         _SC_RTSIG_MAX => (signal::SIGRTMAX - signal::SIGRTMIN)
@@ -171,8 +170,7 @@ pub(super) fn sysconf_impl(name: c_int) -> c_long {
             .unwrap_or(-1),
         // TODO: limits.h
         _SC_SEM_NSEMS_MAX => -1,
-        // TODO: limits.h
-        _SC_SEM_VALUE_MAX => -1,
+        _SC_SEM_VALUE_MAX => SEM_VALUE_MAX.into(),
         _SC_SIGQUEUE_MAX => -1,
         _SC_TIMER_MAX => -1,
         _SC_BC_BASE_MAX => BC_BASE_MAX,
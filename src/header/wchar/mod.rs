@@ -2,6 +2,7 @@
 //!
 //! See <https://pubs.opengroup.org/onlinepubs/9799919799/basedefs/wchar.h.html>.
 
+use alloc::string::String;
 use core::{char, ffi::VaList as va_list, mem, ptr, slice};
 
 use crate::{
@@ -9,10 +10,11 @@ use crate::{
     header::{
         ctype::isspace,
         errno::{EILSEQ, ENOMEM, ERANGE},
+        locale::uselocale,
         stdio::*,
         stdlib::{MB_CUR_MAX, MB_LEN_MAX, malloc},
         string,
-        time::*,
+        time::{strftime, *},
         wchar::reader::Reader,
         wctype::*,
     },
@@ -568,15 +570,39 @@ pub unsafe extern "C" fn wcscspn(wcs: *const wchar_t, set: *const wchar_t) -> si
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/wcsftime.html>.
 #[unsafe(no_mangle)]
-#[expect(unused_variables, reason = "function not yet implemented")]
-pub extern "C" fn wcsftime(
+pub unsafe extern "C" fn wcsftime(
     wcs: *mut wchar_t,
     maxsize: size_t,
     format: *const wchar_t,
     timptr: *const tm,
 ) -> size_t {
-    todo_skip!(0, "wcsftime is not implemented");
-    0
+    if maxsize == 0 {
+        return 0;
+    }
+    let format: String = unsafe { NulTerminated::new(format).unwrap() }
+        .map(|&wc| char::from_u32(wc as u32).unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect();
+
+    // Each wide character takes at most 4 bytes in UTF-8.
+    let Some(out) = strftime::strftime(
+        format.as_bytes(),
+        unsafe { &*timptr },
+        unsafe { uselocale(ptr::null_mut()) },
+        maxsize.saturating_mul(4),
+    ) else {
+        return 0;
+    };
+
+    let mut len = 0;
+    for c in String::from_utf8_lossy(&out).chars() {
+        if len + 1 >= maxsize {
+            return 0;
+        }
+        unsafe { *wcs.add(len) = c as wchar_t };
+        len += 1;
+    }
+    unsafe { *wcs.add(len) = 0 };
+    len
 }
 
 /// See <https://pubs.opengroup.org/onlinepubs/9799919799/functions/wcslen.html>.
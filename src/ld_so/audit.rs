@@ -0,0 +1,152 @@
+//! Minimal run-time linker auditing interface.
+//!
+//! See <https://www.man7.org/linux/man-pages/man7/rtld-audit.7.html>.
+
+use alloc::{boxed::Box, collections::BTreeMap, sync::Arc, vec::Vec};
+use core::{mem, sync::atomic::AtomicUsize};
+
+use object::{NativeEndian, SymbolIndex};
+
+use super::{
+    dso::{DSO, Sym},
+    linker::Symbol,
+};
+use crate::{
+    c_str::CString,
+    header::{
+        dlfcn::{LM_ID_BASE, Lmid_t},
+        link::{LA_FLG_BINDFROM, LA_FLG_BINDTO, LAV_CURRENT, link_map},
+    },
+    platform::types::{c_char, c_uint, c_void},
+    sync::Mutex,
+};
+
+type LaVersionFn = unsafe extern "C" fn(version: c_uint) -> c_uint;
+type LaObjopenFn =
+    unsafe extern "C" fn(map: *mut link_map, lmid: Lmid_t, cookie: *mut usize) -> c_uint;
+type LaSymbindFn = unsafe extern "C" fn(
+    sym: *mut Sym,
+    ndx: c_uint,
+    refcook: *mut usize,
+    defcook: *mut usize,
+    flags: *mut c_uint,
+    symname: *const c_char,
+) -> usize;
+
+#[cfg(target_pointer_width = "32")]
+const LA_SYMBIND: &str = "la_symbind32";
+#[cfg(target_pointer_width = "64")]
+const LA_SYMBIND: &str = "la_symbind64";
+
+/// The auditing libraries requested by `LD_AUDIT`, in order.
+pub(super) static AUDITORS: spin::Once<Vec<Auditor>> = spin::Once::new();
+
+pub(super) struct Auditor {
+    /// Keeps the auditing library loaded.
+    _obj: Arc<DSO>,
+    objopen: Option<LaObjopenFn>,
+    symbind: Option<LaSymbindFn>,
+    /// The cookie and the `la_objopen()` flags of each audited object, by
+    /// object ID.
+    objects: Mutex<BTreeMap<usize, (Box<AtomicUsize>, c_uint)>>,
+}
+
+impl Auditor {
+    /// Negotiates the interface version with the auditing library `obj`.
+    ///
+    /// Returns `None` if `obj` does not provide `la_version()` or does not
+    /// support our version of the interface.
+    pub fn new(obj: Arc<DSO>) -> Option<Self> {
+        let lookup = |name| obj.get_sym(name, None).map(|(sym, _)| sym.as_ptr());
+
+        let la_version =
+            unsafe { mem::transmute::<*mut c_void, LaVersionFn>(lookup("la_version")?) };
+        let version = unsafe { la_version(LAV_CURRENT) };
+        if version == 0 || version > LAV_CURRENT {
+            return None;
+        }
+
+        Some(Self {
+            objopen: lookup("la_objopen")
+                .map(|f| unsafe { mem::transmute::<*mut c_void, LaObjopenFn>(f) }),
+            symbind: lookup(LA_SYMBIND)
+                .map(|f| unsafe { mem::transmute::<*mut c_void, LaSymbindFn>(f) }),
+            _obj: obj,
+            objects: Mutex::new(BTreeMap::new()),
+        })
+    }
+}
+
+/// Notifies the auditing libraries that `obj` has been loaded.
+pub(super) fn objopen(obj: &DSO) {
+    let Some(auditors) = AUDITORS.get() else {
+        return;
+    };
+
+    for auditor in auditors {
+        let cookie = Box::new(AtomicUsize::new(obj.link_map as usize));
+        let flags = match auditor.objopen {
+            Some(la_objopen) => unsafe { la_objopen(obj.link_map, LM_ID_BASE, cookie.as_ptr()) },
+            None => LA_FLG_BINDTO | LA_FLG_BINDFROM,
+        };
+        auditor.objects.lock().insert(obj.id, (cookie, flags));
+    }
+}
+
+/// Lets the auditing libraries observe, or redirect, the binding of a
+/// reference in `refobj` to the definition `sym` in `defobj`.
+///
+/// Returns the address the reference should be bound to.
+pub(super) fn symbind(refobj: &DSO, defobj: &DSO, sym: &Symbol) -> usize {
+    let mut addr = sym.resolve() as usize;
+
+    let Some(auditors) = AUDITORS.get() else {
+        return addr;
+    };
+    let Some(&def_sym) = defobj.dynamic.symbol(SymbolIndex(sym.index)) else {
+        return addr;
+    };
+    let name = CString::new(sym.name).unwrap();
+
+    for auditor in auditors {
+        let Some(la_symbind) = auditor.symbind else {
+            continue;
+        };
+
+        let (refcook, defcook) = {
+            let objects = auditor.objects.lock();
+            match (objects.get(&refobj.id), objects.get(&defobj.id)) {
+                (Some((refcook, refflags)), Some((defcook, defflags)))
+                    if refflags & LA_FLG_BINDFROM != 0 && defflags & LA_FLG_BINDTO != 0 =>
+                {
+                    (refcook.as_ptr(), defcook.as_ptr())
+                }
+                _ => continue,
+            }
+        };
+
+        let mut def_sym = def_sym;
+        #[cfg(target_pointer_width = "32")]
+        {
+            def_sym.st_value = object::U32::new(NativeEndian, addr as u32);
+        }
+        #[cfg(target_pointer_width = "64")]
+        {
+            def_sym.st_value = object::U64::new(NativeEndian, addr as u64);
+        }
+
+        let mut flags = 0;
+        addr = unsafe {
+            la_symbind(
+                &raw mut def_sym,
+                sym.index as c_uint,
+                refcook,
+                defcook,
+                &raw mut flags,
+                name.as_ptr(),
+            )
+        };
+    }
+
+    addr
+}
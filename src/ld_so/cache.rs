@@ -0,0 +1,420 @@
+//! Library search configuration and cache.
+//!
+//! `/etc/ld.so.conf` lists the directories searched for libraries, besides the
+//! default ones. As searching many directories for each dependency is slow,
+//! the libraries found in these directories can be recorded in
+//! `/etc/ld.so.cache`, which is regenerated by running `ld.so --update-cache`.
+//!
+//! The cache file, in native byte order, consists of:
+//! * a header: the magic bytes `RLDCACHE`, the format version and the number
+//!   of entries, as `u32`s,
+//! * the entries, sorted by library name: the offsets of the name and of the
+//!   path of the library in the string table, as `u32`s,
+//! * the string table, made of null-terminated strings.
+
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{mem, slice, str};
+
+use crate::{
+    c_str::{CStr, CString},
+    error::Errno,
+    fs::File,
+    header::{
+        dirent::{DIR, closedir, readdir},
+        errno::{EINVAL, ENOENT},
+        fcntl,
+        glob::{glob, glob_t, globfree},
+    },
+    io::Write,
+    platform::{Pal, Sys},
+};
+
+use super::linker::MmapFile;
+
+/// Configuration file listing the directories to search for libraries.
+pub const CONFIG_PATH: &str = "/etc/ld.so.conf";
+
+/// Cache of the libraries found in the configured directories.
+pub const CACHE_PATH: &str = "/etc/ld.so.cache";
+
+/// Directories searched for libraries after the configured ones.
+pub const DEFAULT_DIRS: [&str; 2] = ["/lib", "/usr/lib"];
+
+const MAGIC: [u8; 8] = *b"RLDCACHE";
+const VERSION: u32 = 1;
+const HEADER_SIZE: usize = MAGIC.len() + 2 * mem::size_of::<u32>();
+const ENTRY_SIZE: usize = 2 * mem::size_of::<u32>();
+
+/// Maximum nesting of `include` directives.
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + mem::size_of::<u32>())?;
+    Some(u32::from_ne_bytes(bytes.try_into().unwrap()))
+}
+
+/// A memory-mapped cache file.
+pub struct Cache {
+    file: MmapFile,
+    count: usize,
+}
+
+impl Cache {
+    /// Opens the cache file at `path`, returning `None` if it is missing or
+    /// malformed.
+    pub fn open(path: &str) -> Option<Self> {
+        let path = CString::new(path).ok()?;
+        let mut file =
+            MmapFile::open(CStr::borrow(&path), fcntl::O_RDONLY | fcntl::O_CLOEXEC).ok()?;
+        // The cache stays mapped for the lifetime of the program, which should
+        // not see its file descriptor.
+        file.close();
+
+        let data = file.data();
+        if data.get(..MAGIC.len())? != MAGIC || read_u32(data, MAGIC.len())? != VERSION {
+            return None;
+        }
+        let count = read_u32(data, MAGIC.len() + mem::size_of::<u32>())? as usize;
+        if data.len() < HEADER_SIZE + count.checked_mul(ENTRY_SIZE)? {
+            return None;
+        }
+
+        Some(Self { file, count })
+    }
+
+    fn string(&self, offset: u32) -> Option<&str> {
+        let strings = &self.file.data()[HEADER_SIZE + self.count * ENTRY_SIZE..];
+        let string = strings.get(offset as usize..)?;
+        let len = string.iter().position(|&c| c == 0)?;
+        str::from_utf8(&string[..len]).ok()
+    }
+
+    fn entry(&self, index: usize) -> Option<(&str, &str)> {
+        let offset = HEADER_SIZE + index * ENTRY_SIZE;
+        let data = self.file.data();
+        let name = self.string(read_u32(data, offset)?)?;
+        let path = self.string(read_u32(data, offset + mem::size_of::<u32>())?)?;
+        Some((name, path))
+    }
+
+    /// Returns the path of the library `name`, if it is in the cache.
+    pub fn lookup(&self, name: &str) -> Option<&str> {
+        let (mut low, mut high) = (0, self.count);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let (entry_name, path) = self.entry(mid)?;
+            match entry_name.cmp(name) {
+                core::cmp::Ordering::Less => low = mid + 1,
+                core::cmp::Ordering::Greater => high = mid,
+                core::cmp::Ordering::Equal => return Some(path),
+            }
+        }
+        None
+    }
+}
+
+/// Returns the directories listed in the configuration file at `path`, and in
+/// the files it includes.
+pub fn read_config(path: &str) -> Vec<String> {
+    let mut dirs = Vec::new();
+    read_config_into(path, &mut dirs, 0);
+    dirs
+}
+
+fn read_config_into(path: &str, dirs: &mut Vec<String>, depth: usize) {
+    let Ok(path_c) = CString::new(path) else {
+        return;
+    };
+    let Ok(file) = MmapFile::open(CStr::borrow(&path_c), fcntl::O_RDONLY | fcntl::O_CLOEXEC) else {
+        return;
+    };
+    let Ok(config) = str::from_utf8(file.data()) else {
+        return;
+    };
+
+    for line in config.lines() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+
+        if let Some(pattern) = line.strip_prefix("include")
+            && pattern.starts_with([' ', '\t'])
+        {
+            if depth >= MAX_INCLUDE_DEPTH {
+                continue;
+            }
+            for pattern in pattern.split_ascii_whitespace() {
+                // Relative patterns are relative to the including file.
+                let pattern = if pattern.starts_with('/') {
+                    pattern.to_string()
+                } else {
+                    let dir = path.rsplit_once('/').map_or(".", |(dir, _)| dir);
+                    format!("{dir}/{pattern}")
+                };
+                for included in expand_glob(&pattern) {
+                    read_config_into(&included, dirs, depth + 1);
+                }
+            }
+        } else {
+            for dir in line.split([' ', '\t', ',', ':']) {
+                // Ignore the library types of the legacy `dir=type` syntax.
+                let dir = dir.split('=').next().unwrap_or_default();
+                let dir = match dir.trim_end_matches('/') {
+                    "" if dir.starts_with('/') => "/",
+                    dir => dir,
+                };
+                if dir.starts_with('/') && !dirs.iter().any(|other| other == dir) {
+                    dirs.push(dir.to_string());
+                }
+            }
+        }
+    }
+}
+
+/// Returns the paths matching the shell pattern `pattern`, sorted.
+fn expand_glob(pattern: &str) -> Vec<String> {
+    let Ok(pattern) = CString::new(pattern) else {
+        return Vec::new();
+    };
+
+    let mut results: glob_t = unsafe { mem::zeroed() };
+    if unsafe { glob(pattern.as_ptr(), 0, None, &raw mut results) } != 0 {
+        return Vec::new();
+    }
+
+    let paths = unsafe { slice::from_raw_parts(results.gl_pathv, results.gl_pathc) }
+        .iter()
+        .filter_map(|&path| {
+            let path = unsafe { CStr::from_ptr(path) };
+            str::from_utf8(path.to_bytes()).ok().map(String::from)
+        })
+        .collect();
+    unsafe { globfree(&raw mut results) };
+    paths
+}
+
+/// Returns whether `name` looks like the name of a shared library, such as
+/// `libfoo.so` or `libfoo.so.1.2`.
+fn is_library_name(name: &str) -> bool {
+    name.match_indices(".so").any(|(i, _)| {
+        let rest = &name[i + ".so".len()..];
+        rest.is_empty() || rest.starts_with('.')
+    })
+}
+
+/// Lists the libraries in `dirs`, by name. Libraries in earlier directories
+/// take precedence.
+fn scan_dirs(dirs: &[String]) -> BTreeMap<String, String> {
+    let mut libraries = BTreeMap::new();
+
+    for dir in dirs {
+        let Ok(dir_c) = CString::new(dir.as_str()) else {
+            continue;
+        };
+        let Ok(mut handle) = DIR::new(CStr::borrow(&dir_c)) else {
+            continue;
+        };
+
+        while let Some(entry) = unsafe { readdir(&mut handle).as_ref() } {
+            let name = unsafe { CStr::from_ptr(entry.d_name.as_ptr()) };
+            let Ok(name) = str::from_utf8(name.to_bytes()) else {
+                continue;
+            };
+            if is_library_name(name) && !libraries.contains_key(name) {
+                libraries.insert(name.to_string(), format!("{dir}/{name}"));
+            }
+        }
+
+        closedir(handle);
+    }
+
+    libraries
+}
+
+/// Regenerates the cache file at `cache_path` from the libraries found in the
+/// directories listed by the configuration file at `config_path`, and in the
+/// default directories.
+///
+/// Returns the number of libraries in the new cache.
+pub fn update(config_path: &str, cache_path: &str) -> Result<usize, Errno> {
+    let mut dirs = read_config(config_path);
+    for dir in DEFAULT_DIRS {
+        if !dirs.iter().any(|other| other == dir) {
+            dirs.push(dir.to_string());
+        }
+    }
+    let libraries = scan_dirs(&dirs);
+
+    let mut entries = Vec::with_capacity(libraries.len() * ENTRY_SIZE);
+    let mut strings = Vec::new();
+    for (name, path) in libraries.iter() {
+        for string in [name, path] {
+            let offset = u32::try_from(strings.len()).map_err(|_| Errno(EINVAL))?;
+            entries.extend_from_slice(&offset.to_ne_bytes());
+            strings.extend_from_slice(string.as_bytes());
+            strings.push(0);
+        }
+    }
+
+    let count = u32::try_from(libraries.len()).map_err(|_| Errno(EINVAL))?;
+    let mut data = Vec::with_capacity(HEADER_SIZE + entries.len() + strings.len());
+    data.extend_from_slice(&MAGIC);
+    data.extend_from_slice(&VERSION.to_ne_bytes());
+    data.extend_from_slice(&count.to_ne_bytes());
+    data.extend_from_slice(&entries);
+    data.extend_from_slice(&strings);
+
+    // Replace the cache atomically, so that concurrently starting programs
+    // never see a partially written cache.
+    let tmp_path = CString::new(format!("{cache_path}~")).map_err(|_| Errno(ENOENT))?;
+    let cache_path = CString::new(cache_path).map_err(|_| Errno(ENOENT))?;
+    {
+        let mut file = File::create(
+            CStr::borrow(&tmp_path),
+            fcntl::O_WRONLY | fcntl::O_TRUNC | fcntl::O_CLOEXEC,
+            0o644,
+        )?;
+        file.write_all(&data).map_err(|_| Errno(EINVAL))?;
+        file.sync_all()?;
+    }
+    Sys::rename(CStr::borrow(&tmp_path), CStr::borrow(&cache_path))?;
+
+    Ok(libraries.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cache, read_config, update};
+    use crate::{
+        c_str::{CStr, CString},
+        fs::File,
+        header::fcntl,
+        io::Write,
+        platform::{Pal, Sys},
+    };
+    use alloc::{format, string::String, vec, vec::Vec};
+
+    /// Temporary directory, removed with its content when dropped.
+    struct TempDir {
+        path: String,
+        files: Vec<String>,
+        dirs: Vec<String>,
+    }
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = format!("/tmp/relibc-{name}-{}", Sys::getpid());
+            let mut dir = Self {
+                path: path.clone(),
+                files: Vec::new(),
+                dirs: Vec::new(),
+            };
+            dir.mkdir("");
+            dir
+        }
+
+        fn path(&self, name: &str) -> String {
+            format!("{}/{name}", self.path)
+        }
+
+        fn mkdir(&mut self, name: &str) -> String {
+            let path = self.path(name);
+            let path_c = CString::new(path.as_str()).unwrap();
+            Sys::mkdir(CStr::borrow(&path_c), 0o755).unwrap();
+            self.dirs.push(path.clone());
+            path
+        }
+
+        fn write(&mut self, name: &str, content: &str) -> String {
+            let path = self.path(name);
+            let path_c = CString::new(path.as_str()).unwrap();
+            let mut file = File::create(
+                CStr::borrow(&path_c),
+                fcntl::O_WRONLY | fcntl::O_TRUNC | fcntl::O_CLOEXEC,
+                0o644,
+            )
+            .unwrap();
+            file.write_all(content.as_bytes()).unwrap();
+            self.files.push(path.clone());
+            path
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            for file in self.files.iter() {
+                let _ = Sys::unlink(CStr::borrow(&CString::new(file.as_str()).unwrap()));
+            }
+            for dir in self.dirs.iter().rev() {
+                let _ = Sys::rmdir(CStr::borrow(&CString::new(dir.as_str()).unwrap()));
+            }
+        }
+    }
+
+    #[test]
+    fn config_includes() {
+        let mut tmp = TempDir::new("ld-so-conf");
+        tmp.mkdir("conf.d");
+        tmp.write("conf.d/b.conf", "/opt/b # comment\n/opt/a\n");
+        tmp.write("conf.d/a.conf", "# only a comment\n/opt/c/\n");
+        tmp.write("conf.d/ignored", "/opt/ignored\n");
+        let config = tmp.write(
+            "ld.so.conf",
+            "/opt/first\ninclude conf.d/*.conf\n/opt/a:/opt/last=libc5\n",
+        );
+
+        // Included files are read in sorted order, and duplicates are dropped.
+        assert_eq!(
+            read_config(&config),
+            vec!["/opt/first", "/opt/c", "/opt/b", "/opt/a", "/opt/last"]
+        );
+    }
+
+    #[test]
+    fn config_include_loop() {
+        let mut tmp = TempDir::new("ld-so-conf-loop");
+        let config = tmp.path("ld.so.conf");
+        tmp.write("ld.so.conf", &format!("/opt/dir\ninclude {config}\n"));
+
+        assert_eq!(read_config(&config), vec!["/opt/dir"]);
+    }
+
+    #[test]
+    fn cache_lookup() {
+        let mut tmp = TempDir::new("ld-so-cache");
+        let first = tmp.mkdir("first");
+        let second = tmp.mkdir("second");
+        tmp.write("first/libfoo.so", "");
+        tmp.write("second/libfoo.so", "");
+        tmp.write("second/libbar.so.1", "");
+        tmp.write("second/notalib.txt", "");
+        let config = tmp.write("ld.so.conf", &format!("{first}\n{second}\n"));
+        let cache_path = tmp.path("ld.so.cache");
+        tmp.files.push(cache_path.clone());
+
+        // The default directories are scanned too.
+        assert!(update(&config, &cache_path).unwrap() >= 2);
+        let cache = Cache::open(&cache_path).unwrap();
+        assert_eq!(
+            cache.lookup("libfoo.so"),
+            Some(format!("{first}/libfoo.so").as_str())
+        );
+        assert_eq!(
+            cache.lookup("libbar.so.1"),
+            Some(format!("{second}/libbar.so.1").as_str())
+        );
+        assert_eq!(cache.lookup("notalib.txt"), None);
+        assert_eq!(cache.lookup("libmissing-relibc-test.so"), None);
+    }
+
+    #[test]
+    fn cache_malformed() {
+        let mut tmp = TempDir::new("ld-so-cache-malformed");
+        let path = tmp.write("ld.so.cache", "RLDCACHE");
+        assert!(Cache::open(&path).is_none());
+        assert!(Cache::open(&tmp.path("missing")).is_none());
+    }
+}
@@ -2,6 +2,8 @@ use crate::{c_str::CString, platform::types::*};
 use alloc::boxed::Box;
 use core::ptr;
 
+pub use crate::header::link::link_map as LinkMap;
+
 #[repr(C)]
 pub enum RTLDState {
     /// Mapping change is complete.
@@ -45,14 +47,15 @@ impl RTLDDebug {
         r_ldbase: 0,
     };
 
-    pub fn insert(&mut self, l_addr: usize, name: &str, l_ld: usize) {
+    pub fn insert(&mut self, l_addr: usize, name: &str, l_ld: usize) -> *mut LinkMap {
         if self.r_map.is_null() {
             self.r_map = LinkMap::new_with_args(l_addr, name, l_ld);
+            self.r_map
         } else {
-            unsafe { (*self.r_map).add_object(l_addr, name, l_ld) };
+            unsafe { (*self.r_map).add_object(l_addr, name, l_ld) }
         }
     }
-    pub fn insert_first(&mut self, l_addr: usize, name: &str, l_ld: usize) {
+    pub fn insert_first(&mut self, l_addr: usize, name: &str, l_ld: usize) -> *mut LinkMap {
         if self.r_map.is_null() {
             self.r_map = LinkMap::new_with_args(l_addr, name, l_ld);
         } else {
@@ -60,6 +63,28 @@ impl RTLDDebug {
             self.r_map = LinkMap::new_with_args(l_addr, name, l_ld);
             unsafe { (*self.r_map).link(&mut *tmp) };
         }
+        self.r_map
+    }
+
+    /// Unlinks `map` from the chain of loaded objects and frees it.
+    pub fn remove(&mut self, map: *mut LinkMap) {
+        unsafe {
+            let prev = (*map).l_prev;
+            let next = (*map).l_next;
+            if prev.is_null() {
+                if self.r_map == map {
+                    self.r_map = next;
+                }
+            } else {
+                (*prev).l_next = next;
+            }
+            if !next.is_null() {
+                (*next).l_prev = prev;
+            }
+
+            let map = Box::from_raw(map);
+            drop(CString::from_raw(map.l_name.cast_mut()));
+        }
     }
 }
 
@@ -72,21 +97,6 @@ unsafe impl Send for RTLDDebug {}
 /// Separate instances must not contain pointers to the same LinkMap instance.
 unsafe impl Sync for RTLDDebug {}
 
-#[repr(C)]
-struct LinkMap {
-    /* These members are part of the protocol with the debugger.
-    This is the same format used in SVR4.  */
-    /// Difference between the address in the ELF
-    /// file and the addresses in memory.
-    l_addr: usize,
-    /// Absolute file name object was found in.
-    l_name: *const c_char,
-    /// Dynamic section of the shared object.
-    l_ld: usize,
-    l_next: *mut LinkMap,
-    l_prev: *mut LinkMap,
-}
-
 impl LinkMap {
     fn new() -> *mut Self {
         let map = Box::new(LinkMap {
@@ -102,7 +112,7 @@ impl LinkMap {
         map.l_prev = ptr::from_mut::<LinkMap>(self);
         self.l_next = ptr::from_mut::<LinkMap>(map);
     }
-    fn new_with_args(l_addr: usize, name: &str, l_ld: usize) -> *mut Self {
+    pub(super) fn new_with_args(l_addr: usize, name: &str, l_ld: usize) -> *mut Self {
         let map = LinkMap::new();
         unsafe {
             (*map).l_addr = l_addr;
@@ -113,7 +123,7 @@ impl LinkMap {
         map
     }
 
-    fn add_object(&mut self, l_addr: usize, name: &str, l_ld: usize) {
+    fn add_object(&mut self, l_addr: usize, name: &str, l_ld: usize) -> *mut LinkMap {
         let node = LinkMap::new_with_args(l_addr, name, l_ld);
         let mut last = self;
         while !last.l_next.is_null() {
@@ -123,6 +133,7 @@ impl LinkMap {
             (*node).l_prev = last;
             last.l_next = node;
         }
+        node
     }
 }
 
@@ -5,17 +5,20 @@
 use object::{
     NativeEndian, Object, StringTable, SymbolIndex, U32, elf,
     read::elf::{
-        Dyn as _, GnuHashTable, HashTable as SysVHashTable, ProgramHeader as _, Rel as _,
-        Rela as _, Sym as _, Version, VersionTable,
+        Dyn as _, FileHeader as _, GnuHashTable, HashTable as SysVHashTable, ProgramHeader as _,
+        Rel as _, Rela as _, Sym as _,
     },
 };
 
 #[cfg(any(target_arch = "aarch64", target_arch = "x86_64"))]
 use super::tcb::Tcb;
 use super::{
-    debug::{_r_debug, RTLDDebug},
-    linker::{__plt_resolve_trampoline, GLOBAL_SCOPE, Resolve, Scope, Symbol},
+    audit,
+    debug::{_r_debug, LinkMap, RTLDDebug},
+    ifunc,
+    linker::{__plt_resolve_trampoline, DebugFlags, GLOBAL_SCOPE, Resolve, Scope, Symbol},
     tcb::Master,
+    trace::{self, debug_println},
 };
 use crate::{
     header::{dl_tls::__tls_get_addr, sys_mman},
@@ -34,7 +37,7 @@ use core::{
     mem::size_of,
     ptr::{self, NonNull},
     slice,
-    sync::atomic::{AtomicBool, Ordering},
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
 };
 
 pub const CHAR_BITS: usize = c_char::BITS as usize;
@@ -84,43 +87,81 @@ enum HashTable<'a> {
 }
 
 impl<'a> HashTable<'a> {
-    /// Use the hash table to find the symbol table entry with the given name, hash, and version.
-    #[inline]
+    /// Use the hash table to find the symbol table entry with the given name
+    /// for which `accept` returns true.
     pub fn find(
         &self,
         name: &str,
-        version: Option<&Version<'_>>,
         symbols: &'a [Sym],
         strings: StringTable<'a>,
-        versions: &VersionTable<'a, FileHeader>,
+        accept: impl Fn(SymbolIndex, &Sym) -> bool,
     ) -> Option<(SymbolIndex, &'a Sym)> {
         let name = name.as_bytes();
+        let check = |index: usize| {
+            let sym = symbols.get(index)?;
+            (sym.name(NativeEndian, strings)
+                .is_ok_and(|sym_name| sym_name == name)
+                && accept(SymbolIndex(index), sym))
+            .then_some((SymbolIndex(index), sym))
+        };
 
         match self {
             Self::Gnu(hash_table) => {
                 let hash = elf::gnu_hash(name);
-                hash_table.find(
-                    NativeEndian,
-                    name,
-                    hash,
-                    version,
-                    symbols,
-                    strings,
-                    versions,
-                )
+
+                // Check the bloom filter first, it rejects most symbols that
+                // are not present.
+                let word_size = size_of::<usize>();
+                let word_bits = usize::BITS;
+                let bloom_count = hash_table.bloom_filters.len() / word_size;
+                let offset = ((hash / word_bits) as usize).checked_rem(bloom_count)? * word_size;
+                let word = usize::from_ne_bytes(
+                    hash_table.bloom_filters[offset..offset + word_size]
+                        .try_into()
+                        .unwrap(),
+                );
+                let mask = (1 << (hash % word_bits))
+                    | (1 << ((hash >> hash_table.bloom_shift) % word_bits));
+                if word & mask != mask {
+                    return None;
+                }
+
+                let bucket = (hash as usize).checked_rem(hash_table.buckets.len())?;
+                let bucket = hash_table.buckets[bucket].get(NativeEndian);
+                if bucket < hash_table.symbol_base {
+                    return None;
+                }
+
+                // Walk the chain, the last entry has its lowest bit set.
+                let mut index = bucket as usize;
+                loop {
+                    let value = hash_table
+                        .values
+                        .get(index - hash_table.symbol_base as usize)?
+                        .get(NativeEndian);
+                    if value | 1 == hash | 1
+                        && let Some(found) = check(index)
+                    {
+                        return Some(found);
+                    }
+                    if value & 1 != 0 {
+                        return None;
+                    }
+                    index += 1;
+                }
             }
 
             Self::Sysv(hash_table) => {
                 let hash = elf::hash(name);
-                hash_table.find(
-                    NativeEndian,
-                    name,
-                    hash,
-                    version,
-                    symbols,
-                    strings,
-                    versions,
-                )
+                let bucket = (hash as usize).checked_rem(hash_table.buckets.len())?;
+                let mut index = hash_table.buckets[bucket].get(NativeEndian) as usize;
+                while index != 0 {
+                    if let Some(found) = check(index) {
+                        return Some(found);
+                    }
+                    index = hash_table.chains.get(index)?.get(NativeEndian) as usize;
+                }
+                None
             }
         }
     }
@@ -136,9 +177,167 @@ impl<'a> HashTable<'a> {
     }
 }
 
+/// A symbol version, as named by a `DT_VERDEF` or `DT_VERNEED` entry.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SymbolVersion<'a> {
+    pub name: &'a str,
+    pub hash: u32,
+}
+
+impl<'a> SymbolVersion<'a> {
+    pub fn new(name: &'a str) -> Self {
+        Self {
+            name,
+            hash: elf::hash(name.as_bytes()),
+        }
+    }
+}
+
+/// Symbol versioning information of a DSO.
+///
+/// See <https://refspecs.linuxfoundation.org/LSB_5.0.0/LSB-Core-generic/LSB-Core-generic/symversion.html>.
+#[derive(Default)]
+pub(super) struct Versions<'data> {
+    /// `DT_VERSYM`: the version index of each dynamic symbol.
+    versym: &'data [elf::Versym<NativeEndian>],
+    /// Versions defined (`DT_VERDEF`) or needed (`DT_VERNEED`), indexed by
+    /// their version index. The base version is left out, as it names the
+    /// object rather than a symbol version.
+    versions: Vec<Option<SymbolVersion<'data>>>,
+    /// `DT_VERDEF`: the versions defined by the object.
+    defined: Vec<SymbolVersion<'data>>,
+    /// `DT_VERNEED`: the versions needed from each dependency, along with
+    /// whether the requirement is weak.
+    needed: Vec<(&'data str, Vec<(SymbolVersion<'data>, bool)>)>,
+}
+
+impl<'data> Versions<'data> {
+    unsafe fn parse(
+        versym: &'data [elf::Versym<NativeEndian>],
+        verdef: Option<(*const u8, usize)>,
+        verneed: Option<(*const u8, usize)>,
+        strings: StringTable<'data>,
+    ) -> Result<Self, &'static str> {
+        let get_str = |offset: U32<NativeEndian>| {
+            let name = strings
+                .get(offset.get(NativeEndian))
+                .map_err(|_| "invalid symbol version name")?;
+            core::str::from_utf8(name).map_err(|_| "non utf-8 symbol version name")
+        };
+
+        let mut versions = Vec::new();
+        let mut set = |index: u16, version| {
+            let index = usize::from(index & elf::VERSYM_VERSION);
+            if versions.len() <= index {
+                versions.resize(index + 1, None);
+            }
+            versions[index] = Some(version);
+        };
+
+        let mut defined = Vec::new();
+        if let Some((mut ptr, count)) = verdef {
+            for _ in 0..count {
+                let def = unsafe { &*ptr.cast::<elf::Verdef<NativeEndian>>() };
+                if def.vd_flags.get(NativeEndian) & elf::VER_FLG_BASE == 0 {
+                    // The first auxiliary entry names the version, the rest
+                    // name its parents.
+                    let aux = unsafe {
+                        &*ptr
+                            .byte_add(def.vd_aux.get(NativeEndian) as usize)
+                            .cast::<elf::Verdaux<NativeEndian>>()
+                    };
+                    let version = SymbolVersion {
+                        name: get_str(aux.vda_name)?,
+                        hash: def.vd_hash.get(NativeEndian),
+                    };
+                    set(def.vd_ndx.get(NativeEndian), version);
+                    defined.push(version);
+                }
+                ptr = unsafe { ptr.byte_add(def.vd_next.get(NativeEndian) as usize) };
+            }
+        }
+
+        let mut needed = Vec::new();
+        if let Some((mut ptr, count)) = verneed {
+            for _ in 0..count {
+                let need = unsafe { &*ptr.cast::<elf::Verneed<NativeEndian>>() };
+                let mut aux_ptr = unsafe { ptr.byte_add(need.vn_aux.get(NativeEndian) as usize) };
+                let mut file_versions = Vec::new();
+                for _ in 0..need.vn_cnt.get(NativeEndian) {
+                    let aux = unsafe { &*aux_ptr.cast::<elf::Vernaux<NativeEndian>>() };
+                    let version = SymbolVersion {
+                        name: get_str(aux.vna_name)?,
+                        hash: aux.vna_hash.get(NativeEndian),
+                    };
+                    set(aux.vna_other.get(NativeEndian), version);
+                    file_versions.push((
+                        version,
+                        aux.vna_flags.get(NativeEndian) & elf::VER_FLG_WEAK != 0,
+                    ));
+                    aux_ptr = unsafe { aux_ptr.byte_add(aux.vna_next.get(NativeEndian) as usize) };
+                }
+                needed.push((get_str(need.vn_file)?, file_versions));
+                ptr = unsafe { ptr.byte_add(need.vn_next.get(NativeEndian) as usize) };
+            }
+        }
+
+        Ok(Self {
+            versym,
+            versions,
+            defined,
+            needed,
+        })
+    }
+
+    /// Returns the raw `DT_VERSYM` entry of the symbol at `index`.
+    fn versym(&self, index: SymbolIndex) -> Option<u16> {
+        self.versym
+            .get(index.0)
+            .map(|versym| versym.0.get(NativeEndian))
+    }
+
+    fn version(&self, versym: u16) -> Option<SymbolVersion<'data>> {
+        self.versions
+            .get(usize::from(versym & elf::VERSYM_VERSION))
+            .copied()
+            .flatten()
+    }
+
+    /// Returns the version that a reference to the symbol at `index` requires.
+    pub fn required(&self, index: SymbolIndex) -> Option<SymbolVersion<'data>> {
+        self.version(self.versym(index)?)
+    }
+
+    /// Returns whether the definition of the symbol at `index` satisfies a
+    /// lookup for `version`.
+    fn matches(&self, index: SymbolIndex, version: Option<&SymbolVersion<'_>>) -> bool {
+        // Objects without version information satisfy every lookup.
+        let Some(versym) = self.versym(index) else {
+            return true;
+        };
+
+        match (self.version(versym), version) {
+            (Some(defined), Some(version)) => defined == *version,
+            (None, None) => true,
+            // Unversioned lookups only see the default (`@@`) version of a
+            // symbol, and unversioned definitions satisfy versioned lookups,
+            // unless they are hidden.
+            _ => versym & elf::VERSYM_HIDDEN == 0,
+        }
+    }
+
+    /// Returns whether the object defines `version`, or `None` if it does not
+    /// define any versions.
+    fn defines(&self, version: &SymbolVersion<'_>) -> Option<bool> {
+        (!self.defined.is_empty()).then(|| self.defined.iter().any(|defined| defined == version))
+    }
+}
+
 type InitFn = unsafe extern "C" fn();
 
 pub(super) struct Dynamic<'data> {
+    /// `DT_RPATH` entry, only used if there is no `DT_RUNPATH` entry.
+    rpath: Option<String>,
     runpath: Option<String>,
     got: Option<NonNull<usize>>,
     needed: Vec<&'data str>,
@@ -152,8 +351,11 @@ pub(super) struct Dynamic<'data> {
     relr: &'data [Relr],
     rel: &'data [Rel],
     symbols: &'data [Sym],
+    pub(super) versions: Versions<'data>,
     explicit_addend: bool,
     pltrelsz: usize,
+    /// `DT_FLAGS_1` entry.
+    flags_1: u32,
 }
 
 impl<'data> Dynamic<'data> {
@@ -366,6 +568,11 @@ pub struct DSO {
     pub mmap: Option<MemoryMapHandle>,
     pub tls_module_id: usize,
     pub tls_offset: usize,
+    /// Link map entry of this object. The entry of ld.so itself is not part
+    /// of the list shared with debuggers.
+    pub link_map: *mut LinkMap,
+    /// Program headers of this object, as mapped in memory.
+    pub phdrs: &'static [ProgramHeader],
 
     pub(super) dynamic: Dynamic<'static>,
 
@@ -373,9 +580,29 @@ pub struct DSO {
     /// Position Independent Executable.
     pub pie: bool,
 
-    /// Whether this DSO *and* its dependencies have been successfully loaded.
+    /// Whether this DSO *and* its dependencies have been successfully loaded,
+    /// and the DSO has not been finalized yet.
     is_ready: AtomicBool,
 
+    /// Whether this DSO must never be unloaded, from `DF_1_NODELETE` or
+    /// `RTLD_NODELETE`.
+    nodelete: AtomicBool,
+
+    /// Number of `dlopen` handles to this DSO that have not been closed.
+    handles: AtomicUsize,
+
+    /// Objects loaded with `dlopen` that references in this DSO have been
+    /// bound to, besides its dependencies. They are kept loaded as long as
+    /// this DSO is.
+    reldeps: spin::Mutex<Vec<usize>>,
+
+    /// Whether this DSO was loaded with `RTLD_DEEPBIND`, in which case its own
+    /// scope is searched before the global scope.
+    deepbind: AtomicBool,
+
+    /// Whether the PLT entries of this DSO may still be bound lazily.
+    lazy: AtomicBool,
+
     /// Is this DSO for ld.so?
     is_me: bool,
 }
@@ -385,7 +612,7 @@ impl DSO {
     pub fn from_raw(
         base: *const u8,
         dyns: &[Dyn],
-        phdrs: &[ProgramHeader],
+        phdrs: &'static [ProgramHeader],
         id: usize,
         tls_module_id: usize,
         tls_offset: usize,
@@ -397,11 +624,18 @@ impl DSO {
         let tcb_master = phdrs
             .iter()
             .find(|ph| ph.p_type(NativeEndian) == elf::PT_TLS)
-            .map(|ph| Master {
-                ptr: unsafe { base.byte_add(ph.p_vaddr(NativeEndian) as usize) },
-                image_size: ph.p_filesz(NativeEndian) as usize,
-                segment_size: ph.p_memsz(NativeEndian) as usize,
-                offset: tls_offset + ph.p_memsz(NativeEndian) as usize,
+            .map(|ph| {
+                let align = (ph.p_align(NativeEndian) as usize).max(1);
+                Master {
+                    ptr: unsafe { base.byte_add(ph.p_vaddr(NativeEndian) as usize) },
+                    image_size: ph.p_filesz(NativeEndian) as usize,
+                    segment_size: ph.p_memsz(NativeEndian) as usize,
+                    offset: (tls_offset + ph.p_memsz(NativeEndian) as usize)
+                        .next_multiple_of(align),
+                    align,
+                    dynamic: false,
+                    unloaded: AtomicBool::new(false),
+                }
             })
             .unwrap();
 
@@ -415,10 +649,21 @@ impl DSO {
                 entry_point: 0,
                 base,
                 mmap: None,
+                link_map: LinkMap::new_with_args(
+                    base as usize,
+                    "libc.so.6",
+                    dyns.as_ptr() as usize,
+                ),
+                phdrs,
                 dynamic,
                 scope: spin::Once::new(),
                 pie: true,
                 is_ready: AtomicBool::new(false),
+                nodelete: AtomicBool::new(true),
+                handles: AtomicUsize::new(0),
+                reldeps: spin::Mutex::new(Vec::new()),
+                deepbind: AtomicBool::new(false),
+                lazy: AtomicBool::new(false),
                 is_me: true,
             },
             tcb_master,
@@ -435,8 +680,13 @@ impl DSO {
         tls_offset: usize,
     ) -> Result<(DSO, Option<Master>, Vec<ProgramHeader>), String> {
         let elf = ElfFile::parse(data).map_err(|err| err.to_string())?;
-        let (mmap, tcb_master, dynamic) =
+        let (mmap, mut tcb_master, dynamic, link_map) =
             DSO::mmap_and_copy(path, &elf, data, base_addr, tls_offset)?;
+        if let Some(master) = tcb_master.as_mut() {
+            // There is no room left in the static TLS block of running
+            // threads for objects loaded with `dlopen`.
+            master.dynamic = dlopened;
+        }
 
         let name = match dynamic.soname {
             Some(soname) => soname.to_string(),
@@ -451,7 +701,18 @@ impl DSO {
         } else {
             elf.entry() as usize
         };
+        let load_bias = if is_pie_enabled(&elf) {
+            mmap.as_ptr() as usize
+        } else {
+            0
+        };
+        let phdrs = loaded_phdrs(
+            load_bias,
+            elf.elf_program_headers(),
+            elf.elf_header().e_phoff(NativeEndian) as usize,
+        );
 
+        let nodelete = dynamic.flags_1 & elf::DF_1_NODELETE != 0;
         let dso = DSO {
             name,
             id,
@@ -460,6 +721,8 @@ impl DSO {
 
             base: mmap.as_ptr(),
             mmap: Some(mmap),
+            link_map,
+            phdrs,
 
             tls_module_id: if tcb_master.is_some() {
                 tls_module_id
@@ -472,6 +735,11 @@ impl DSO {
             dynamic,
             scope: spin::Once::new(),
             is_ready: AtomicBool::new(false),
+            nodelete: AtomicBool::new(nodelete),
+            handles: AtomicUsize::new(0),
+            reldeps: spin::Mutex::new(Vec::new()),
+            deepbind: AtomicBool::new(false),
+            lazy: AtomicBool::new(false),
             is_me: false,
         };
 
@@ -488,6 +756,84 @@ impl DSO {
         self.scope.get().expect("scope not initialized")
     }
 
+    /// Returns the scopes to search for the symbols referenced by this object,
+    /// in order.
+    #[inline]
+    pub fn lookup_scopes<'a>(&'a self, global_scope: &'a Scope) -> [&'a Scope; 2] {
+        if self.deepbind.load(Ordering::Relaxed) {
+            [self.scope(), global_scope]
+        } else {
+            [global_scope, self.scope()]
+        }
+    }
+
+    #[inline]
+    pub fn is_nodelete(&self) -> bool {
+        self.nodelete.load(Ordering::Relaxed)
+    }
+
+    /// Prevents this object from ever being unloaded.
+    #[inline]
+    pub fn set_nodelete(&self) {
+        self.nodelete.store(true, Ordering::Relaxed);
+    }
+
+    /// Records a new `dlopen` handle to this object.
+    #[inline]
+    pub fn add_handle(&self) {
+        self.handles.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records that a `dlopen` handle to this object was closed. Returns
+    /// `false` if there was no open handle.
+    #[inline]
+    pub fn remove_handle(&self) -> bool {
+        self.handles
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |count| {
+                count.checked_sub(1)
+            })
+            .is_ok()
+    }
+
+    /// Returns whether this object is referenced by open `dlopen` handles.
+    #[inline]
+    pub fn has_handles(&self) -> bool {
+        self.handles.load(Ordering::Relaxed) != 0
+    }
+
+    /// Returns the IDs of the objects loaded with `dlopen` that references in
+    /// this object have been bound to.
+    pub fn reldeps(&self) -> Vec<usize> {
+        self.reldeps.lock().clone()
+    }
+
+    /// Makes this object search its own scope before the global scope.
+    #[inline]
+    pub fn set_deepbind(&self) {
+        self.deepbind.store(true, Ordering::Relaxed);
+    }
+
+    /// Binds the PLT entries of this DSO that are still bound lazily, as when
+    /// it is opened again with `RTLD_NOW`.
+    pub fn bind_now(&self) {
+        if !self.lazy.swap(false, Ordering::AcqRel) {
+            return;
+        }
+        let global_scope = GLOBAL_SCOPE.read();
+
+        if trace::enabled(DebugFlags::RELOC) {
+            debug_println!("[ld.so]: relocation processing: {} (now)", self.name);
+        }
+
+        for reloc in self.plt_relocations() {
+            if reloc.kind == RelocationKind::PLT {
+                trace::RELOCATIONS.fetch_add(1, Ordering::Relaxed);
+                let ptr = self.relocation_ptr(reloc);
+                self.bind_plt(&global_scope, reloc, ptr);
+            }
+        }
+    }
+
     /// Global Offset Table
     #[inline]
     pub fn got(&self) -> Option<NonNull<usize>> {
@@ -499,28 +845,107 @@ impl DSO {
         self.dynamic.runpath.as_ref()
     }
 
+    #[inline]
+    pub fn rpath(&self) -> Option<&String> {
+        self.dynamic.rpath.as_ref()
+    }
+
     #[inline]
     pub fn dependencies(&self) -> &[&str] {
         &self.dynamic.needed
     }
 
-    pub fn get_sym<'a>(&self, name: &'a str) -> Option<(Symbol<'a>, SymbolBinding)> {
-        let (_, sym) = self.dynamic.hash_table.find(
+    /// Returns the symbol versions required from each dependency, along with
+    /// whether the requirement is weak.
+    #[inline]
+    pub fn version_requirements(&self) -> &[(&str, Vec<(SymbolVersion<'_>, bool)>)] {
+        &self.dynamic.versions.needed
+    }
+
+    /// Returns whether this object defines `version`, or `None` if it does not
+    /// define any versions.
+    #[inline]
+    pub fn defines_version(&self, version: &SymbolVersion<'_>) -> Option<bool> {
+        self.dynamic.versions.defines(version)
+    }
+
+    /// Difference between the addresses in memory and the virtual addresses in
+    /// the ELF file.
+    #[inline]
+    pub fn load_bias(&self) -> usize {
+        if self.pie { self.base as usize } else { 0 }
+    }
+
+    /// Returns whether `addr` lies within one of the loaded segments of this
+    /// object.
+    pub fn contains(&self, addr: usize) -> bool {
+        let load_bias = self.load_bias();
+        self.phdrs
+            .iter()
+            .filter(|ph| ph.p_type(NativeEndian) == elf::PT_LOAD)
+            .any(|ph| {
+                let start = load_bias + ph.p_vaddr(NativeEndian) as usize;
+                (start..start + ph.p_memsz(NativeEndian) as usize).contains(&addr)
+            })
+    }
+
+    /// Finds the defined symbol that is closest to, and not above, `addr`.
+    ///
+    /// Returns the null-terminated name of the symbol along with its address.
+    pub fn symbol_at(&self, addr: usize) -> Option<(&'static [u8], usize)> {
+        let load_bias = self.load_bias();
+        let mut nearest: Option<(&Sym, usize)> = None;
+
+        for sym in self.dynamic.symbols.iter().skip(1) {
+            if sym.st_shndx(NativeEndian) == elf::SHN_UNDEF
+                || !matches!(
+                    sym.st_type(),
+                    elf::STT_NOTYPE | elf::STT_OBJECT | elf::STT_FUNC | elf::STT_GNU_IFUNC
+                )
+            {
+                continue;
+            }
+
+            let value = load_bias + sym.st_value(NativeEndian) as usize;
+            let size = sym.st_size(NativeEndian) as usize;
+            // Symbols with a known size must span `addr`.
+            if value > addr || (size != 0 && addr >= value + size) {
+                continue;
+            }
+            if nearest.is_none_or(|(_, nearest)| value > nearest) {
+                nearest = Some((sym, value));
+            }
+        }
+
+        let (sym, value) = nearest?;
+        let name = sym.name(NativeEndian, self.dynamic.dynstrtab).ok()?;
+        // Names in the string table are null-terminated.
+        let name = unsafe { slice::from_raw_parts(name.as_ptr(), name.len() + 1) };
+        Some((name, value))
+    }
+
+    /// Looks up the definition of `name` with the given `version`. Unversioned
+    /// lookups resolve to the default version of the symbol.
+    pub fn get_sym<'a>(
+        &self,
+        name: &'a str,
+        version: Option<&SymbolVersion<'_>>,
+    ) -> Option<(Symbol<'a>, SymbolBinding)> {
+        let (index, sym) = self.dynamic.hash_table.find(
             name,
-            None,
             self.dynamic.symbols,
             self.dynamic.dynstrtab,
-            &VersionTable::default(),
+            |index, sym| {
+                sym.st_shndx(NativeEndian) != elf::SHN_UNDEF
+                    && self.dynamic.versions.matches(index, version)
+            },
         )?;
 
-        if sym.st_shndx(NativeEndian) == elf::SHN_UNDEF {
-            return None;
-        }
-
         Some((
             Symbol {
                 name,
-                base: if self.pie { self.base as usize } else { 0 },
+                index: index.0,
+                base: self.load_bias(),
                 value: sym.st_value(NativeEndian) as usize,
                 size: sym.st_size(NativeEndian) as usize,
                 sym_type: sym.st_type(),
@@ -540,7 +965,13 @@ impl DSO {
         }
     }
 
+    /// Runs the finalizers of this object, unless it is not ready or they
+    /// already ran.
     pub unsafe fn run_fini(&self) {
+        if !self.is_ready.swap(false, Ordering::SeqCst) {
+            return;
+        }
+
         for f in self.dynamic.fini_array.iter().rev() {
             unsafe { f() }
         }
@@ -552,7 +983,15 @@ impl DSO {
         data: &'a [u8],
         base_addr: Option<usize>,
         tls_offset: usize,
-    ) -> Result<(MemoryMapHandle, Option<Master>, Dynamic<'static>), String> {
+    ) -> Result<
+        (
+            MemoryMapHandle,
+            Option<Master>,
+            Dynamic<'static>,
+            *mut LinkMap,
+        ),
+        String,
+    > {
         let endian = elf.endian();
         log::trace!("# {}", path);
         // data for struct LinkMap
@@ -590,6 +1029,7 @@ impl DSO {
         };
         log::trace!("  bounds {:#x}, {:#x}", bounds.0, bounds.1);
         // Allocate memory
+        let link_map;
         let mmap = unsafe {
             if let Some(addr) = base_addr {
                 let size = if is_pie_enabled(elf) {
@@ -597,9 +1037,10 @@ impl DSO {
                 } else {
                     bounds.1 - bounds.0
                 };
-                _r_debug
-                    .lock()
-                    .insert_first(addr + bounds.0, path, addr + l_ld as usize);
+                link_map =
+                    _r_debug
+                        .lock()
+                        .insert_first(addr + bounds.0, path, addr + l_ld as usize);
                 slice::from_raw_parts_mut((addr + bounds.0) as *mut u8, size)
             } else {
                 let (start, end) = bounds;
@@ -627,7 +1068,7 @@ impl DSO {
                     );
                 }
                 log::trace!("    = {:p}", ptr);
-                _r_debug
+                link_map = _r_debug
                     .lock()
                     .insert(ptr as usize, path, ptr as usize + l_ld as usize);
                 slice::from_raw_parts_mut(ptr.cast::<u8>(), size)
@@ -690,11 +1131,17 @@ impl DSO {
                             ph.p_vaddr(endian) as *const u8
                         }
                     };
+                    // The TLS block is placed so that it is aligned, as the
+                    // thread pointer is page-aligned.
+                    let align = (ph.p_align(endian) as usize).max(1);
                     tcb_master = Some(Master {
                         ptr,
                         image_size: ph.p_filesz(endian) as usize,
                         segment_size: ph.p_memsz(endian) as usize,
-                        offset: tls_offset + ph.p_memsz(endian) as usize,
+                        offset: (tls_offset + ph.p_memsz(endian) as usize).next_multiple_of(align),
+                        align,
+                        dynamic: false,
+                        unloaded: AtomicBool::new(false),
                     });
                     log::trace!("  tcb master {:x?}", tcb_master);
                 }
@@ -713,8 +1160,7 @@ impl DSO {
         let dynamic = dynamic.ok_or_else(|| "Unable to find PT_DYNAMIC section".to_string())?;
 
         let (parsed_dynamic, debug) =
-            Self::parse_dynamic(path, mmap.as_ptr(), is_pie_enabled(elf), dynamic.1)
-                .map_err(|e| e.to_string())?;
+            Self::parse_dynamic(path, mmap.as_ptr(), is_pie_enabled(elf), dynamic.1)?;
 
         if let Some(i) = debug {
             // FIXME: cleanup
@@ -744,6 +1190,7 @@ impl DSO {
             },
             tcb_master,
             parsed_dynamic,
+            link_map,
         ))
     }
 
@@ -752,7 +1199,8 @@ impl DSO {
         mmap: *const u8,
         is_pie: bool,
         entries: &[Dyn],
-    ) -> object::Result<(Dynamic<'a>, Option<usize>)> {
+    ) -> Result<(Dynamic<'a>, Option<usize>), String> {
+        let mut rpath = None;
         let mut runpath = None;
         let mut got = None;
         let mut needed = vec![];
@@ -761,8 +1209,12 @@ impl DSO {
         let mut hash_table = None;
         let mut explicit_addend = None;
         let mut pltrelsz = None;
+        let mut flags_1 = 0;
         let mut debug = None;
         let mut symtab_ptr = None;
+        let mut versym_ptr = None;
+        let (mut verdef_ptr, mut verdef_num) = (None, None);
+        let (mut verneed_ptr, mut verneed_num) = (None, None);
         let (mut rel_ptr, mut rel_len) = (None, None);
         let (mut relr_ptr, mut relr_len) = (None, None);
         let (mut strtab_offset, mut strtab_size) = (None, None);
@@ -856,7 +1308,8 @@ impl DSO {
 
                 elf::DT_NEEDED => needed.push(entry),
                 elf::DT_JMPREL => jmprel = Some(ptr as usize),
-                elf::DT_RPATH | elf::DT_RUNPATH => runpath = Some(entry),
+                elf::DT_RPATH => rpath = Some(entry),
+                elf::DT_RUNPATH => runpath = Some(entry),
                 elf::DT_STRTAB => strtab_offset = Some(relative_idx),
                 elf::DT_STRSZ => strtab_size = Some(val),
                 elf::DT_SONAME => soname = Some(entry),
@@ -890,6 +1343,7 @@ impl DSO {
                     }
                 }
                 elf::DT_PLTRELSZ => pltrelsz = Some(val as usize),
+                elf::DT_FLAGS_1 => flags_1 = val as u32,
 
                 elf::DT_INIT_ARRAY if val != 0 => init_array_ptr = Some(ptr.cast::<InitFn>()),
                 elf::DT_INIT_ARRAYSZ => init_array_len = Some(val as usize / size_of::<InitFn>()),
@@ -902,6 +1356,12 @@ impl DSO {
                     assert_eq!(val as usize, size_of::<Sym>());
                 }
 
+                elf::DT_VERSYM => versym_ptr = Some(ptr.cast::<elf::Versym<NativeEndian>>()),
+                elf::DT_VERDEF => verdef_ptr = Some(ptr),
+                elf::DT_VERDEFNUM => verdef_num = Some(val as usize),
+                elf::DT_VERNEED => verneed_ptr = Some(ptr),
+                elf::DT_VERNEEDNUM => verneed_num = Some(val as usize),
+
                 _ => {}
             }
         }
@@ -928,6 +1388,7 @@ impl DSO {
             entry
                 .string(NativeEndian, dynstrtab)
                 .map(|bytes| core::str::from_utf8(bytes).expect("non utf-8 elf symbol name"))
+                .map_err(|err| err.to_string())
         };
 
         unsafe fn get_array<'a, T>(ptr: Option<*const T>, len: Option<usize>) -> &'a [T] {
@@ -943,14 +1404,22 @@ impl DSO {
         let needed = needed
             .into_iter()
             .map(get_str)
-            .collect::<object::Result<Vec<_>>>()?;
+            .collect::<Result<Vec<_>, _>>()?;
 
         let base = dirname(path);
 
         let runpath = runpath
             .map(get_str)
             .transpose()?
-            .map(|value| value.replace("$ORIGIN", &base));
+            .map(|value| expand_tokens(value, &base));
+        // DT_RPATH is superseded by DT_RUNPATH.
+        let rpath = match runpath {
+            Some(_) => None,
+            None => rpath
+                .map(get_str)
+                .transpose()?
+                .map(|value| expand_tokens(value, &base)),
+        };
 
         let soname = soname.map(get_str).transpose()?;
 
@@ -962,9 +1431,21 @@ impl DSO {
         let relr = unsafe { get_array(relr_ptr, relr_len) };
         let rel = unsafe { get_array(rel_ptr, rel_len) };
 
+        let symbol_count = hash_table.symbol_table_length();
+        let versions = unsafe {
+            Versions::parse(
+                get_array(versym_ptr, versym_ptr.map(|_| symbol_count)),
+                verdef_ptr.zip(verdef_num),
+                verneed_ptr.zip(verneed_num),
+                dynstrtab,
+            )
+        }?;
+
         Ok((
             Dynamic {
-                symbols: unsafe { get_array(symtab_ptr, Some(hash_table.symbol_table_length())) },
+                symbols: unsafe { get_array(symtab_ptr, Some(symbol_count)) },
+                versions,
+                rpath,
                 runpath,
                 got,
                 needed,
@@ -979,11 +1460,47 @@ impl DSO {
                 relr,
                 explicit_addend: explicit_addend.unwrap_or_default(),
                 pltrelsz: pltrelsz.unwrap_or_default(),
+                flags_1,
             },
             debug,
         ))
     }
 
+    /// Records the binding of a reference to `name` in this object to its
+    /// definition in `def`, which must then stay loaded as long as this object
+    /// is.
+    pub(super) fn record_binding(
+        &self,
+        def: &DSO,
+        name: &str,
+        version: Option<&SymbolVersion<'_>>,
+    ) {
+        if def.dlopened && def.id != self.id {
+            let mut reldeps = self.reldeps.lock();
+            if !reldeps.contains(&def.id) {
+                reldeps.push(def.id);
+            }
+        }
+
+        if trace::enabled(DebugFlags::BINDINGS) {
+            match version {
+                Some(version) => debug_println!(
+                    "[ld.so]: binding file {} to {}: symbol '{}' [{}]",
+                    self.name,
+                    def.name,
+                    name,
+                    version.name
+                ),
+                None => debug_println!(
+                    "[ld.so]: binding file {} to {}: symbol '{}'",
+                    self.name,
+                    def.name,
+                    name
+                ),
+            }
+        }
+    }
+
     /// `TLSDESC` relocation being an extension to the original TLS ABI spec can
     /// be present in either `.rela.plt` (handled in [`Self::static_relocate`])
     /// or `.rela.dyn` (handled in [`Self::lazy_relocate`]) due to the lack of a
@@ -999,7 +1516,14 @@ impl DSO {
         // too messy to cfg out
         let (sym, tls_module_id, tls_offset) = if reloc.sym != SymbolIndex(0) {
             let sym_name = self.dynamic.symbol_name(reloc.sym).unwrap();
-            let (sym, _, obj) = resolve_sym(sym_name, &[global_scope, self.scope()]).unwrap();
+            let version = self.dynamic.versions.required(reloc.sym);
+            let (sym, _, obj) = resolve_sym(
+                sym_name,
+                version.as_ref(),
+                &self.lookup_scopes(global_scope),
+            )
+            .unwrap();
+            self.record_binding(&obj, sym_name, version.as_ref());
             (sym.value, obj.tls_module_id, obj.tls_offset)
         } else {
             (0, self.tls_module_id, self.tls_offset)
@@ -1036,16 +1560,20 @@ impl DSO {
     fn static_relocate(&self, global_scope: &Scope, reloc: Relocation) -> object::Result<()> {
         let (sym, my_sym) = if reloc.sym != STN_UNDEF {
             let name = self.dynamic.symbol_name(reloc.sym).unwrap();
+            let version = self.dynamic.versions.required(reloc.sym);
 
-            let lookup_scopes = [global_scope, self.scope()];
+            let lookup_scopes = self.lookup_scopes(global_scope);
             let sym = if matches!(reloc.kind, RelocationKind::COPY) {
                 lookup_scopes
                     .iter()
-                    .find_map(|scope| scope._get_sym(name, 1))
+                    .find_map(|scope| scope._get_sym(name, version.as_ref(), 1))
             } else {
-                resolve_sym(name, &lookup_scopes)
+                resolve_sym(name, version.as_ref(), &lookup_scopes)
             }
             .map(|(sym, _, obj)| (sym, obj));
+            if let Some((_, obj)) = sym.as_ref() {
+                self.record_binding(obj, name, version.as_ref());
+            }
 
             (sym, self.dynamic.symbol(reloc.sym))
         } else {
@@ -1054,7 +1582,7 @@ impl DSO {
 
         let (s, tls_obj) = sym
             .as_ref()
-            .map(|(sym, obj)| (sym.as_ptr() as usize, obj.as_ref()))
+            .map(|(sym, obj)| (sym.resolve() as usize, obj.as_ref()))
             // (1) According to the System V gABI (Chapter 4, "Relocation"): if
             // the symbol index (`reloc.sym`) is undefined, the symbol value
             // (`s`) is defined as 0.
@@ -1116,11 +1644,9 @@ impl DSO {
                     set_usize(a.wrapping_sub(tls_obj.tls_offset));
                 }
             }
-            RelocationKind::IRELATIVE => unsafe {
-                let f: unsafe extern "C" fn() -> usize =
-                    core::mem::transmute(self.base as usize + a);
-                set_usize(f());
-            },
+            RelocationKind::IRELATIVE => {
+                set_usize(unsafe { ifunc::resolve(self.base as usize + a) })
+            }
             RelocationKind::COPY => unsafe {
                 let (sym, obj) = sym
                     .as_ref()
@@ -1145,40 +1671,80 @@ impl DSO {
         Ok(())
     }
 
+    /// Returns the relocations of the PLT entries.
+    fn plt_relocations(&self) -> impl Iterator<Item = Relocation> + '_ {
+        let jmprel = self.dynamic.jmprel;
+        let relsz = if self.dynamic.explicit_addend {
+            size_of::<Rela>()
+        } else {
+            size_of::<Rel>()
+        };
+
+        (jmprel..(jmprel + self.dynamic.pltrelsz))
+            .step_by(relsz)
+            .map(|addr| {
+                if self.dynamic.explicit_addend {
+                    unsafe { &*(addr as *const Rela) }.into()
+                } else {
+                    unsafe { &*(addr as *const Rel) }.into()
+                }
+            })
+    }
+
+    /// Returns the address that `reloc` applies to.
+    fn relocation_ptr(&self, reloc: Relocation) -> *mut usize {
+        if self.pie {
+            (self.base as usize + reloc.offset) as *mut usize
+        } else {
+            reloc.offset as *mut usize
+        }
+    }
+
+    /// Binds the PLT entry at `ptr` to the symbol it refers to.
+    fn bind_plt(&self, global_scope: &Scope, reloc: Relocation, ptr: *mut usize) {
+        let name = self.dynamic.symbol_name(reloc.sym).unwrap();
+        let version = self.dynamic.versions.required(reloc.sym);
+
+        let resolved = resolve_sym(name, version.as_ref(), &self.lookup_scopes(global_scope))
+            .map(|(sym, _, obj)| {
+                self.record_binding(&obj, name, version.as_ref());
+                audit::symbind(self, &obj, &sym)
+            })
+            .unwrap_or_else(|| {
+                panic!(
+                    "unresolved symbol: {name} for soname {:?}",
+                    self.dynamic.soname
+                )
+            });
+
+        unsafe {
+            *ptr = resolved + reloc.addend.unwrap_or(0);
+        }
+    }
+
     fn lazy_relocate(&self, global_scope: &Scope, resolve: Resolve) -> object::Result<()> {
         let Some(got) = self.got() else {
             assert_eq!(self.dynamic.jmprel, 0);
             return Ok(());
         };
 
-        let jmprel = self.dynamic.jmprel;
-        let pltrelsz = self.dynamic.pltrelsz;
-
         unsafe {
             got.add(1).write(core::ptr::addr_of!(*self) as usize);
             got.add(2)
                 .write(__plt_resolve_trampoline as *const () as usize);
         }
 
-        let relsz = if self.dynamic.explicit_addend {
-            size_of::<Rela>()
-        } else {
-            size_of::<Rel>()
-        };
-
-        for addr in (jmprel..(jmprel + pltrelsz)).step_by(relsz) {
-            let reloc: Relocation = if self.dynamic.explicit_addend {
-                unsafe { &*(addr as *const Rela) }.into()
-            } else {
-                unsafe { &*(addr as *const Rel) }.into()
-            };
+        self.lazy
+            .store(resolve == Resolve::Lazy && !self.is_me, Ordering::Release);
 
-            let ptr = if self.pie {
-                (self.base as usize + reloc.offset) as *mut usize
-            } else {
-                reloc.offset as *mut usize
-            };
+        let mut irelative = Vec::new();
+        for reloc in self.plt_relocations() {
+            let ptr = self.relocation_ptr(reloc);
 
+            // Lazy PLT slots are counted once resolved, as lazy relocations.
+            if reloc.kind != RelocationKind::PLT || resolve != Resolve::Lazy {
+                trace::RELOCATIONS.fetch_add(1, Ordering::Relaxed);
+            }
             match (reloc.kind, resolve) {
                 (RelocationKind::PLT, Resolve::Lazy) if self.pie => unsafe {
                     *ptr += self.base as usize;
@@ -1189,20 +1755,7 @@ impl DSO {
                 }
 
                 (RelocationKind::PLT, Resolve::Now) => {
-                    let name = self.dynamic.symbol_name(reloc.sym).unwrap();
-
-                    let resolved = resolve_sym(name, &[global_scope, self.scope()])
-                        .map(|(sym, _, _)| sym.as_ptr() as usize)
-                        .unwrap_or_else(|| {
-                            panic!(
-                                "unresolved symbol: {name} for soname {:?}",
-                                self.dynamic.soname
-                            )
-                        });
-
-                    unsafe {
-                        *ptr = resolved + reloc.addend.unwrap_or(0);
-                    }
+                    self.bind_plt(global_scope, reloc, ptr);
                 }
 
                 (RelocationKind::TLSDESC, Resolve::Now) => {
@@ -1212,6 +1765,14 @@ impl DSO {
                     unreachable!("TLSDESC cannot be lazily resolved")
                 }
 
+                // Resolvers of indirect functions local to the object may
+                // call through the PLT, so they are only called once all the
+                // other entries are set up.
+                (RelocationKind::IRELATIVE, _) => {
+                    let a = reloc.addend.unwrap_or_else(|| unsafe { *ptr });
+                    irelative.push((ptr, self.base as usize + a));
+                }
+
                 _ => {
                     unimplemented!(
                         "relocation type {:?} with resolve {:?}",
@@ -1222,12 +1783,28 @@ impl DSO {
             }
         }
 
+        for (ptr, resolver) in irelative {
+            unsafe { *ptr = ifunc::resolve(resolver) };
+        }
+
         Ok(())
     }
 
     pub fn relocate(&self, ph: Option<&[ProgramHeader]>, resolve: Resolve) -> object::Result<()> {
         let global_scope = GLOBAL_SCOPE.read();
 
+        if trace::enabled(DebugFlags::RELOC) {
+            debug_println!(
+                "[ld.so]: relocation processing: {}{}",
+                self.name,
+                if resolve == Resolve::Lazy {
+                    " (lazy)"
+                } else {
+                    ""
+                }
+            );
+        }
+
         if !self.is_me {
             unsafe {
                 apply_relr(self.base, self.dynamic.relr);
@@ -1238,6 +1815,7 @@ impl DSO {
             if reloc.kind == RelocationKind::RELATIVE && self.is_me {
                 continue;
             }
+            trace::RELOCATIONS.fetch_add(1, Ordering::Relaxed);
             self.static_relocate(&global_scope, reloc)?;
         }
 
@@ -1298,13 +1876,42 @@ impl Drop for DSO {
         if self.is_me {
             return;
         }
-        if self.is_ready.load(Ordering::SeqCst) {
-            // `run_fini` should not be called if we are being prematurely
-            // dropped (e.g. failed to satisfy dependencies).
-            unsafe {
-                self.run_fini();
+        // `run_fini` does nothing if we are being prematurely dropped (e.g.
+        // failed to satisfy dependencies), or if the finalizers already ran
+        // when the object was unloaded.
+        unsafe {
+            self.run_fini();
+        }
+    }
+}
+
+/// Locates the program headers `elf_phdrs`, found at offset `phoff` in the
+/// file, in the memory image of the object.
+fn loaded_phdrs(
+    load_bias: usize,
+    elf_phdrs: &[ProgramHeader],
+    phoff: usize,
+) -> &'static [ProgramHeader] {
+    let size = elf_phdrs.len() * size_of::<ProgramHeader>();
+    let vaddr = elf_phdrs.iter().find_map(|ph| {
+        let offset = ph.p_offset(NativeEndian) as usize;
+        match ph.p_type(NativeEndian) {
+            elf::PT_PHDR => Some(ph.p_vaddr(NativeEndian) as usize),
+            elf::PT_LOAD
+                if offset <= phoff
+                    && phoff + size <= offset + ph.p_filesz(NativeEndian) as usize =>
+            {
+                Some(ph.p_vaddr(NativeEndian) as usize + phoff - offset)
             }
+            _ => None,
         }
+    });
+
+    match vaddr {
+        Some(vaddr) => unsafe {
+            slice::from_raw_parts((load_bias + vaddr) as *const ProgramHeader, elf_phdrs.len())
+        },
+        None => &[],
     }
 }
 
@@ -1312,6 +1919,63 @@ fn is_pie_enabled(elf: &ElfFile) -> bool {
     elf.elf_header().e_type.get(elf.endian()) == elf::ET_DYN
 }
 
+/// Name of the directory libraries are installed in, substituted for `$LIB`.
+const LIB: &str = "lib";
+
+/// Name of the processor type, substituted for `$PLATFORM`.
+#[cfg(target_arch = "aarch64")]
+const PLATFORM: &str = "aarch64";
+#[cfg(target_arch = "riscv64")]
+const PLATFORM: &str = "riscv64";
+#[cfg(target_arch = "x86")]
+const PLATFORM: &str = "i686";
+#[cfg(target_arch = "x86_64")]
+const PLATFORM: &str = "x86_64";
+
+/// Expands the `$ORIGIN`, `$LIB` and `$PLATFORM` tokens, or their `${...}`
+/// forms, in a search path of the object found in the directory `origin`.
+fn expand_tokens(value: &str, origin: &str) -> String {
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(i) = rest.find('$') {
+        expanded.push_str(&rest[..i]);
+        rest = &rest[i + 1..];
+
+        let token = [("ORIGIN", origin), ("LIB", LIB), ("PLATFORM", PLATFORM)]
+            .into_iter()
+            .find_map(|(name, replacement)| {
+                let len = if rest.starts_with(name) {
+                    // The token must not be followed by an identifier character.
+                    let next = rest[name.len()..].chars().next();
+                    if next.is_some_and(|c| c.is_ascii_alphanumeric() || c == '_') {
+                        return None;
+                    }
+                    name.len()
+                } else if rest.starts_with('{')
+                    && rest[1..].starts_with(name)
+                    && rest[1 + name.len()..].starts_with('}')
+                {
+                    name.len() + 2
+                } else {
+                    return None;
+                };
+                Some((len, replacement))
+            });
+
+        match token {
+            Some((len, replacement)) => {
+                expanded.push_str(replacement);
+                rest = &rest[len..];
+            }
+            None => expanded.push('$'),
+        }
+    }
+
+    expanded.push_str(rest);
+    expanded
+}
+
 fn basename(path: &str) -> String {
     path.split("/").last().unwrap_or(path).to_string()
 }
@@ -1324,9 +1988,10 @@ fn dirname(path: &str) -> String {
 
 pub fn resolve_sym<'a>(
     name: &'a str,
+    version: Option<&SymbolVersion<'_>>,
     scopes: &[&'a Scope],
 ) -> Option<(Symbol<'a>, SymbolBinding, Arc<DSO>)> {
-    scopes.iter().find_map(|scope| scope.get_sym(name))
+    scopes.iter().find_map(|scope| scope.get_sym(name, version))
 }
 
 #[repr(C)]
@@ -1477,3 +2142,71 @@ pub unsafe fn apply_relr(base: *const u8, relr: &[Relr]) {
 // TODO: Add safety comment.
 unsafe impl Send for DSO {}
 unsafe impl Sync for DSO {}
+
+#[cfg(test)]
+mod tests {
+    use super::{HashTable, LIB, PLATFORM, Versions, expand_tokens};
+    use alloc::format;
+    use object::{NativeEndian, StringTable, U16, U32, elf, read::elf::GnuHashTable};
+
+    #[test]
+    fn find_in_empty_gnu_hash_table() {
+        let table = HashTable::Gnu(GnuHashTable {
+            symbol_base: 1,
+            bloom_shift: 0,
+            bloom_filters: &[],
+            buckets: &[],
+            values: &[],
+        });
+        assert!(
+            table
+                .find("foo", &[], StringTable::default(), |_, _| true)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn reject_invalid_version_names() {
+        let need = elf::Verneed {
+            vn_version: U16::new(NativeEndian, 1),
+            vn_cnt: U16::new(NativeEndian, 0),
+            vn_file: U32::new(NativeEndian, 100),
+            vn_aux: U32::new(NativeEndian, 0),
+            vn_next: U32::new(NativeEndian, 0),
+        };
+        let strings = StringTable::new(&b"\0"[..], 0, 1);
+        let verneed = Some(((&raw const need).cast::<u8>(), 1));
+        assert!(unsafe { Versions::parse(&[], None, verneed, strings) }.is_err());
+    }
+
+    #[test]
+    fn expand_origin() {
+        assert_eq!(
+            expand_tokens("$ORIGIN/../lib", "/opt/app"),
+            "/opt/app/../lib"
+        );
+        assert_eq!(expand_tokens("${ORIGIN}/lib", "/opt/app"), "/opt/app/lib");
+        assert_eq!(expand_tokens("$ORIGIN:$ORIGIN/sub", "/a"), "/a:/a/sub");
+    }
+
+    #[test]
+    fn expand_lib_and_platform() {
+        assert_eq!(
+            expand_tokens("/usr/$LIB/${PLATFORM}", "/"),
+            format!("/usr/{LIB}/{PLATFORM}")
+        );
+        assert_eq!(
+            expand_tokens("/opt/${LIB}_$PLATFORM", "/"),
+            format!("/opt/{LIB}_{PLATFORM}")
+        );
+    }
+
+    #[test]
+    fn keep_unknown_tokens() {
+        // Tokens must not be followed by an identifier character.
+        assert_eq!(expand_tokens("$ORIGINAL/lib", "/a"), "$ORIGINAL/lib");
+        assert_eq!(expand_tokens("$HOME/lib", "/a"), "$HOME/lib");
+        assert_eq!(expand_tokens("${ORIGIN/lib", "/a"), "${ORIGIN/lib");
+        assert_eq!(expand_tokens("/lib$", "/a"), "/lib$");
+    }
+}
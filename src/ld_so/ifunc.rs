@@ -0,0 +1,128 @@
+//! GNU indirect functions (`STT_GNU_IFUNC`).
+//!
+//! The address of an indirect function is chosen at load time by calling its
+//! resolver, which usually picks the implementation best suited to the
+//! hardware capabilities of the CPU. Resolvers are called:
+//! * for `IRELATIVE` relocations, in dynamically linked objects and in the
+//!   `__rela_iplt` section of statically linked programs,
+//! * when a symbol reference (`GLOB_DAT`, `JUMP_SLOT`, ...) or `dlsym` binds
+//!   to an `STT_GNU_IFUNC` symbol.
+//!
+//! See <https://sourceware.org/glibc/wiki/GNU_IFUNC>.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{
+    header::elf::{AT_HWCAP, AT_HWCAP2},
+    platform::get_auxv,
+};
+
+use super::dso::{Relocation, RelocationKind};
+
+static HWCAP: AtomicUsize = AtomicUsize::new(0);
+static HWCAP2: AtomicUsize = AtomicUsize::new(0);
+
+/// Records the hardware capabilities passed to resolvers, from the auxiliary
+/// vector.
+pub fn init(auxv: &[[usize; 2]]) {
+    HWCAP.store(get_auxv(auxv, AT_HWCAP).unwrap_or(0), Ordering::Relaxed);
+    HWCAP2.store(get_auxv(auxv, AT_HWCAP2).unwrap_or(0), Ordering::Relaxed);
+}
+
+/// Second argument of the resolvers on aarch64, as `__ifunc_arg_t`.
+#[cfg(target_arch = "aarch64")]
+#[repr(C)]
+struct IfuncArg {
+    size: usize,
+    hwcap: usize,
+    hwcap2: usize,
+}
+
+/// Set in the first argument of the resolvers on aarch64 when the second one
+/// is given.
+#[cfg(target_arch = "aarch64")]
+const IFUNC_ARG_HWCAP: usize = 1 << 62;
+
+/// Calls the resolver at `resolver`, returning the address of the function
+/// it selects.
+pub unsafe fn resolve(resolver: usize) -> usize {
+    let hwcap = HWCAP.load(Ordering::Relaxed);
+    let hwcap2 = HWCAP2.load(Ordering::Relaxed);
+
+    #[cfg(target_arch = "aarch64")]
+    {
+        let arg = IfuncArg {
+            size: size_of::<IfuncArg>(),
+            hwcap,
+            hwcap2,
+        };
+        let resolver: unsafe extern "C" fn(usize, *const IfuncArg) -> usize =
+            unsafe { core::mem::transmute(resolver) };
+        unsafe { resolver(hwcap | IFUNC_ARG_HWCAP, &arg) }
+    }
+
+    #[cfg(target_arch = "riscv64")]
+    {
+        let _ = hwcap2;
+        // TODO: pass `__riscv_hwprobe` once it is implemented.
+        let resolver: unsafe extern "C" fn(
+            usize,
+            *const core::ffi::c_void,
+            *const core::ffi::c_void,
+        ) -> usize = unsafe { core::mem::transmute(resolver) };
+        unsafe { resolver(hwcap, core::ptr::null(), core::ptr::null()) }
+    }
+
+    // x86 resolvers query the CPU with `cpuid` instead.
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        let _ = (hwcap, hwcap2);
+        let resolver: unsafe extern "C" fn() -> usize = unsafe { core::mem::transmute(resolver) };
+        unsafe { resolver() }
+    }
+}
+
+/// Applies the `IRELATIVE` relocations of a statically linked program, which
+/// the static linker gathers between `__rela_iplt_start` and
+/// `__rela_iplt_end` (`__rel_iplt_*` on x86).
+pub unsafe fn apply_static_relocations() {
+    #[cfg(not(target_arch = "x86"))]
+    type Entry = super::dso::Rela;
+    #[cfg(target_arch = "x86")]
+    type Entry = super::dso::Rel;
+
+    // Only defined in statically linked, non-PIE programs.
+    #[cfg(not(target_arch = "x86"))]
+    unsafe extern "C" {
+        #[linkage = "extern_weak"]
+        static __rela_iplt_start: *const Entry;
+        #[linkage = "extern_weak"]
+        static __rela_iplt_end: *const Entry;
+    }
+    #[cfg(not(target_arch = "x86"))]
+    let (mut entry, end) = unsafe { (__rela_iplt_start, __rela_iplt_end) };
+
+    #[cfg(target_arch = "x86")]
+    unsafe extern "C" {
+        #[linkage = "extern_weak"]
+        static __rel_iplt_start: *const Entry;
+        #[linkage = "extern_weak"]
+        static __rel_iplt_end: *const Entry;
+    }
+    #[cfg(target_arch = "x86")]
+    let (mut entry, end) = unsafe { (__rel_iplt_start, __rel_iplt_end) };
+
+    if entry.is_null() {
+        return;
+    }
+
+    while entry < end {
+        let reloc = Relocation::from(unsafe { &*entry });
+        if reloc.kind == RelocationKind::IRELATIVE {
+            let ptr = reloc.offset as *mut usize;
+            let resolver = reloc.addend.unwrap_or_else(|| unsafe { *ptr });
+            unsafe { *ptr = resolve(resolver) };
+        }
+        entry = unsafe { entry.add(1) };
+    }
+}
@@ -1,5 +1,5 @@
 use alloc::{
-    collections::BTreeMap,
+    collections::{BTreeMap, BTreeSet},
     string::{String, ToString},
     sync::{Arc, Weak},
     vec::Vec,
@@ -15,32 +15,39 @@ use core::ptr;
 
 use crate::{
     c_str::{CStr, CString},
+    cxa,
     error::Errno,
     header::{
         dl_tls::{__tls_get_addr, dl_tls_index},
-        fcntl, sys_mman,
+        fcntl,
+        link::{LA_SER_DEFAULT, LA_SER_LIBPATH, LA_SER_RUNPATH},
+        sys_mman,
         unistd::F_OK,
     },
-    ld_so::dso::{Dyn, SymbolBinding},
+    ld_so::dso::{Dyn, SymbolBinding, SymbolVersion},
     out::Out,
     platform::{
         Pal, Sys,
-        types::{c_int, c_void},
+        types::{c_int, c_uint, c_void},
     },
     sync::rwlock::RwLock,
 };
 
 #[cfg(not(target_arch = "x86"))]
-use crate::{ld_so::dso::resolve_sym, platform::types::c_uint};
+use crate::ld_so::dso::resolve_sym;
 
 #[cfg(not(target_arch = "x86"))]
 use super::dso::Rela;
 use super::{
     PATH_SEP,
     access::accessible,
+    audit::{self, AUDITORS, Auditor},
+    cache::{self, Cache},
     debug::{_dl_debug_state, _r_debug, RTLDState},
     dso::{DSO, ProgramHeader},
+    ifunc,
     tcb::{Master, Tcb},
+    trace::{self, debug_println},
 };
 
 #[derive(Debug, Copy, Clone)]
@@ -53,6 +60,8 @@ pub enum DlError {
     InvalidHandle,
     /// Out of memory.
     Oom,
+    /// Unsupported request.
+    InvalidRequest,
 }
 
 impl DlError {
@@ -72,6 +81,8 @@ impl DlError {
             }
 
             DlError::Oom => c"Out of memory.",
+
+            DlError::InvalidRequest => c"Unsupported request.",
         }
     }
 }
@@ -80,19 +91,21 @@ pub type Result<T> = core::result::Result<T, DlError>;
 
 pub(super) static GLOBAL_SCOPE: RwLock<Scope> = RwLock::new(Scope::global());
 
-struct MmapFile {
+pub(super) struct MmapFile {
     fd: i32,
     ptr: *mut c_void,
     size: usize,
 }
 
 impl MmapFile {
-    fn open(path: CStr, oflag: c_int) -> core::result::Result<Self, Errno> {
+    pub(super) fn open(path: CStr, oflag: c_int) -> core::result::Result<Self, Errno> {
         let fd = Sys::open(path, oflag, 0 /* mode */)?;
         let mut stat = crate::header::sys_stat::stat::default();
-        Sys::fstat(fd, Out::from_mut(&mut stat))?;
-
-        Self::from_fd(fd, stat.st_size as usize)
+        Sys::fstat(fd, Out::from_mut(&mut stat))
+            .and_then(|()| Self::from_fd(fd, stat.st_size as usize))
+            .inspect_err(|_| {
+                let _ = Sys::close(fd);
+            })
     }
 
     fn from_fd(fd: i32, size: usize) -> core::result::Result<Self, Errno> {
@@ -125,7 +138,15 @@ impl MmapFile {
         Ok(Self { fd: -1, ptr, size })
     }
 
-    fn data(&self) -> &[u8] {
+    /// Closes the underlying file, keeping the mapping.
+    pub(super) fn close(&mut self) {
+        if self.fd != -1 {
+            let _ = Sys::close(self.fd);
+            self.fd = -1;
+        }
+    }
+
+    pub(super) fn data(&self) -> &[u8] {
         unsafe { core::slice::from_raw_parts(self.ptr.cast::<u8>(), self.size) }
     }
 
@@ -149,6 +170,8 @@ impl Drop for MmapFile {
 #[derive(Clone, Debug)]
 pub struct Symbol<'a> {
     pub name: &'a str,
+    /// Index of the symbol in the symbol table of the defining object.
+    pub index: usize,
     pub value: usize,
     pub base: usize,
     pub size: usize,
@@ -159,6 +182,16 @@ impl Symbol<'_> {
     pub fn as_ptr(&self) -> *mut c_void {
         (self.base + self.value) as *mut c_void
     }
+
+    /// Returns the address references to the symbol bind to, which for
+    /// `STT_GNU_IFUNC` symbols is the one selected by their resolver.
+    pub fn resolve(&self) -> *mut c_void {
+        if self.sym_type == elf::STT_GNU_IFUNC {
+            unsafe { ifunc::resolve(self.as_ptr() as usize) as *mut c_void }
+        } else {
+            self.as_ptr()
+        }
+    }
 }
 
 #[derive(Debug, Default, Copy, Clone, PartialEq)]
@@ -241,19 +274,24 @@ impl Scope {
     pub(super) fn get_sym<'a>(
         &self,
         name: &'a str,
+        version: Option<&SymbolVersion<'_>>,
     ) -> Option<(Symbol<'a>, SymbolBinding, Arc<DSO>)> {
-        self._get_sym(name, 0)
+        self._get_sym(name, version, 0)
     }
 
     pub(super) fn _get_sym<'a>(
         &self,
         name: &'a str,
+        version: Option<&SymbolVersion<'_>>,
         skip: usize,
     ) -> Option<(Symbol<'a>, SymbolBinding, Arc<DSO>)> {
         let mut res = None;
 
         let get_sym = |obj: Arc<DSO>| {
-            if let Some((sym, binding)) = obj.get_sym(name) {
+            if trace::enabled(DebugFlags::SYMBOLS) {
+                debug_println!("[ld.so]: symbol={}; lookup in file={}", name, obj.name);
+            }
+            if let Some((sym, binding)) = obj.get_sym(name, version) {
                 if binding.is_global() {
                     return Some((sym, binding, obj.clone()));
                 }
@@ -286,13 +324,38 @@ impl Scope {
         .or(res)
     }
 
+    /// Returns the objects of a local scope besides its owner.
+    fn dependencies(&self) -> &[Arc<DSO>] {
+        match self {
+            Self::Global { .. } => &[],
+            Self::Local { objs, .. } => objs,
+        }
+    }
+
+    /// Returns the position of `target` in the search order of this scope.
+    fn position(&self, target: &DSO) -> Option<usize> {
+        match self {
+            Self::Global { objs } => objs.iter().position(|obj| ptr::eq(obj.as_ptr(), target)),
+            Self::Local { owner, objs } => owner
+                .iter()
+                .map(Weak::as_ptr)
+                .chain(objs.iter().map(Arc::as_ptr))
+                .position(|obj| ptr::eq(obj, target)),
+        }
+    }
+
     fn copy_into(&self, other: &mut Self) {
         match (self, other) {
-            (Self::Local { owner, objs }, Self::Global { objs: other_objs }) => {
-                // FIXME: may have duplicates
-                let owner = owner.as_ref().expect("local scope without owner");
-                other_objs.push(owner.clone());
-                other_objs.extend(objs.iter().map(Arc::downgrade));
+            (Self::Local { owner, objs }, other @ Self::Global { .. }) => {
+                let owner = owner
+                    .as_ref()
+                    .expect("local scope without owner")
+                    .upgrade()
+                    .expect("local scope owner was dropped");
+                other.add(&owner);
+                for obj in objs {
+                    other.add(obj);
+                }
             }
 
             _ => unreachable!(),
@@ -302,7 +365,7 @@ impl Scope {
     fn debug(&self) {
         match self {
             Self::Global { objs } => {
-                println!(
+                debug_println!(
                     "[@global] {:?}",
                     objs.iter()
                         .map(|x| x.upgrade().unwrap().name.clone())
@@ -312,7 +375,7 @@ impl Scope {
 
             Self::Local { owner, objs } => {
                 let owner = owner.as_ref().unwrap().upgrade().unwrap();
-                println!(
+                debug_println!(
                     "[{}] {:?}",
                     owner.name,
                     objs.iter().map(|x| x.name.clone()).collect::<Vec<_>>()
@@ -322,6 +385,19 @@ impl Scope {
     }
 }
 
+bitflags::bitflags! {
+    /// Flags of [`Linker::load_library`], besides the binding mode and scope.
+    #[derive(Debug, Default, Clone, Copy, PartialEq)]
+    pub struct OpenFlags: u32 {
+        /// Do not load the object if it is not already loaded.
+        const NOLOAD = 1 << 0;
+        /// Never unload the object.
+        const NODELETE = 1 << 1;
+        /// Search the scope of the object before the global scope.
+        const DEEPBIND = 1 << 2;
+    }
+}
+
 bitflags::bitflags! {
     #[derive(Debug, Default)]
     pub struct DebugFlags: u32 {
@@ -331,19 +407,83 @@ bitflags::bitflags! {
         const SEARCH = 1 << 2;
         /// Display scope information.
         const SCOPES = 1 << 3;
+        /// Display which object each symbol reference is bound to.
+        const BINDINGS = 1 << 4;
+        /// Display relocation processing.
+        const RELOC = 1 << 5;
+        /// Display each object searched during symbol lookups.
+        const SYMBOLS = 1 << 6;
+        /// Display version dependency checks.
+        const VERSIONS = 1 << 7;
+        /// Display relocation statistics and startup time.
+        const STATISTICS = 1 << 8;
+    }
+}
+
+/// Objects removed by [`Linker::unload`], whose finalizers have not run yet.
+///
+/// The finalizers may call back into the linker, e.g. to close another
+/// handle, so they must run once the linker is unlocked.
+#[must_use]
+pub struct Unloaded {
+    objs: Vec<Arc<DSO>>,
+}
+
+impl Unloaded {
+    /// Runs the finalizers of the objects, then unmaps them.
+    pub fn finalize(self) {
+        if self.objs.is_empty() {
+            return;
+        }
+
+        // All the finalizers run before any object is unmapped, as they may
+        // still call into each other.
+        for obj in self.objs.iter() {
+            unsafe { obj.run_fini() };
+            cxa::finalize_object(|addr| obj.contains(addr));
+        }
+
+        for obj in self.objs.iter() {
+            if obj.tls_module_id != 0 {
+                Tcb::unload_master(obj.tls_module_id);
+            }
+        }
+
+        // The objects are unmapped once the last references to them, which
+        // may be held by the local scopes of each other, are dropped.
+        drop(self.objs);
+
+        _r_debug.lock().state = RTLDState::RtConsistent;
+        _dl_debug_state();
     }
 }
 
 #[derive(Default)]
 pub struct Config {
     pub debug_flags: DebugFlags,
+    /// File the traces are written to, from `LD_DEBUG_OUTPUT`.
+    debug_output: Option<String>,
     library_path: Option<String>,
     /// Resolve symbols at program startup.
     bind_now: bool,
+    /// Libraries loaded ahead of the dependencies of the program, from
+    /// `LD_PRELOAD` and `/etc/ld.so.preload`.
+    preload: Vec<String>,
+    /// Auditing libraries, from `LD_AUDIT`.
+    audit: Vec<String>,
+    /// Cache of the libraries in `system_dirs`, from `/etc/ld.so.cache`.
+    cache: Option<Cache>,
+    /// Directories searched after `LD_LIBRARY_PATH` and the `DT_RUNPATH` of
+    /// the requesting object, from `/etc/ld.so.conf`.
+    system_dirs: Vec<String>,
 }
 
 impl Config {
-    pub fn from_env(env: &BTreeMap<String, String>) -> Self {
+    /// Parses the configuration from the environment. In secure-execution
+    /// mode (e.g. for set-user-ID programs), libraries named by a path are
+    /// not preloaded nor used for auditing, and `LD_LIBRARY_PATH` and
+    /// `LD_DEBUG_OUTPUT` are ignored.
+    pub fn from_env(env: &BTreeMap<String, String>, secure: bool) -> Self {
         let debug_flags = env
             .get("LD_DEBUG")
             .map(|value| {
@@ -353,6 +493,11 @@ impl Config {
                         "load" => DebugFlags::LOAD,
                         "search" => DebugFlags::SEARCH,
                         "scopes" => DebugFlags::SCOPES,
+                        "bindings" => DebugFlags::BINDINGS,
+                        "reloc" => DebugFlags::RELOC,
+                        "symbols" => DebugFlags::SYMBOLS,
+                        "versions" => DebugFlags::VERSIONS,
+                        "statistics" => DebugFlags::STATISTICS,
                         "all" => DebugFlags::all(),
                         _ => {
                             eprintln!("[ld.so]: unknown debug flag '{}'", opt);
@@ -365,15 +510,129 @@ impl Config {
             })
             .unwrap_or(DebugFlags::empty());
 
+        let libraries = |value: &str| {
+            value
+                .split([PATH_SEP, ' ', '\t', '\n'])
+                .filter(|name| !name.is_empty() && !(secure && name.contains('/')))
+                .map(String::from)
+                .collect::<Vec<_>>()
+        };
+
+        let mut preload = env
+            .get("LD_PRELOAD")
+            .map(|value| libraries(value))
+            .unwrap_or_default();
+        // The preload file is trusted, as only the administrator can write it.
+        if let Ok(file) = MmapFile::open(
+            c"/etc/ld.so.preload".into(),
+            fcntl::O_RDONLY | fcntl::O_CLOEXEC,
+        ) && let Ok(value) = core::str::from_utf8(file.data())
+        {
+            preload.extend(
+                value
+                    .split_ascii_whitespace()
+                    .filter(|name| !name.is_empty())
+                    .map(String::from),
+            );
+        }
+
+        let cache = Cache::open(cache::CACHE_PATH);
+        let system_dirs = system_dirs(cache.is_none());
+
         Self {
             debug_flags,
-            library_path: env.get("LD_LIBRARY_PATH").cloned(),
+            // The output file could be used to overwrite arbitrary files.
+            debug_output: env.get("LD_DEBUG_OUTPUT").filter(|_| !secure).cloned(),
+            // Libraries named by `LD_PRELOAD` and `LD_AUDIT` would otherwise
+            // be found in any directory.
+            library_path: env.get("LD_LIBRARY_PATH").filter(|_| !secure).cloned(),
             bind_now: env
                 .get("LD_BIND_NOW")
                 .map(|value| !value.is_empty())
                 .unwrap_or_default(),
+            preload,
+            audit: env
+                .get("LD_AUDIT")
+                .map(|value| libraries(value))
+                .unwrap_or_default(),
+            cache,
+            system_dirs,
         }
     }
+
+    /// Ignores the library cache, searching the configured directories
+    /// instead.
+    pub fn inhibit_cache(&mut self) {
+        if self.cache.take().is_some() {
+            self.system_dirs = system_dirs(true);
+        }
+    }
+}
+
+/// Returns the path of the program at `path`, which is never searched for.
+fn program_path(path: &str) -> String {
+    if path.contains('/') {
+        path.to_string()
+    } else {
+        format!("./{}", path)
+    }
+}
+
+/// Returns the system directories to search for libraries. The configuration
+/// file only needs to be read if the cache is not used.
+fn system_dirs(read_config: bool) -> Vec<String> {
+    let mut dirs = if read_config {
+        cache::read_config(cache::CONFIG_PATH)
+    } else {
+        Vec::new()
+    };
+    for dir in cache::DEFAULT_DIRS {
+        if !dirs.iter().any(|other| other == dir) {
+            dirs.push(dir.to_string());
+        }
+    }
+    dirs
+}
+
+/// Search paths of the object requesting a library.
+#[derive(Clone, Debug, Default)]
+struct SearchPaths {
+    /// `DT_RPATH` entries of the requesting object and of the objects that
+    /// loaded it, innermost first.
+    rpath: Vec<String>,
+    /// `DT_RUNPATH` entry of the requesting object.
+    runpath: Option<String>,
+}
+
+impl SearchPaths {
+    /// Returns the search paths for the dependencies of `obj`, loaded by an
+    /// object with these search paths.
+    fn inherit(&self, obj: &DSO) -> Self {
+        Self {
+            rpath: obj
+                .rpath()
+                .into_iter()
+                .chain(self.rpath.iter())
+                .cloned()
+                .collect(),
+            runpath: obj.runpath().cloned(),
+        }
+    }
+}
+
+/// Returns the address of the definition `symbol` in `obj`, for the calling
+/// thread if it is a TLS symbol.
+fn symbol_address(symbol: &Symbol<'_>, obj: &DSO) -> *mut c_void {
+    if symbol.sym_type != elf::STT_TLS {
+        symbol.resolve()
+    } else {
+        let mut tls_index = dl_tls_index {
+            ti_module: obj.tls_module_id,
+            ti_offset: symbol.value,
+        };
+
+        unsafe { __tls_get_addr(&raw mut tls_index) }
+    }
 }
 
 pub struct Me {
@@ -392,12 +651,23 @@ pub struct Linker {
     tls_size: usize,
     objects: BTreeMap<usize, Arc<DSO>>,
     name_to_object_id_map: BTreeMap<String, usize>,
+    /// IDs of the direct dependencies of each object.
+    dependencies: BTreeMap<usize, Vec<usize>>,
+    /// IDs of the objects, in the order their initializers ran. Finalizers
+    /// run in the reverse order.
+    init_order: Vec<usize>,
+    /// Number of objects loaded so far.
+    adds: u64,
+    /// Number of objects unloaded so far.
+    subs: u64,
 }
 
 const ROOT_ID: usize = 1;
 
 impl Linker {
     pub fn new(me: Me, config: Config) -> Self {
+        trace::init(config.debug_flags, config.debug_output.as_deref());
+
         Self {
             me,
             config,
@@ -406,13 +676,17 @@ impl Linker {
             tls_size: 0,
             objects: BTreeMap::new(),
             name_to_object_id_map: BTreeMap::new(),
+            dependencies: BTreeMap::new(),
+            init_order: Vec::new(),
+            adds: 0,
+            subs: 0,
         }
     }
 
     pub fn load_program(&mut self, path: &str, base_addr: Option<usize>) -> Result<usize> {
         let dso = self.load_object(
-            path,
-            &None,
+            &program_path(path),
+            &SearchPaths::default(),
             base_addr,
             false,
             if self.config.bind_now {
@@ -421,31 +695,66 @@ impl Linker {
                 Resolve::default()
             },
             ScopeKind::Global,
+            false,
         )?;
         Ok(dso.entry_point)
     }
 
+    /// Loads the program at `path` and its dependencies, without relocating
+    /// nor initializing them, and prints where each dependency was loaded
+    /// from, like `ldd`.
+    pub fn trace_program(&mut self, path: &str) -> Result<()> {
+        let mut new_objects = Vec::new();
+        self.load_objects_recursive(
+            &program_path(path),
+            &SearchPaths::default(),
+            None,
+            false,
+            &mut new_objects,
+            &mut Vec::new(),
+            &mut Vec::new(),
+            None,
+            ScopeKind::Global,
+            true,
+        )?;
+
+        // List the dependencies in load order, leaving out the program.
+        new_objects.sort_by_key(|obj| obj.id);
+        for obj in new_objects.iter().skip(1) {
+            let path = unsafe { CStr::from_ptr((*obj.link_map).l_name) };
+            println!(
+                "\t{} => {} ({:#x})",
+                obj.name,
+                path.to_str().unwrap_or("?"),
+                obj.base as usize
+            );
+        }
+
+        Ok(())
+    }
+
     pub fn load_library(
         &mut self,
         name: Option<&str>,
         resolve: Resolve,
         scope: ScopeKind,
-        noload: bool,
+        flags: OpenFlags,
+        caller: usize,
     ) -> Result<Arc<DSO>> {
         log::trace!(
-            "[ld.so] load_library(name={:?}, resolve={:#?}, scope={:#?}, noload={})",
+            "[ld.so] load_library(name={:?}, resolve={:#?}, scope={:#?}, flags={:?}, caller={:#x})",
             name,
             resolve,
             scope,
-            noload
+            flags,
+            caller
         );
 
-        if noload && resolve == Resolve::Now {
-            // Do not perform lazy binding anymore.
-            // * Check if loaded with Resolve::Now and if so, early return.
-            // * If not, resolve all symbols now.
-            todo!("resolve symbols now!");
-        }
+        let resolve = if self.config.bind_now {
+            Resolve::Now
+        } else {
+            resolve
+        };
 
         match name {
             Some(name) => {
@@ -454,9 +763,9 @@ impl Linker {
 
                     // We may be upgrading the object from a local scope to the
                     // global scope.
-                    if scope == ScopeKind::Global {
+                    if scope == ScopeKind::Global && GLOBAL_SCOPE.read().position(obj).is_none() {
                         if self.config.debug_flags.contains(DebugFlags::SCOPES) {
-                            eprintln!("[ld.so]: moving {} into the global scope", obj.name);
+                            debug_println!("[ld.so]: moving {} into the global scope", obj.name);
                         }
 
                         {
@@ -466,25 +775,40 @@ impl Linker {
                         self.scope_debug();
                     }
 
+                    // Objects that were bound lazily, along with their
+                    // dependencies, are bound now if requested.
+                    if resolve == Resolve::Now {
+                        obj.bind_now();
+                        for dep in obj.scope().dependencies() {
+                            dep.bind_now();
+                        }
+                    }
+
+                    if flags.contains(OpenFlags::NODELETE) {
+                        obj.set_nodelete();
+                    }
+                    obj.add_handle();
+
                     Ok(obj.clone())
-                } else if !noload {
-                    let parent_runpath = &self
-                        .objects
-                        .get(&ROOT_ID)
-                        .and_then(|parent| parent.runpath().cloned());
-
-                    Ok(self.load_object(
-                        name,
-                        parent_runpath,
-                        None,
-                        true,
-                        if self.config.bind_now {
-                            Resolve::Now
-                        } else {
-                            resolve
-                        },
-                        scope,
-                    )?)
+                } else if !flags.contains(OpenFlags::NOLOAD) {
+                    let search_paths = self.dlopen_search_paths(caller);
+
+                    Ok(self
+                        .load_object(
+                            name,
+                            &search_paths,
+                            None,
+                            true,
+                            resolve,
+                            scope,
+                            flags.contains(OpenFlags::DEEPBIND),
+                        )
+                        .inspect(|obj| {
+                            if flags.contains(OpenFlags::NODELETE) {
+                                obj.set_nodelete();
+                            }
+                            obj.add_handle();
+                        })?)
                 } else {
                     Err(DlError::NotFound)
                 }
@@ -497,7 +821,35 @@ impl Linker {
         }
     }
 
-    pub fn get_sym(&self, handle: Option<&DSO>, name: &str) -> Option<*mut c_void> {
+    /// Returns the search paths of `dlopen` called from the address `caller`:
+    /// those of the calling object, followed by the `DT_RPATH` of the program
+    /// if the calling object has no `DT_RUNPATH`, as in glibc.
+    fn dlopen_search_paths(&self, caller: usize) -> SearchPaths {
+        match self.find_object(caller).or(self.objects.get(&ROOT_ID)) {
+            Some(obj) => self.object_search_paths(obj),
+            None => SearchPaths::default(),
+        }
+    }
+
+    /// Returns the search paths of `dlopen` called from `obj`.
+    fn object_search_paths(&self, obj: &DSO) -> SearchPaths {
+        let root = self.objects.get(&ROOT_ID);
+        let mut search_paths = SearchPaths::default().inherit(obj);
+        if search_paths.runpath.is_none()
+            && let Some(root) = root
+            && !ptr::eq(Arc::as_ptr(root), obj)
+        {
+            search_paths.rpath.extend(root.rpath().cloned());
+        }
+        search_paths
+    }
+
+    pub fn get_sym(
+        &self,
+        handle: Option<&DSO>,
+        name: &str,
+        version: Option<&SymbolVersion<'_>>,
+    ) -> Option<*mut c_void> {
         let guard;
 
         if let Some(handle) = handle {
@@ -506,65 +858,145 @@ impl Linker {
             guard = GLOBAL_SCOPE.read();
             &guard
         }
-        .get_sym(name)
-        .map(|(symbol, _, obj)| {
-            if symbol.sym_type != elf::STT_TLS {
-                symbol.as_ptr()
-            } else {
-                let mut tls_index = dl_tls_index {
-                    ti_module: obj.tls_module_id,
-                    ti_offset: symbol.value,
-                };
+        .get_sym(name, version)
+        .map(|(symbol, _, obj)| symbol_address(&symbol, &obj))
+    }
 
-                unsafe { __tls_get_addr(&raw mut tls_index) }
-            }
-        })
+    /// Looks up the next definition of `name` after the object containing the
+    /// address `caller`, in the scope that object was found in.
+    pub fn get_next_sym(
+        &self,
+        caller: usize,
+        name: &str,
+        version: Option<&SymbolVersion<'_>>,
+    ) -> Option<*mut c_void> {
+        let caller = self.find_object(caller)?;
+        let global_scope = GLOBAL_SCOPE.read();
+
+        match global_scope.position(caller) {
+            Some(position) => global_scope._get_sym(name, version, position + 1),
+            None => caller.scope()._get_sym(name, version, 1),
+        }
+        .map(|(symbol, _, obj)| symbol_address(&symbol, &obj))
     }
 
-    pub fn unload(&mut self, obj: Arc<DSO>) {
-        if !obj.dlopened {
-            return;
+    /// Returns the loaded objects, in load order.
+    pub fn objects(&self) -> impl Iterator<Item = &Arc<DSO>> {
+        self.objects.values()
+    }
+
+    /// Returns the loaded object containing `addr`.
+    pub fn find_object(&self, addr: usize) -> Option<&Arc<DSO>> {
+        self.objects.values().find(|obj| obj.contains(addr))
+    }
+
+    /// Returns the number of objects loaded and unloaded so far.
+    pub fn load_counts(&self) -> (u64, u64) {
+        (self.adds, self.subs)
+    }
+
+    /// Closes a `dlopen` handle to `obj`, then unloads the objects that are
+    /// no longer needed. Their finalizers are left to [`Unloaded::finalize`],
+    /// to be run once the linker is unlocked.
+    pub fn unload(&mut self, obj: Arc<DSO>) -> Result<Unloaded> {
+        if !obj.remove_handle() && obj.dlopened {
+            // The handle was already closed, so this reference is not ours
+            // to drop.
+            core::mem::forget(obj);
+            return Err(DlError::InvalidHandle);
         }
 
         log::trace!(
-            "[ld.so] unloading {} (sc={}, wc={})",
+            "[ld.so] closing {} (sc={}, wc={})",
             obj.name,
             Arc::strong_count(&obj),
             Arc::weak_count(&obj)
         );
 
-        // One for the reference we have and the other for the one in the
-        // objects map.
-        if Arc::strong_count(&obj) == 2 {
-            // Remove from the global scope.
+        if obj.dlopened && !obj.has_handles() {
+            drop(obj);
+            return Ok(self.unload_unused());
+        }
+
+        Ok(Unloaded { objs: Vec::new() })
+    }
+
+    /// Removes the objects loaded with `dlopen` that are neither referenced by
+    /// an open handle nor needed by another object that is still loaded.
+    fn unload_unused(&mut self) -> Unloaded {
+        // Objects are needed if they cannot be unloaded, or if they are a
+        // dependency of, or have been bound to by, a needed object.
+        let mut needed = BTreeSet::new();
+        let mut pending = self
+            .objects
+            .values()
+            .filter(|obj| !obj.dlopened || obj.is_nodelete() || obj.has_handles())
+            .map(|obj| obj.id)
+            .collect::<Vec<_>>();
+        while let Some(id) = pending.pop() {
+            if !needed.insert(id) {
+                continue;
+            }
+            if let Some(dependencies) = self.dependencies.get(&id) {
+                pending.extend(dependencies);
+            }
+            if let Some(obj) = self.objects.get(&id) {
+                pending.extend(obj.reldeps());
+            }
+        }
+
+        // Objects are finalized before their dependencies, in the reverse
+        // order of their initialization.
+        let unused = self
+            .init_order
+            .iter()
+            .rev()
+            .filter(|id| !needed.contains(*id))
+            .filter_map(|id| self.objects.get(id).cloned())
+            .collect::<Vec<_>>();
+        if unused.is_empty() {
+            return Unloaded { objs: unused };
+        }
+
+        _r_debug.lock().state = RTLDState::RtDelete;
+        _dl_debug_state();
+
+        // The objects are removed right away, so that they are neither found
+        // nor loaded again while their finalizers run.
+        for obj in unused.iter() {
+            if self.config.debug_flags.contains(DebugFlags::LOAD) {
+                debug_println!("[ld.so]: unloading {}", obj.name);
+            }
+
             match *GLOBAL_SCOPE.write() {
                 Scope::Global { ref mut objs } => {
-                    objs.retain(|o| !Weak::ptr_eq(o, &Arc::downgrade(&obj)));
+                    objs.retain(|o| !ptr::eq(o.as_ptr(), Arc::as_ptr(obj)));
                 }
 
                 _ => unreachable!(),
             }
 
-            let _ = self.objects.remove(&obj.id).unwrap();
-            for dep in obj.dependencies() {
-                if let Some(name) = self.name_to_object_id_map.get(*dep)
-                    && let Some(object_name) = self.objects.get(name)
-                {
-                    self.unload(object_name.clone());
-                }
+            self.objects.remove(&obj.id);
+            self.dependencies.remove(&obj.id);
+            self.init_order.retain(|&id| id != obj.id);
+            if self.name_to_object_id_map.get(&obj.name) == Some(&obj.id) {
+                self.name_to_object_id_map.remove(&obj.name);
             }
-            self.name_to_object_id_map.remove(&obj.name);
-            assert!(Arc::strong_count(&obj) == 1);
-            drop(obj);
+            _r_debug.lock().remove(obj.link_map);
+            self.subs += 1;
         }
 
-        // obj is dropped here.
+        Unloaded { objs: unused }
     }
 
+    /// Runs the finalizers of all objects at exit, in the reverse order of
+    /// their initialization.
     pub fn fini(&self) {
-        for obj in self.objects.values() {
-            unsafe {
-                obj.run_fini();
+        for id in self.init_order.iter().rev() {
+            if let Some(obj) = self.objects.get(id) {
+                unsafe {
+                    obj.run_fini();
+                }
             }
         }
     }
@@ -572,11 +1004,12 @@ impl Linker {
     fn load_object(
         &mut self,
         path: &str,
-        runpath: &Option<String>,
+        search_paths: &SearchPaths,
         base_addr: Option<usize>,
         dlopened: bool,
         resolve: Resolve,
         scope: ScopeKind,
+        deepbind: bool,
     ) -> Result<Arc<DSO>> {
         let resolve = if cfg!(target_arch = "x86_64") {
             resolve
@@ -593,7 +1026,7 @@ impl Linker {
         let mut tcb_masters = Vec::new();
         let loaded_dso = self.load_objects_recursive(
             path,
-            runpath,
+            search_paths,
             base_addr,
             dlopened,
             &mut new_objects,
@@ -601,8 +1034,51 @@ impl Linker {
             &mut tcb_masters,
             None,
             scope,
+            !dlopened,
         )?;
 
+        // Auditing libraries are loaded along with the program, in their own
+        // local scopes, and are not audited themselves.
+        let audited_count = new_objects.len();
+        let mut audit_objects = Vec::new();
+        if !dlopened {
+            for name in self.config.audit.clone() {
+                match self.load_objects_recursive(
+                    &name,
+                    &SearchPaths::default(),
+                    None,
+                    dlopened,
+                    &mut new_objects,
+                    &mut objects_data,
+                    &mut tcb_masters,
+                    None,
+                    ScopeKind::Local,
+                    false,
+                ) {
+                    Ok(obj) => audit_objects.push(obj),
+                    Err(err) => eprintln!(
+                        "[ld.so]: cannot load auditing interface '{}': {:?}: ignored",
+                        name, err
+                    ),
+                }
+            }
+        }
+
+        for obj in new_objects.iter() {
+            self.check_versions(obj, &new_objects)?;
+            if deepbind {
+                obj.set_deepbind();
+            }
+        }
+
+        if dlopened {
+            let tcb = unsafe { Tcb::current() }.expect("failed to get current tcb");
+
+            // TLS variables for dlopen'ed objects are lazily allocated in `__tls_get_addr`,
+            // which `TLSDESC` relocations already need.
+            unsafe { tcb.append_masters(core::mem::take(&mut tcb_masters)) };
+        }
+
         for (i, obj) in new_objects.iter().enumerate() {
             obj.relocate(objects_data[i].as_deref(), resolve).unwrap();
         }
@@ -711,32 +1187,112 @@ impl Linker {
                     // Unmap the old TCB.
                     Sys::munmap(old_tcb, syscall::PAGE_SIZE).unwrap();
                 }
-            } else {
-                let tcb = Tcb::current().expect("failed to get current tcb");
-
-                // TLS variables for dlopen'ed objects are lazily allocated in `__tls_get_addr`.
-                tcb.append_masters(tcb_masters);
             }
         }
 
+        let audited_objects = new_objects[..audited_count].to_vec();
+
         for obj in new_objects.into_iter() {
             // SAFETY: `obj` and its dependencies have been successfuly loaded.
             unsafe {
                 obj.mark_ready();
             }
+            self.init_order.push(obj.id);
             self.run_init(&obj);
             self.register_object(obj);
         }
 
+        // The auditing libraries can only be called into once their
+        // dependencies have been initialized.
+        if !audit_objects.is_empty() {
+            let auditors = audit_objects
+                .into_iter()
+                .filter_map(|obj| {
+                    let name = obj.name.clone();
+                    let auditor = Auditor::new(obj);
+                    if auditor.is_none() {
+                        eprintln!(
+                            "[ld.so]: '{}' is not a supported auditing interface: ignored",
+                            name
+                        );
+                    }
+                    auditor
+                })
+                .collect();
+            AUDITORS.call_once(|| auditors);
+        }
+
+        for obj in audited_objects.iter() {
+            audit::objopen(obj);
+        }
+
         _r_debug.lock().state = RTLDState::RtConsistent;
         _dl_debug_state();
 
         Ok(loaded_dso)
     }
 
+    /// Checks that the dependencies of `obj` define the symbol versions it
+    /// requires.
+    fn check_versions(&self, obj: &DSO, new_objects: &[Arc<DSO>]) -> Result<()> {
+        for (file, versions) in obj.version_requirements() {
+            let dependency = new_objects
+                .iter()
+                .find(|dep| dep.name == *file)
+                .or_else(|| {
+                    self.name_to_object_id_map
+                        .get(*file)
+                        .and_then(|id| self.objects.get(id))
+                });
+            let Some(dependency) = dependency else {
+                continue;
+            };
+
+            for (version, weak) in versions {
+                if trace::enabled(DebugFlags::VERSIONS) {
+                    debug_println!(
+                        "[ld.so]: checking for version '{}' in file {} required by file {}",
+                        version.name,
+                        dependency.name,
+                        obj.name
+                    );
+                }
+
+                match dependency.defines_version(version) {
+                    Some(true) => {}
+                    Some(false) if *weak => {}
+                    Some(false) => {
+                        eprintln!(
+                            "[ld.so]: version '{}' not found (required by {})",
+                            version.name, obj.name
+                        );
+                        return Err(DlError::Malformed);
+                    }
+                    None => {
+                        if self
+                            .config
+                            .debug_flags
+                            .intersects(DebugFlags::LOAD | DebugFlags::VERSIONS)
+                        {
+                            debug_println!(
+                                "[ld.so]: {}: no version information available (required by {})",
+                                dependency.name,
+                                obj.name
+                            );
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     fn register_object(&mut self, obj: Arc<DSO>) {
         self.name_to_object_id_map.insert(obj.name.clone(), obj.id);
         self.objects.insert(obj.id, obj);
+        self.adds += 1;
     }
 
     /// Loads the specified object and all of its dependencies.
@@ -756,7 +1312,7 @@ impl Linker {
     fn load_objects_recursive(
         &mut self,
         name: &str,
-        parent_runpath: &Option<String>,
+        search_paths: &SearchPaths,
         base_addr: Option<usize>,
         dlopened: bool,
         new_objects: &mut Vec<Arc<DSO>>,
@@ -765,6 +1321,9 @@ impl Linker {
         // Scope of the object that caused this object to be loaded.
         dependent_scope: Option<&mut Scope>,
         scope_kind: ScopeKind,
+        // Whether this is the program, after which the preloaded libraries
+        // are loaded.
+        is_program: bool,
     ) -> Result<Arc<DSO>> {
         // fixme: double lookup slow
         if let Some(id) = self.name_to_object_id_map.get(name) {
@@ -795,7 +1354,7 @@ impl Linker {
 
         if name == "libc.so.6" || name == "libc.so" {
             if debug {
-                println!(
+                debug_println!(
                     "[ld.so]: loading libc.so.6 (aka. ld.so) at {:#?}",
                     self.me.base
                 );
@@ -836,7 +1395,7 @@ impl Linker {
             return Ok(obj);
         }
 
-        let path = self.search_object(name, parent_runpath)?;
+        let path = self.search_object(name, search_paths)?;
         let file = self.read_file(&path)?;
         let data = file.data();
         let (obj, tcb_master, elf) = DSO::new(
@@ -851,14 +1410,14 @@ impl Linker {
         )
         .map_err(|err| {
             if debug {
-                eprintln!("[ld.so]: failed to load '{}': {}", name, err)
+                debug_println!("[ld.so]: failed to load '{}': {}", name, err)
             }
 
             DlError::Malformed
         })?;
 
         if debug {
-            eprintln!(
+            debug_println!(
                 "[ld.so]: loading object: {} at {:#x}:{:#x} (pie: {})",
                 name,
                 obj.mmap.as_ref().unwrap().as_ptr() as usize,
@@ -878,7 +1437,7 @@ impl Linker {
             self.next_tls_module_id += 1;
         }
 
-        let runpath = obj.runpath().cloned();
+        let search_paths = search_paths.inherit(&obj);
         let dependencies = obj
             .dependencies()
             .iter()
@@ -897,10 +1456,39 @@ impl Linker {
             GLOBAL_SCOPE.write().add(&obj);
         }
 
+        // Preloaded libraries come right after the program in the global
+        // scope, so that their definitions interpose on those of all other
+        // libraries.
+        if is_program {
+            for preload in self.config.preload.clone() {
+                if self.search_object(&preload, &search_paths).is_err() {
+                    eprintln!(
+                        "[ld.so]: object '{}' from LD_PRELOAD cannot be preloaded: ignored",
+                        preload
+                    );
+                    continue;
+                }
+
+                self.load_objects_recursive(
+                    &preload,
+                    &search_paths,
+                    None,
+                    dlopened,
+                    new_objects,
+                    objects_data,
+                    tcb_masters,
+                    Some(&mut scope),
+                    scope_kind,
+                    false,
+                )?;
+            }
+        }
+
+        let mut dependency_ids = Vec::with_capacity(dependencies.len());
         for dep_name in dependencies.iter() {
-            self.load_objects_recursive(
+            let dep = self.load_objects_recursive(
                 dep_name,
-                &runpath,
+                &search_paths,
                 None,
                 dlopened,
                 new_objects,
@@ -908,8 +1496,11 @@ impl Linker {
                 tcb_masters,
                 Some(&mut scope),
                 scope_kind,
+                false,
             )?;
+            dependency_ids.push(dep.id);
         }
+        self.dependencies.insert(obj.id, dependency_ids);
 
         objects_data.push(Some(elf));
         new_objects.push(obj.clone());
@@ -920,54 +1511,119 @@ impl Linker {
         Ok(obj)
     }
 
-    fn search_object(&self, name: &str, parent_runpath: &Option<String>) -> Result<String> {
+    /// Searches for the library `name`, requested by an object with the given
+    /// search paths.
+    ///
+    /// As specified by the ELF gABI, names containing a slash are used as is.
+    /// Other names are looked up in the `DT_RPATH` chain (only if there is no
+    /// `DT_RUNPATH` entry), `LD_LIBRARY_PATH`, `DT_RUNPATH`, the library cache
+    /// and finally the system directories.
+    fn search_object(&self, name: &str, search_paths: &SearchPaths) -> Result<String> {
         let debug = self.config.debug_flags.contains(DebugFlags::SEARCH);
         if debug {
-            eprintln!("[ld.so]: looking for '{}'", name);
+            debug_println!("[ld.so]: looking for '{}'", name);
         }
 
-        let mut full_path = name.to_string();
-        if accessible(&full_path, F_OK).is_ok() {
-            if debug {
-                eprintln!("[ld.so]: found at '{}'!", full_path);
+        let found = |full_path: &str| {
+            let found = accessible(full_path, F_OK).is_ok();
+            if found && debug {
+                debug_println!("[ld.so]: found at '{}'!", full_path);
+            }
+            found
+        };
+
+        if name.contains('/') {
+            if found(name) {
+                return Ok(name.to_string());
             }
-            return Ok(full_path);
         } else {
-            let mut search_paths = Vec::new();
-            if let Some(runpath) = parent_runpath {
-                search_paths.extend(runpath.split(PATH_SEP));
+            for (dir, _) in self.path_dirs(search_paths) {
+                let full_path = format!("{}/{}", dir, name);
+                if debug {
+                    debug_println!("[ld.so]: trying path '{}'", full_path);
+                }
+                if found(&full_path) {
+                    return Ok(full_path);
+                }
             }
-            if let Some(ld_path) = self.config.library_path.as_ref() {
-                search_paths.extend(ld_path.split(PATH_SEP));
+
+            if let Some(full_path) = self
+                .config
+                .cache
+                .as_ref()
+                .and_then(|cache| cache.lookup(name))
+            {
+                if debug {
+                    debug_println!("[ld.so]: trying cached path '{}'", full_path);
+                }
+                if found(full_path) {
+                    return Ok(full_path.to_string());
+                }
             }
-            search_paths.push("/lib");
-            for part in search_paths.iter() {
-                full_path = format!("{}/{}", part, name);
+
+            for dir in self.config.system_dirs.iter() {
+                let full_path = format!("{}/{}", dir, name);
                 if debug {
-                    eprintln!("[ld.so]: trying path '{}'", full_path);
+                    debug_println!("[ld.so]: trying path '{}'", full_path);
                 }
-                if accessible(&full_path, F_OK).is_ok() {
-                    if debug {
-                        eprintln!("[ld.so]: found at '{}'!", full_path);
-                    }
+                if found(&full_path) {
                     return Ok(full_path);
                 }
             }
         }
 
         if debug {
-            eprintln!("[ld.so]: failed to locate '{}'", name);
+            debug_println!("[ld.so]: failed to locate '{}'", name);
         }
 
         Err(DlError::NotFound)
     }
 
+    /// Returns the directories named by the `DT_RPATH` chain (only if there is
+    /// no `DT_RUNPATH` entry), `LD_LIBRARY_PATH` and `DT_RUNPATH`, in search
+    /// order, along with the `LA_SER_*` flag of their origin.
+    fn path_dirs<'a>(&'a self, search_paths: &'a SearchPaths) -> Vec<(&'a str, c_uint)> {
+        let mut dirs = Vec::new();
+        if search_paths.runpath.is_none() {
+            for rpath in search_paths.rpath.iter() {
+                dirs.extend(rpath.split(PATH_SEP).map(|dir| (dir, LA_SER_RUNPATH)));
+            }
+        }
+        if let Some(ld_path) = self.config.library_path.as_ref() {
+            dirs.extend(ld_path.split(PATH_SEP).map(|dir| (dir, LA_SER_LIBPATH)));
+        }
+        if let Some(runpath) = search_paths.runpath.as_ref() {
+            dirs.extend(runpath.split(PATH_SEP).map(|dir| (dir, LA_SER_RUNPATH)));
+        }
+        dirs.retain(|(dir, _)| !dir.is_empty());
+        dirs
+    }
+
+    /// Returns the directories searched for the libraries that `obj` opens,
+    /// along with the `LA_SER_*` flag of their origin, for `dlinfo`. Libraries
+    /// found through the cache are also in the system directories.
+    pub fn search_dirs(&self, obj: &DSO) -> Vec<(String, c_uint)> {
+        let search_paths = self.object_search_paths(obj);
+        let mut dirs: Vec<_> = self
+            .path_dirs(&search_paths)
+            .into_iter()
+            .map(|(dir, flags)| (dir.to_string(), flags))
+            .collect();
+        dirs.extend(
+            self.config
+                .system_dirs
+                .iter()
+                .map(|dir| (dir.clone(), LA_SER_DEFAULT)),
+        );
+        dirs
+    }
+
     fn read_file(&self, path: &str) -> Result<MmapFile> {
         let debug = self.config.debug_flags.contains(DebugFlags::SEARCH);
 
         let path_c = CString::new(path).map_err(|err| {
             if debug {
-                eprintln!("[ld.so]: invalid path '{}': {}", path, err)
+                debug_println!("[ld.so]: invalid path '{}': {}", path, err)
             }
 
             DlError::NotFound
@@ -977,7 +1633,7 @@ impl Linker {
             let flags = fcntl::O_RDONLY | fcntl::O_CLOEXEC;
             MmapFile::open(CStr::borrow(&path_c), flags).map_err(|err| {
                 if debug {
-                    eprintln!("[ld.so]: failed to open '{}': {}", path, err)
+                    debug_println!("[ld.so]: failed to open '{}': {}", path, err)
                 }
 
                 DlError::NotFound
@@ -990,7 +1646,7 @@ impl Linker {
     fn run_init(&self, obj: &DSO) {
         use crate::platform::{self, types::*};
 
-        if let Some((symbol, SymbolBinding::Global)) = obj.get_sym("__relibc_init_environ") {
+        if let Some((symbol, SymbolBinding::Global)) = obj.get_sym("__relibc_init_environ", None) {
             unsafe {
                 symbol
                     .as_ptr()
@@ -1004,12 +1660,12 @@ impl Linker {
 
     fn scope_debug(&self) {
         if self.config.debug_flags.contains(DebugFlags::SCOPES) {
-            println!("[ld.so]: =========== SCOPES ==========");
+            debug_println!("[ld.so]: =========== SCOPES ==========");
             GLOBAL_SCOPE.read().debug();
             for obj in self.objects.values() {
                 obj.scope().debug();
             }
-            println!("[ld.so]: ==============================");
+            debug_println!("[ld.so]: ==============================");
         }
     }
 }
@@ -1032,10 +1688,8 @@ extern "C" fn __plt_resolve_inner(obj: *const DSO, relocation_index: c_uint) ->
     let rela = unsafe { &*(jmprel as *const Rela).add(relocation_index as usize) };
     assert_eq!(rela.r_type(NativeEndian, false), elf::R_X86_64_JUMP_SLOT);
 
-    let sym = obj
-        .dynamic
-        .symbol(rela.symbol(NativeEndian, false).unwrap())
-        .expect("symbol not found");
+    let symbol_index = rela.symbol(NativeEndian, false).unwrap();
+    let sym = obj.dynamic.symbol(symbol_index).expect("symbol not found");
     assert_ne!(sym.st_name(NativeEndian), 0);
 
     let name = core::str::from_utf8(
@@ -1046,10 +1700,18 @@ extern "C" fn __plt_resolve_inner(obj: *const DSO, relocation_index: c_uint) ->
     )
     .expect("non utf8 symbol name");
 
-    let resolved = resolve_sym(name, &[&GLOBAL_SCOPE.read(), obj.scope()])
-        .map(|(sym, _, _)| sym)
-        .unwrap_or_else(|| panic!("symbol '{name}' not found"))
-        .as_ptr();
+    let version = obj.dynamic.versions.required(symbol_index);
+    let resolved = resolve_sym(
+        name,
+        version.as_ref(),
+        &obj.lookup_scopes(&GLOBAL_SCOPE.read()),
+    )
+    .map(|(sym, _, def_obj)| {
+        obj.record_binding(&def_obj, name, version.as_ref());
+        audit::symbind(obj, &def_obj, &sym) as *mut c_void
+    })
+    .unwrap_or_else(|| panic!("symbol '{name}' not found"));
+    trace::LAZY_RELOCATIONS.fetch_add(1, core::sync::atomic::Ordering::Relaxed);
 
     let ptr = if obj.pie {
         (obj_base as u64 + rela.r_offset(NativeEndian)) as *mut u64
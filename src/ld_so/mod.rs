@@ -4,14 +4,17 @@
 // dropped from src/lib.rs.
 #![warn(warnings, unused_variables)]
 
-use core::{mem, ptr};
+use core::{mem, ptr, slice, sync::atomic::AtomicBool};
 use object::{
-    Endianness,
+    Endianness, NativeEndian,
     elf::{self, ProgramHeader32, ProgramHeader64},
     read::elf::ProgramHeader,
 };
 
-use self::tcb::{Master, Tcb};
+use self::{
+    dso::ProgramHeader as NativeProgramHeader,
+    tcb::{Master, Tcb},
+};
 use crate::{
     header::sys_auxv::{AT_NULL, AT_PHDR, AT_PHENT, AT_PHNUM},
     platform::{Pal, Sys},
@@ -21,19 +24,48 @@ use crate::{
 pub const PATH_SEP: char = ':';
 
 mod access;
+mod audit;
+mod cache;
 pub mod debug;
 pub mod dso;
+pub mod ifunc;
 pub mod linker;
 pub mod start;
 pub mod tcb;
+pub mod trace;
 
 static mut STATIC_TCB_MASTER: Master = Master {
     ptr: ptr::null_mut(),
     image_size: 0,
     segment_size: 0,
     offset: 0,
+    align: 0,
+    dynamic: false,
+    unloaded: AtomicBool::new(false),
 };
 
+/// Program headers of the statically linked program, as found in the
+/// auxiliary vector.
+static mut STATIC_PHDRS: &[NativeProgramHeader] = &[];
+
+/// Returns the program headers of the program if it is statically linked, or
+/// an empty slice otherwise.
+pub fn static_phdrs() -> &'static [NativeProgramHeader] {
+    unsafe { STATIC_PHDRS }
+}
+
+/// Returns the difference between the addresses in memory and the virtual
+/// addresses in the file of the statically linked program.
+pub fn static_load_bias() -> usize {
+    let phdrs = static_phdrs();
+    phdrs
+        .iter()
+        .find(|ph| ph.p_type(NativeEndian) == elf::PT_PHDR)
+        .map_or(0, |ph| {
+            phdrs.as_ptr() as usize - ph.p_vaddr(NativeEndian) as usize
+        })
+}
+
 #[inline(never)]
 fn static_init(
     sp: &'static Stack,
@@ -67,6 +99,10 @@ fn static_init(
     let phent = phent_opt.expect("failed to find AT_PHENT");
     let phnum = phnum_opt.expect("failed to find AT_PHNUM");
 
+    if phent == mem::size_of::<NativeProgramHeader>() {
+        unsafe { STATIC_PHDRS = slice::from_raw_parts(phdr as *const NativeProgramHeader, phnum) };
+    }
+
     for i in 0..phnum {
         let ph_addr = phdr + phent * i;
         let endian = Endianness::default();
@@ -111,6 +147,7 @@ fn static_init(
                 STATIC_TCB_MASTER.ptr = p_vaddr as *const u8;
                 STATIC_TCB_MASTER.image_size = p_filesz;
                 STATIC_TCB_MASTER.offset = valign;
+                STATIC_TCB_MASTER.align = p_align;
 
                 let tcb = Tcb::new(vsize).expect("failed to allocate TCB");
                 tcb.masters_ptr = ptr::addr_of_mut!(STATIC_TCB_MASTER);
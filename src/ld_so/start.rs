@@ -16,19 +16,23 @@ use object::{
 };
 
 use crate::{
-    c_str::CStr,
+    c_str::{CStr, CString},
     header::{
-        elf::{AT_BASE, AT_ENTRY, AT_PHDR, AT_PHENT, AT_PHNUM},
-        unistd,
+        elf::{AT_BASE, AT_ENTRY, AT_PHDR, AT_PHENT, AT_PHNUM, AT_SECURE},
+        fcntl, unistd,
     },
     ld_so::{
         dso::{
-            DT_RELR, DT_RELRENT, DT_RELRSZ, Dyn, FileHeader, ProgramHeader, Rel, Rela, Relocation,
-            RelocationKind, Relr, apply_relr,
+            DT_RELR, DT_RELRENT, DT_RELRSZ, Dyn, ElfFile, FileHeader, ProgramHeader, Rel, Rela,
+            Relocation, RelocationKind, Relr, apply_relr,
         },
         linker::{DebugFlags, Me},
     },
-    platform::{auxv_iter, get_auxvs, logger::RELIBC_LOG_ENV_VAR_STR, types::c_char},
+    platform::{
+        auxv_iter, get_auxv, get_auxvs,
+        logger::RELIBC_LOG_ENV_VAR_STR,
+        types::{c_char, c_int},
+    },
     start::Stack,
     sync::mutex::Mutex,
 };
@@ -36,9 +40,12 @@ use crate::{
 use super::{
     PATH_SEP,
     access::accessible,
+    cache,
     debug::_r_debug,
-    linker::{Config, Linker},
+    ifunc,
+    linker::{Config, Linker, MmapFile},
     tcb::Tcb,
+    trace::{self, debug_println},
 };
 
 unsafe fn get_argv(mut ptr: *const usize) -> (Vec<String>, *const usize) {
@@ -78,47 +85,179 @@ unsafe fn get_env(mut ptr: *const usize) -> (BTreeMap<String, String>, *const us
     (envs, ptr)
 }
 
+/// Removes the first `count` arguments from the stack, moving the remaining
+/// arguments, the environment and the auxiliary vector down.
 #[expect(unsafe_op_in_unsafe_fn)]
-unsafe fn adjust_stack(sp: &'static mut Stack) {
-    let mut argv = sp.argv() as *mut usize;
-
-    // Move arguments
-    loop {
-        let next_argv = argv.add(1);
-        let arg = *next_argv;
-        *argv = arg;
-        argv = next_argv;
-        if arg == 0 {
-            break;
+unsafe fn adjust_stack(sp: &mut Stack, count: usize) {
+    let mut dst = sp.argv() as *mut usize;
+    let mut src = dst.add(count);
+
+    // Move arguments, then environment
+    for _ in 0..2 {
+        loop {
+            let arg = *src;
+            *dst = arg;
+            src = src.add(1);
+            dst = dst.add(1);
+            if arg == 0 {
+                break;
+            }
         }
     }
 
-    // Move environment
+    // Move auxiliary vectors
     loop {
-        let next_argv = argv.add(1);
-        let arg = *next_argv;
-        *argv = arg;
-        argv = next_argv;
-        if arg == 0 {
+        let kind = *src;
+        let value = *src.add(1);
+        *dst = kind;
+        *dst.add(1) = value;
+        src = src.add(2);
+        dst = dst.add(2);
+        if kind == 0 {
             break;
         }
     }
+    sp.argc -= count as isize;
+}
 
-    // Move auxiliary vectors
-    loop {
-        let next_argv = argv.add(1);
-        let kind = *next_argv;
-        *argv = kind;
-        argv = next_argv;
-        let next_argv = argv.add(1);
-        let value = *next_argv;
-        *argv = value;
-        argv = next_argv;
-        if kind == 0 {
-            break;
+/// What ld.so does when run as a command.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Mode {
+    /// Run the program.
+    #[default]
+    Run,
+    /// List the dependencies of the program.
+    List,
+    /// Check whether the program can be run.
+    Verify,
+    /// Regenerate the library cache.
+    UpdateCache,
+}
+
+/// Command-line options of ld.so.
+#[derive(Debug, Default)]
+struct Options {
+    mode: Mode,
+    /// Overrides `LD_LIBRARY_PATH`.
+    library_path: Option<String>,
+    /// Overrides `LD_PRELOAD`.
+    preload: Option<String>,
+    inhibit_cache: bool,
+    /// Index of the argument to pass to the program as `argv[0]`.
+    argv0: Option<usize>,
+}
+
+const USAGE: &str = "\
+usage: ld.so [options] program [arguments...]
+       ld.so --update-cache [cache]
+
+options:
+  --list                 list the dependencies of the program and exit
+  --verify               check whether the program is dynamically linked and exit
+  --library-path PATH    use PATH instead of LD_LIBRARY_PATH
+  --preload LIST         preload the libraries in LIST, instead of LD_PRELOAD
+  --inhibit-cache        do not use /etc/ld.so.cache
+  --argv0 STRING         set argv[0] of the program to STRING
+  --help                 display this help and exit";
+
+/// Parses the options in `argv`, returning them along with the index of the
+/// first non-option argument.
+fn parse_options(argv: &[String]) -> Result<(Options, usize), String> {
+    let mut options = Options::default();
+    let mut i = 1;
+
+    while let Some(arg) = argv.get(i) {
+        let mut value = || {
+            i += 1;
+            argv.get(i)
+                .cloned()
+                .ok_or_else(|| format!("option '{arg}' requires an argument"))
+        };
+
+        match arg.as_str() {
+            "--list" => options.mode = Mode::List,
+            "--verify" => options.mode = Mode::Verify,
+            "--update-cache" => options.mode = Mode::UpdateCache,
+            "--library-path" => options.library_path = Some(value()?),
+            "--preload" => options.preload = Some(value()?),
+            "--inhibit-cache" => options.inhibit_cache = true,
+            "--argv0" => {
+                value()?;
+                options.argv0 = Some(i);
+            }
+            "--help" => {
+                println!("{USAGE}");
+                unistd::_exit(0);
+            }
+            "--" => {
+                i += 1;
+                break;
+            }
+            _ if arg.starts_with("--") => return Err(format!("unrecognized option '{arg}'")),
+            _ => break,
         }
+        i += 1;
+    }
+
+    Ok((options, i))
+}
+
+/// Checks whether the file at `path` is a program this dynamic linker can run.
+/// Returns 0 if it is dynamically linked, 1 if it is statically linked and 2
+/// if it cannot be run at all.
+///
+/// Programs are dynamically linked if they request an interpreter or have
+/// dependencies. A `PT_DYNAMIC` segment alone is not enough, as static PIE
+/// programs have one for their own relocations.
+fn verify(path: &str) -> c_int {
+    #[cfg(target_arch = "aarch64")]
+    const MACHINE: u16 = elf::EM_AARCH64;
+    #[cfg(target_arch = "riscv64")]
+    const MACHINE: u16 = elf::EM_RISCV;
+    #[cfg(target_arch = "x86")]
+    const MACHINE: u16 = elf::EM_386;
+    #[cfg(target_arch = "x86_64")]
+    const MACHINE: u16 = elf::EM_X86_64;
+
+    let Ok(path) = CString::new(path) else {
+        return 2;
+    };
+    let Ok(file) = MmapFile::open(CStr::borrow(&path), fcntl::O_RDONLY | fcntl::O_CLOEXEC) else {
+        return 2;
+    };
+    let Ok(elf) = ElfFile::parse(file.data()) else {
+        return 2;
+    };
+
+    let header = elf.elf_header();
+    if header.e_machine(NativeEndian) != MACHINE
+        || !matches!(header.e_type(NativeEndian), elf::ET_EXEC | elf::ET_DYN)
+    {
+        return 2;
+    }
+
+    let data = file.data();
+    if elf
+        .elf_program_headers()
+        .iter()
+        .any(|ph| match ph.p_type(NativeEndian) {
+            elf::PT_INTERP => true,
+            PT_DYNAMIC => ph
+                .dynamic(NativeEndian, data)
+                .ok()
+                .flatten()
+                .is_some_and(|entries| {
+                    entries
+                        .iter()
+                        .any(|entry| entry.tag32(NativeEndian) == Some(elf::DT_NEEDED))
+                }),
+            _ => false,
+        })
+    {
+        0
+    } else {
+        1
     }
-    sp.argc -= 1;
 }
 
 fn resolve_path_name(
@@ -325,6 +464,9 @@ fn stage2(
     my_dyns: &'static [Dyn],
     my_phdrs: &'static [ProgramHeader],
 ) -> usize {
+    // Must happen before anything is allocated
+    unsafe { crate::platform::init_malloc_debug(sp.envp()) };
+
     // Setup TCB for ourselves.
     unsafe {
         #[cfg(target_os = "redox")]
@@ -383,6 +525,8 @@ fn stage2(
         }
     }
 
+    trace::record_start();
+
     // We get the arguments, the environment, and the auxilary vector
     let (argv, envs, auxv) = unsafe {
         let argv_start = sp.argv() as *mut usize;
@@ -391,6 +535,7 @@ fn stage2(
         let auxv = get_auxvs(envs_end.add(1));
         (argv, envs, auxv)
     };
+    ifunc::init(&auxv);
 
     unsafe {
         crate::platform::OUR_ENVIRON.unsafe_set(
@@ -414,18 +559,50 @@ fn stage2(
     // we might need global lock for this kind of stuff
     _r_debug.lock().r_ldbase = self_base;
 
+    let mut options = Options::default();
     let name_or_path = if is_manual {
         // ld.so is run directly by user and not via execve() or similar systemcall
-        println!("argv: {:#?}", argv);
-        println!("envs: {:#?}", envs);
-        println!("auxv: {:#x?}", auxv);
+        let index;
+        (options, index) = parse_options(&argv).unwrap_or_else(|err| {
+            eprintln!("ld.so: {err}");
+            eprintln!("{USAGE}");
+            unistd::_exit(1);
+        });
+
+        if options.mode == Mode::UpdateCache {
+            let cache_path = argv
+                .get(index)
+                .map_or(cache::CACHE_PATH, |path| path.as_str());
+            match cache::update(cache::CONFIG_PATH, cache_path) {
+                Ok(count) => {
+                    eprintln!("[ld.so]: cached {count} libraries in '{cache_path}'");
+                    unistd::_exit(0);
+                }
+                Err(err) => {
+                    eprintln!("[ld.so]: failed to update '{cache_path}': {err}");
+                    unistd::_exit(1);
+                }
+            }
+        }
 
-        if sp.argc < 2 {
-            eprintln!("ld.so [executable] [arguments...]");
+        let Some(program) = argv.get(index) else {
+            eprintln!("{USAGE}");
             unistd::_exit(1);
+        };
+        if options.mode == Mode::Verify {
+            unistd::_exit(verify(program));
+        }
+
+        // The program only gets its own arguments, preceded by the value of
+        // `--argv0` if given.
+        unsafe {
+            let argv0 = options.argv0.map(|i| *sp.argv().add(i));
+            adjust_stack(sp, index);
+            if let Some(argv0) = argv0 {
+                sp.argv0 = argv0;
+            }
         }
-        unsafe { adjust_stack(sp) };
-        argv[1].clone()
+        program.clone()
     } else {
         argv[0].clone()
     };
@@ -438,6 +615,9 @@ fn stage2(
         }
     }
 
+    // Set-user-ID and similar programs run in secure-execution mode.
+    let secure = get_auxv(&auxv, AT_SECURE).is_some_and(|value| value != 0);
+
     // TODO: Fix memory leak, although minimal.
     #[cfg(target_os = "redox")]
     unsafe {
@@ -449,14 +629,19 @@ fn stage2(
         unistd::_exit(1);
     };
 
-    let config = Config::from_env(&envs);
-    if config.debug_flags.contains(DebugFlags::LOAD) {
-        println!("[ld.so]: relocated self at {self_base:#x}!");
-        if let Some(base_addr) = base_addr {
-            println!("[ld.so]: executable has been already loaded at {base_addr:#x?}");
+    let mut config = {
+        let mut envs = envs.clone();
+        if let Some(library_path) = options.library_path.take() {
+            envs.insert("LD_LIBRARY_PATH".to_string(), library_path);
+        }
+        if let Some(preload) = options.preload.take() {
+            envs.insert("LD_PRELOAD".to_string(), preload);
         }
+        Config::from_env(&envs, secure)
+    };
+    if options.inhibit_cache {
+        config.inhibit_cache();
     }
-
     let mut linker = Linker::new(
         Me {
             base: self_base as *const u8,
@@ -465,6 +650,22 @@ fn stage2(
         },
         config,
     );
+    if trace::enabled(DebugFlags::LOAD) {
+        debug_println!("[ld.so]: relocated self at {self_base:#x}!");
+        if let Some(base_addr) = base_addr {
+            debug_println!("[ld.so]: executable has been already loaded at {base_addr:#x?}");
+        }
+    }
+    if options.mode == Mode::List {
+        match linker.trace_program(&path) {
+            Ok(()) => unistd::_exit(0),
+            Err(err) => {
+                eprintln!("[ld.so]: failed to list dependencies of '{path}': {err:?}");
+                unistd::_exit(1);
+            }
+        }
+    }
+
     let entry = match linker.load_program(&path, base_addr) {
         Ok(entry) => entry,
         Err(err) => {
@@ -473,6 +674,7 @@ fn stage2(
             unistd::_exit(1);
         }
     };
+    trace::print_statistics();
     if let Some(tcb) = unsafe { Tcb::current() } {
         tcb.linker_ptr = Box::into_raw(Box::new(Mutex::new(linker)));
     }
@@ -1,17 +1,20 @@
-use alloc::vec::Vec;
+use alloc::{
+    alloc::{Layout, alloc, dealloc},
+    vec::Vec,
+};
 use core::{
     cell::UnsafeCell,
     mem,
     ops::{Deref, DerefMut},
     ptr, slice,
-    sync::atomic::AtomicBool,
+    sync::atomic::{AtomicBool, AtomicI32, AtomicUsize, Ordering},
 };
 use generic_rt::GenericTcb;
 
 use crate::{
-    header::sys_mman,
+    header::{sched::SCHED_OTHER, sys_mman},
     ld_so::linker::Linker,
-    platform::{Pal, Sys},
+    platform::{Pal, Sys, ThreadCache},
     pthread::{OsTid, Pthread},
     sync::{mutex::Mutex, waitval::Waitval},
 };
@@ -19,7 +22,7 @@ use crate::{
 use super::linker::DlError;
 
 #[repr(C)]
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct Master {
     /// Pointer to initial data
     pub ptr: *const u8,
@@ -28,15 +31,89 @@ pub struct Master {
     pub segment_size: usize,
     /// Offset in TLS to copy initial data to
     pub offset: usize,
+    /// Alignment of the TLS region, from `p_align`
+    pub align: usize,
+    /// Whether the TLS region is allocated on first use by `__tls_get_addr`,
+    /// instead of being part of the static TLS block. This is the case for
+    /// objects loaded with `dlopen`.
+    pub dynamic: bool,
+    /// Whether the object has been unloaded, in which case its dynamically
+    /// allocated TLS regions are freed.
+    ///
+    /// Set by `dlclose` on one thread and read by all the others when they
+    /// update their DTV, hence atomic.
+    pub unloaded: AtomicBool,
+}
+
+impl Clone for Master {
+    fn clone(&self) -> Self {
+        Self {
+            ptr: self.ptr,
+            image_size: self.image_size,
+            segment_size: self.segment_size,
+            offset: self.offset,
+            align: self.align,
+            dynamic: self.dynamic,
+            unloaded: AtomicBool::new(self.is_unloaded()),
+        }
+    }
 }
 
 impl Master {
+    /// Whether the object has been unloaded, see [`Master::unloaded`].
+    pub fn is_unloaded(&self) -> bool {
+        self.unloaded.load(Ordering::Acquire)
+    }
+
     /// The initial data for this TLS region
     pub unsafe fn data(&self) -> &'static [u8] {
         unsafe { slice::from_raw_parts(self.ptr, self.image_size) }
     }
+
+    /// Layout of a dynamically allocated TLS region.
+    fn layout(&self) -> Layout {
+        Layout::from_size_align(self.segment_size.max(1), self.align.max(1))
+            .expect("invalid TLS segment alignment")
+    }
+
+    /// Allocates and initializes a TLS region for a thread.
+    fn allocate(&self) -> *mut u8 {
+        unsafe {
+            let ptr = alloc(self.layout());
+            if ptr.is_null() {
+                return ptr;
+            }
+
+            ptr::copy_nonoverlapping(self.ptr, ptr, self.image_size);
+            ptr::write_bytes(
+                ptr.add(self.image_size),
+                0,
+                self.segment_size - self.image_size,
+            );
+            ptr
+        }
+    }
+}
+
+/// Generation of the list of TLS masters, incremented whenever objects with
+/// TLS are loaded or unloaded. A thread whose DTV is from an older generation
+/// updates it on its next call to `__tls_get_addr`.
+static TLS_GENERATION: AtomicUsize = AtomicUsize::new(0);
+
+/// The latest list of TLS masters, shared by all threads.
+static MASTERS: Mutex<MasterList> = Mutex::new(MasterList {
+    ptr: ptr::null_mut(),
+    len: 0,
+});
+
+struct MasterList {
+    ptr: *mut Master,
+    /// Size of the list in bytes, as in `Tcb::masters_len`.
+    len: usize,
 }
 
+unsafe impl Send for MasterList {}
+
 #[cfg(target_os = "linux")]
 pub type OsSpecific = ();
 
@@ -63,6 +140,10 @@ pub struct Tcb {
     pub dtv_ptr: *mut *mut u8,
     // Number of DTV entries.
     pub dtv_len: usize,
+    /// Generation of the TLS masters the DTV is up to date with.
+    pub dtv_generation: usize,
+    /// Small blocks freed by this thread, reused by `malloc()`.
+    pub alloc_cache: ThreadCache,
 }
 
 #[cfg(target_os = "redox")]
@@ -99,8 +180,12 @@ impl Tcb {
                 pthread: Pthread {
                     waitval: Waitval::new(),
                     flags: Default::default(),
-                    has_enabled_cancelation: AtomicBool::new(false),
+                    has_enabled_cancelation: AtomicBool::new(true),
                     has_queued_cancelation: AtomicBool::new(false),
+                    has_async_cancelation: AtomicBool::new(false),
+                    in_cancelation_point: AtomicBool::new(false),
+                    sched_policy: AtomicI32::new(SCHED_OTHER),
+                    sched_priority: AtomicI32::new(0),
                     stack_base: core::ptr::null_mut(),
                     stack_size: 0,
                     os_tid: UnsafeCell::new(OsTid::default()),
@@ -109,6 +194,8 @@ impl Tcb {
 
                 dtv_ptr: ptr::null_mut(),
                 dtv_len: 0,
+                dtv_generation: 0,
+                alloc_cache: ThreadCache::new(),
             },
         );
 
@@ -153,7 +240,7 @@ impl Tcb {
             && let Some(masters) = self.masters()
         {
             for (i, master) in masters.iter().skip(self.num_copied_masters).enumerate() {
-                if master.image_size == 0 {
+                if master.image_size == 0 || master.dynamic {
                     continue;
                 }
 
@@ -191,6 +278,14 @@ impl Tcb {
 
     /// The initial images for TLS
     pub unsafe fn append_masters(&mut self, mut new_masters: Vec<Master>) {
+        if new_masters.is_empty() {
+            return;
+        }
+
+        // Start from the latest list, which this thread may not have seen yet.
+        self.update_dtv();
+        let mut list = MASTERS.lock();
+
         if self.masters_ptr.is_null() {
             self.masters_ptr = new_masters.as_mut_ptr();
             self.masters_len = new_masters.len() * mem::size_of::<Master>();
@@ -205,6 +300,115 @@ impl Tcb {
             self.masters_len = masters.len() * mem::size_of::<Master>();
             mem::forget(masters);
         }
+
+        // Older lists are leaked, as other threads may still be using them.
+        list.ptr = self.masters_ptr;
+        list.len = self.masters_len;
+        self.dtv_generation = TLS_GENERATION.fetch_add(1, Ordering::AcqRel) + 1;
+    }
+
+    /// Marks the TLS master of the module `tls_module_id` as unloaded, so
+    /// that the TLS regions of all threads for it are freed.
+    pub fn unload_master(tls_module_id: usize) {
+        {
+            let list = MASTERS.lock();
+            let count = list.len / mem::size_of::<Master>();
+            if tls_module_id == 0 || tls_module_id > count {
+                return;
+            }
+
+            let master = unsafe { &*list.ptr.add(tls_module_id - 1) };
+            master.unloaded.store(true, Ordering::Release);
+            TLS_GENERATION.fetch_add(1, Ordering::AcqRel);
+        }
+
+        if let Some(tcb) = unsafe { Self::current() } {
+            tcb.update_dtv();
+        }
+    }
+
+    /// Brings the TLS masters and the DTV of this thread up to date with the
+    /// objects loaded and unloaded since the last update, freeing the TLS
+    /// regions of unloaded objects.
+    pub fn update_dtv(&mut self) {
+        if self.dtv_generation == TLS_GENERATION.load(Ordering::Acquire) {
+            return;
+        }
+
+        let list = MASTERS.lock();
+        // The list is only published by dynamically linked programs.
+        if !list.ptr.is_null() {
+            self.masters_ptr = list.ptr;
+            self.masters_len = list.len;
+        }
+        self.dtv_generation = TLS_GENERATION.load(Ordering::Acquire);
+
+        if let Some(masters) = self.masters() {
+            let dtv = self.dtv_mut();
+            for (entry, master) in dtv.iter_mut().zip(masters.iter()) {
+                if master.dynamic && master.is_unloaded() && !entry.is_null() {
+                    unsafe { dealloc(*entry, master.layout()) };
+                    *entry = ptr::null_mut();
+                }
+            }
+        }
+    }
+
+    /// Returns the TLS region of the module at `dtv_index` for this thread,
+    /// allocating it if needed. Returns null if the module has been unloaded.
+    pub fn tls_region(&mut self, dtv_index: usize) -> *mut u8 {
+        self.update_dtv();
+
+        let Some(masters) = self.masters() else {
+            return ptr::null_mut();
+        };
+        if self.dtv_mut().len() < masters.len() {
+            // Reallocate DTV.
+            self.setup_dtv(masters.len());
+        }
+
+        let Some(master) = masters.get(dtv_index) else {
+            return ptr::null_mut();
+        };
+        let entry = &mut self.dtv_mut()[dtv_index];
+        if entry.is_null() && master.dynamic && !master.is_unloaded() {
+            // Allocate TLS for module.
+            *entry = master.allocate();
+        }
+        *entry
+    }
+
+    /// Returns the TLS region of the module at `dtv_index` for this thread, or
+    /// null if it has not been allocated yet or the module has been unloaded.
+    pub fn allocated_tls_region(&mut self, dtv_index: usize) -> *mut u8 {
+        self.update_dtv();
+
+        let Some(masters) = self.masters() else {
+            return ptr::null_mut();
+        };
+        if self.dtv_mut().len() < masters.len() {
+            // The static TLS regions are only recorded in the DTV then.
+            self.setup_dtv(masters.len());
+        }
+        self.dtv_mut()
+            .get(dtv_index)
+            .copied()
+            .unwrap_or(ptr::null_mut())
+    }
+
+    /// Frees the dynamically allocated TLS regions of this thread, which is
+    /// exiting.
+    pub unsafe fn free_dynamic_tls(&mut self) {
+        let Some(masters) = self.masters() else {
+            return;
+        };
+
+        for (entry, master) in self.dtv_mut().iter_mut().zip(masters.iter()) {
+            if master.dynamic && !entry.is_null() {
+                unsafe { dealloc(*entry, master.layout()) };
+                *entry = ptr::null_mut();
+            }
+        }
     }
 
     /// Activate TLS
@@ -229,6 +433,10 @@ impl Tcb {
 
             if let Some(masters) = self.masters() {
                 for (i, master) in masters.iter().enumerate() {
+                    if master.dynamic {
+                        continue;
+                    }
+
                     let tls = unsafe { self.tls().unwrap() };
                     let offset = if cfg!(any(target_arch = "x86", target_arch = "x86_64")) {
                         // x86{_64} TLS layout is backwards
@@ -0,0 +1,115 @@
+//! Output of the `LD_DEBUG` traces.
+//!
+//! Traces are written to standard error, or to `<LD_DEBUG_OUTPUT>.<pid>` if
+//! `LD_DEBUG_OUTPUT` is set.
+
+use core::{
+    fmt::{self, Write},
+    sync::atomic::{AtomicI32, AtomicU32, AtomicU64, AtomicUsize, Ordering},
+};
+
+use alloc::format;
+
+use crate::{
+    c_str::{CStr, CString},
+    header::{bits_timespec::timespec, fcntl, time::constants::CLOCK_MONOTONIC},
+    out::Out,
+    platform::{FileWriter, Pal, Sys},
+};
+
+use super::linker::DebugFlags;
+
+static FLAGS: AtomicU32 = AtomicU32::new(0);
+static OUTPUT_FD: AtomicI32 = AtomicI32::new(2);
+
+/// Time ld.so started at, in nanoseconds.
+static START_TIME: AtomicU64 = AtomicU64::new(0);
+
+/// Number of relocations processed at load time, leaving out the PLT slots
+/// bound lazily.
+pub static RELOCATIONS: AtomicUsize = AtomicUsize::new(0);
+/// Number of relocations resolved lazily, on the first call through the PLT.
+pub static LAZY_RELOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// Prints a line to the `LD_DEBUG` output.
+macro_rules! debug_println {
+    ($($arg:tt)*) => {
+        $crate::ld_so::trace::print(format_args!($($arg)*))
+    };
+}
+pub(crate) use debug_println;
+
+fn now() -> u64 {
+    let mut time = timespec::default();
+    let _ = Sys::clock_gettime(CLOCK_MONOTONIC, Out::from_mut(&mut time));
+    time.tv_sec as u64 * 1_000_000_000 + time.tv_nsec as u64
+}
+
+/// Records the time ld.so started at, for the statistics.
+pub fn record_start() {
+    START_TIME.store(now(), Ordering::Relaxed);
+}
+
+/// Enables the traces in `flags`, written to `<output>.<pid>` if `output` is
+/// given. The output file is not opened through a symbolic link, which could
+/// otherwise redirect the traces to overwrite another file.
+pub fn init(flags: DebugFlags, output: Option<&str>) {
+    FLAGS.store(flags.bits(), Ordering::Relaxed);
+
+    if let Some(output) = output
+        && !flags.is_empty()
+    {
+        let path = format!("{}.{}", output, Sys::getpid());
+        let fd = CString::new(path).ok().and_then(|path| {
+            Sys::open(
+                CStr::borrow(&path),
+                fcntl::O_WRONLY
+                    | fcntl::O_CREAT
+                    | fcntl::O_TRUNC
+                    | fcntl::O_APPEND
+                    | fcntl::O_CLOEXEC
+                    | fcntl::O_NOFOLLOW,
+                0o666,
+            )
+            .ok()
+        });
+        match fd {
+            Some(fd) => OUTPUT_FD.store(fd, Ordering::Relaxed),
+            None => eprintln!("[ld.so]: cannot open debug output '{output}': ignored"),
+        }
+    }
+}
+
+/// Returns whether the traces in `flag` are enabled.
+#[inline]
+pub fn enabled(flag: DebugFlags) -> bool {
+    DebugFlags::from_bits_retain(FLAGS.load(Ordering::Relaxed)).contains(flag)
+}
+
+pub fn print(args: fmt::Arguments) {
+    let mut writer = FileWriter::new(OUTPUT_FD.load(Ordering::Relaxed));
+    let _ = writer.write_fmt(format_args!("{}\n", args));
+}
+
+/// Prints the `statistics` trace, once the program has been loaded.
+pub fn print_statistics() {
+    if !enabled(DebugFlags::STATISTICS) {
+        return;
+    }
+
+    let elapsed = now().saturating_sub(START_TIME.load(Ordering::Relaxed));
+    debug_println!("[ld.so]: runtime linker statistics:");
+    debug_println!(
+        "[ld.so]:   total startup time in dynamic loader: {}.{:03} us",
+        elapsed / 1000,
+        elapsed % 1000
+    );
+    debug_println!(
+        "[ld.so]:             number of relocations: {}",
+        RELOCATIONS.load(Ordering::Relaxed)
+    );
+    debug_println!(
+        "[ld.so]:        number of lazy relocations: {}",
+        LAZY_RELOCATIONS.load(Ordering::Relaxed)
+    );
+}
@@ -0,0 +1,244 @@
+//! Debugging mode of the allocator, to diagnose heap corruption.
+//!
+//! Enabled by setting `MALLOC_CHECK_` to a nonzero value, or
+//! `RELIBC_MALLOC_DEBUG` to anything but `0`. Every block is then surrounded
+//! by a header and a redzone:
+//!
+//! ```text
+//! | padding | Header | data (size bytes) | redzone |
+//!                    ^ pointer returned to the caller
+//! ```
+//!
+//! Invalid and double frees, and writes past either end of a block, are
+//! detected when the block is freed or reallocated, and abort the program
+//! with the address of the block. Freed blocks are filled with
+//! [`POISON`]. If `RELIBC_MALLOC_QUARANTINE` is set, that many freed blocks
+//! are kept aside before being reused, so that writes after free are detected
+//! as well.
+
+use core::{
+    fmt::Write,
+    mem::{align_of, size_of},
+    ptr, slice,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::{
+    c_str::CStr,
+    header::stdlib::abort,
+    platform::{
+        FileWriter,
+        types::{c_char, max_align_t},
+    },
+    sync::Mutex,
+};
+
+use super::Allocator;
+
+/// Byte written over freed blocks.
+pub const POISON: u8 = 0xdf;
+/// Byte written in the redzone after each block.
+const REDZONE_BYTE: u8 = 0xfe;
+const REDZONE: usize = 16;
+
+// Combined with the address of the block, so that the header of one block
+// cannot be mistaken for the header of another.
+const ALLOCATED: usize = 0xa110_ca7e_d0d0_cafe_u64 as usize;
+const FREED: usize = 0xf4ee_d0d0_dead_beef_u64 as usize;
+const CANARY: usize = 0xca4a_4ee5_0b5e_55ed_u64 as usize;
+
+/// Maximum number of blocks in quarantine.
+const MAX_QUARANTINE: usize = 4096;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+#[repr(C)]
+struct Header {
+    /// Start of the underlying block.
+    raw: *mut u8,
+    size: usize,
+    state: usize,
+    /// Last, so that writes just before the block are detected.
+    canary: usize,
+}
+
+struct Quarantine {
+    blocks: [usize; MAX_QUARANTINE],
+    start: usize,
+    len: usize,
+    capacity: usize,
+}
+
+static QUARANTINE: Mutex<Quarantine> = Mutex::new(Quarantine {
+    blocks: [0; MAX_QUARANTINE],
+    start: 0,
+    len: 0,
+    capacity: 0,
+});
+
+#[inline]
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Enables the debugging mode if requested by the environment `envp`.
+///
+/// Blocks allocated before cannot be checked, so this must be called before
+/// the first allocation.
+pub unsafe fn init(mut envp: *const *const c_char) {
+    let mut enabled = false;
+    let mut quarantine = 0;
+
+    while !unsafe { *envp }.is_null() {
+        let var = unsafe { CStr::from_ptr(*envp) }.to_bytes();
+        let (name, value) = match var.iter().position(|&b| b == b'=') {
+            Some(i) => (&var[..i], &var[i + 1..]),
+            None => (var, &[][..]),
+        };
+        let number = || {
+            core::str::from_utf8(value)
+                .ok()
+                .and_then(|value| value.parse::<usize>().ok())
+        };
+        match name {
+            b"MALLOC_CHECK_" => enabled |= number().is_some_and(|value| value != 0),
+            b"RELIBC_MALLOC_DEBUG" => enabled |= value != b"0",
+            b"RELIBC_MALLOC_QUARANTINE" => quarantine = number().unwrap_or(0),
+            _ => (),
+        }
+        envp = unsafe { envp.add(1) };
+    }
+
+    if enabled {
+        QUARANTINE.lock().capacity = quarantine.min(MAX_QUARANTINE);
+        ENABLED.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Reports heap corruption at `ptr` and aborts.
+fn report(function: &str, problem: &str, ptr: *mut u8) -> ! {
+    let _ = FileWriter::new(2).write_fmt(format_args!("relibc: {function}: {problem}: {ptr:p}\n"));
+    unsafe { abort() }
+}
+
+fn header(ptr: *mut u8) -> *mut Header {
+    ptr.wrapping_sub(size_of::<Header>()).cast()
+}
+
+pub unsafe fn alloc(allocator: &Allocator, size: usize, align: usize) -> *mut u8 {
+    let align = align.max(align_of::<max_align_t>());
+    let prefix = size_of::<Header>().next_multiple_of(align);
+    let Some(total) = prefix
+        .checked_add(size)
+        .and_then(|total| total.checked_add(REDZONE))
+    else {
+        return ptr::null_mut();
+    };
+
+    let raw = unsafe { allocator.alloc_raw(total, align) };
+    if raw.is_null() {
+        return raw;
+    }
+
+    let ptr = unsafe { raw.add(prefix) };
+    unsafe {
+        header(ptr).write(Header {
+            raw,
+            size,
+            state: ptr as usize ^ ALLOCATED,
+            canary: ptr as usize ^ CANARY,
+        });
+        ptr.add(size).write_bytes(REDZONE_BYTE, REDZONE);
+    }
+    ptr
+}
+
+/// Checks that `ptr` is an intact block, returning its header.
+unsafe fn check(function: &str, ptr: *mut u8) -> &'static mut Header {
+    if ptr as usize % align_of::<max_align_t>() != 0 {
+        report(function, "invalid pointer", ptr);
+    }
+
+    let header = unsafe { &mut *header(ptr) };
+    if header.state == ptr as usize ^ FREED {
+        report(function, "double free detected", ptr);
+    }
+    if header.state != ptr as usize ^ ALLOCATED {
+        report(function, "invalid pointer", ptr);
+    }
+    if header.canary != ptr as usize ^ CANARY {
+        report(
+            function,
+            "write before the start of the block detected",
+            ptr,
+        );
+    }
+    let redzone = unsafe { slice::from_raw_parts(ptr.add(header.size), REDZONE) };
+    if redzone.iter().any(|&b| b != REDZONE_BYTE) {
+        report(function, "write past the end of the block detected", ptr);
+    }
+    header
+}
+
+pub unsafe fn free(allocator: &Allocator, ptr: *mut u8) {
+    let header = unsafe { check("free()", ptr) };
+    unsafe { release(allocator, ptr, header) };
+}
+
+unsafe fn release(allocator: &Allocator, ptr: *mut u8, header: &mut Header) {
+    header.state = ptr as usize ^ FREED;
+    unsafe { ptr.write_bytes(POISON, header.size) };
+
+    let mut quarantine = QUARANTINE.lock();
+    if quarantine.capacity == 0 {
+        drop(quarantine);
+        unsafe { allocator.free(header.raw) };
+        return;
+    }
+
+    let evicted = if quarantine.len == quarantine.capacity {
+        let start = quarantine.start;
+        quarantine.start = (start + 1) % quarantine.capacity;
+        quarantine.len -= 1;
+        Some(quarantine.blocks[start] as *mut u8)
+    } else {
+        None
+    };
+    let end = (quarantine.start + quarantine.len) % quarantine.capacity;
+    quarantine.blocks[end] = ptr as usize;
+    quarantine.len += 1;
+    drop(quarantine);
+
+    if let Some(evicted) = evicted {
+        let header = unsafe { &*self::header(evicted) };
+        let data = unsafe { slice::from_raw_parts(evicted, header.size) };
+        if header.state != evicted as usize ^ FREED || data.iter().any(|&b| b != POISON) {
+            report("free()", "write after free detected", evicted);
+        }
+        unsafe { allocator.free(header.raw) };
+    }
+}
+
+pub unsafe fn realloc(allocator: &Allocator, ptr: *mut u8, size: usize, align: usize) -> *mut u8 {
+    let header = unsafe { check("realloc()", ptr) };
+    let new = unsafe { alloc(allocator, size, align) };
+    if !new.is_null() {
+        unsafe { ptr::copy_nonoverlapping(ptr, new, header.size.min(size)) };
+        unsafe { release(allocator, ptr, header) };
+    }
+    new
+}
+
+pub unsafe fn usable_size(ptr: *mut u8) -> usize {
+    unsafe { check("malloc_usable_size()", ptr) }.size
+}
+
+/// Takes the quarantine lock before `fork()`.
+pub fn fork_prepare() {
+    unsafe { QUARANTINE.manual_lock() };
+}
+
+/// Releases the quarantine lock after `fork()`.
+pub fn fork_release() {
+    unsafe { QUARANTINE.manual_unlock() };
+}
@@ -0,0 +1,151 @@
+//! Blocks of at least `M_MMAP_THRESHOLD` bytes, mapped on their own rather
+//! than allocated from dlmalloc, so that freeing them returns the memory to
+//! the system right away, as in glibc.
+//!
+//! The three words in front of a block hold the offset of the block in its
+//! mapping, its usable size and a tag derived from its address. dlmalloc
+//! keeps the head word of a chunk right before the block: for chunks in its
+//! heap, the `CINUSE` bit is set, and for the chunks it maps itself (above its
+//! own threshold, or when `M_MMAP_MAX` stops this module), it is the size of
+//! the chunk, a multiple of 8. The tag has the `INUSE_BITS` clear and bit 2
+//! set, so it matches neither.
+
+use core::{
+    mem::{align_of, size_of},
+    ptr::{self, copy_nonoverlapping},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use super::options;
+use crate::{
+    header::sys_mman::{MAP_ANON, MAP_PRIVATE, MREMAP_MAYMOVE, PROT_READ, PROT_WRITE},
+    platform::{
+        Pal, Sys,
+        types::{max_align_t, size_t},
+    },
+};
+
+/// Bytes in front of each block.
+const HEADER: usize = 3 * size_of::<usize>();
+/// Low bits of the head word of dlmalloc chunks in use, either of which is
+/// set for chunks in its heap.
+const INUSE_BITS: usize = 3;
+/// Mixed into the address of a block to make its tag.
+const MAGIC: usize = 0x6c61_7267_65b1_0c4b_u64 as usize;
+
+/// Number of mapped blocks.
+static COUNT: AtomicUsize = AtomicUsize::new(0);
+/// Bytes of the mappings of the blocks, including their headers.
+static BYTES: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns whether a block of `size` bytes is to be mapped on its own.
+pub(super) fn wants(size: size_t) -> bool {
+    size >= options::mmap_threshold() && COUNT.load(Ordering::Relaxed) < options::mmap_max()
+}
+
+/// Returns the tag written right before the block `ptr`.
+fn tag(ptr: *mut u8) -> usize {
+    (ptr as usize ^ MAGIC) & !INUSE_BITS | 4
+}
+
+/// Returns whether `ptr` is a mapped block rather than a dlmalloc block.
+pub(super) unsafe fn owns(ptr: *mut u8) -> bool {
+    unsafe { ptr.cast::<usize>().sub(1).read() == tag(ptr) }
+}
+
+/// Returns the number of mapped blocks and the bytes of their mappings.
+pub(super) fn stats() -> (usize, usize) {
+    (COUNT.load(Ordering::Relaxed), BYTES.load(Ordering::Relaxed))
+}
+
+fn page_round(size: usize) -> Option<usize> {
+    let page = Sys::getpagesize();
+    Some(size.checked_add(page - 1)? & !(page - 1))
+}
+
+/// Returns the mapping of the block `ptr` and its length.
+unsafe fn mapping(ptr: *mut u8) -> (*mut u8, usize) {
+    let offset = unsafe { ptr.cast::<usize>().sub(3).read() };
+    (
+        unsafe { ptr.sub(offset) },
+        offset + unsafe { usable_size(ptr) },
+    )
+}
+
+/// Writes the header of the block at `offset` in `map` of `len` bytes.
+unsafe fn init(map: *mut u8, offset: usize, len: usize) -> *mut u8 {
+    unsafe {
+        let ptr = map.add(offset);
+        ptr.cast::<usize>().sub(3).write(offset);
+        ptr.cast::<usize>().sub(2).write(len - offset);
+        ptr.cast::<usize>().sub(1).write(tag(ptr));
+        ptr
+    }
+}
+
+/// Maps a block of `size` bytes aligned to `align`.
+pub(super) unsafe fn alloc(size: size_t, align: size_t) -> *mut u8 {
+    let align = align.max(align_of::<max_align_t>());
+    // The block starts less than `HEADER + align` bytes into the mapping.
+    let Some(len) = HEADER
+        .checked_add(align)
+        .and_then(|extra| size.checked_add(extra))
+        .and_then(page_round)
+    else {
+        return ptr::null_mut();
+    };
+    let Ok(map) = (unsafe {
+        Sys::mmap(
+            ptr::null_mut(),
+            len,
+            PROT_READ | PROT_WRITE,
+            MAP_ANON | MAP_PRIVATE,
+            -1,
+            0,
+        )
+    }) else {
+        return ptr::null_mut();
+    };
+    let map = map.cast::<u8>();
+    let offset = (map as usize + HEADER).next_multiple_of(align) - map as usize;
+    COUNT.fetch_add(1, Ordering::Relaxed);
+    BYTES.fetch_add(len, Ordering::Relaxed);
+    unsafe { init(map, offset, len) }
+}
+
+pub(super) unsafe fn free(ptr: *mut u8) {
+    let (map, len) = unsafe { mapping(ptr) };
+    COUNT.fetch_sub(1, Ordering::Relaxed);
+    BYTES.fetch_sub(len, Ordering::Relaxed);
+    let _ = unsafe { Sys::munmap(map.cast(), len) };
+}
+
+/// Resizes the block `ptr` with `mremap()`, or by copying it to a new block
+/// where the mapping cannot be moved.
+pub(super) unsafe fn realloc(ptr: *mut u8, size: size_t) -> *mut u8 {
+    let (map, len) = unsafe { mapping(ptr) };
+    let offset = len - unsafe { usable_size(ptr) };
+    let Some(new_len) = size.checked_add(offset).and_then(page_round) else {
+        return ptr::null_mut();
+    };
+    if let Ok(new_map) =
+        unsafe { Sys::mremap(map.cast(), len, new_len, MREMAP_MAYMOVE, ptr::null_mut()) }
+    {
+        BYTES.fetch_sub(len, Ordering::Relaxed);
+        BYTES.fetch_add(new_len, Ordering::Relaxed);
+        return unsafe { init(new_map.cast(), offset, new_len) };
+    }
+
+    let new = unsafe { alloc(size, align_of::<max_align_t>()) };
+    if !new.is_null() {
+        unsafe {
+            copy_nonoverlapping(ptr, new, usable_size(ptr).min(size));
+            free(ptr);
+        }
+    }
+    new
+}
+
+pub(super) unsafe fn usable_size(ptr: *mut u8) -> size_t {
+    unsafe { ptr.cast::<usize>().sub(2).read() }
+}
@@ -4,13 +4,30 @@ use core::{
     cmp,
     mem::align_of,
     ptr::{copy_nonoverlapping, write_bytes},
+    sync::atomic::{AtomicUsize, Ordering},
 };
 
+mod debug;
+mod large;
+mod options;
 mod sys;
+mod thread_cache;
+mod trace;
 use super::types::*;
-use crate::{ALLOCATOR, sync::Mutex};
+use crate::{
+    ALLOCATOR,
+    sync::{Mutex, MutexGuard},
+};
 use dlmalloc::DlmallocCApi;
 
+pub use self::{
+    options::{init_malloc_options, set_malloc_option},
+    thread_cache::ThreadCache,
+    trace::{
+        start_alloc_trace, stop_alloc_trace, trace_alloc, trace_free, trace_realloc, traced_size,
+    },
+};
+
 pub type Dlmalloc = DlmallocCApi<sys::System>;
 
 #[expect(clippy::declare_interior_mutable_const)]
@@ -29,15 +46,213 @@ impl Allocator {
     pub fn get(&self) -> *const Mutex<Dlmalloc> {
         self.0.get()
     }
+
+    fn lock(&self) -> Heap<'_> {
+        Heap(unsafe { (*self.get()).lock() })
+    }
+
+    /// Allocates a block, bypassing the debugging mode.
+    unsafe fn alloc_raw(&self, size: size_t, align: size_t) -> *mut u8 {
+        if large::wants(size) {
+            unsafe { large::alloc(size, align) }
+        } else if align <= align_of::<max_align_t>() {
+            unsafe { self.malloc(size) }
+        } else {
+            unsafe { self.lock().memalign(align, size) }
+        }
+    }
+
+    unsafe fn malloc(&self, size: size_t) -> *mut u8 {
+        if let Some(cache) = thread_cache()
+            && let Some(ptr) = cache.pop(size)
+        {
+            return ptr;
+        }
+        unsafe { self.lock().malloc(size) }
+    }
+
+    unsafe fn free(&self, ptr: *mut u8) {
+        if unsafe { large::owns(ptr) } {
+            return unsafe { large::free(ptr) };
+        }
+        if let Some(cache) = thread_cache()
+            && let Some(class) = ThreadCache::class_of(unsafe { self.usable_size_unlocked(ptr) })
+        {
+            if cache.is_full(class) {
+                let mut heap = self.lock();
+                for block in cache.take(class) {
+                    unsafe { heap.free(block) };
+                }
+            }
+            unsafe { cache.push(class, ptr) };
+            return;
+        }
+        unsafe { self.lock().free(ptr) }
+    }
+
+    /// Returns the usable size of `ptr` without taking the lock.
+    ///
+    /// This only reads the header of the block, which belongs to the caller
+    /// until it is freed, as glibc does in front of its thread cache.
+    unsafe fn usable_size_unlocked(&self, ptr: *mut u8) -> size_t {
+        if unsafe { large::owns(ptr) } {
+            return unsafe { large::usable_size(ptr) };
+        }
+        unsafe { (*(*self.get()).as_ptr()).usable_size(ptr) }
+    }
+
+    unsafe fn usable_size(&self, ptr: *mut u8) -> size_t {
+        if unsafe { large::owns(ptr) } {
+            unsafe { large::usable_size(ptr) }
+        } else {
+            unsafe { self.lock().usable_size(ptr) }
+        }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, size: size_t) -> *mut u8 {
+        if unsafe { large::owns(ptr) } {
+            unsafe { large::realloc(ptr, size) }
+        } else {
+            unsafe { self.lock().realloc(ptr, size) }
+        }
+    }
+
+    /// Returns whether the block `ptr` must be cleared to be zeroed. Mapped
+    /// blocks are zeroed by the system.
+    unsafe fn calloc_must_clear(&self, ptr: *mut u8) -> bool {
+        unsafe { !large::owns(ptr) && self.lock().calloc_must_clear(ptr) }
+    }
+}
+
+/// Returns the cache of the calling thread, unless thread caches are disabled.
+fn thread_cache() -> Option<&'static mut ThreadCache> {
+    options::thread_caches()
+        .then(ThreadCache::current)
+        .flatten()
+}
+
+/// Bytes of the blocks allocated from dlmalloc, including the blocks kept in
+/// thread caches. Only modified with the lock held.
+static IN_USE: AtomicUsize = AtomicUsize::new(0);
+
+/// Free bytes after the last trim, see [`Heap::trim_if_needed`]. Only
+/// accessed with the lock held.
+static TRIM_FLOOR: AtomicUsize = AtomicUsize::new(0);
+
+/// The locked dlmalloc instance, which keeps track of the bytes in use.
+struct Heap<'a>(MutexGuard<'a, Dlmalloc>);
+
+impl Heap<'_> {
+    unsafe fn malloc(&mut self, size: size_t) -> *mut u8 {
+        let ptr = unsafe { self.0.malloc(size) };
+        unsafe { self.allocated(ptr) };
+        ptr
+    }
+
+    unsafe fn memalign(&mut self, alignment: size_t, size: size_t) -> *mut u8 {
+        let ptr = unsafe { self.0.memalign(alignment, size) };
+        unsafe { self.allocated(ptr) };
+        ptr
+    }
+
+    unsafe fn realloc(&mut self, ptr: *mut u8, size: size_t) -> *mut u8 {
+        let old_size = unsafe { self.0.usable_size(ptr) };
+        let new = unsafe { self.0.realloc(ptr, size) };
+        if !new.is_null() {
+            IN_USE.fetch_sub(old_size, Ordering::Relaxed);
+            unsafe { self.allocated(new) };
+        }
+        new
+    }
+
+    unsafe fn free(&mut self, ptr: *mut u8) {
+        IN_USE.fetch_sub(unsafe { self.0.usable_size(ptr) }, Ordering::Relaxed);
+        unsafe { self.0.free(ptr) };
+        self.trim_if_needed();
+    }
+
+    unsafe fn allocated(&self, ptr: *mut u8) {
+        if !ptr.is_null() {
+            IN_USE.fetch_add(unsafe { self.0.usable_size(ptr) }, Ordering::Relaxed);
+        }
+    }
+
+    unsafe fn usable_size(&self, ptr: *mut u8) -> size_t {
+        unsafe { self.0.usable_size(ptr) }
+    }
+
+    unsafe fn calloc_must_clear(&self, ptr: *mut u8) -> bool {
+        unsafe { self.0.calloc_must_clear(ptr) }
+    }
+
+    /// Returns the unused memory at the top of the heap to the system,
+    /// keeping `pad` bytes. Returns whether any memory was released.
+    fn trim(&mut self, pad: size_t) -> bool {
+        let released = unsafe { self.0.trim(pad) };
+        TRIM_FLOOR.store(self.stats().free(), Ordering::Relaxed);
+        released
+    }
+
+    /// Trims the heap once the free memory grew by `M_TRIM_THRESHOLD` bytes
+    /// since the last trim. Free memory that cannot be released, in the
+    /// middle of the heap, does not cause a trim on every `free()`.
+    ///
+    /// dlmalloc also trims the heap on its own, past 2 MiB of free memory at
+    /// the top.
+    fn trim_if_needed(&mut self) {
+        let free = self.stats().free();
+        let floor = TRIM_FLOOR.load(Ordering::Relaxed);
+        if free < floor {
+            TRIM_FLOOR.store(free, Ordering::Relaxed);
+        } else if free - floor > options::trim_threshold() {
+            self.trim(options::top_pad());
+        }
+    }
+
+    fn stats(&self) -> AllocStats {
+        let (system, max_system) = sys::footprint();
+        let (mapped_blocks, mapped) = large::stats();
+        AllocStats {
+            system,
+            max_system,
+            in_use: IN_USE.load(Ordering::Relaxed),
+            mapped_blocks,
+            mapped,
+        }
+    }
+}
+
+/// Statistics of the allocator.
+#[derive(Clone, Copy, Debug)]
+pub struct AllocStats {
+    /// Bytes obtained from the system.
+    pub system: usize,
+    /// Largest number of bytes obtained from the system at once.
+    pub max_system: usize,
+    /// Bytes of the allocated blocks. The blocks freed by a thread but kept
+    /// in its cache are still counted.
+    pub in_use: usize,
+    /// Number of blocks mapped on their own, see `M_MMAP_THRESHOLD`.
+    pub mapped_blocks: usize,
+    /// Bytes of the blocks mapped on their own, which are not counted in the
+    /// other fields.
+    pub mapped: usize,
+}
+
+impl AllocStats {
+    /// Bytes obtained from the system that are not allocated.
+    pub fn free(&self) -> usize {
+        self.system.saturating_sub(self.in_use)
+    }
 }
 
 unsafe impl GlobalAlloc for Allocator {
     #[inline]
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-        if layout.align() <= align_of::<max_align_t>() {
-            unsafe { (*self.get()).lock().malloc(layout.size()) }
+        if debug::enabled() {
+            unsafe { debug::alloc(self, layout.size(), layout.align()) }
         } else {
-            unsafe { (*self.get()).lock().memalign(layout.align(), layout.size()) }
+            unsafe { self.alloc_raw(layout.size(), layout.align()) }
         }
     }
 
@@ -47,13 +262,17 @@ unsafe impl GlobalAlloc for Allocator {
         reason = "Layout part of GlobalAlloc trait but Dlmalloc free() doesn't require it"
     )]
     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
-        unsafe { (*self.get()).lock().free(ptr) }
+        if debug::enabled() {
+            unsafe { debug::free(self, ptr) }
+        } else {
+            unsafe { self.free(ptr) }
+        }
     }
 
     #[inline]
     unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
         let ptr = unsafe { self.alloc(layout) };
-        if !ptr.is_null() && unsafe { (*self.get()).lock().calloc_must_clear(ptr) } {
+        if !ptr.is_null() && (debug::enabled() || unsafe { self.calloc_must_clear(ptr) }) {
             unsafe { write_bytes(ptr, 0, layout.size()) };
         }
         ptr
@@ -61,8 +280,10 @@ unsafe impl GlobalAlloc for Allocator {
 
     #[inline]
     unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
-        if layout.align() <= align_of::<max_align_t>() {
-            unsafe { (*self.get()).lock().realloc(ptr, new_size) }
+        if debug::enabled() {
+            unsafe { debug::realloc(self, ptr, new_size, layout.align()) }
+        } else if layout.align() <= align_of::<max_align_t>() {
+            unsafe { self.realloc(ptr, new_size) }
         } else {
             let new =
                 unsafe { self.alloc(Layout::from_size_align_unchecked(new_size, layout.align())) };
@@ -73,7 +294,7 @@ unsafe impl GlobalAlloc for Allocator {
                 unsafe { copy_nonoverlapping(ptr, new, size) };
             }
 
-            unsafe { (*self.get()).lock().free(ptr) };
+            unsafe { self.free(ptr) };
 
             new
         }
@@ -81,18 +302,30 @@ unsafe impl GlobalAlloc for Allocator {
 }
 
 pub unsafe fn alloc(size: size_t) -> *mut c_void {
-    unsafe { (*ALLOCATOR.get()).lock().malloc(size) }.cast()
+    unsafe { alloc_align(size, align_of::<max_align_t>()) }
 }
 
 pub unsafe fn alloc_align(size: size_t, alignment: size_t) -> *mut c_void {
-    unsafe { (*ALLOCATOR.get()).lock().memalign(alignment, size) }.cast()
+    if debug::enabled() {
+        return unsafe { debug::alloc(&ALLOCATOR, size, alignment) }.cast();
+    }
+
+    let ptr = unsafe { ALLOCATOR.alloc_raw(size, alignment) };
+    if let Some(byte) = options::perturb()
+        && !ptr.is_null()
+    {
+        unsafe { write_bytes(ptr, !byte, size) };
+    }
+    ptr.cast()
 }
 
 pub unsafe fn realloc(ptr: *mut c_void, size: size_t) -> *mut c_void {
     if ptr.is_null() {
-        unsafe { (*ALLOCATOR.get()).lock().malloc(size) }.cast()
+        unsafe { alloc(size) }
+    } else if debug::enabled() {
+        unsafe { debug::realloc(&ALLOCATOR, ptr.cast(), size, align_of::<max_align_t>()) }.cast()
     } else {
-        unsafe { (*ALLOCATOR.get()).lock().realloc(ptr.cast(), size) }.cast()
+        unsafe { ALLOCATOR.realloc(ptr.cast(), size) }.cast()
     }
 }
 
@@ -100,12 +333,112 @@ pub unsafe fn free(ptr: *mut c_void) {
     if ptr.is_null() {
         return;
     }
-    unsafe { (*ALLOCATOR.get()).lock().free(ptr.cast()) }
+    if debug::enabled() {
+        return unsafe { debug::free(&ALLOCATOR, ptr.cast()) };
+    }
+
+    if let Some(byte) = options::perturb() {
+        unsafe {
+            write_bytes(
+                ptr.cast::<u8>(),
+                byte,
+                ALLOCATOR.usable_size_unlocked(ptr.cast()),
+            )
+        };
+    }
+    unsafe { ALLOCATOR.free(ptr.cast()) }
 }
 
 pub unsafe fn alloc_usable_size(ptr: *mut c_void) -> size_t {
     if ptr.is_null() {
         return 0;
     }
-    unsafe { (*ALLOCATOR.get()).lock().usable_size(ptr.cast()) }
+    if debug::enabled() {
+        return unsafe { debug::usable_size(ptr.cast()) };
+    }
+    unsafe { ALLOCATOR.usable_size(ptr.cast()) }
+}
+
+/// Enables the debugging mode of the allocator if requested by the
+/// environment `envp`, see [`debug`]. Does nothing once memory was allocated.
+pub unsafe fn init_malloc_debug(envp: *const *const c_char) {
+    if sys::footprint().0 == 0 && large::stats().0 == 0 {
+        unsafe { debug::init(envp) };
+    }
+}
+
+/// Returns the statistics of the allocator.
+pub fn alloc_stats() -> AllocStats {
+    ALLOCATOR.lock().stats()
+}
+
+/// Returns the unused memory at the top of the heap to the system, keeping
+/// `pad` bytes. The blocks cached by the calling thread are released first.
+///
+/// Returns whether any memory was released.
+pub fn alloc_trim(pad: size_t) -> bool {
+    let mut heap = ALLOCATOR.lock();
+    if let Some(cache) = ThreadCache::current() {
+        for block in cache.take_all() {
+            unsafe { heap.free(block) };
+        }
+    }
+    heap.trim(pad)
+}
+
+/// Returns the blocks cached by the calling thread to the global allocator,
+/// before the thread exits. Blocks freed afterwards are not cached anymore.
+pub unsafe fn release_thread_cache() {
+    let Some(cache) = ThreadCache::current() else {
+        return;
+    };
+    let mut heap = ALLOCATOR.lock();
+    for block in cache.release() {
+        unsafe { heap.free(block) };
+    }
+}
+
+/// Takes the allocator locks before `fork()`, so that the child cannot
+/// inherit them while another thread is in the middle of an allocation.
+///
+/// Blocks cached by the other threads are lost in the child.
+pub fn fork_prepare() {
+    // Redox allocates in `fork()` itself, so the locks are reset in the child
+    // instead, see `fork_child()`.
+    #[cfg(target_os = "redox")]
+    trace::flush();
+    #[cfg(not(target_os = "redox"))]
+    unsafe {
+        trace::fork_prepare();
+        debug::fork_prepare();
+        (*ALLOCATOR.get()).manual_lock();
+        sys::acquire_global_lock();
+    }
+}
+
+/// Releases the allocator locks after `fork()`, in both the parent and the
+/// child, where the calling thread still holds them.
+pub fn fork_release() {
+    #[cfg(not(target_os = "redox"))]
+    unsafe {
+        sys::release_global_lock();
+        (*ALLOCATOR.get()).manual_unlock();
+        debug::fork_release();
+        trace::fork_release();
+    }
+}
+
+/// Resets the allocator locks in the child after `fork()`, where they could
+/// not be held across it. Only the calling thread exists in the child, so any
+/// lock still held belonged to another thread of the parent, whose operation
+/// in progress is left unfinished.
+pub fn fork_child() {
+    #[cfg(target_os = "redox")]
+    unsafe {
+        sys::release_global_lock();
+        (*ALLOCATOR.get()).manual_unlock();
+        debug::fork_release();
+        trace::fork_release();
+        trace::fork_child();
+    }
 }
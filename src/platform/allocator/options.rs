@@ -0,0 +1,102 @@
+//! Tuning of the allocator with `mallopt()` and the `MALLOC_*` environment
+//! variables.
+
+use core::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
+
+use crate::{
+    c_str::CStr,
+    header::{
+        malloc::{
+            M_ARENA_MAX, M_MMAP_MAX, M_MMAP_THRESHOLD, M_PERTURB, M_TOP_PAD, M_TRIM_THRESHOLD,
+        },
+        stdlib::getenv,
+    },
+    platform::types::c_int,
+};
+
+/// Same as the trim threshold of dlmalloc.
+const DEFAULT_TRIM_THRESHOLD: usize = 2 * 1024 * 1024;
+
+/// Same defaults as glibc.
+const DEFAULT_MMAP_THRESHOLD: usize = 128 * 1024;
+const DEFAULT_MMAP_MAX: usize = 65536;
+
+static TRIM_THRESHOLD: AtomicUsize = AtomicUsize::new(DEFAULT_TRIM_THRESHOLD);
+static TOP_PAD: AtomicUsize = AtomicUsize::new(0);
+static MMAP_THRESHOLD: AtomicUsize = AtomicUsize::new(DEFAULT_MMAP_THRESHOLD);
+static MMAP_MAX: AtomicUsize = AtomicUsize::new(DEFAULT_MMAP_MAX);
+/// Byte written over freed blocks, and its complement over allocated blocks,
+/// or `0` to leave them as they are.
+static PERTURB: AtomicU8 = AtomicU8::new(0);
+static THREAD_CACHES: AtomicBool = AtomicBool::new(true);
+
+/// Environment variables read at startup, as in glibc.
+const ENV_OPTIONS: [(&core::ffi::CStr, c_int); 6] = [
+    (c"MALLOC_TRIM_THRESHOLD_", M_TRIM_THRESHOLD),
+    (c"MALLOC_TOP_PAD_", M_TOP_PAD),
+    (c"MALLOC_MMAP_THRESHOLD_", M_MMAP_THRESHOLD),
+    (c"MALLOC_MMAP_MAX_", M_MMAP_MAX),
+    (c"MALLOC_PERTURB_", M_PERTURB),
+    (c"MALLOC_ARENA_MAX", M_ARENA_MAX),
+];
+
+pub(super) fn trim_threshold() -> usize {
+    TRIM_THRESHOLD.load(Ordering::Relaxed)
+}
+
+pub(super) fn top_pad() -> usize {
+    TOP_PAD.load(Ordering::Relaxed)
+}
+
+pub(super) fn mmap_threshold() -> usize {
+    MMAP_THRESHOLD.load(Ordering::Relaxed)
+}
+
+pub(super) fn mmap_max() -> usize {
+    MMAP_MAX.load(Ordering::Relaxed)
+}
+
+pub(super) fn thread_caches() -> bool {
+    THREAD_CACHES.load(Ordering::Relaxed)
+}
+
+/// Sets the allocator parameter `param`, returning whether it is supported.
+///
+/// There is a single dlmalloc heap, with a cache of small blocks in front of
+/// it for each thread: limiting the number of arenas to 1 disables the
+/// thread caches. Blocks of at least `M_MMAP_THRESHOLD` bytes are mapped on
+/// their own, up to `M_MMAP_MAX` of them at once.
+pub fn set_malloc_option(param: c_int, value: isize) -> bool {
+    match param {
+        // Negative values disable the trimming, as the value is unsigned in glibc.
+        M_TRIM_THRESHOLD => TRIM_THRESHOLD.store(value as usize, Ordering::Relaxed),
+        M_TOP_PAD if value >= 0 => TOP_PAD.store(value as usize, Ordering::Relaxed),
+        M_MMAP_THRESHOLD if value >= 0 => MMAP_THRESHOLD.store(value as usize, Ordering::Relaxed),
+        M_MMAP_MAX if value >= 0 => MMAP_MAX.store(value as usize, Ordering::Relaxed),
+        M_PERTURB => PERTURB.store(value as u8, Ordering::Relaxed),
+        M_ARENA_MAX if value > 0 => THREAD_CACHES.store(value != 1, Ordering::Relaxed),
+        _ => return false,
+    }
+    true
+}
+
+/// Applies the `MALLOC_*` environment variables.
+pub unsafe fn init_malloc_options() {
+    for (name, param) in ENV_OPTIONS {
+        let value = unsafe { getenv(name.as_ptr()) };
+        if value.is_null() {
+            continue;
+        }
+        if let Ok(value) = unsafe { CStr::from_ptr(value) }.to_str()
+            && let Ok(value) = value.parse()
+        {
+            set_malloc_option(param, value);
+        }
+    }
+}
+
+/// Returns the byte written over freed blocks, whose complement is written
+/// over allocated blocks, if `M_PERTURB` is set.
+pub(super) fn perturb() -> Option<u8> {
+    Some(PERTURB.load(Ordering::Relaxed)).filter(|&byte| byte != 0)
+}
@@ -6,7 +6,10 @@ use crate::{
     platform::{Pal, Sys},
     sync::Mutex,
 };
-use core::ptr;
+use core::{
+    ptr,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
 use dlmalloc::Allocator;
 
@@ -23,6 +26,29 @@ impl System {
 
 static LOCK: Mutex<()> = Mutex::new(());
 
+/// Bytes currently obtained from the system.
+static FOOTPRINT: AtomicUsize = AtomicUsize::new(0);
+/// Largest value of `FOOTPRINT` so far.
+static MAX_FOOTPRINT: AtomicUsize = AtomicUsize::new(0);
+
+fn grow(size: usize) {
+    let footprint = FOOTPRINT.fetch_add(size, Ordering::Relaxed) + size;
+    MAX_FOOTPRINT.fetch_max(footprint, Ordering::Relaxed);
+}
+
+fn shrink(size: usize) {
+    FOOTPRINT.fetch_sub(size, Ordering::Relaxed);
+}
+
+/// Returns the number of bytes currently obtained from the system, and the
+/// largest number of bytes obtained at once.
+pub fn footprint() -> (usize, usize) {
+    (
+        FOOTPRINT.load(Ordering::Relaxed),
+        MAX_FOOTPRINT.load(Ordering::Relaxed),
+    )
+}
+
 unsafe impl Allocator for System {
     fn alloc(&self, size: usize) -> (*mut u8, usize, u32) {
         let Ok(addr) = (unsafe {
@@ -37,6 +63,7 @@ unsafe impl Allocator for System {
         }) else {
             return (ptr::null_mut(), 0, 0);
         };
+        grow(size);
         (addr.cast::<u8>(), size, 0)
     }
 
@@ -47,20 +74,31 @@ unsafe impl Allocator for System {
         else {
             return ptr::null_mut();
         };
+        if newsize > oldsize {
+            grow(newsize - oldsize);
+        } else {
+            shrink(oldsize - newsize);
+        }
         ptr.cast::<u8>()
     }
 
     fn free_part(&self, ptr: *mut u8, oldsize: usize, newsize: usize) -> bool {
-        unsafe {
-            if Sys::mremap(ptr.cast(), oldsize, newsize, 0, ptr::null_mut()).is_ok() {
-                return true;
-            }
-            Sys::munmap(ptr.add(newsize).cast(), oldsize - newsize).is_ok()
+        let freed = unsafe {
+            Sys::mremap(ptr.cast(), oldsize, newsize, 0, ptr::null_mut()).is_ok()
+                || Sys::munmap(ptr.add(newsize).cast(), oldsize - newsize).is_ok()
+        };
+        if freed {
+            shrink(oldsize - newsize);
         }
+        freed
     }
 
     fn free(&self, ptr: *mut u8, size: usize) -> bool {
-        unsafe { Sys::munmap(ptr.cast(), size).is_ok() }
+        let freed = unsafe { Sys::munmap(ptr.cast(), size).is_ok() };
+        if freed {
+            shrink(size);
+        }
+        freed
     }
 
     fn can_release_part(&self, _flags: u32) -> bool {
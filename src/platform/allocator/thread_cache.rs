@@ -0,0 +1,117 @@
+//! Per-thread caches of small free blocks, in front of the global dlmalloc
+//! instance.
+//!
+//! Blocks freed by a thread are kept in free lists of its cache, by size
+//! class, and handed out again by `malloc()` without taking the global lock.
+//! Cached blocks remain allocated as far as dlmalloc is concerned, so a block
+//! freed by another thread than the one that allocated it simply moves to the
+//! cache of the freeing thread. Free lists are returned to dlmalloc at once
+//! when they fill up, and all of them when the thread exits.
+
+use core::ptr;
+
+use crate::ld_so::tcb::Tcb;
+
+/// Granularity of the size classes, in bytes.
+const CLASS_SIZE: usize = 16;
+/// Number of size classes. Class `n` holds the blocks with a usable size
+/// between `n * CLASS_SIZE` and `(n + 1) * CLASS_SIZE - 1` bytes.
+const CLASSES: usize = 32;
+/// Maximum number of blocks in each free list.
+const MAX_BLOCKS: u8 = 16;
+
+struct FreeBlock {
+    next: *mut FreeBlock,
+}
+
+pub struct ThreadCache {
+    heads: [*mut FreeBlock; CLASSES],
+    counts: [u8; CLASSES],
+    /// Set once the cache has been released, when the thread exits.
+    released: bool,
+}
+
+impl ThreadCache {
+    #[expect(clippy::new_without_default)]
+    pub const fn new() -> Self {
+        Self {
+            heads: [ptr::null_mut(); CLASSES],
+            counts: [0; CLASSES],
+            released: false,
+        }
+    }
+
+    /// Returns the cache of the calling thread, or `None` before its TCB is
+    /// set up and after the cache is released.
+    pub(super) fn current() -> Option<&'static mut Self> {
+        let cache = &mut unsafe { Tcb::current() }?.alloc_cache;
+        (!cache.released).then_some(cache)
+    }
+
+    /// Returns the size class of a block with `usable` bytes, if blocks of
+    /// that size are cached.
+    pub(super) fn class_of(usable: usize) -> Option<usize> {
+        let class = usable / CLASS_SIZE;
+        (class < CLASSES).then_some(class)
+    }
+
+    /// Takes a cached block of at least `size` bytes.
+    pub(super) fn pop(&mut self, size: usize) -> Option<*mut u8> {
+        let class = size.div_ceil(CLASS_SIZE).max(1);
+        let head = *self.heads.get(class)?;
+        if head.is_null() {
+            return None;
+        }
+
+        self.heads[class] = unsafe { (*head).next };
+        self.counts[class] -= 1;
+        Some(head.cast())
+    }
+
+    pub(super) fn is_full(&self, class: usize) -> bool {
+        self.counts[class] >= MAX_BLOCKS
+    }
+
+    /// Caches `block`, which must belong to `class` and must not be full.
+    pub(super) unsafe fn push(&mut self, class: usize, block: *mut u8) {
+        let block = block.cast::<FreeBlock>();
+        unsafe { (*block).next = self.heads[class] };
+        self.heads[class] = block;
+        self.counts[class] += 1;
+    }
+
+    /// Empties the free list of `class`, returning its blocks.
+    pub(super) fn take(&mut self, class: usize) -> Blocks {
+        self.counts[class] = 0;
+        Blocks(core::mem::replace(&mut self.heads[class], ptr::null_mut()))
+    }
+
+    /// Empties all the free lists, returning their blocks.
+    pub(super) fn take_all(&mut self) -> impl Iterator<Item = *mut u8> {
+        (0..CLASSES).flat_map(|class| self.take(class))
+    }
+
+    /// Empties all the free lists and stops caching blocks, returning the
+    /// blocks that were cached.
+    pub(super) fn release(&mut self) -> impl Iterator<Item = *mut u8> {
+        self.released = true;
+        self.take_all()
+    }
+}
+
+/// The blocks of a free list.
+pub(super) struct Blocks(*mut FreeBlock);
+
+impl Iterator for Blocks {
+    type Item = *mut u8;
+
+    fn next(&mut self) -> Option<*mut u8> {
+        let block = self.0;
+        if block.is_null() {
+            return None;
+        }
+        // Read before the caller frees the block.
+        self.0 = unsafe { (*block).next };
+        Some(block.cast())
+    }
+}
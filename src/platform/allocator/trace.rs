@@ -0,0 +1,273 @@
+//! Tracing of the allocations made through the C API, in the format of
+//! glibc's `mtrace()`, so that its `mtrace` script can be used to find leaks.
+//!
+//! Tracing is started by `mtrace()`, or at startup, if `MALLOC_TRACE` names
+//! the file to write the trace to, and stopped by `muntrace()` or at exit.
+//! Each event is written with the address of its caller:
+//!
+//! ```text
+//! = Start
+//! @ ./prog:(main+0x1a)[0x55d4c2a4b1ba] + 0x55d4c3e7e2a0 0x40
+//! @ ./prog:(main+0x2e)[0x55d4c2a4b1ce] - 0x55d4c3e7e2a0
+//! = End
+//! ```
+//!
+//! The number of blocks still allocated when tracing stops is written as
+//! well, on a line that the `mtrace` script ignores.
+
+use core::{
+    fmt::{self, Write},
+    ptr,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::{
+    c_str::CStr,
+    header::{
+        dlfcn::{Dl_info_t, try_dladdr},
+        fcntl::{O_CLOEXEC, O_CREAT, O_TRUNC, O_WRONLY},
+        stdlib::secure_getenv,
+    },
+    platform::{
+        FileWriter, Pal, Sys,
+        types::{c_int, c_void},
+    },
+    sync::Mutex,
+};
+
+use super::alloc_usable_size;
+
+const BUF_SIZE: usize = 4096;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+struct Trace {
+    fd: c_int,
+    buf: [u8; BUF_SIZE],
+    len: usize,
+    /// Number of blocks allocated since tracing started, and their usable
+    /// size.
+    blocks: usize,
+    bytes: usize,
+}
+
+static TRACE: Mutex<Trace> = Mutex::new(Trace {
+    fd: -1,
+    buf: [0; BUF_SIZE],
+    len: 0,
+    blocks: 0,
+    bytes: 0,
+});
+
+impl Trace {
+    fn flush(&mut self) {
+        if self.len > 0 {
+            let _ = FileWriter::new(self.fd).write(&self.buf[..self.len]);
+            self.len = 0;
+        }
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        if self.len + bytes.len() > BUF_SIZE {
+            self.flush();
+        }
+        if bytes.len() > BUF_SIZE {
+            let _ = FileWriter::new(self.fd).write(bytes);
+        } else {
+            self.buf[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+            self.len += bytes.len();
+        }
+    }
+
+    /// Writes the location of `caller` at the start of an event.
+    fn write_caller(&mut self, caller: *const c_void) -> fmt::Result {
+        let mut info = Dl_info_t {
+            dli_fname: ptr::null(),
+            dli_fbase: ptr::null_mut(),
+            dli_sname: ptr::null(),
+            dli_saddr: ptr::null_mut(),
+        };
+        if !unsafe { try_dladdr(caller, &mut info) } {
+            return write!(self, "@ [{caller:p}] ");
+        }
+
+        self.write_str("@ ")?;
+        if let Some(fname) = unsafe { CStr::from_nullable_ptr(info.dli_fname) } {
+            self.write_bytes(fname.to_bytes());
+            self.write_str(":")?;
+        }
+        if let Some(sname) = unsafe { CStr::from_nullable_ptr(info.dli_sname) } {
+            let (sign, offset) = match (caller as usize).checked_sub(info.dli_saddr as usize) {
+                Some(offset) => ('+', offset),
+                None => ('-', info.dli_saddr as usize - caller as usize),
+            };
+            self.write_str("(")?;
+            self.write_bytes(sname.to_bytes());
+            write!(self, "{sign}{offset:#x})")?;
+        }
+        write!(self, "[{caller:p}] ")
+    }
+
+    fn added(&mut self, ptr: *mut c_void) {
+        if !ptr.is_null() {
+            self.blocks += 1;
+            self.bytes += unsafe { alloc_usable_size(ptr) };
+        }
+    }
+
+    fn removed(&mut self, size: usize) {
+        self.blocks = self.blocks.saturating_sub(1);
+        self.bytes = self.bytes.saturating_sub(size);
+    }
+}
+
+impl Write for Trace {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_bytes(s.as_bytes());
+        Ok(())
+    }
+}
+
+#[inline]
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Starts tracing to the file named by `MALLOC_TRACE`, if it is set and
+/// tracing has not started yet.
+pub fn start_alloc_trace() {
+    let path = unsafe { secure_getenv(c"MALLOC_TRACE".as_ptr()) };
+    let Some(path) = (unsafe { CStr::from_nullable_ptr(path) }) else {
+        return;
+    };
+
+    let mut trace = TRACE.lock();
+    if trace.fd >= 0 {
+        return;
+    }
+    let Ok(fd) = Sys::open(path, O_WRONLY | O_CREAT | O_TRUNC | O_CLOEXEC, 0o666) else {
+        return;
+    };
+    trace.fd = fd;
+    trace.blocks = 0;
+    trace.bytes = 0;
+    let _ = trace.write_str("= Start\n");
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Stops tracing, writing the number of blocks that were not freed.
+pub fn stop_alloc_trace() {
+    let mut trace = TRACE.lock();
+    if trace.fd < 0 {
+        return;
+    }
+    ENABLED.store(false, Ordering::Relaxed);
+
+    let (blocks, bytes) = (trace.blocks, trace.bytes);
+    let _ = writeln!(trace, "= Not freed: {blocks} blocks, {bytes} bytes\n= End");
+    trace.flush();
+    let _ = Sys::close(trace.fd);
+    trace.fd = -1;
+}
+
+/// Records the allocation of `size` bytes at `ptr` by `caller`.
+pub unsafe fn trace_alloc(caller: *const c_void, ptr: *mut c_void, size: usize) {
+    if !enabled() {
+        return;
+    }
+    let mut trace = TRACE.lock();
+    if trace.fd < 0 {
+        return;
+    }
+    let _ = trace.write_caller(caller);
+    let _ = writeln!(trace, "+ {ptr:p} {size:#x}");
+    trace.added(ptr);
+}
+
+/// Records that `caller` is about to free `ptr`.
+pub unsafe fn trace_free(caller: *const c_void, ptr: *mut c_void) {
+    if !enabled() || ptr.is_null() {
+        return;
+    }
+    let size = unsafe { alloc_usable_size(ptr) };
+    let mut trace = TRACE.lock();
+    if trace.fd < 0 {
+        return;
+    }
+    let _ = trace.write_caller(caller);
+    let _ = writeln!(trace, "- {ptr:p}");
+    trace.removed(size);
+}
+
+/// Returns the usable size of `ptr` if allocations are traced, to be passed
+/// to [`trace_realloc`] once it is reallocated.
+pub unsafe fn traced_size(ptr: *mut c_void) -> usize {
+    if enabled() {
+        unsafe { alloc_usable_size(ptr) }
+    } else {
+        0
+    }
+}
+
+/// Records the reallocation by `caller` of `old`, of usable size `old_size`,
+/// to `size` bytes at `new`.
+pub unsafe fn trace_realloc(
+    caller: *const c_void,
+    old: *mut c_void,
+    old_size: usize,
+    new: *mut c_void,
+    size: usize,
+) {
+    if !enabled() {
+        return;
+    }
+    let mut trace = TRACE.lock();
+    if trace.fd < 0 {
+        return;
+    }
+    let _ = trace.write_caller(caller);
+    if new.is_null() && size != 0 {
+        let _ = writeln!(trace, "! {old:p} {size:#x}");
+        return;
+    }
+    if old.is_null() {
+        let _ = writeln!(trace, "+ {new:p} {size:#x}");
+        trace.added(new);
+        return;
+    }
+
+    let _ = writeln!(trace, "< {old:p}");
+    trace.removed(old_size);
+    if !new.is_null() {
+        let _ = trace.write_caller(caller);
+        let _ = writeln!(trace, "> {new:p} {size:#x}");
+        trace.added(new);
+    }
+}
+
+/// Takes the trace lock before `fork()`, writing the buffered events first so
+/// that they are not written again by the child.
+pub fn fork_prepare() {
+    unsafe { TRACE.manual_lock() }.flush();
+}
+
+/// Writes the buffered events before `fork()` on Redox, where the trace lock
+/// is not held across it.
+#[cfg(target_os = "redox")]
+pub fn flush() {
+    if enabled() {
+        TRACE.lock().flush();
+    }
+}
+
+/// Discards the events buffered in the child after `fork()` on Redox, which
+/// are written by the parent.
+#[cfg(target_os = "redox")]
+pub fn fork_child() {
+    TRACE.lock().len = 0;
+}
+
+/// Releases the trace lock after `fork()`.
+pub fn fork_release() {
+    unsafe { TRACE.manual_unlock() };
+}
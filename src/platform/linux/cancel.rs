@@ -0,0 +1,106 @@
+//! Syscalls that are cancellation points.
+//!
+//! `cancellation_point` checks for a request before running its blocking operation, and the
+//! cancellation signal interrupts the operation once it blocks. A request arriving in between
+//! would neither be seen by the check nor interrupt anything. As in musl, the blocking syscall is
+//! therefore issued from a stub which checks for a request right before the syscall instruction,
+//! and the signal handler acts upon the request whenever it interrupted the stub before the
+//! syscall instruction completed.
+
+use crate::header::signal::ucontext_t;
+
+#[cfg(target_arch = "x86_64")]
+core::arch::global_asm!(
+    "
+    .global __relibc_syscall_cp
+    .hidden __relibc_syscall_cp
+    __relibc_syscall_cp:
+    __relibc_syscall_cp_begin:
+        // rdi: request flag, rsi: syscall number, rdx, rcx, r8, r9, [rsp + 8], [rsp + 16]: args
+        cmp byte ptr [rdi], 0
+        jne __relibc_syscall_cp_cancel
+        mov rax, rsi
+        mov rdi, rdx
+        mov rsi, rcx
+        mov rdx, r8
+        mov r10, r9
+        mov r8, [rsp + 8]
+        mov r9, [rsp + 16]
+        syscall
+    __relibc_syscall_cp_end:
+        ret
+    __relibc_syscall_cp_cancel:
+        jmp {cancelled}
+
+    .global __relibc_syscall_cp_begin
+    .hidden __relibc_syscall_cp_begin
+    .global __relibc_syscall_cp_end
+    .hidden __relibc_syscall_cp_end
+    .global __relibc_syscall_cp_cancel
+    .hidden __relibc_syscall_cp_cancel
+    ",
+    cancelled = sym cancelled,
+);
+
+#[cfg(target_arch = "x86_64")]
+unsafe extern "C" {
+    fn __relibc_syscall_cp(
+        flag: *const core::sync::atomic::AtomicBool,
+        nr: usize,
+        a1: usize,
+        a2: usize,
+        a3: usize,
+        a4: usize,
+        a5: usize,
+        a6: usize,
+    ) -> usize;
+
+    static __relibc_syscall_cp_begin: u8;
+    static __relibc_syscall_cp_end: u8;
+    static __relibc_syscall_cp_cancel: u8;
+}
+
+#[cfg(target_arch = "x86_64")]
+extern "C" fn cancelled() -> ! {
+    unsafe { crate::pthread::testcancel() };
+    unreachable!("cancellation request vanished before the syscall")
+}
+
+/// Issues syscall `nr`, acting upon a cancellation request of the current thread right before the
+/// syscall blocks, if it is inside a cancellation point.
+///
+/// Used through the `syscall_cp!` macro by the syscalls that `cancellation_point` wraps.
+pub unsafe fn syscall_cp<const N: usize>(nr: usize, args: [usize; N]) -> usize {
+    let mut a = [0; 6];
+    a[..N].copy_from_slice(&args);
+
+    #[cfg(target_arch = "x86_64")]
+    if let Some(flag) = crate::pthread::syscall_cancel_flag() {
+        return unsafe { __relibc_syscall_cp(flag, nr, a[0], a[1], a[2], a[3], a[4], a[5]) };
+    }
+
+    // TODO: The stub is only implemented on x86_64. Elsewhere, a request arriving right before
+    // the syscall blocks is only acted upon once the syscall returns.
+    unsafe { sc::syscall6(nr, a[0], a[1], a[2], a[3], a[4], a[5]) }
+}
+
+/// Called by the cancellation signal handler with the interrupted `context`. If the thread was
+/// interrupted in [`syscall_cp`] before entering the syscall, resumes it in the cancellation
+/// routine instead.
+pub unsafe fn cancel_before_syscall(context: *mut ucontext_t) {
+    #[cfg(target_arch = "x86_64")]
+    {
+        const REG_RIP: usize = 16;
+
+        let pc = unsafe { &mut (*context).uc_mcontext.gregs[REG_RIP] };
+        let begin = &raw const __relibc_syscall_cp_begin as usize;
+        let end = &raw const __relibc_syscall_cp_end as usize;
+        // Interrupted syscalls return with the PC at the end, so that the request is acted upon
+        // by cancellation_point instead, depending on whether the syscall completed.
+        if (begin..end).contains(&(*pc as usize)) {
+            *pc = &raw const __relibc_syscall_cp_cancel as i64;
+        }
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    let _ = context;
+}
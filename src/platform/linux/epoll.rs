@@ -31,7 +31,7 @@ impl PalEpoll for Sys {
     ) -> Result<usize> {
         let sigsetsize: size_t = mem::size_of::<sigset_t>();
         unsafe {
-            e_raw(syscall!(
+            e_raw(syscall_cp!(
                 EPOLL_PWAIT,
                 epfd,
                 events,
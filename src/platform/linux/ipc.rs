@@ -0,0 +1,200 @@
+use core::ptr;
+
+use super::{Sys, e_raw};
+use crate::{
+    error::Result,
+    header::{
+        sys_ipc::{IPC_SET, IPC_STAT, ipc_perm},
+        sys_msg::msqid_ds,
+        sys_sem::{GETALL, SETALL, SETVAL, sembuf, semid_ds, semun},
+        time::timespec,
+    },
+    platform::{
+        PalIpc,
+        types::{c_int, c_long, c_uint, c_ulong, c_ushort, c_void, gid_t, key_t, pid_t, uid_t},
+    },
+};
+
+/// Architectures with the legacy IPC structures need this flag to select the 64-bit variants
+/// below, other architectures reject it.
+#[cfg(target_arch = "x86")]
+const IPC_64: c_int = 0x100;
+#[cfg(not(target_arch = "x86"))]
+const IPC_64: c_int = 0;
+
+/// Kernel `struct ipc64_perm`.
+#[repr(C)]
+#[derive(Default)]
+struct ipc64_perm {
+    key: key_t,
+    uid: uid_t,
+    gid: gid_t,
+    cuid: uid_t,
+    cgid: gid_t,
+    // Followed by padding if the kernel `mode_t` is 16 bits wide.
+    mode: c_uint,
+    seq: c_ushort,
+    __pad2: c_ushort,
+    __unused1: c_ulong,
+    __unused2: c_ulong,
+}
+
+impl ipc64_perm {
+    fn from_perm(perm: &ipc_perm) -> Self {
+        Self {
+            uid: perm.uid,
+            gid: perm.gid,
+            mode: perm.mode as c_uint,
+            ..Self::default()
+        }
+    }
+
+    fn to_perm(&self) -> ipc_perm {
+        ipc_perm {
+            __key: self.key,
+            uid: self.uid,
+            gid: self.gid,
+            cuid: self.cuid,
+            cgid: self.cgid,
+            mode: (self.mode & 0o777) as _,
+            __seq: self.seq,
+        }
+    }
+}
+
+/// Kernel `struct semid64_ds`. The time fields are followed by their high halves on 32-bit
+/// architectures, and by unused padding on x86_64.
+#[repr(C)]
+#[derive(Default)]
+struct semid64_ds {
+    sem_perm: ipc64_perm,
+    sem_otime: c_long,
+    #[cfg(any(target_arch = "x86_64", target_pointer_width = "32"))]
+    __sem_otime_high: c_ulong,
+    sem_ctime: c_long,
+    #[cfg(any(target_arch = "x86_64", target_pointer_width = "32"))]
+    __sem_ctime_high: c_ulong,
+    sem_nsems: c_ulong,
+    __unused3: c_ulong,
+    __unused4: c_ulong,
+}
+
+/// Kernel `struct msqid64_ds`. The time fields are followed by their high halves on 32-bit
+/// architectures.
+#[repr(C)]
+#[derive(Default)]
+struct msqid64_ds {
+    msg_perm: ipc64_perm,
+    msg_stime: c_long,
+    #[cfg(target_pointer_width = "32")]
+    __msg_stime_high: c_ulong,
+    msg_rtime: c_long,
+    #[cfg(target_pointer_width = "32")]
+    __msg_rtime_high: c_ulong,
+    msg_ctime: c_long,
+    #[cfg(target_pointer_width = "32")]
+    __msg_ctime_high: c_ulong,
+    msg_cbytes: c_ulong,
+    msg_qnum: c_ulong,
+    msg_qbytes: c_ulong,
+    msg_lspid: pid_t,
+    msg_lrpid: pid_t,
+    __unused4: c_ulong,
+    __unused5: c_ulong,
+}
+
+impl PalIpc for Sys {
+    unsafe fn msgctl(msqid: c_int, cmd: c_int, buf: *mut msqid_ds) -> Result<()> {
+        match cmd {
+            IPC_STAT => {
+                let mut ds = msqid64_ds::default();
+                e_raw(unsafe { syscall!(MSGCTL, msqid, cmd | IPC_64, &raw mut ds) })?;
+                unsafe {
+                    buf.write(msqid_ds {
+                        msg_perm: ds.msg_perm.to_perm(),
+                        msg_qnum: ds.msg_qnum,
+                        msg_qbytes: ds.msg_qbytes,
+                        msg_lspid: ds.msg_lspid,
+                        msg_lrpid: ds.msg_lrpid,
+                        msg_stime: ds.msg_stime.into(),
+                        msg_rtime: ds.msg_rtime.into(),
+                        msg_ctime: ds.msg_ctime.into(),
+                    })
+                };
+            }
+            IPC_SET => {
+                let buf = unsafe { &*buf };
+                let mut ds = msqid64_ds {
+                    msg_perm: ipc64_perm::from_perm(&buf.msg_perm),
+                    msg_qbytes: buf.msg_qbytes,
+                    ..msqid64_ds::default()
+                };
+                e_raw(unsafe { syscall!(MSGCTL, msqid, cmd | IPC_64, &raw mut ds) })?;
+            }
+            _ => {
+                e_raw(unsafe { syscall!(MSGCTL, msqid, cmd | IPC_64, ptr::null_mut::<c_void>()) })?;
+            }
+        }
+        Ok(())
+    }
+
+    fn msgget(key: key_t, msgflg: c_int) -> Result<c_int> {
+        Ok(e_raw(unsafe { syscall!(MSGGET, key, msgflg) })? as c_int)
+    }
+
+    unsafe fn msgrcv(
+        msqid: c_int,
+        msgp: *mut c_void,
+        msgsz: usize,
+        msgtyp: c_long,
+        msgflg: c_int,
+    ) -> Result<usize> {
+        e_raw(unsafe { syscall_cp!(MSGRCV, msqid, msgp, msgsz, msgtyp, msgflg) })
+    }
+
+    unsafe fn msgsnd(msqid: c_int, msgp: *const c_void, msgsz: usize, msgflg: c_int) -> Result<()> {
+        e_raw(unsafe { syscall_cp!(MSGSND, msqid, msgp, msgsz, msgflg) })?;
+        Ok(())
+    }
+
+    unsafe fn semctl(semid: c_int, semnum: c_int, cmd: c_int, arg: semun) -> Result<c_int> {
+        let res = match cmd {
+            IPC_STAT => {
+                let mut ds = semid64_ds::default();
+                e_raw(unsafe { syscall!(SEMCTL, semid, semnum, cmd | IPC_64, &raw mut ds) })?;
+                unsafe {
+                    arg.buf.write(semid_ds {
+                        sem_perm: ds.sem_perm.to_perm(),
+                        sem_nsems: ds.sem_nsems as c_ushort,
+                        sem_otime: ds.sem_otime.into(),
+                        sem_ctime: ds.sem_ctime.into(),
+                    })
+                };
+                0
+            }
+            IPC_SET => {
+                let mut ds = semid64_ds {
+                    sem_perm: ipc64_perm::from_perm(unsafe { &(*arg.buf).sem_perm }),
+                    ..semid64_ds::default()
+                };
+                e_raw(unsafe { syscall!(SEMCTL, semid, semnum, cmd | IPC_64, &raw mut ds) })?
+            }
+            GETALL | SETALL => {
+                e_raw(unsafe { syscall!(SEMCTL, semid, semnum, cmd | IPC_64, arg.array) })?
+            }
+            SETVAL => e_raw(unsafe { syscall!(SEMCTL, semid, semnum, cmd | IPC_64, arg.val) })?,
+            _ => e_raw(unsafe { syscall!(SEMCTL, semid, semnum, cmd | IPC_64, 0) })?,
+        };
+        Ok(res as c_int)
+    }
+
+    fn semget(key: key_t, nsems: c_int, semflg: c_int) -> Result<c_int> {
+        Ok(e_raw(unsafe { syscall!(SEMGET, key, nsems, semflg) })? as c_int)
+    }
+
+    fn semtimedop(semid: c_int, sops: &[sembuf], timeout: Option<&timespec>) -> Result<()> {
+        let timeout = timeout.map_or(ptr::null(), ptr::from_ref);
+        e_raw(unsafe { syscall!(SEMTIMEDOP, semid, sops.as_ptr(), sops.len(), timeout) })?;
+        Ok(())
+    }
+}
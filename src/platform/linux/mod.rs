@@ -8,7 +8,8 @@ use crate::{
         dirent::dirent,
         errno::{EINVAL, EIO, ENOSYS},
         fcntl::AT_EMPTY_PATH,
-        signal::{SIGCHLD, sigevent},
+        sched::sched_param,
+        signal::{SIGCHLD, SIGEV_THREAD, sigevent},
         sys_resource::{rlimit, rusage},
         sys_select::timeval,
         sys_stat::{S_IFIFO, stat},
@@ -24,10 +25,21 @@ use crate::{
 use core::{num::NonZeroU64, ptr};
 use sc::nr::{CLONE, EXIT};
 
+/// Like `syscall!`, for the blocking syscalls of cancellation points. See [`cancel`].
+macro_rules! syscall_cp {
+    ($nr:ident $(, $arg:expr)* $(,)?) => {
+        $crate::platform::sys::cancel::syscall_cp(sc::nr::$nr, [$($arg as usize),*])
+    };
+}
+
+pub(crate) mod cancel;
 mod epoll;
+mod ipc;
+mod mqueue;
 mod ptrace;
 mod signal;
 mod socket;
+mod timer;
 
 const CLONE_VM: usize = 0x0100;
 const CLONE_FS: usize = 0x0200;
@@ -35,6 +47,8 @@ const CLONE_FILES: usize = 0x0400;
 const CLONE_SIGHAND: usize = 0x0800;
 const CLONE_THREAD: usize = 0x00010000;
 
+const SCHED_RESET_ON_FORK: c_int = 0x4000_0000;
+
 #[repr(C)]
 #[derive(Default)]
 struct linux_statfs {
@@ -69,7 +83,7 @@ pub struct Sys;
 impl Sys {
     pub unsafe fn ioctl(fd: c_int, request: c_ulong, out: *mut c_void) -> Result<c_int> {
         // TODO: Somehow support varargs to syscall??
-        Ok(e_raw(syscall!(IOCTL, fd, request, out))? as c_int)
+        Ok(e_raw(unsafe { syscall_cp!(IOCTL, fd, request, out) })? as c_int)
     }
 
     // fn times(out: *mut tms) -> clock_t {
@@ -119,12 +133,21 @@ impl Pal for Sys {
         e_raw(unsafe { syscall!(CLOCK_GETTIME, clk_id, tp.as_mut_ptr()) }).map(|_| ())
     }
 
+    unsafe fn clock_nanosleep(
+        clk_id: clockid_t,
+        flags: c_int,
+        rqtp: *const timespec,
+        rmtp: *mut timespec,
+    ) -> Result<()> {
+        e_raw(unsafe { syscall_cp!(CLOCK_NANOSLEEP, clk_id, flags, rqtp, rmtp) }).map(|_| ())
+    }
+
     unsafe fn clock_settime(clk_id: clockid_t, tp: *const timespec) -> Result<()> {
         e_raw(syscall!(CLOCK_SETTIME, clk_id, tp)).map(|_| ())
     }
 
     fn close(fildes: c_int) -> Result<()> {
-        e_raw(unsafe { syscall!(CLOSE, fildes) }).map(|_| ())
+        e_raw(unsafe { syscall_cp!(CLOSE, fildes) }).map(|_| ())
     }
 
     fn dup(fildes: c_int) -> Result<c_int> {
@@ -192,7 +215,7 @@ impl Pal for Sys {
     }
 
     fn fdatasync(fildes: c_int) -> Result<()> {
-        e_raw(unsafe { syscall!(FDATASYNC, fildes) }).map(|_| ())
+        e_raw(unsafe { syscall_cp!(FDATASYNC, fildes) }).map(|_| ())
     }
 
     fn flock(fd: c_int, operation: c_int) -> Result<()> {
@@ -242,7 +265,7 @@ impl Pal for Sys {
     }
 
     fn fcntl(fildes: c_int, cmd: c_int, arg: c_ulonglong) -> Result<c_int> {
-        Ok(e_raw(unsafe { syscall!(FCNTL, fildes, cmd, arg) })? as c_int)
+        Ok(e_raw(unsafe { syscall_cp!(FCNTL, fildes, cmd, arg) })? as c_int)
     }
 
     unsafe fn fork() -> Result<pid_t> {
@@ -255,7 +278,7 @@ impl Pal for Sys {
     }
 
     fn fsync(fildes: c_int) -> Result<()> {
-        e_raw(unsafe { syscall!(FSYNC, fildes) }).map(|_| ())
+        e_raw(unsafe { syscall_cp!(FSYNC, fildes) }).map(|_| ())
     }
 
     fn ftruncate(fildes: c_int, length: off_t) -> Result<()> {
@@ -266,7 +289,7 @@ impl Pal for Sys {
     unsafe fn futex_wait(addr: *mut u32, val: u32, deadline: Option<&timespec>) -> Result<()> {
         let deadline = deadline.map_or(0, |d| ptr::from_ref(d) as usize);
         e_raw(unsafe {
-            syscall!(
+            syscall_cp!(
                 FUTEX, addr,       // uaddr
                 9,          // futex_op: FUTEX_WAIT_BITSET
                 val,        // val
@@ -499,7 +522,7 @@ impl Pal for Sys {
     }
 
     unsafe fn msync(addr: *mut c_void, len: usize, flags: c_int) -> Result<()> {
-        e_raw(syscall!(MSYNC, addr, len, flags)).map(|_| ())
+        e_raw(unsafe { syscall_cp!(MSYNC, addr, len, flags) }).map(|_| ())
     }
 
     unsafe fn munlock(addr: *const c_void, len: usize) -> Result<()> {
@@ -519,11 +542,12 @@ impl Pal for Sys {
     }
 
     unsafe fn nanosleep(rqtp: *const timespec, rmtp: *mut timespec) -> Result<()> {
-        e_raw(unsafe { syscall!(NANOSLEEP, rqtp, rmtp) }).map(|_| ())
+        e_raw(unsafe { syscall_cp!(NANOSLEEP, rqtp, rmtp) }).map(|_| ())
     }
 
     fn openat(dirfd: c_int, path: CStr, oflag: c_int, mode: mode_t) -> Result<c_int> {
-        e_raw(unsafe { syscall!(OPENAT, dirfd, path.as_ptr(), oflag, mode) }).map(|fd| fd as c_int)
+        e_raw(unsafe { syscall_cp!(OPENAT, dirfd, path.as_ptr(), oflag, mode) })
+            .map(|fd| fd as c_int)
     }
 
     fn pipe2(mut fildes: Out<[c_int; 2]>, flags: c_int) -> Result<()> {
@@ -679,6 +703,23 @@ impl Pal for Sys {
         e_raw(unsafe { syscall!(TGKILL, tgid, os_tid.thread_id, signal) }).map(|_| ())
     }
 
+    unsafe fn rlct_setschedparam(
+        os_tid: crate::pthread::OsTid,
+        policy: c_int,
+        param: &sched_param,
+    ) -> Result<()> {
+        // Linux schedules threads individually, so the process calls accept a TID as well.
+        e_raw(unsafe {
+            syscall!(
+                SCHED_SETSCHEDULER,
+                os_tid.thread_id,
+                policy,
+                ptr::from_ref(param)
+            )
+        })
+        .map(|_| ())
+    }
+
     fn current_os_tid() -> crate::pthread::OsTid {
         crate::pthread::OsTid {
             thread_id: unsafe { syscall!(GETTID) },
@@ -686,10 +727,10 @@ impl Pal for Sys {
     }
 
     fn read(fildes: c_int, buf: &mut [u8]) -> Result<usize> {
-        e_raw(unsafe { syscall!(READ, fildes, buf.as_mut_ptr(), buf.len()) })
+        e_raw(unsafe { syscall_cp!(READ, fildes, buf.as_mut_ptr(), buf.len()) })
     }
     fn pread(fildes: c_int, buf: &mut [u8], off: off_t) -> Result<usize> {
-        e_raw(unsafe { syscall!(PREAD64, fildes, buf.as_mut_ptr(), buf.len(), off) })
+        e_raw(unsafe { syscall_cp!(PREAD64, fildes, buf.as_mut_ptr(), buf.len(), off) })
     }
 
     fn readlinkat(dirfd: c_int, pathname: CStr, out: &mut [u8]) -> Result<usize> {
@@ -724,6 +765,38 @@ impl Pal for Sys {
         .map(|_| ())
     }
 
+    fn sched_get_priority_max(policy: c_int) -> Result<c_int> {
+        e_raw(unsafe { syscall!(SCHED_GET_PRIORITY_MAX, policy) }).map(|p| p as c_int)
+    }
+
+    fn sched_get_priority_min(policy: c_int) -> Result<c_int> {
+        e_raw(unsafe { syscall!(SCHED_GET_PRIORITY_MIN, policy) }).map(|p| p as c_int)
+    }
+
+    fn sched_getparam(pid: pid_t, mut param: Out<sched_param>) -> Result<()> {
+        e_raw(unsafe { syscall!(SCHED_GETPARAM, pid, param.as_mut_ptr()) }).map(|_| ())
+    }
+
+    fn sched_getscheduler(pid: pid_t) -> Result<c_int> {
+        e_raw(unsafe { syscall!(SCHED_GETSCHEDULER, pid) })
+            .map(|policy| (policy as c_int) & !SCHED_RESET_ON_FORK)
+    }
+
+    fn sched_rr_get_interval(pid: pid_t, mut interval: Out<timespec>) -> Result<()> {
+        e_raw(unsafe { syscall!(SCHED_RR_GET_INTERVAL, pid, interval.as_mut_ptr()) }).map(|_| ())
+    }
+
+    fn sched_setparam(pid: pid_t, param: &sched_param) -> Result<()> {
+        e_raw(unsafe { syscall!(SCHED_SETPARAM, pid, ptr::from_ref(param)) }).map(|_| ())
+    }
+
+    fn sched_setscheduler(pid: pid_t, policy: c_int, param: &sched_param) -> Result<c_int> {
+        // The kernel returns 0 rather than the former policy required by POSIX.
+        let old = Self::sched_getscheduler(pid)?;
+        e_raw(unsafe { syscall!(SCHED_SETSCHEDULER, pid, policy, ptr::from_ref(param)) })?;
+        Ok(old)
+    }
+
     fn sched_yield() -> Result<()> {
         e_raw(unsafe { syscall!(SCHED_YIELD) }).map(|_| ())
     }
@@ -761,6 +834,10 @@ impl Pal for Sys {
     }
 
     fn timer_create(clock_id: clockid_t, evp: &sigevent) -> Result<timer_t> {
+        if evp.sigev_notify == SIGEV_THREAD {
+            return timer::create(clock_id, evp);
+        }
+
         let mut timer_id: timer_t = ptr::null_mut();
         let mut timerptr = Out::from_mut(&mut timer_id);
         e_raw(unsafe {
@@ -775,7 +852,11 @@ impl Pal for Sys {
     }
 
     fn timer_delete(timerid: timer_t) -> Result<()> {
-        e_raw(unsafe { syscall!(TIMER_DELETE, timerid) }).map(|_| ())
+        timer::delete(timerid)
+    }
+
+    fn timer_getoverrun(timerid: timer_t) -> Result<c_int> {
+        e_raw(unsafe { syscall!(TIMER_GETOVERRUN, timerid) }).map(|o| o as c_int)
     }
 
     fn timer_gettime(timerid: timer_t) -> Result<itimerspec> {
@@ -819,7 +900,7 @@ impl Pal for Sys {
 
     fn waitpid(pid: pid_t, stat_loc: Option<Out<c_int>>, options: c_int) -> Result<pid_t> {
         e_raw(unsafe {
-            syscall!(
+            syscall_cp!(
                 WAIT4,
                 pid,
                 stat_loc.map_or(core::ptr::null_mut(), |mut o| o.as_mut_ptr()),
@@ -831,10 +912,10 @@ impl Pal for Sys {
     }
 
     fn write(fildes: c_int, buf: &[u8]) -> Result<usize> {
-        e_raw(unsafe { syscall!(WRITE, fildes, buf.as_ptr(), buf.len()) })
+        e_raw(unsafe { syscall_cp!(WRITE, fildes, buf.as_ptr(), buf.len()) })
     }
     fn pwrite(fildes: c_int, buf: &[u8], off: off_t) -> Result<usize> {
-        e_raw(unsafe { syscall!(PWRITE64, fildes, buf.as_ptr(), buf.len(), off) })
+        e_raw(unsafe { syscall_cp!(PWRITE64, fildes, buf.as_ptr(), buf.len(), off) })
     }
 
     fn verify() -> bool {
@@ -0,0 +1,211 @@
+use alloc::boxed::Box;
+use core::{mem, ptr};
+
+use super::{Sys, e_raw};
+use crate::{
+    c_str::CStr,
+    error::{Errno, Result},
+    header::{
+        bits_sigset_t::sigset_t,
+        errno::{EACCES, EINVAL, EPERM},
+        mqueue::{mq_attr, mqd_t},
+        pthread::{PTHREAD_CREATE_DETACHED, RlctAttr},
+        signal::{SIG_SETMASK, SIGEV_THREAD, sigevent, sigval},
+        sys_socket::constants::{MSG_NOSIGNAL, MSG_WAITALL, SOCK_CLOEXEC, SOCK_RAW},
+        time::timespec,
+    },
+    out::Out,
+    platform::{
+        Pal, PalMqueue, PalSignal,
+        types::{c_int, c_uint, c_void, mode_t},
+    },
+    pthread,
+};
+
+const AF_NETLINK: c_int = 16;
+/// Length of the message the kernel sends to the netlink socket of a `SIGEV_THREAD` registration.
+const NOTIFY_COOKIE_LEN: usize = 32;
+/// Last byte of the message when the notification is delivered, rather than removed.
+const NOTIFY_WOKENUP: u8 = 1;
+
+/// The kernel expects queue names without the leading slash.
+fn strip_slash(name: CStr) -> Result<CStr> {
+    match name.split_first() {
+        Some((b'/', rest)) => Ok(rest),
+        _ => Err(Errno(EINVAL)),
+    }
+}
+
+/// State passed to the helper thread of a `SIGEV_THREAD` registration.
+struct Notifier {
+    socket: c_int,
+    function: extern "C" fn(sigval),
+    value: sigval,
+}
+
+unsafe impl Send for Notifier {}
+
+extern "C" fn notify_routine(arg: *mut c_void) -> *mut c_void {
+    let notifier = unsafe { Box::from_raw(arg.cast::<Notifier>()) };
+
+    // The kernel sends a message when the notification is delivered, and also when it is removed
+    // by mq_notify() or by closing the descriptor, so the helper never outlives the registration.
+    let mut buf = [0_u8; NOTIFY_COOKIE_LEN];
+    let received = e_raw(unsafe {
+        syscall!(
+            RECVFROM,
+            notifier.socket,
+            buf.as_mut_ptr(),
+            buf.len(),
+            MSG_NOSIGNAL | MSG_WAITALL,
+            0,
+            0
+        )
+    });
+    let _ = Sys::close(notifier.socket);
+
+    if received == Ok(buf.len()) && buf[NOTIFY_COOKIE_LEN - 1] == NOTIFY_WOKENUP {
+        (notifier.function)(notifier.value);
+    }
+    ptr::null_mut()
+}
+
+/// Registers a notification calling `notification.sigev_notify_function` from a helper thread,
+/// which is created with `notification.sigev_notify_attributes`.
+///
+/// The kernel cannot call functions, so it notifies the helper through a netlink socket instead.
+fn notify_thread(mqdes: mqd_t, notification: &sigevent) -> Result<()> {
+    let function = notification.sigev_notify_function.ok_or(Errno(EINVAL))?;
+    let socket =
+        e_raw(unsafe { syscall!(SOCKET, AF_NETLINK, SOCK_RAW | SOCK_CLOEXEC, 0) })? as c_int;
+
+    // The socket is registered before the helper exists, so that registration errors are
+    // reported without a thread to clean up. Notifications are kept in the socket meanwhile.
+    let cookie = [0_u8; NOTIFY_COOKIE_LEN];
+    let mut kernel_notification: sigevent = unsafe { mem::zeroed() };
+    kernel_notification.sigev_notify = SIGEV_THREAD;
+    kernel_notification.sigev_signo = socket;
+    kernel_notification.sigev_value = sigval {
+        sival_ptr: cookie.as_ptr().cast_mut().cast(),
+    };
+    if let Err(err) = e_raw(unsafe { syscall!(MQ_NOTIFY, mqdes, &raw const kernel_notification) }) {
+        let _ = Sys::close(socket);
+        return Err(err);
+    }
+
+    // Nobody joins the helper, so it must be detached whatever the attributes say.
+    let mut attr = unsafe {
+        notification
+            .sigev_notify_attributes
+            .cast::<RlctAttr>()
+            .as_ref()
+    }
+    .cloned()
+    .unwrap_or_default();
+    attr.detachstate = PTHREAD_CREATE_DETACHED as _;
+
+    // The helper inherits the signal mask, so it never runs signal handlers.
+    let mut old_mask: sigset_t = 0;
+    Sys::sigprocmask(SIG_SETMASK, Some(&!0), Some(&mut old_mask))?;
+    let arg = Box::into_raw(Box::new(Notifier {
+        socket,
+        function,
+        value: notification.sigev_value,
+    }))
+    .cast::<c_void>();
+    let thread = unsafe { pthread::create(Some(&attr), notify_routine, arg) };
+    Sys::sigprocmask(SIG_SETMASK, Some(&old_mask), None).expect("failed to restore signal mask");
+
+    if let Err(err) = thread {
+        drop(unsafe { Box::from_raw(arg.cast::<Notifier>()) });
+        let _ = e_raw(unsafe { syscall!(MQ_NOTIFY, mqdes, ptr::null::<sigevent>()) });
+        let _ = Sys::close(socket);
+        return Err(err);
+    }
+    Ok(())
+}
+
+impl PalMqueue for Sys {
+    fn mq_close(mqdes: mqd_t) -> Result<()> {
+        Sys::close(mqdes)
+    }
+
+    fn mq_getsetattr(
+        mqdes: mqd_t,
+        new: Option<&mq_attr>,
+        mut old: Option<Out<mq_attr>>,
+    ) -> Result<()> {
+        let new = new.map_or(ptr::null(), ptr::from_ref);
+        let old = old.as_mut().map_or(ptr::null_mut(), Out::as_mut_ptr);
+        e_raw(unsafe { syscall!(MQ_GETSETATTR, mqdes, new, old) })?;
+        Ok(())
+    }
+
+    fn mq_notify(mqdes: mqd_t, notification: Option<&sigevent>) -> Result<()> {
+        if let Some(notification) = notification
+            && notification.sigev_notify == SIGEV_THREAD
+        {
+            return notify_thread(mqdes, notification);
+        }
+
+        let notification = notification.map_or(ptr::null(), ptr::from_ref);
+        e_raw(unsafe { syscall!(MQ_NOTIFY, mqdes, notification) })?;
+        Ok(())
+    }
+
+    fn mq_open(name: CStr, oflag: c_int, mode: mode_t, attr: Option<&mq_attr>) -> Result<mqd_t> {
+        let name = strip_slash(name)?;
+        let attr = attr.map_or(ptr::null(), ptr::from_ref);
+        Ok(e_raw(unsafe { syscall!(MQ_OPEN, name.as_ptr(), oflag, mode, attr) })? as mqd_t)
+    }
+
+    fn mq_timedreceive(
+        mqdes: mqd_t,
+        mut msg: Out<[u8]>,
+        mut msg_prio: Option<Out<c_uint>>,
+        abstime: Option<&timespec>,
+    ) -> Result<usize> {
+        let msg_prio = msg_prio.as_mut().map_or(ptr::null_mut(), Out::as_mut_ptr);
+        let abstime = abstime.map_or(ptr::null(), ptr::from_ref);
+        e_raw(unsafe {
+            syscall_cp!(
+                MQ_TIMEDRECEIVE,
+                mqdes,
+                msg.as_mut_ptr().cast::<u8>(),
+                msg.len(),
+                msg_prio,
+                abstime
+            )
+        })
+    }
+
+    fn mq_timedsend(
+        mqdes: mqd_t,
+        msg: &[u8],
+        msg_prio: c_uint,
+        abstime: Option<&timespec>,
+    ) -> Result<()> {
+        let abstime = abstime.map_or(ptr::null(), ptr::from_ref);
+        e_raw(unsafe {
+            syscall_cp!(
+                MQ_TIMEDSEND,
+                mqdes,
+                msg.as_ptr(),
+                msg.len(),
+                msg_prio,
+                abstime
+            )
+        })?;
+        Ok(())
+    }
+
+    fn mq_unlink(name: CStr) -> Result<()> {
+        let name = strip_slash(name)?;
+        match e_raw(unsafe { syscall!(MQ_UNLINK, name.as_ptr()) }) {
+            Ok(_) => Ok(()),
+            // POSIX requires EACCES for missing permissions.
+            Err(Errno(EPERM)) => Err(Errno(EACCES)),
+            Err(err) => Err(err),
+        }
+    }
+}
@@ -134,7 +134,7 @@ impl PalSignal for Sys {
 
     fn sigsuspend(mask: &sigset_t) -> Errno {
         unsafe {
-            e_raw(syscall!(
+            e_raw(syscall_cp!(
                 RT_SIGSUSPEND,
                 ptr::from_ref::<sigset_t>(mask),
                 size_of::<sigset_t>()
@@ -149,7 +149,7 @@ impl PalSignal for Sys {
         tp: Option<&timespec>,
     ) -> Result<c_int> {
         unsafe {
-            e_raw(syscall!(
+            e_raw(syscall_cp!(
                 RT_SIGTIMEDWAIT,
                 ptr::from_ref(set),
                 sig.map_or_else(ptr::null_mut, ptr::from_mut),
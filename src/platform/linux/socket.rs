@@ -14,7 +14,7 @@ impl PalSocket for Sys {
         address: *mut sockaddr,
         address_len: *mut socklen_t,
     ) -> Result<c_int> {
-        Ok(e_raw(syscall!(ACCEPT, socket, address, address_len))? as c_int)
+        Ok(e_raw(unsafe { syscall_cp!(ACCEPT, socket, address, address_len) })? as c_int)
     }
 
     unsafe fn bind(socket: c_int, address: *const sockaddr, address_len: socklen_t) -> Result<()> {
@@ -27,7 +27,7 @@ impl PalSocket for Sys {
         address: *const sockaddr,
         address_len: socklen_t,
     ) -> Result<c_int> {
-        Ok(e_raw(syscall!(CONNECT, socket, address, address_len))? as c_int)
+        Ok(e_raw(unsafe { syscall_cp!(CONNECT, socket, address, address_len) })? as c_int)
     }
 
     unsafe fn getpeername(
@@ -81,23 +81,15 @@ impl PalSocket for Sys {
         address: *mut sockaddr,
         address_len: *mut socklen_t,
     ) -> Result<usize> {
-        e_raw(syscall!(
-            RECVFROM,
-            socket,
-            buf,
-            len,
-            flags,
-            address,
-            address_len
-        ))
+        e_raw(unsafe { syscall_cp!(RECVFROM, socket, buf, len, flags, address, address_len) })
     }
 
     unsafe fn recvmsg(socket: c_int, msg: *mut msghdr, flags: c_int) -> Result<usize> {
-        e_raw(syscall!(RECVMSG, socket, msg, flags))
+        e_raw(unsafe { syscall_cp!(RECVMSG, socket, msg, flags) })
     }
 
     unsafe fn sendmsg(socket: c_int, msg: *const msghdr, flags: c_int) -> Result<usize> {
-        e_raw(syscall!(SENDMSG, socket, msg, flags))
+        e_raw(unsafe { syscall_cp!(SENDMSG, socket, msg, flags) })
     }
 
     unsafe fn sendto(
@@ -108,9 +100,7 @@ impl PalSocket for Sys {
         dest_addr: *const sockaddr,
         dest_len: socklen_t,
     ) -> Result<usize> {
-        e_raw(syscall!(
-            SENDTO, socket, buf, len, flags, dest_addr, dest_len
-        ))
+        e_raw(unsafe { syscall_cp!(SENDTO, socket, buf, len, flags, dest_addr, dest_len) })
     }
 
     unsafe fn setsockopt(
@@ -0,0 +1,155 @@
+//! `SIGEV_THREAD` notifications for POSIX timers.
+//!
+//! The kernel cannot call functions, so every `SIGEV_THREAD` timer gets a
+//! helper thread that the kernel signals with [`SIGRT_RLCT_TIMER`] through
+//! `SIGEV_THREAD_ID`. The helper waits for that signal and calls the
+//! notification function.
+
+use alloc::{collections::BTreeMap, sync::Arc};
+use core::{
+    mem, ptr,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use super::{Sys, e_raw};
+use crate::{
+    error::{Errno, Result},
+    header::{
+        bits_sigset_t::sigset_t,
+        errno::EINVAL,
+        pthread::{PTHREAD_CREATE_DETACHED, RlctAttr},
+        signal::{SIG_SETMASK, SIGEV_THREAD_ID, sigevent, sigval},
+    },
+    out::Out,
+    platform::{
+        Pal, PalSignal,
+        types::{c_int, c_void, clockid_t, timer_t},
+    },
+    pthread::{self, OsTid, Pthread, SIGRT_RLCT_TIMER},
+    sync::Mutex,
+};
+
+/// Helper threads of `SIGEV_THREAD` timers, by kernel timer ID.
+static NOTIFIERS: Mutex<BTreeMap<usize, (OsTid, Arc<Notifier>)>> = Mutex::new(BTreeMap::new());
+
+/// The kernel's `sigevent`, where the thread ID takes the place of
+/// `sigev_notify_function`.
+#[repr(C)]
+struct KernelSigevent {
+    sigev_value: sigval,
+    sigev_signo: c_int,
+    sigev_notify: c_int,
+    sigev_notify_thread_id: c_int,
+    #[cfg(target_pointer_width = "64")]
+    __unused1: [c_int; 11],
+    #[cfg(target_pointer_width = "32")]
+    __unused1: [c_int; 12],
+}
+
+/// State shared by a timer and its helper thread.
+struct Notifier {
+    function: extern "C" fn(sigval),
+    value: sigval,
+    /// Set when the timer is deleted, before waking the helper for the last
+    /// time.
+    deleted: AtomicBool,
+}
+
+unsafe impl Send for Notifier {}
+unsafe impl Sync for Notifier {}
+
+impl Notifier {
+    /// Make the helper thread exit once it is done with any notification in
+    /// progress. This may be called from the helper itself.
+    fn stop(&self, os_tid: OsTid) {
+        self.deleted.store(true, Ordering::Release);
+        if let Err(e) = unsafe { Sys::rlct_kill(os_tid, SIGRT_RLCT_TIMER) } {
+            log::warn!("failed to wake the timer notification thread: {e:?}");
+        }
+    }
+}
+
+extern "C" fn notify_routine(arg: *mut c_void) -> *mut c_void {
+    let notifier = unsafe { Arc::from_raw(arg.cast_const().cast::<Notifier>()) };
+    let set: sigset_t = 1 << (SIGRT_RLCT_TIMER - 1);
+
+    // Every signal is blocked in this thread, so only the timer's can wake it.
+    while !notifier.deleted.load(Ordering::Acquire) {
+        if Sys::sigtimedwait(&set, None, None).is_err() || notifier.deleted.load(Ordering::Acquire)
+        {
+            continue;
+        }
+        (notifier.function)(notifier.value);
+    }
+    ptr::null_mut()
+}
+
+/// Create a timer calling `evp.sigev_notify_function` from a helper thread,
+/// which is created with `evp.sigev_notify_attributes`.
+pub(super) fn create(clock_id: clockid_t, evp: &sigevent) -> Result<timer_t> {
+    let function = evp.sigev_notify_function.ok_or(Errno(EINVAL))?;
+    let notifier = Arc::new(Notifier {
+        function,
+        value: evp.sigev_value,
+        deleted: AtomicBool::new(false),
+    });
+
+    // Nobody joins the helper, so it must be detached whatever the attributes
+    // say.
+    let mut attr = unsafe { evp.sigev_notify_attributes.cast::<RlctAttr>().as_ref() }
+        .cloned()
+        .unwrap_or_default();
+    attr.detachstate = PTHREAD_CREATE_DETACHED as _;
+
+    // The helper inherits the signal mask, so it never runs signal handlers
+    // and is not woken up by anything else than the timer.
+    let mut old_mask: sigset_t = 0;
+    Sys::sigprocmask(SIG_SETMASK, Some(&!0), Some(&mut old_mask))?;
+    let arg = Arc::into_raw(Arc::clone(&notifier))
+        .cast_mut()
+        .cast::<c_void>();
+    let thread = unsafe { pthread::create(Some(&attr), notify_routine, arg) };
+    Sys::sigprocmask(SIG_SETMASK, Some(&old_mask), None).expect("failed to restore signal mask");
+    let thread = match thread {
+        Ok(thread) => thread,
+        Err(e) => {
+            drop(unsafe { Arc::from_raw(arg.cast_const().cast::<Notifier>()) });
+            return Err(e);
+        }
+    };
+    let os_tid = unsafe { (*thread.cast::<Pthread>()).os_tid.get().read() };
+
+    let mut kevp: KernelSigevent = unsafe { mem::zeroed() };
+    kevp.sigev_signo = SIGRT_RLCT_TIMER as c_int;
+    kevp.sigev_notify = SIGEV_THREAD_ID;
+    kevp.sigev_notify_thread_id = os_tid.thread_id as c_int;
+
+    let mut timer_id: timer_t = ptr::null_mut();
+    let mut timerptr = Out::from_mut(&mut timer_id);
+    if let Err(e) = e_raw(unsafe {
+        syscall!(
+            TIMER_CREATE,
+            clock_id,
+            &raw const kevp,
+            timerptr.as_mut_ptr()
+        )
+    }) {
+        notifier.stop(os_tid);
+        return Err(e);
+    }
+
+    NOTIFIERS.lock().insert(timer_id.addr(), (os_tid, notifier));
+    Ok(timer_id)
+}
+
+/// Delete a timer, along with its helper thread if it has one.
+pub(super) fn delete(timerid: timer_t) -> Result<()> {
+    // The lock is held across the syscall, so that a new timer reusing the
+    // ID cannot be registered before the old one is removed.
+    let mut notifiers = NOTIFIERS.lock();
+    e_raw(unsafe { syscall!(TIMER_DELETE, timerid) })?;
+    if let Some((os_tid, notifier)) = notifiers.remove(&timerid.addr()) {
+        notifier.stop(os_tid);
+    }
+    Ok(())
+}
@@ -14,7 +14,7 @@ mod allocator;
 
 pub mod logger;
 
-pub use self::pal::{Pal, PalEpoll, PalPtrace, PalSignal, PalSocket};
+pub use self::pal::{Pal, PalEpoll, PalIpc, PalMqueue, PalPtrace, PalSignal, PalSocket};
 
 mod pal;
 
@@ -0,0 +1,42 @@
+use crate::{
+    error::Result,
+    header::{
+        sys_msg::msqid_ds,
+        sys_sem::{sembuf, semun},
+        time::timespec,
+    },
+    platform::{
+        Pal,
+        types::{c_int, c_long, c_void, key_t},
+    },
+};
+
+/// Platform abstraction of XSI interprocess communication functionality.
+pub trait PalIpc: Pal {
+    /// Platform implementation of [`msgctl()`](crate::header::sys_msg::msgctl) from [`sys/msg.h`](crate::header::sys_msg).
+    unsafe fn msgctl(msqid: c_int, cmd: c_int, buf: *mut msqid_ds) -> Result<()>;
+
+    /// Platform implementation of [`msgget()`](crate::header::sys_msg::msgget) from [`sys/msg.h`](crate::header::sys_msg).
+    fn msgget(key: key_t, msgflg: c_int) -> Result<c_int>;
+
+    /// Platform implementation of [`msgrcv()`](crate::header::sys_msg::msgrcv) from [`sys/msg.h`](crate::header::sys_msg).
+    unsafe fn msgrcv(
+        msqid: c_int,
+        msgp: *mut c_void,
+        msgsz: usize,
+        msgtyp: c_long,
+        msgflg: c_int,
+    ) -> Result<usize>;
+
+    /// Platform implementation of [`msgsnd()`](crate::header::sys_msg::msgsnd) from [`sys/msg.h`](crate::header::sys_msg).
+    unsafe fn msgsnd(msqid: c_int, msgp: *const c_void, msgsz: usize, msgflg: c_int) -> Result<()>;
+
+    /// Platform implementation of [`semctl()`](crate::header::sys_sem::semctl) from [`sys/sem.h`](crate::header::sys_sem).
+    unsafe fn semctl(semid: c_int, semnum: c_int, cmd: c_int, arg: semun) -> Result<c_int>;
+
+    /// Platform implementation of [`semget()`](crate::header::sys_sem::semget) from [`sys/sem.h`](crate::header::sys_sem).
+    fn semget(key: key_t, nsems: c_int, semflg: c_int) -> Result<c_int>;
+
+    /// Platform implementation of [`semtimedop()`](crate::header::sys_sem::semtimedop) from [`sys/sem.h`](crate::header::sys_sem).
+    fn semtimedop(semid: c_int, sops: &[sembuf], timeout: Option<&timespec>) -> Result<()>;
+}
@@ -6,6 +6,7 @@ use crate::{
     error::{Errno, Result},
     header::{
         fcntl::{AT_EMPTY_PATH, AT_FDCWD, AT_REMOVEDIR, AT_SYMLINK_NOFOLLOW, F_DUPFD},
+        sched::sched_param,
         signal::sigevent,
         sys_resource::{rlimit, rusage},
         sys_select::timeval,
@@ -24,6 +25,12 @@ use crate::{
 pub use self::epoll::PalEpoll;
 mod epoll;
 
+pub use self::ipc::PalIpc;
+mod ipc;
+
+pub use self::mqueue::PalMqueue;
+mod mqueue;
+
 pub use self::ptrace::PalPtrace;
 mod ptrace;
 
@@ -66,6 +73,14 @@ pub trait Pal {
     /// Platform implementation of [`clock_gettime()`](crate::header::time::clock_gettime) from [`time.h`](crate::header::time).
     fn clock_gettime(clk_id: clockid_t, tp: Out<timespec>) -> Result<()>;
 
+    /// Platform implementation of [`clock_nanosleep()`](crate::header::time::clock_nanosleep) from [`time.h`](crate::header::time).
+    unsafe fn clock_nanosleep(
+        clk_id: clockid_t,
+        flags: c_int,
+        rqtp: *const timespec,
+        rmtp: *mut timespec,
+    ) -> Result<()>;
+
     /// Platform implementation of [`clock_settime()`](crate::header::time::clock_settime) from [`time.h`](crate::header::time).
     unsafe fn clock_settime(clk_id: clockid_t, tp: *const timespec) -> Result<()>;
 
@@ -373,6 +388,13 @@ pub trait Pal {
 
     unsafe fn rlct_kill(os_tid: pthread::OsTid, signal: usize) -> Result<()>;
 
+    /// Applies a scheduling policy and parameters to a single thread.
+    unsafe fn rlct_setschedparam(
+        os_tid: pthread::OsTid,
+        policy: c_int,
+        param: &sched_param,
+    ) -> Result<()>;
+
     fn current_os_tid() -> pthread::OsTid;
 
     /// Platform implementation of [`read()`](crate::header::unistd::read) from [`unistd.h`](crate::header::unistd).
@@ -413,6 +435,29 @@ pub trait Pal {
         Self::unlinkat(AT_FDCWD, path, AT_REMOVEDIR)
     }
 
+    /// Platform implementation of [`sched_get_priority_max()`](crate::header::sched::sched_get_priority_max) from [`sched.h`](crate::header::sched).
+    fn sched_get_priority_max(policy: c_int) -> Result<c_int>;
+
+    /// Platform implementation of [`sched_get_priority_min()`](crate::header::sched::sched_get_priority_min) from [`sched.h`](crate::header::sched).
+    fn sched_get_priority_min(policy: c_int) -> Result<c_int>;
+
+    /// Platform implementation of [`sched_getparam()`](crate::header::sched::sched_getparam) from [`sched.h`](crate::header::sched).
+    fn sched_getparam(pid: pid_t, param: Out<sched_param>) -> Result<()>;
+
+    /// Platform implementation of [`sched_getscheduler()`](crate::header::sched::sched_getscheduler) from [`sched.h`](crate::header::sched).
+    fn sched_getscheduler(pid: pid_t) -> Result<c_int>;
+
+    /// Platform implementation of [`sched_rr_get_interval()`](crate::header::sched::sched_rr_get_interval) from [`sched.h`](crate::header::sched).
+    fn sched_rr_get_interval(pid: pid_t, interval: Out<timespec>) -> Result<()>;
+
+    /// Platform implementation of [`sched_setparam()`](crate::header::sched::sched_setparam) from [`sched.h`](crate::header::sched).
+    fn sched_setparam(pid: pid_t, param: &sched_param) -> Result<()>;
+
+    /// Platform implementation of [`sched_setscheduler()`](crate::header::sched::sched_setscheduler) from [`sched.h`](crate::header::sched).
+    ///
+    /// Returns the former scheduling policy.
+    fn sched_setscheduler(pid: pid_t, policy: c_int, param: &sched_param) -> Result<c_int>;
+
     /// Platform implementation of [`sched_yield()`](crate::header::sched::sched_yield) from [`sched.h`](crate::header::sched).
     fn sched_yield() -> Result<()>;
 
@@ -459,6 +504,9 @@ pub trait Pal {
     /// Platform implementation of [`timer_delete()`](crate::header::time::timer_delete) from [`time.h`](crate::header::time).
     fn timer_delete(timerid: timer_t) -> Result<()>;
 
+    /// Platform implementation of [`timer_getoverrun()`](crate::header::time::timer_getoverrun) from [`time.h`](crate::header::time).
+    fn timer_getoverrun(timerid: timer_t) -> Result<c_int>;
+
     /// Platform implementation of [`timer_gettime()`](crate::header::time::timer_gettime) from [`time.h`](crate::header::time).
     fn timer_gettime(timerid: timer_t) -> Result<itimerspec>;
 
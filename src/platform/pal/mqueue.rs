@@ -0,0 +1,48 @@
+use crate::{
+    c_str::CStr,
+    error::Result,
+    header::{
+        mqueue::{mq_attr, mqd_t},
+        signal::sigevent,
+        time::timespec,
+    },
+    out::Out,
+    platform::{
+        Pal,
+        types::{c_int, c_uint, mode_t},
+    },
+};
+
+/// Platform abstraction of POSIX message queue functionality.
+pub trait PalMqueue: Pal {
+    /// Platform implementation of [`mq_close()`](crate::header::mqueue::mq_close) from [`mqueue.h`](crate::header::mqueue).
+    fn mq_close(mqdes: mqd_t) -> Result<()>;
+
+    /// Platform implementation of [`mq_getattr()`](crate::header::mqueue::mq_getattr) and [`mq_setattr()`](crate::header::mqueue::mq_setattr) from [`mqueue.h`](crate::header::mqueue).
+    fn mq_getsetattr(mqdes: mqd_t, new: Option<&mq_attr>, old: Option<Out<mq_attr>>) -> Result<()>;
+
+    /// Platform implementation of [`mq_notify()`](crate::header::mqueue::mq_notify) from [`mqueue.h`](crate::header::mqueue).
+    fn mq_notify(mqdes: mqd_t, notification: Option<&sigevent>) -> Result<()>;
+
+    /// Platform implementation of [`mq_open()`](crate::header::mqueue::mq_open) from [`mqueue.h`](crate::header::mqueue).
+    fn mq_open(name: CStr, oflag: c_int, mode: mode_t, attr: Option<&mq_attr>) -> Result<mqd_t>;
+
+    /// Platform implementation of [`mq_timedreceive()`](crate::header::mqueue::mq_timedreceive) from [`mqueue.h`](crate::header::mqueue).
+    fn mq_timedreceive(
+        mqdes: mqd_t,
+        msg: Out<[u8]>,
+        msg_prio: Option<Out<c_uint>>,
+        abstime: Option<&timespec>,
+    ) -> Result<usize>;
+
+    /// Platform implementation of [`mq_timedsend()`](crate::header::mqueue::mq_timedsend) from [`mqueue.h`](crate::header::mqueue).
+    fn mq_timedsend(
+        mqdes: mqd_t,
+        msg: &[u8],
+        msg_prio: c_uint,
+        abstime: Option<&timespec>,
+    ) -> Result<()>;
+
+    /// Platform implementation of [`mq_unlink()`](crate::header::mqueue::mq_unlink) from [`mqueue.h`](crate::header::mqueue).
+    fn mq_unlink(name: CStr) -> Result<()>;
+}
@@ -0,0 +1,1107 @@
+//! XSI semaphores and message queues, implemented in userspace.
+//!
+//! Identifiers are allocated in a registry, a shared memory object that maps keys (see
+//! [`ftok()`](crate::header::sys_ipc::ftok)) to identifiers, and is mapped by every process using
+//! XSI IPC. Each semaphore set and message queue is a separate shared memory object named after its
+//! identifier. Processes map these objects on first use, and synchronize through futexes in their
+//! headers.
+//!
+//! `SEM_UNDO` adjustments are kept in the semaphore set, along with the process that made them.
+//! A process reverts its own adjustments when it exits, and those of processes that died without
+//! doing so, such as when killed by a signal, are reverted by the next caller using the set.
+
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::{
+    mem::{self, align_of, size_of},
+    ptr, slice,
+    sync::atomic::{AtomicI32, AtomicU32, Ordering},
+};
+
+use crate::{
+    c_str::{CStr, CString},
+    error::{Errno, Result},
+    header::{
+        errno::{
+            E2BIG, EACCES, EAGAIN, EEXIST, EFBIG, EIDRM, EINVAL, ENOENT, ENOMSG, ENOSPC, EPERM,
+            ERANGE, ESRCH,
+        },
+        fcntl::{O_CLOEXEC, O_CREAT, O_EXCL, O_RDWR},
+        sys_ipc::{
+            IPC_CREAT, IPC_EXCL, IPC_NOWAIT, IPC_PRIVATE, IPC_RMID, IPC_SET, IPC_STAT, ipc_perm,
+        },
+        sys_mman::{MAP_SHARED, PROT_READ, PROT_WRITE, SHM_PATH},
+        sys_msg::{MSG_EXCEPT, MSG_NOERROR, msqid_ds},
+        sys_sem::{
+            GETALL, GETNCNT, GETPID, GETVAL, GETZCNT, SEM_UNDO, SETALL, SETVAL, sembuf, semid_ds,
+            semun,
+        },
+        sys_stat::stat,
+        time::{CLOCK_MONOTONIC, timespec},
+    },
+    out::Out,
+    platform::{
+        Pal, PalIpc, PalSignal, Sys,
+        types::{c_int, c_long, c_ushort, c_void, key_t, mode_t, off_t, pid_t, time_t},
+    },
+    sync::{
+        FutexWaitResult, Mutex,
+        mutex::{manual_lock_generic, manual_unlock_generic},
+    },
+};
+
+/// Maximum number of identifiers of all kinds.
+const IPC_MNI: usize = 1024;
+/// Maximum number of semaphores per set.
+const SEMMSL: usize = 250;
+/// Maximum number of operations per `semop()` call.
+const SEMOPM: usize = 500;
+/// Maximum value of a semaphore.
+const SEMVMX: c_int = 32767;
+/// Maximum number of `SEM_UNDO` adjustments per semaphore set.
+const SEMUNDO: usize = 512;
+/// Maximum size of a message.
+const MSGMAX: usize = 8192;
+/// Default, and unprivileged maximum, number of bytes in a message queue.
+const MSGMNB: usize = 16384;
+/// Size of the message storage, which also holds a [`MsgRecord`] per message.
+const MSG_ARENA: usize = 2 * MSGMNB;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u32)]
+enum Kind {
+    Free = 0,
+    Sem = 1,
+    Msg = 2,
+}
+
+#[repr(C)]
+struct RegistryEntry {
+    kind: u32,
+    key: key_t,
+    /// Incremented whenever the entry is freed, so that identifiers are not reused immediately.
+    seq: u32,
+}
+
+/// The shared registry. A zero-initialized registry is empty and unlocked, so it can be created
+/// concurrently by any process.
+#[repr(C)]
+struct Registry {
+    lock: AtomicI32,
+    entries: [RegistryEntry; IPC_MNI],
+}
+
+fn make_id(index: usize, seq: u32) -> c_int {
+    let seq = seq as usize % (c_int::MAX as usize / IPC_MNI);
+    (seq * IPC_MNI + index) as c_int
+}
+
+fn object_path(kind: Kind, id: c_int) -> CString {
+    let name = match kind {
+        Kind::Sem => "sysv_sem",
+        Kind::Msg => "sysv_msg",
+        Kind::Free => unreachable!(),
+    };
+    let mut path = SHM_PATH.to_vec();
+    path.extend_from_slice(format!("{name}.{id}").as_bytes());
+    CString::new(path).unwrap()
+}
+
+/// Address of the mapped registry, or `0` before the first use.
+static REGISTRY: Mutex<usize> = Mutex::new(0);
+
+fn registry() -> Result<*mut Registry> {
+    let mut registry = REGISTRY.lock();
+    if *registry == 0 {
+        let mut path = SHM_PATH.to_vec();
+        path.extend_from_slice(b"sysv_ipc");
+        let path = CString::new(path).unwrap();
+
+        let fd = Sys::open(CStr::borrow(&path), O_RDWR | O_CREAT | O_CLOEXEC, 0o666)?;
+        // Every user of XSI IPC needs access, regardless of the umask of the first one.
+        let _ = Sys::fchmod(fd, 0o666);
+        let mapped = map_fd(fd, Some(size_of::<Registry>()));
+        let _ = Sys::close(fd);
+        *registry = mapped? as usize;
+    }
+    Ok(*registry as *mut Registry)
+}
+
+/// Holds the registry lock.
+struct RegistryGuard(*mut Registry);
+
+impl RegistryGuard {
+    fn lock() -> Result<Self> {
+        let registry = registry()?;
+        unsafe { manual_lock_generic(&(*registry).lock) };
+        Ok(Self(registry))
+    }
+
+    fn entry(&mut self, index: usize) -> &mut RegistryEntry {
+        unsafe { &mut (*self.0).entries[index] }
+    }
+
+    /// Returns whether `id` currently refers to an object of `kind`.
+    fn is_live(&mut self, kind: Kind, id: c_int) -> bool {
+        let Ok(id) = usize::try_from(id) else {
+            return false;
+        };
+        let entry = self.entry(id % IPC_MNI);
+        entry.kind == kind as u32 && make_id(id % IPC_MNI, entry.seq) as usize == id
+    }
+}
+
+impl Drop for RegistryGuard {
+    fn drop(&mut self) {
+        unsafe { manual_unlock_generic(&(*self.0).lock) };
+    }
+}
+
+/// Maps the shared memory object `fd`, resizing it to `size` if it is smaller.
+fn map_fd(fd: c_int, size: Option<usize>) -> Result<*mut u8> {
+    let mut st = stat::default();
+    Sys::fstat(fd, Out::from_mut(&mut st))?;
+    let current = usize::try_from(st.st_size).map_err(|_| Errno(EINVAL))?;
+
+    let size = match size {
+        Some(size) if current < size => {
+            Sys::ftruncate(fd, size as off_t)?;
+            size
+        }
+        _ => current,
+    };
+    let addr = unsafe {
+        Sys::mmap(
+            ptr::null_mut(),
+            size,
+            PROT_READ | PROT_WRITE,
+            MAP_SHARED,
+            fd,
+            0,
+        )?
+    };
+    Ok(addr.cast())
+}
+
+/// Header shared by semaphore sets and message queues.
+#[repr(C)]
+struct ObjectHeader {
+    lock: AtomicI32,
+    /// Incremented on every change that blocked callers may wait for.
+    changes: AtomicU32,
+    /// Set by `IPC_RMID`, blocked callers then fail with `EIDRM`.
+    removed: AtomicU32,
+    /// Size of the whole object.
+    size: usize,
+    perm: ipc_perm,
+    ctime: time_t,
+}
+
+impl ObjectHeader {
+    fn lock(&self) {
+        unsafe { manual_lock_generic(&self.lock) };
+    }
+
+    fn unlock(&self) {
+        unsafe { manual_unlock_generic(&self.lock) };
+    }
+
+    /// Wakes all blocked callers.
+    fn changed(&self) {
+        self.changes.fetch_add(1, Ordering::Relaxed);
+        crate::sync::futex_wake(&self.changes, i32::MAX);
+    }
+
+    /// Unlocks the object and waits until it changes, or until `deadline` on the monotonic clock.
+    /// Returns with the object locked again, failing with `EINTR` when interrupted by a signal
+    /// handler.
+    fn wait(&self, deadline: Option<&timespec>) -> Result<()> {
+        let seen = self.changes.load(Ordering::Relaxed);
+        self.unlock();
+        let result = crate::sync::futex_wait_interruptible(&self.changes, seen, deadline);
+        self.lock();
+
+        if self.removed.load(Ordering::Relaxed) != 0 {
+            return Err(Errno(EIDRM));
+        }
+        match result? {
+            FutexWaitResult::TimedOut => Err(Errno(EAGAIN)),
+            FutexWaitResult::Waited | FutexWaitResult::Stale => Ok(()),
+        }
+    }
+}
+
+const ACCESS_READ: mode_t = 0o4;
+const ACCESS_WRITE: mode_t = 0o2;
+
+fn check_access(perm: &ipc_perm, requested: mode_t) -> Result<()> {
+    let euid = Sys::geteuid();
+    if euid == 0 {
+        return Ok(());
+    }
+
+    let mode = perm.mode;
+    let granted = if euid == perm.uid || euid == perm.cuid {
+        mode >> 6
+    } else if Sys::getegid() == perm.gid || Sys::getegid() == perm.cgid {
+        mode >> 3
+    } else {
+        mode
+    };
+    if requested & !granted & 0o7 != 0 {
+        return Err(Errno(EACCES));
+    }
+    Ok(())
+}
+
+/// `IPC_SET` and `IPC_RMID` are restricted to the owner and creator.
+fn check_owner(perm: &ipc_perm) -> Result<()> {
+    let euid = Sys::geteuid();
+    if euid != 0 && euid != perm.uid && euid != perm.cuid {
+        return Err(Errno(EPERM));
+    }
+    Ok(())
+}
+
+fn now() -> time_t {
+    crate::sync::rttime().tv_sec
+}
+
+struct Mapping {
+    base: *mut ObjectHeader,
+    /// Number of calls currently using the mapping.
+    users: usize,
+    /// Set when the object has been removed, the mapping is released by the last user.
+    stale: bool,
+}
+
+unsafe impl Send for Mapping {}
+
+/// Objects mapped by this process, indexed by identifier.
+static MAPPINGS: Mutex<BTreeMap<c_int, Mapping>> = Mutex::new(BTreeMap::new());
+
+/// A reference to a mapped object, valid until dropped.
+struct Object {
+    id: c_int,
+    header: *mut ObjectHeader,
+}
+
+impl Object {
+    /// Looks up the object `id` of `kind`, mapping it if necessary.
+    fn get(kind: Kind, id: c_int) -> Result<Self> {
+        let mut registry = RegistryGuard::lock()?;
+        let mut mappings = MAPPINGS.lock();
+
+        if !registry.is_live(kind, id) {
+            if let Some(mapping) = mappings.get_mut(&id) {
+                mapping.stale = true;
+                if mapping.users == 0 {
+                    let mapping = mappings.remove(&id).unwrap();
+                    unmap(mapping.base);
+                }
+            }
+            return Err(Errno(EINVAL));
+        }
+
+        let header = match mappings.get_mut(&id) {
+            Some(mapping) => {
+                mapping.users += 1;
+                mapping.base
+            }
+            None => {
+                let path = object_path(kind, id);
+                let fd = Sys::open(CStr::borrow(&path), O_RDWR | O_CLOEXEC, 0)?;
+                let base = map_fd(fd, None);
+                let _ = Sys::close(fd);
+                let base = base?.cast::<ObjectHeader>();
+                mappings.insert(
+                    id,
+                    Mapping {
+                        base,
+                        users: 1,
+                        stale: false,
+                    },
+                );
+                base
+            }
+        };
+        drop(registry);
+
+        Ok(Self { id, header })
+    }
+
+    /// Creates a new object of `kind` and `size` bytes for `key`, which is initialized by `init`
+    /// while the registry is locked.
+    fn create(
+        registry: &mut RegistryGuard,
+        kind: Kind,
+        key: key_t,
+        flags: c_int,
+        size: usize,
+        init: impl FnOnce(*mut ObjectHeader),
+    ) -> Result<c_int> {
+        let index = (0..IPC_MNI)
+            .find(|&i| registry.entry(i).kind == Kind::Free as u32)
+            .ok_or(Errno(ENOSPC))?;
+        let id = make_id(index, registry.entry(index).seq);
+
+        // Remove leftovers of processes that crashed while removing the object.
+        let path = object_path(kind, id);
+        let path = CStr::borrow(&path);
+        let _ = Sys::unlink(path);
+
+        let fd = Sys::open(path, O_RDWR | O_CREAT | O_EXCL | O_CLOEXEC, 0o600)?;
+        let base = map_fd(fd, Some(size));
+        let _ = Sys::close(fd);
+        let base = match base {
+            Ok(base) => base.cast::<ObjectHeader>(),
+            Err(err) => {
+                let _ = Sys::unlink(path);
+                return Err(err);
+            }
+        };
+
+        let (uid, gid) = (Sys::geteuid(), Sys::getegid());
+        unsafe {
+            let header = &mut *base;
+            header.size = size;
+            header.perm = ipc_perm {
+                __key: key,
+                uid,
+                gid,
+                cuid: uid,
+                cgid: gid,
+                mode: (flags & 0o777) as _,
+                __seq: registry.entry(index).seq as c_ushort,
+            };
+            header.ctime = now();
+        }
+        init(base);
+
+        let entry = registry.entry(index);
+        entry.kind = kind as u32;
+        entry.key = key;
+
+        MAPPINGS.lock().insert(
+            id,
+            Mapping {
+                base,
+                users: 0,
+                stale: false,
+            },
+        );
+        Ok(id)
+    }
+
+    /// Implements `semget()` and `msgget()`. `check` validates an existing object, `size` returns
+    /// the size of a new one.
+    fn get_or_create(
+        kind: Kind,
+        key: key_t,
+        flags: c_int,
+        check: impl FnOnce(&Object) -> Result<()>,
+        size: impl FnOnce() -> Result<usize>,
+        init: impl FnOnce(*mut ObjectHeader),
+    ) -> Result<c_int> {
+        let mut registry = RegistryGuard::lock()?;
+
+        if key != IPC_PRIVATE {
+            let existing = (0..IPC_MNI).find(|&i| {
+                let entry = registry.entry(i);
+                entry.kind == kind as u32 && entry.key == key
+            });
+            if let Some(index) = existing {
+                if flags & (IPC_CREAT | IPC_EXCL) == IPC_CREAT | IPC_EXCL {
+                    return Err(Errno(EEXIST));
+                }
+                let id = make_id(index, registry.entry(index).seq);
+                drop(registry);
+
+                let object = Object::get(kind, id)?;
+                let requested = (flags >> 6 | flags >> 3 | flags) & 0o7;
+                check_access(&object.header().perm, requested as mode_t)?;
+                check(&object)?;
+                return Ok(id);
+            }
+            if flags & IPC_CREAT == 0 {
+                return Err(Errno(ENOENT));
+            }
+        }
+
+        let size = size()?;
+        Self::create(&mut registry, kind, key, flags, size, init)
+    }
+
+    fn header(&self) -> &ObjectHeader {
+        unsafe { &*self.header }
+    }
+
+    fn data<T>(&self) -> *mut T {
+        data(self.header)
+    }
+
+    /// Implements `IPC_RMID`, the object must be locked.
+    fn remove(&self, kind: Kind) -> Result<()> {
+        check_owner(&self.header().perm)?;
+
+        let mut registry = RegistryGuard::lock()?;
+        if registry.is_live(kind, self.id) {
+            let entry = registry.entry(self.id as usize % IPC_MNI);
+            entry.kind = Kind::Free as u32;
+            entry.seq = entry.seq.wrapping_add(1);
+            let _ = Sys::unlink(CStr::borrow(&object_path(kind, self.id)));
+        }
+        drop(registry);
+
+        self.header().removed.store(1, Ordering::Relaxed);
+        self.header().changed();
+        Ok(())
+    }
+}
+
+impl Drop for Object {
+    fn drop(&mut self) {
+        let mut mappings = MAPPINGS.lock();
+        if let Some(mapping) = mappings.get_mut(&self.id) {
+            mapping.users -= 1;
+            if mapping.stale && mapping.users == 0 {
+                let mapping = mappings.remove(&self.id).unwrap();
+                unmap(mapping.base);
+            }
+        }
+    }
+}
+
+/// Returns the object-specific data following the header.
+fn data<T>(header: *mut ObjectHeader) -> *mut T {
+    unsafe { header.add(1).cast() }
+}
+
+fn unmap(header: *mut ObjectHeader) {
+    let size = unsafe { (*header).size };
+    let _ = unsafe { Sys::munmap(header.cast(), size) };
+}
+
+/// Locks the object for the lifetime of the guard.
+struct Locked<'a>(&'a Object);
+
+impl<'a> Locked<'a> {
+    fn new(object: &'a Object) -> Result<Self> {
+        object.header().lock();
+        let locked = Self(object);
+        if object.header().removed.load(Ordering::Relaxed) != 0 {
+            return Err(Errno(EINVAL));
+        }
+        Ok(locked)
+    }
+}
+
+impl Drop for Locked<'_> {
+    fn drop(&mut self) {
+        self.0.header().unlock();
+    }
+}
+
+#[repr(C)]
+struct SemSet {
+    nsems: usize,
+    otime: time_t,
+}
+
+#[repr(C)]
+struct Sem {
+    val: c_int,
+    /// Process that performed the last operation.
+    pid: pid_t,
+    /// Number of processes waiting for the value to increase.
+    ncnt: u32,
+    /// Number of processes waiting for the value to become zero.
+    zcnt: u32,
+}
+
+/// Adjustment of a semaphore by the `SEM_UNDO` operations of a process, reverted when it exits.
+#[derive(Clone, Copy, Default)]
+#[repr(C)]
+struct SemUndo {
+    /// Process that made the adjustment, or `0` if the entry is free.
+    pid: pid_t,
+    num: u32,
+    adj: c_int,
+}
+
+/// Process in which the semaphore sets of [`UNDO_SETS`] were used, so that a child does not revert
+/// the adjustments of its parent after `fork()`.
+static UNDO_PID: AtomicI32 = AtomicI32::new(0);
+/// Semaphore sets in which this process made `SEM_UNDO` adjustments.
+static UNDO_SETS: Mutex<Vec<c_int>> = Mutex::new(Vec::new());
+
+fn sem_size(nsems: usize) -> usize {
+    size_of::<ObjectHeader>()
+        + size_of::<SemSet>()
+        + nsems * size_of::<Sem>()
+        + SEMUNDO * size_of::<SemUndo>()
+}
+
+/// Returns whether the process `pid`, other than the calling one, no longer exists.
+fn process_gone(pid: pid_t) -> bool {
+    pid != Sys::getpid() && Sys::kill(pid, 0) == Err(Errno(ESRCH))
+}
+
+impl Object {
+    fn sem_set(&self) -> &mut SemSet {
+        unsafe { &mut *self.data::<SemSet>() }
+    }
+
+    fn sems(&self) -> &mut [Sem] {
+        let set = self.data::<SemSet>();
+        unsafe { slice::from_raw_parts_mut(set.add(1).cast::<Sem>(), (*set).nsems) }
+    }
+
+    /// The `SEM_UNDO` adjustments, following the semaphores.
+    fn sem_undos(&self) -> &mut [SemUndo] {
+        let set = self.data::<SemSet>();
+        unsafe {
+            let undos = set.add(1).cast::<Sem>().add((*set).nsems).cast::<SemUndo>();
+            slice::from_raw_parts_mut(undos, SEMUNDO)
+        }
+    }
+
+    /// Reverts the adjustments of the processes for which `exited` returns `true`. The object must
+    /// be locked.
+    fn sem_revert(&self, mut exited: impl FnMut(pid_t) -> bool) {
+        let sems = self.sems();
+        let mut changed = false;
+        for undo in self.sem_undos() {
+            if undo.pid == 0 || !exited(undo.pid) {
+                continue;
+            }
+            let sem = &mut sems[undo.num as usize];
+            // As in Linux, a value that would become negative is set to zero instead.
+            sem.val = (sem.val + undo.adj).clamp(0, SEMVMX);
+            sem.pid = undo.pid;
+            *undo = SemUndo::default();
+            changed = true;
+        }
+        if changed {
+            self.header().changed();
+        }
+    }
+
+    /// Records the adjustments of the `SEM_UNDO` operations of `sops` by the process `pid`. Nothing
+    /// is recorded if an adjustment is out of range or there is no room left. The object must be
+    /// locked.
+    fn sem_record_undo(&self, pid: pid_t, sops: &[sembuf]) -> Result<()> {
+        let undos = self.sem_undos();
+        let find = |undos: &[SemUndo], num| undos.iter().position(|u| u.pid == pid && u.num == num);
+
+        let mut adjs: Vec<(u32, c_int)> = Vec::new();
+        for sop in sops {
+            if sop.sem_flg & SEM_UNDO != SEM_UNDO || sop.sem_op == 0 {
+                continue;
+            }
+            let num = u32::from(sop.sem_num);
+            let i = match adjs.iter().position(|&(n, _)| n == num) {
+                Some(i) => i,
+                None => {
+                    let adj = find(undos, num).map_or(0, |i| undos[i].adj);
+                    adjs.push((num, adj));
+                    adjs.len() - 1
+                }
+            };
+            adjs[i].1 -= c_int::from(sop.sem_op);
+            if adjs[i].1.abs() > SEMVMX {
+                return Err(Errno(ERANGE));
+            }
+        }
+        if adjs.is_empty() {
+            return Ok(());
+        }
+
+        let needed = adjs
+            .iter()
+            .filter(|&&(num, _)| find(undos, num).is_none())
+            .count();
+        if undos.iter().filter(|u| u.pid == 0).count() < needed {
+            return Err(Errno(ENOSPC));
+        }
+
+        let mut sets = UNDO_SETS.lock();
+        if UNDO_PID.swap(pid, Ordering::Relaxed) != pid {
+            // Inherited from the parent.
+            sets.clear();
+        }
+        if !sets.contains(&self.id) {
+            sets.push(self.id);
+        }
+        drop(sets);
+
+        for (num, adj) in adjs {
+            let i = find(undos, num)
+                .or_else(|| undos.iter().position(|u| u.pid == 0))
+                .unwrap();
+            undos[i] = match adj {
+                0 => SemUndo::default(),
+                _ => SemUndo { pid, num, adj },
+            };
+        }
+        Ok(())
+    }
+}
+
+/// Reverts the `SEM_UNDO` adjustments of the calling process, which is exiting.
+pub fn sem_exit() {
+    let pid = Sys::getpid();
+    if UNDO_PID.load(Ordering::Relaxed) != pid {
+        return;
+    }
+
+    for semid in mem::take(&mut *UNDO_SETS.lock()) {
+        let Ok(object) = Object::get(Kind::Sem, semid) else {
+            continue;
+        };
+        if let Ok(_locked) = Locked::new(&object) {
+            object.sem_revert(|owner| owner == pid);
+        }
+    }
+}
+
+#[repr(C)]
+struct MsgQueue {
+    qbytes: usize,
+    cbytes: usize,
+    qnum: usize,
+    lspid: pid_t,
+    lrpid: pid_t,
+    stime: time_t,
+    rtime: time_t,
+    /// Bytes of the arena in use.
+    used: usize,
+}
+
+/// Header of a message in the arena, followed by its text.
+#[repr(C)]
+struct MsgRecord {
+    mtype: c_long,
+    len: usize,
+}
+
+fn record_size(len: usize) -> usize {
+    (size_of::<MsgRecord>() + len).next_multiple_of(align_of::<MsgRecord>())
+}
+
+impl Object {
+    fn msg_queue(&self) -> &mut MsgQueue {
+        unsafe { &mut *self.data::<MsgQueue>() }
+    }
+
+    fn msg_arena(&self) -> *mut u8 {
+        unsafe { self.data::<MsgQueue>().add(1).cast() }
+    }
+
+    /// Returns the arena offset and header of the message to receive, if any.
+    fn msg_find(&self, msgtyp: c_long, msgflg: c_int) -> Option<(usize, MsgRecord)> {
+        let arena = self.msg_arena();
+        let used = self.msg_queue().used;
+
+        let mut best: Option<(usize, MsgRecord)> = None;
+        let mut offset = 0;
+        while offset < used {
+            let record = unsafe { arena.add(offset).cast::<MsgRecord>().read() };
+            let next = offset + record_size(record.len);
+
+            let matches = match msgtyp {
+                0 => true,
+                _ if msgtyp > 0 && msgflg & MSG_EXCEPT == MSG_EXCEPT => record.mtype != msgtyp,
+                _ if msgtyp > 0 => record.mtype == msgtyp,
+                // The first message with the lowest type less than or equal to `-msgtyp`.
+                _ => {
+                    record.mtype <= msgtyp.saturating_neg()
+                        && best
+                            .as_ref()
+                            .is_none_or(|(_, best)| record.mtype < best.mtype)
+                }
+            };
+            if matches {
+                best = Some((offset, record));
+                if msgtyp >= 0 {
+                    break;
+                }
+            }
+            offset = next;
+        }
+        best
+    }
+}
+
+impl PalIpc for Sys {
+    unsafe fn msgctl(msqid: c_int, cmd: c_int, buf: *mut msqid_ds) -> Result<()> {
+        let object = Object::get(Kind::Msg, msqid)?;
+        let _locked = Locked::new(&object)?;
+        let header = unsafe { &mut *object.header };
+        let queue = object.msg_queue();
+
+        match cmd {
+            IPC_STAT => {
+                check_access(&header.perm, ACCESS_READ)?;
+                unsafe {
+                    buf.write(msqid_ds {
+                        msg_perm: header.perm,
+                        msg_qnum: queue.qnum as _,
+                        msg_qbytes: queue.qbytes as _,
+                        msg_lspid: queue.lspid,
+                        msg_lrpid: queue.lrpid,
+                        msg_stime: queue.stime,
+                        msg_rtime: queue.rtime,
+                        msg_ctime: header.ctime,
+                    })
+                };
+            }
+            IPC_SET => {
+                check_owner(&header.perm)?;
+                let buf = unsafe { &*buf };
+                let qbytes = usize::try_from(buf.msg_qbytes).map_err(|_| Errno(EINVAL))?;
+                if qbytes > MSGMNB {
+                    return Err(Errno(EPERM));
+                }
+
+                header.perm.uid = buf.msg_perm.uid;
+                header.perm.gid = buf.msg_perm.gid;
+                header.perm.mode = buf.msg_perm.mode & 0o777;
+                queue.qbytes = qbytes;
+                header.ctime = now();
+                // Blocked senders may fit now.
+                header.changed();
+            }
+            IPC_RMID => object.remove(Kind::Msg)?,
+            _ => return Err(Errno(EINVAL)),
+        }
+        Ok(())
+    }
+
+    fn msgget(key: key_t, msgflg: c_int) -> Result<c_int> {
+        Object::get_or_create(
+            Kind::Msg,
+            key,
+            msgflg,
+            |_| Ok(()),
+            || Ok(size_of::<ObjectHeader>() + size_of::<MsgQueue>() + MSG_ARENA),
+            |header| unsafe { (*data::<MsgQueue>(header)).qbytes = MSGMNB },
+        )
+    }
+
+    unsafe fn msgrcv(
+        msqid: c_int,
+        msgp: *mut c_void,
+        msgsz: usize,
+        msgtyp: c_long,
+        msgflg: c_int,
+    ) -> Result<usize> {
+        if isize::try_from(msgsz).is_err() {
+            return Err(Errno(EINVAL));
+        }
+
+        let object = Object::get(Kind::Msg, msqid)?;
+        let _locked = Locked::new(&object)?;
+        let header = object.header();
+        check_access(&header.perm, ACCESS_READ)?;
+
+        loop {
+            if let Some((offset, record)) = object.msg_find(msgtyp, msgflg) {
+                if record.len > msgsz && msgflg & MSG_NOERROR == 0 {
+                    return Err(Errno(E2BIG));
+                }
+                let len = record.len.min(msgsz);
+
+                let arena = object.msg_arena();
+                let queue = object.msg_queue();
+                let size = record_size(record.len);
+                unsafe {
+                    msgp.cast::<c_long>().write_unaligned(record.mtype);
+                    ptr::copy_nonoverlapping(
+                        arena.add(offset + size_of::<MsgRecord>()),
+                        msgp.cast::<u8>().add(size_of::<c_long>()),
+                        len,
+                    );
+                    ptr::copy(
+                        arena.add(offset + size),
+                        arena.add(offset),
+                        queue.used - offset - size,
+                    );
+                }
+
+                queue.used -= size;
+                queue.cbytes -= record.len;
+                queue.qnum -= 1;
+                queue.lrpid = Sys::getpid();
+                queue.rtime = now();
+                header.changed();
+                return Ok(len);
+            }
+
+            if msgflg & IPC_NOWAIT == IPC_NOWAIT {
+                return Err(Errno(ENOMSG));
+            }
+            header.wait(None)?;
+        }
+    }
+
+    unsafe fn msgsnd(msqid: c_int, msgp: *const c_void, msgsz: usize, msgflg: c_int) -> Result<()> {
+        if msgsz > MSGMAX {
+            return Err(Errno(EINVAL));
+        }
+        let mtype = unsafe { msgp.cast::<c_long>().read_unaligned() };
+        if mtype < 1 {
+            return Err(Errno(EINVAL));
+        }
+
+        let object = Object::get(Kind::Msg, msqid)?;
+        let _locked = Locked::new(&object)?;
+        let header = object.header();
+        check_access(&header.perm, ACCESS_WRITE)?;
+
+        let size = record_size(msgsz);
+        loop {
+            let queue = object.msg_queue();
+            if queue.cbytes + msgsz <= queue.qbytes
+                && queue.qnum < queue.qbytes
+                && queue.used + size <= MSG_ARENA
+            {
+                let record = unsafe { object.msg_arena().add(queue.used) };
+                unsafe {
+                    record
+                        .cast::<MsgRecord>()
+                        .write(MsgRecord { mtype, len: msgsz });
+                    ptr::copy_nonoverlapping(
+                        msgp.cast::<u8>().add(size_of::<c_long>()),
+                        record.add(size_of::<MsgRecord>()),
+                        msgsz,
+                    );
+                }
+
+                queue.used += size;
+                queue.cbytes += msgsz;
+                queue.qnum += 1;
+                queue.lspid = Sys::getpid();
+                queue.stime = now();
+                header.changed();
+                return Ok(());
+            }
+
+            if msgflg & IPC_NOWAIT == IPC_NOWAIT {
+                return Err(Errno(EAGAIN));
+            }
+            header.wait(None)?;
+        }
+    }
+
+    unsafe fn semctl(semid: c_int, semnum: c_int, cmd: c_int, arg: semun) -> Result<c_int> {
+        let object = Object::get(Kind::Sem, semid)?;
+        let _locked = Locked::new(&object)?;
+        object.sem_revert(process_gone);
+        let header = unsafe { &mut *object.header };
+        let sems = object.sems();
+
+        let index = usize::try_from(semnum)
+            .ok()
+            .filter(|&index| index < sems.len())
+            .ok_or(Errno(EINVAL));
+
+        match cmd {
+            GETVAL | GETPID | GETNCNT | GETZCNT => {
+                check_access(&header.perm, ACCESS_READ)?;
+                let sem = &sems[index?];
+                Ok(match cmd {
+                    GETVAL => sem.val,
+                    GETPID => sem.pid,
+                    GETNCNT => sem.ncnt as c_int,
+                    _ => sem.zcnt as c_int,
+                })
+            }
+            SETVAL => {
+                check_access(&header.perm, ACCESS_WRITE)?;
+                let val = unsafe { arg.val };
+                if !(0..=SEMVMX).contains(&val) {
+                    return Err(Errno(ERANGE));
+                }
+                let index = index?;
+                let sem = &mut sems[index];
+                sem.val = val;
+                sem.pid = Sys::getpid();
+                // The adjustments of every process are discarded along with the value.
+                for undo in object.sem_undos() {
+                    if undo.num as usize == index {
+                        *undo = SemUndo::default();
+                    }
+                }
+                header.ctime = now();
+                header.changed();
+                Ok(0)
+            }
+            GETALL => {
+                check_access(&header.perm, ACCESS_READ)?;
+                for (i, sem) in sems.iter().enumerate() {
+                    unsafe { arg.array.add(i).write(sem.val as c_ushort) };
+                }
+                Ok(0)
+            }
+            SETALL => {
+                check_access(&header.perm, ACCESS_WRITE)?;
+                let vals = unsafe { slice::from_raw_parts(arg.array, sems.len()) };
+                if vals.iter().any(|&val| c_int::from(val) > SEMVMX) {
+                    return Err(Errno(ERANGE));
+                }
+                let pid = Sys::getpid();
+                for (sem, &val) in sems.iter_mut().zip(vals) {
+                    sem.val = val.into();
+                    sem.pid = pid;
+                }
+                object.sem_undos().fill(SemUndo::default());
+                header.ctime = now();
+                header.changed();
+                Ok(0)
+            }
+            IPC_STAT => {
+                check_access(&header.perm, ACCESS_READ)?;
+                unsafe {
+                    arg.buf.write(semid_ds {
+                        sem_perm: header.perm,
+                        sem_nsems: sems.len() as c_ushort,
+                        sem_otime: object.sem_set().otime,
+                        sem_ctime: header.ctime,
+                    })
+                };
+                Ok(0)
+            }
+            IPC_SET => {
+                check_owner(&header.perm)?;
+                let buf = unsafe { &*arg.buf };
+                header.perm.uid = buf.sem_perm.uid;
+                header.perm.gid = buf.sem_perm.gid;
+                header.perm.mode = buf.sem_perm.mode & 0o777;
+                header.ctime = now();
+                Ok(0)
+            }
+            IPC_RMID => {
+                object.remove(Kind::Sem)?;
+                Ok(0)
+            }
+            _ => Err(Errno(EINVAL)),
+        }
+    }
+
+    fn semget(key: key_t, nsems: c_int, semflg: c_int) -> Result<c_int> {
+        let nsems = usize::try_from(nsems).map_err(|_| Errno(EINVAL))?;
+        if nsems > SEMMSL {
+            return Err(Errno(EINVAL));
+        }
+
+        Object::get_or_create(
+            Kind::Sem,
+            key,
+            semflg,
+            |object| {
+                if nsems > object.sem_set().nsems {
+                    return Err(Errno(EINVAL));
+                }
+                Ok(())
+            },
+            || match nsems {
+                0 => Err(Errno(EINVAL)),
+                _ => Ok(sem_size(nsems)),
+            },
+            |header| unsafe { (*data::<SemSet>(header)).nsems = nsems },
+        )
+    }
+
+    fn semtimedop(semid: c_int, sops: &[sembuf], timeout: Option<&timespec>) -> Result<()> {
+        if sops.len() > SEMOPM {
+            return Err(Errno(E2BIG));
+        }
+        let deadline = match timeout {
+            Some(timeout) => {
+                if !(0..1_000_000_000).contains(&timeout.tv_nsec) || timeout.tv_sec < 0 {
+                    return Err(Errno(EINVAL));
+                }
+                let mut now = timespec::default();
+                Sys::clock_gettime(CLOCK_MONOTONIC, Out::from_mut(&mut now))?;
+                Some(timespec::add(&now, timeout).ok_or(Errno(EINVAL))?)
+            }
+            None => None,
+        };
+
+        let object = Object::get(Kind::Sem, semid)?;
+        let _locked = Locked::new(&object)?;
+        object.sem_revert(process_gone);
+        let header = object.header();
+
+        let nsems = object.sem_set().nsems;
+        if sops.iter().any(|sop| usize::from(sop.sem_num) >= nsems) {
+            return Err(Errno(EFBIG));
+        }
+        let alter = sops.iter().any(|sop| sop.sem_op != 0);
+        check_access(&header.perm, if alter { ACCESS_WRITE } else { ACCESS_READ })?;
+
+        loop {
+            let sems = object.sems();
+            let mut vals: Vec<c_int> = sems.iter().map(|sem| sem.val).collect();
+
+            // All operations are performed atomically, so try them on a copy first.
+            let mut blocked = None;
+            for sop in sops {
+                let val = &mut vals[usize::from(sop.sem_num)];
+                let op = c_int::from(sop.sem_op);
+                if op > 0 {
+                    if *val + op > SEMVMX {
+                        return Err(Errno(ERANGE));
+                    }
+                    *val += op;
+                } else if op < 0 {
+                    if *val < -op {
+                        blocked = Some(sop);
+                        break;
+                    }
+                    *val += op;
+                } else if *val != 0 {
+                    blocked = Some(sop);
+                    break;
+                }
+            }
+
+            let Some(sop) = blocked else {
+                let pid = Sys::getpid();
+                object.sem_record_undo(pid, sops)?;
+                for sop in sops {
+                    let num = usize::from(sop.sem_num);
+                    sems[num].val = vals[num];
+                    sems[num].pid = pid;
+                }
+                object.sem_set().otime = now();
+                if alter {
+                    header.changed();
+                }
+                return Ok(());
+            };
+
+            if c_int::from(sop.sem_flg) & IPC_NOWAIT == IPC_NOWAIT {
+                return Err(Errno(EAGAIN));
+            }
+
+            let num = usize::from(sop.sem_num);
+            let waiting_for_zero = sop.sem_op == 0;
+            if waiting_for_zero {
+                sems[num].zcnt += 1;
+            } else {
+                sems[num].ncnt += 1;
+            }
+
+            let result = header.wait(deadline.as_ref());
+
+            let sem = &mut object.sems()[num];
+            if waiting_for_zero {
+                sem.zcnt -= 1;
+            } else {
+                sem.ncnt -= 1;
+            }
+            result?;
+        }
+    }
+}
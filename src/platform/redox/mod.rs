@@ -4,6 +4,7 @@ use core::{
     mem::{self, size_of},
     num::NonZeroU64,
     ptr, slice, str,
+    sync::atomic::{AtomicI32, Ordering},
 };
 use object::bytes_of_slice_mut;
 use redox_path::RedoxStr;
@@ -37,12 +38,13 @@ use crate::{
             F_OFD_GETLK, F_OFD_SETLK, F_RDLCK, F_SETLK, F_SETLKW, F_UNLCK, F_WRLCK, flock,
         },
         limits::{self},
+        sched::{self, SCHED_FIFO, SCHED_OTHER, SCHED_RR, sched_param},
         signal::{NSIG, SIGEV_NONE, SIGEV_SIGNAL, SIGEV_THREAD, SIGRTMIN, sigevent},
         stdio::RENAME_NOREPLACE,
         sys_file,
         sys_mman::{MAP_ANONYMOUS, PROT_READ, PROT_WRITE},
         sys_random,
-        sys_resource::{PRIO_PROCESS, RLIM_INFINITY, rlimit, rusage, setpriority},
+        sys_resource::{PRIO_PROCESS, RLIM_INFINITY, rlimit, rusage},
         sys_select::timeval,
         sys_stat::{S_ISGID, S_ISUID, S_ISVTX, stat},
         sys_statvfs::statvfs,
@@ -55,12 +57,9 @@ use crate::{
     iter::NulTerminated,
     ld_so::tcb::OsSpecific,
     out::Out,
-    platform::{
-        ERRNO,
-        sys::{
-            path::{CwdPath, to_cwd_path},
-            timer::{RlctTimer, TIMERS, timer_routine, timer_update_wake_time},
-        },
+    platform::sys::{
+        path::{CwdPath, to_cwd_path},
+        timer::{RlctTimer, TIMERS, timer_routine, timer_update_wake_time},
     },
     pthread,
     sync::rwlock::RwLock,
@@ -72,8 +71,10 @@ mod epoll;
 mod event;
 pub(crate) mod exec;
 mod extra;
+mod ipc;
 mod libcscheme;
 mod libredox;
+mod mqueue;
 pub(crate) mod path;
 mod ptrace;
 pub mod ring;
@@ -99,6 +100,24 @@ fn cvt_uid(id: c_int) -> Result<Option<u32>> {
 
 static CLONE_LOCK: RwLock<()> = RwLock::new(());
 
+/// Scheduling policy of this process, as set by `sched_setscheduler`.
+static SCHED_POLICY: AtomicI32 = AtomicI32::new(SCHED_OTHER);
+
+/// Priority range of SCHED_FIFO and SCHED_RR, mapped onto nice values 19 to -20.
+const SCHED_PRIORITY_MIN: c_int = 1;
+const SCHED_PRIORITY_MAX: c_int = 40;
+
+/// Returns the nice value that `param` maps to under `policy`, or `None` for
+/// SCHED_OTHER, which leaves the nice value alone.
+fn sched_nice(policy: c_int, param: &sched_param) -> Option<c_int> {
+    (policy != SCHED_OTHER).then(|| 20 - param.sched_priority)
+}
+
+/// Whether `pid` refers to the calling process in the sched_* functions.
+fn is_current_process(pid: pid_t) -> bool {
+    pid == 0 || pid == Sys::getpid()
+}
+
 /// Redox syscall implementation of [`Pal`].
 pub struct Sys;
 
@@ -233,6 +252,43 @@ impl Pal for Sys {
         Ok(())
     }
 
+    unsafe fn clock_nanosleep(
+        clk_id: clockid_t,
+        flags: c_int,
+        rqtp: *const timespec,
+        rmtp: *mut timespec,
+    ) -> Result<()> {
+        let request = unsafe { &*rqtp };
+        if !matches!(clk_id, CLOCK_REALTIME | CLOCK_MONOTONIC)
+            || !(0..1_000_000_000).contains(&request.tv_nsec)
+        {
+            return Err(Errno(EINVAL));
+        }
+        if flags & TIMER_ABSTIME == 0 {
+            // Relative sleeps are not affected by setting the clock, so all clocks sleep alike.
+            return unsafe { Self::nanosleep(rqtp, rmtp) };
+        }
+
+        // Sleep until the clock reaches the deadline, checking again after waking up in case the
+        // clock was set meanwhile. The remaining time is not reported for absolute sleeps.
+        loop {
+            let mut now = timespec::default();
+            Self::clock_gettime(clk_id, Out::from_mut(&mut now))?;
+            let mut remaining = timespec {
+                tv_sec: request.tv_sec - now.tv_sec,
+                tv_nsec: request.tv_nsec - now.tv_nsec,
+            };
+            if remaining.tv_nsec < 0 {
+                remaining.tv_sec -= 1;
+                remaining.tv_nsec += 1_000_000_000;
+            }
+            if remaining.tv_sec < 0 || remaining.is_zero() {
+                return Ok(());
+            }
+            unsafe { Self::nanosleep(&remaining, ptr::null_mut()) }?;
+        }
+    }
+
     unsafe fn clock_settime(clk_id: clockid_t, tp: *const timespec) -> Result<()> {
         todo_skip!(0, "clock_settime({}, {:p}): not implemented", clk_id, tp);
         Err(Errno(ENOSYS))
@@ -248,6 +304,7 @@ impl Pal for Sys {
     }
 
     fn exit(status: c_int) -> ! {
+        ipc::sem_exit();
         redox_rt::sys::posix_exit(status)
     }
 
@@ -1041,6 +1098,21 @@ impl Pal for Sys {
         redox_rt::sys::posix_kill_thread(os_tid.thread_fd, signal as u32)?;
         Ok(())
     }
+    unsafe fn rlct_setschedparam(
+        _os_tid: crate::pthread::OsTid,
+        policy: c_int,
+        param: &sched_param,
+    ) -> Result<()> {
+        sched::check_param(policy, param)?;
+        // The kernel only has per-process priorities, so the priority applies to the whole
+        // process. The thread's policy and priority are recorded by pthread, and read back by
+        // sched_getscheduler and sched_getparam.
+        if let Some(nice) = sched_nice(policy, param) {
+            Self::setpriority(PRIO_PROCESS, 0, nice)?;
+        }
+        Ok(())
+    }
+
     fn current_os_tid() -> crate::pthread::OsTid {
         crate::pthread::OsTid {
             thread_fd: RtTcb::current().thread_fd().as_raw_fd(),
@@ -1140,6 +1212,89 @@ impl Pal for Sys {
             .map_err(Into::into)
     }
 
+    fn sched_get_priority_max(policy: c_int) -> Result<c_int> {
+        match policy {
+            SCHED_FIFO | SCHED_RR => Ok(SCHED_PRIORITY_MAX),
+            SCHED_OTHER => Ok(0),
+            _ => Err(Errno(EINVAL)),
+        }
+    }
+
+    fn sched_get_priority_min(policy: c_int) -> Result<c_int> {
+        match policy {
+            SCHED_FIFO | SCHED_RR => Ok(SCHED_PRIORITY_MIN),
+            SCHED_OTHER => Ok(0),
+            _ => Err(Errno(EINVAL)),
+        }
+    }
+
+    fn sched_getparam(pid: pid_t, mut param: Out<sched_param>) -> Result<()> {
+        if is_current_process(pid)
+            && let Some(thread) = crate::pthread::current_thread_if_ready()
+        {
+            let (_, current) = crate::pthread::get_sched_param(thread)?;
+            param.write(current);
+            return Ok(());
+        }
+        let sched_priority = match Self::sched_getscheduler(pid)? {
+            SCHED_OTHER => 0,
+            // getpriority() returns 20 - nice, which is exactly the sched_priority range.
+            _ => Self::getpriority(PRIO_PROCESS, pid as id_t)?,
+        };
+        param.write(sched_param { sched_priority });
+        Ok(())
+    }
+
+    fn sched_getscheduler(pid: pid_t) -> Result<c_int> {
+        if pid < 0 {
+            return Err(Errno(EINVAL));
+        }
+        // The kernel has no notion of scheduling policies, so only the policy of the calling
+        // process is known, as recorded for the calling thread once threads are set up.
+        if is_current_process(pid) {
+            Ok(match crate::pthread::current_thread_if_ready() {
+                Some(thread) => thread.sched_policy.load(Ordering::Acquire),
+                None => SCHED_POLICY.load(Ordering::Relaxed),
+            })
+        } else {
+            Ok(SCHED_OTHER)
+        }
+    }
+
+    fn sched_rr_get_interval(pid: pid_t, _interval: Out<timespec>) -> Result<()> {
+        if pid < 0 {
+            return Err(Errno(EINVAL));
+        }
+        // TODO: the kernel does not expose its time slice
+        Err(Errno(ENOSYS))
+    }
+
+    fn sched_setparam(pid: pid_t, param: &sched_param) -> Result<()> {
+        let policy = Self::sched_getscheduler(pid)?;
+        Self::sched_setscheduler(pid, policy, param).map(|_| ())
+    }
+
+    fn sched_setscheduler(pid: pid_t, policy: c_int, param: &sched_param) -> Result<c_int> {
+        sched::check_param(policy, param)?;
+        let old = Self::sched_getscheduler(pid)?;
+
+        // Redox schedules every context round-robin, so the real-time policies only map their
+        // priority onto the nice value.
+        if let Some(nice) = sched_nice(policy, param) {
+            Self::setpriority(PRIO_PROCESS, pid as id_t, nice)?;
+        }
+        if is_current_process(pid) {
+            SCHED_POLICY.store(policy, Ordering::Relaxed);
+            if let Some(thread) = crate::pthread::current_thread_if_ready() {
+                thread.sched_policy.store(policy, Ordering::Release);
+                thread
+                    .sched_priority
+                    .store(param.sched_priority, Ordering::Release);
+            }
+        }
+        Ok(old)
+    }
+
     fn sched_yield() -> Result<()> {
         syscall::sched_yield()?;
         Ok(())
@@ -1407,27 +1562,19 @@ impl Pal for Sys {
                 )?;
             }
 
-            let set_schedparam = || -> Result<()> {
-                if setpriority(
-                    PRIO_PROCESS,
-                    proc_fd.as_raw_fd() as id_t,
-                    attr.param.sched_priority,
-                ) as usize
-                    != 0
-                {
-                    Err(Errno(ERRNO.get()))
+            // Without POSIX_SPAWN_SETSCHEDULER, the parameters apply to the policy that the
+            // child inherits.
+            if flags.intersects(Flags::POSIX_SPAWN_SETSCHEDULER | Flags::POSIX_SPAWN_SETSCHEDPARAM)
+            {
+                let policy = if flags.contains(Flags::POSIX_SPAWN_SETSCHEDULER) {
+                    attr.policy
                 } else {
-                    Ok(())
+                    Self::sched_getscheduler(0)?
+                };
+                sched::check_param(policy, &attr.param)?;
+                if let Some(nice) = sched_nice(policy, &attr.param) {
+                    Self::setpriority(PRIO_PROCESS, proc_fd.as_raw_fd() as id_t, nice)?;
                 }
-            };
-            let set_scheduler = || -> Result<()> { todo!() };
-
-            // scheduling paramters must be set regardless of whether the flag POSIX_SPAWN_SETSCHEDPARAM is set
-            if flags.contains(Flags::POSIX_SPAWN_SETSCHEDULER) {
-                set_schedparam()?;
-                set_scheduler()?;
-            } else if flags.contains(Flags::POSIX_SPAWN_SETSCHEDPARAM) {
-                set_schedparam()?;
             }
 
             let parent_resugid = redox_rt::sys::posix_getresugid();
@@ -1563,6 +1710,14 @@ impl Pal for Sys {
         Ok(())
     }
 
+    fn timer_getoverrun(timerid: timer_t) -> Result<c_int> {
+        let timers = TIMERS.lock();
+        let Some(timer_st) = timers.get(&timerid.addr()) else {
+            return Err(Errno(EINVAL));
+        };
+        Ok(timer_st.overrun)
+    }
+
     fn timer_gettime(timerid: timer_t) -> Result<itimerspec> {
         let mut timers = TIMERS.lock();
         let Some(timer_st) = timers.get_mut(&timerid.addr()) else {
@@ -1603,6 +1758,7 @@ impl Pal for Sys {
             return Err(Errno(EINVAL));
         };
 
+        timer_st.pending_overrun = 0;
         if value.it_value.is_zero() {
             timer_st.next_wake_version += 1;
             return Ok(());
@@ -0,0 +1,704 @@
+//! POSIX message queues, implemented in userspace.
+//!
+//! Each queue is a shared memory object, see [`shm_open`](crate::header::sys_mman::shm_open),
+//! holding a [`QueueHeader`] followed by `mq_maxmsg` fixed-size message slots. All processes that
+//! open the queue map the whole object, and synchronize through futexes in the header.
+//!
+//! `SIGEV_THREAD` notifications are delivered to a helper thread of the registered process, which
+//! waits on a futex in the header and calls the notification function.
+
+use alloc::{boxed::Box, collections::BTreeMap, vec::Vec};
+use core::{
+    mem::{align_of, size_of},
+    ptr,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use crate::{
+    c_str::{CStr, CString},
+    error::{Errno, Result},
+    header::{
+        bits_sigset_t::sigset_t,
+        errno::{EAGAIN, EBADF, EBUSY, EEXIST, EINVAL, EMSGSIZE, ENAMETOOLONG, ETIMEDOUT},
+        fcntl::{
+            F_DUPFD_CLOEXEC, O_ACCMODE, O_CLOEXEC, O_CREAT, O_EXCL, O_NONBLOCK, O_RDONLY, O_RDWR,
+            O_WRONLY,
+        },
+        limits::{MQ_PRIO_MAX, NAME_MAX},
+        mqueue::{MQ_DEFAULT_MAXMSG, MQ_DEFAULT_MSGSIZE, mq_attr, mqd_t},
+        pthread::{
+            __relibc_internal_pthread_cleanup_pop, __relibc_internal_pthread_cleanup_push,
+            CleanupLinkedListEntry, PTHREAD_CREATE_DETACHED, RlctAttr,
+        },
+        signal::{SIG_SETMASK, SIGEV_NONE, SIGEV_SIGNAL, SIGEV_THREAD, sigevent, sigval},
+        sys_mman::{MAP_SHARED, PROT_READ, PROT_WRITE, SHM_PATH},
+        sys_stat::stat,
+        time::{CLOCK_MONOTONIC, timespec, timespec_realtime_to_monotonic},
+    },
+    out::Out,
+    platform::{
+        Pal, PalMqueue, PalSignal, Sys,
+        types::{c_int, c_long, c_uint, c_void, mode_t, off_t, pid_t},
+    },
+    pthread,
+    sync::{FutexWaitResult, Mutex},
+};
+
+/// Written last when creating a queue, so that other processes can wait for the initialization.
+const MAGIC: u32 = u32::from_be_bytes(*b"RMQ1");
+
+/// How long to wait for the creator of a queue to initialize it, after which it is assumed to have
+/// died.
+const INIT_TIMEOUT: timespec = timespec {
+    tv_sec: 1,
+    tv_nsec: 0,
+};
+
+const UNLOCKED: u32 = 0;
+const LOCKED: u32 = 1;
+const LOCKED_WAITING: u32 = 2;
+
+#[repr(C)]
+struct QueueHeader {
+    magic: AtomicU32,
+    lock: AtomicU32,
+    /// Incremented on every send and receive. Blocked senders and receivers wait on this futex.
+    changes: AtomicU32,
+
+    // Everything below is protected by `lock`.
+    /// Number of threads blocked in `mq_receive()`, which take precedence over notifications.
+    receivers: u32,
+    maxmsg: usize,
+    msgsize: usize,
+    curmsgs: usize,
+    /// Sequence number of the next message, for FIFO order within a priority.
+    next_seq: u64,
+
+    /// Process registered with `mq_notify()`, or `0`.
+    notify_pid: pid_t,
+    notify_signo: c_int,
+    notify_value: usize,
+    /// Whether the registration is for `SIGEV_THREAD`, so that a helper thread of the registered
+    /// process waits on `notify_events`.
+    notify_thread: bool,
+    /// Identifies the current registration, incremented by every `mq_notify()` registering one.
+    notify_id: u32,
+    /// The last registration delivered to a helper thread.
+    notify_delivered: u32,
+    /// Incremented whenever a registration is delivered or removed, waking helper threads.
+    notify_events: AtomicU32,
+}
+
+#[repr(C)]
+struct SlotHeader {
+    used: u32,
+    prio: c_uint,
+    len: usize,
+    seq: u64,
+}
+
+fn slot_stride(msgsize: usize) -> Option<usize> {
+    size_of::<SlotHeader>()
+        .checked_add(msgsize)?
+        .checked_next_multiple_of(align_of::<SlotHeader>())
+}
+fn queue_size(maxmsg: usize, msgsize: usize) -> Option<usize> {
+    slot_stride(msgsize)?
+        .checked_mul(maxmsg)?
+        .checked_add(size_of::<QueueHeader>())
+}
+
+/// A mapped queue.
+#[derive(Clone, Copy)]
+struct Queue {
+    header: *mut QueueHeader,
+    size: usize,
+}
+
+impl Queue {
+    fn header(&self) -> &QueueHeader {
+        unsafe { &*self.header }
+    }
+
+    fn lock(&self) -> LockedQueue<'_> {
+        let lock = &self.header().lock;
+
+        if lock
+            .compare_exchange(UNLOCKED, LOCKED, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            while lock.swap(LOCKED_WAITING, Ordering::Acquire) != UNLOCKED {
+                crate::sync::futex_wait(lock, LOCKED_WAITING, None);
+            }
+        }
+
+        LockedQueue { queue: self }
+    }
+
+    /// Waits until the queue changes, or until `deadline` on the monotonic clock. Fails with
+    /// `EINTR` when interrupted by a signal handler.
+    fn wait(&self, seen: u32, deadline: Option<&timespec>) -> Result<()> {
+        match crate::sync::futex_wait_interruptible(&self.header().changes, seen, deadline)? {
+            FutexWaitResult::TimedOut => Err(Errno(ETIMEDOUT)),
+            FutexWaitResult::Waited | FutexWaitResult::Stale => Ok(()),
+        }
+    }
+}
+
+struct LockedQueue<'a> {
+    queue: &'a Queue,
+}
+
+impl LockedQueue<'_> {
+    fn header(&mut self) -> &mut QueueHeader {
+        unsafe { &mut *self.queue.header }
+    }
+
+    fn slot(&mut self, index: usize) -> (&mut SlotHeader, *mut u8) {
+        let stride = slot_stride(self.header().msgsize).unwrap();
+        unsafe {
+            let slot = self
+                .queue
+                .header
+                .add(1)
+                .cast::<u8>()
+                .add(index * stride)
+                .cast::<SlotHeader>();
+            (&mut *slot, slot.add(1).cast::<u8>())
+        }
+    }
+
+    /// Marks the queue as changed, and wakes all blocked senders and receivers.
+    fn changed(&mut self) {
+        let changes = &self.header().changes;
+        changes.fetch_add(1, Ordering::Relaxed);
+        let _ = crate::sync::futex_wake(changes, i32::MAX);
+    }
+
+    fn send(&mut self, msg: &[u8], prio: c_uint) {
+        let maxmsg = self.header().maxmsg;
+        let seq = self.header().next_seq;
+
+        let index = (0..maxmsg)
+            .find(|&i| self.slot(i).0.used == 0)
+            .expect("message queue full despite curmsgs < maxmsg");
+        let (slot, data) = self.slot(index);
+        slot.used = 1;
+        slot.prio = prio;
+        slot.len = msg.len();
+        slot.seq = seq;
+        unsafe { ptr::copy_nonoverlapping(msg.as_ptr(), data, msg.len()) };
+
+        let header = self.header();
+        header.next_seq += 1;
+        header.curmsgs += 1;
+    }
+
+    fn receive(&mut self, msg: &mut Out<[u8]>) -> (usize, c_uint) {
+        let maxmsg = self.header().maxmsg;
+
+        // Highest priority first, and the oldest message within that priority.
+        let mut best: Option<(usize, c_uint, u64)> = None;
+        for i in 0..maxmsg {
+            let (slot, _) = self.slot(i);
+            if slot.used == 0 {
+                continue;
+            }
+            if best.is_none_or(|(_, prio, seq)| (slot.prio, seq) > (prio, slot.seq)) {
+                best = Some((i, slot.prio, slot.seq));
+            }
+        }
+        let (index, _, _) = best.expect("message queue empty despite curmsgs > 0");
+        let (slot, data) = self.slot(index);
+        slot.used = 0;
+        let (len, prio) = (slot.len, slot.prio);
+
+        msg.subslice(0, len)
+            .copy_from_slice(unsafe { core::slice::from_raw_parts(data, len) });
+
+        self.header().curmsgs -= 1;
+        (len, prio)
+    }
+
+    /// Delivers and removes the registered notification, if any.
+    fn notify(&mut self) {
+        let header = self.header();
+        let pid = core::mem::replace(&mut header.notify_pid, 0);
+
+        if pid != 0 && header.notify_thread {
+            header.notify_delivered = header.notify_id;
+            self.notify_events();
+        } else if pid != 0 && header.notify_signo != 0 {
+            let value = sigval {
+                sival_ptr: header.notify_value as *mut c_void,
+            };
+            // The registered process may have exited in the meantime.
+            let _ = Sys::sigqueue(pid, header.notify_signo, value);
+        }
+    }
+
+    /// Removes the notification registered by `pid`, if any.
+    fn unregister(&mut self, pid: pid_t) {
+        if self.header().notify_pid == pid {
+            self.header().notify_pid = 0;
+            // Lets the helper thread of a `SIGEV_THREAD` registration exit.
+            self.notify_events();
+        }
+    }
+
+    fn notify_events(&mut self) {
+        let events = &self.header().notify_events;
+        events.fetch_add(1, Ordering::Relaxed);
+        crate::sync::futex_wake(events, i32::MAX);
+    }
+}
+
+impl Drop for LockedQueue<'_> {
+    fn drop(&mut self) {
+        let lock = &self.queue.header().lock;
+        if lock.swap(UNLOCKED, Ordering::Release) == LOCKED_WAITING {
+            let _ = crate::sync::futex_wake(lock, 1);
+        }
+    }
+}
+
+/// Per-descriptor state, i.e. the message queue description.
+struct Descriptor {
+    queue: Queue,
+    /// Access mode and `O_NONBLOCK`.
+    flags: c_int,
+}
+
+unsafe impl Send for Descriptor {}
+
+/// Open message queue descriptors, indexed by the file descriptor of the shared memory object.
+static DESCRIPTORS: Mutex<BTreeMap<mqd_t, Descriptor>> = Mutex::new(BTreeMap::new());
+
+fn descriptor(mqdes: mqd_t) -> Result<(Queue, c_int)> {
+    DESCRIPTORS
+        .lock()
+        .get(&mqdes)
+        .map(|desc| (desc.queue, desc.flags))
+        .ok_or(Errno(EBADF))
+}
+
+/// Returns the path of the shared memory object backing the queue `name`.
+fn queue_path(name: CStr) -> Result<CString> {
+    let name = match name.to_bytes() {
+        [b'/', rest @ ..] if !rest.is_empty() && !rest.contains(&b'/') => rest,
+        _ => return Err(Errno(EINVAL)),
+    };
+    if name.len() > NAME_MAX {
+        return Err(Errno(ENAMETOOLONG));
+    }
+
+    let mut path = Vec::with_capacity(SHM_PATH.len() + 7 + name.len());
+    path.extend_from_slice(SHM_PATH);
+    path.extend_from_slice(b"mqueue.");
+    path.extend_from_slice(name);
+    Ok(CString::new(path).unwrap())
+}
+
+fn map_queue(fd: c_int, size: usize) -> Result<Queue> {
+    let header = unsafe {
+        Sys::mmap(
+            ptr::null_mut(),
+            size,
+            PROT_READ | PROT_WRITE,
+            MAP_SHARED,
+            fd,
+            0,
+        )?
+    };
+    Ok(Queue {
+        header: header.cast(),
+        size,
+    })
+}
+
+fn create_queue(fd: c_int, attr: Option<&mq_attr>) -> Result<Queue> {
+    let (maxmsg, msgsize) = match attr {
+        Some(attr) => (attr.mq_maxmsg, attr.mq_msgsize),
+        None => (MQ_DEFAULT_MAXMSG, MQ_DEFAULT_MSGSIZE),
+    };
+    let (Ok(maxmsg), Ok(msgsize)) = (usize::try_from(maxmsg), usize::try_from(msgsize)) else {
+        return Err(Errno(EINVAL));
+    };
+    if maxmsg == 0 || msgsize == 0 {
+        return Err(Errno(EINVAL));
+    }
+    let size = queue_size(maxmsg, msgsize).ok_or(Errno(EINVAL))?;
+
+    Sys::ftruncate(fd, off_t::try_from(size).map_err(|_| Errno(EINVAL))?)?;
+    let queue = map_queue(fd, size)?;
+
+    // The shared memory object is zero-initialized, so only the attributes need to be set.
+    let header = unsafe { &mut *queue.header };
+    header.maxmsg = maxmsg;
+    header.msgsize = msgsize;
+    header.magic.store(MAGIC, Ordering::Release);
+    let _ = crate::sync::futex_wake(&header.magic, i32::MAX);
+
+    Ok(queue)
+}
+
+fn open_queue(fd: c_int) -> Result<Queue> {
+    let mut now = timespec::default();
+    Sys::clock_gettime(CLOCK_MONOTONIC, Out::from_mut(&mut now))?;
+    let deadline = timespec::add(&now, &INIT_TIMEOUT).ok_or(Errno(EINVAL))?;
+    let expired = || {
+        let mut now = timespec::default();
+        Sys::clock_gettime(CLOCK_MONOTONIC, Out::from_mut(&mut now))?;
+        Ok::<_, Errno>((now.tv_sec, now.tv_nsec) >= (deadline.tv_sec, deadline.tv_nsec))
+    };
+
+    // The creator may not have resized the object yet.
+    let size = loop {
+        let mut st = stat::default();
+        Sys::fstat(fd, Out::from_mut(&mut st))?;
+
+        let size = usize::try_from(st.st_size).map_err(|_| Errno(EINVAL))?;
+        if size >= size_of::<QueueHeader>() {
+            break size;
+        }
+        if expired()? {
+            return Err(Errno(EAGAIN));
+        }
+        Sys::sched_yield()?;
+    };
+    let queue = map_queue(fd, size)?;
+
+    let header = queue.header();
+    while header.magic.load(Ordering::Acquire) != MAGIC {
+        if crate::sync::futex_wait(&header.magic, 0, Some(&deadline)) == FutexWaitResult::TimedOut
+            || expired()?
+        {
+            let _ = unsafe { Sys::munmap(queue.header.cast(), size) };
+            return Err(Errno(EAGAIN));
+        }
+    }
+
+    if queue_size(header.maxmsg, header.msgsize) != Some(size) {
+        let _ = unsafe { Sys::munmap(queue.header.cast(), size) };
+        return Err(Errno(EINVAL));
+    }
+
+    Ok(queue)
+}
+
+/// State passed to the helper thread of a `SIGEV_THREAD` registration.
+struct Notifier {
+    /// The helper's own mapping of the queue, which stays valid if the descriptor is closed.
+    queue: Queue,
+    fd: c_int,
+    pid: pid_t,
+    id: u32,
+    function: extern "C" fn(sigval),
+    value: sigval,
+}
+
+unsafe impl Send for Notifier {}
+
+extern "C" fn notify_routine(arg: *mut c_void) -> *mut c_void {
+    let notifier = unsafe { Box::from_raw(arg.cast::<Notifier>()) };
+    let queue = notifier.queue;
+
+    let delivered = loop {
+        let mut locked = queue.lock();
+        let header = locked.header();
+        if header.notify_delivered == notifier.id {
+            break true;
+        }
+        if header.notify_pid != notifier.pid || header.notify_id != notifier.id {
+            break false;
+        }
+        let seen = header.notify_events.load(Ordering::Relaxed);
+        drop(locked);
+
+        crate::sync::futex_wait(&queue.header().notify_events, seen, None);
+    };
+
+    let _ = unsafe { Sys::munmap(queue.header.cast(), queue.size) };
+    let _ = Sys::close(notifier.fd);
+    if delivered {
+        (notifier.function)(notifier.value);
+    }
+    ptr::null_mut()
+}
+
+/// Starts the helper thread of the `SIGEV_THREAD` registration `id`, which is created with
+/// `notification.sigev_notify_attributes`.
+fn spawn_notifier(mqdes: mqd_t, size: usize, id: u32, notification: &sigevent) -> Result<()> {
+    let function = notification.sigev_notify_function.ok_or(Errno(EINVAL))?;
+
+    let fd = Sys::fcntl(mqdes, F_DUPFD_CLOEXEC, 0)?;
+    let queue = match map_queue(fd, size) {
+        Ok(queue) => queue,
+        Err(err) => {
+            let _ = Sys::close(fd);
+            return Err(err);
+        }
+    };
+    let notifier = Box::new(Notifier {
+        queue,
+        fd,
+        pid: Sys::getpid(),
+        id,
+        function,
+        value: notification.sigev_value,
+    });
+
+    // Nobody joins the helper, so it must be detached whatever the attributes say.
+    let mut attr = unsafe {
+        notification
+            .sigev_notify_attributes
+            .cast::<RlctAttr>()
+            .as_ref()
+    }
+    .cloned()
+    .unwrap_or_default();
+    attr.detachstate = PTHREAD_CREATE_DETACHED as _;
+
+    // The helper inherits the signal mask, so it never runs signal handlers.
+    let mut old_mask: sigset_t = 0;
+    Sys::sigprocmask(SIG_SETMASK, Some(&!0), Some(&mut old_mask))?;
+    let arg = Box::into_raw(notifier).cast::<c_void>();
+    let thread = unsafe { pthread::create(Some(&attr), notify_routine, arg) };
+    Sys::sigprocmask(SIG_SETMASK, Some(&old_mask), None).expect("failed to restore signal mask");
+
+    if let Err(err) = thread {
+        let notifier = unsafe { Box::from_raw(arg.cast::<Notifier>()) };
+        let _ = unsafe { Sys::munmap(notifier.queue.header.cast(), size) };
+        let _ = Sys::close(fd);
+        return Err(err);
+    }
+    Ok(())
+}
+
+impl PalMqueue for Sys {
+    fn mq_close(mqdes: mqd_t) -> Result<()> {
+        let desc = DESCRIPTORS.lock().remove(&mqdes).ok_or(Errno(EBADF))?;
+
+        desc.queue.lock().unregister(Sys::getpid());
+
+        unsafe { Sys::munmap(desc.queue.header.cast(), desc.queue.size)? };
+        Sys::close(mqdes)
+    }
+
+    fn mq_getsetattr(mqdes: mqd_t, new: Option<&mq_attr>, old: Option<Out<mq_attr>>) -> Result<()> {
+        let mut descriptors = DESCRIPTORS.lock();
+        let desc = descriptors.get_mut(&mqdes).ok_or(Errno(EBADF))?;
+
+        if let Some(mut old) = old {
+            let mut locked = desc.queue.lock();
+            let header = locked.header();
+            old.write(mq_attr {
+                mq_flags: c_long::from(desc.flags & O_NONBLOCK),
+                mq_maxmsg: header.maxmsg as c_long,
+                mq_msgsize: header.msgsize as c_long,
+                mq_curmsgs: header.curmsgs as c_long,
+                ..mq_attr::default()
+            });
+        }
+        if let Some(new) = new {
+            desc.flags &= !O_NONBLOCK;
+            if new.mq_flags & c_long::from(O_NONBLOCK) != 0 {
+                desc.flags |= O_NONBLOCK;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn mq_notify(mqdes: mqd_t, notification: Option<&sigevent>) -> Result<()> {
+        let (queue, _) = descriptor(mqdes)?;
+        let mut locked = queue.lock();
+        let pid = Sys::getpid();
+
+        let Some(notification) = notification else {
+            locked.unregister(pid);
+            return Ok(());
+        };
+
+        if locked.header().notify_pid != 0 {
+            return Err(Errno(EBUSY));
+        }
+        let signo = match notification.sigev_notify {
+            SIGEV_NONE | SIGEV_THREAD => 0,
+            SIGEV_SIGNAL => notification.sigev_signo,
+            _ => return Err(Errno(EINVAL)),
+        };
+
+        let id = locked.header().notify_id.wrapping_add(1);
+        if notification.sigev_notify == SIGEV_THREAD {
+            // The queue stays locked until the registration below is complete, which the helper
+            // waits for before checking it.
+            spawn_notifier(mqdes, queue.size, id, notification)?;
+        }
+
+        let header = locked.header();
+        header.notify_pid = pid;
+        header.notify_signo = signo;
+        header.notify_value = unsafe { notification.sigev_value.sival_ptr } as usize;
+        header.notify_thread = notification.sigev_notify == SIGEV_THREAD;
+        header.notify_id = id;
+        Ok(())
+    }
+
+    fn mq_open(name: CStr, oflag: c_int, mode: mode_t, attr: Option<&mq_attr>) -> Result<mqd_t> {
+        let path = queue_path(name)?;
+        let path = CStr::borrow(&path);
+
+        let accmode = oflag & O_ACCMODE;
+        if ![O_RDONLY, O_WRONLY, O_RDWR].contains(&accmode) {
+            return Err(Errno(EINVAL));
+        }
+
+        // Senders and receivers both modify the queue, so it is always mapped read-write.
+        let flags = O_RDWR | O_CLOEXEC;
+        let (fd, created) = if oflag & O_CREAT == O_CREAT {
+            match Sys::open(path, flags | O_CREAT | O_EXCL, mode) {
+                Ok(fd) => (fd, true),
+                Err(Errno(EEXIST)) if oflag & O_EXCL == 0 => (Sys::open(path, flags, 0)?, false),
+                Err(err) => return Err(err),
+            }
+        } else {
+            (Sys::open(path, flags, 0)?, false)
+        };
+
+        let queue = if created {
+            create_queue(fd, attr).inspect_err(|_| {
+                let _ = Sys::unlink(path);
+            })
+        } else {
+            open_queue(fd)
+        };
+        let queue = match queue {
+            Ok(queue) => queue,
+            Err(err) => {
+                let _ = Sys::close(fd);
+                return Err(err);
+            }
+        };
+
+        DESCRIPTORS.lock().insert(
+            fd,
+            Descriptor {
+                queue,
+                flags: accmode | (oflag & O_NONBLOCK),
+            },
+        );
+        Ok(fd)
+    }
+
+    fn mq_timedreceive(
+        mqdes: mqd_t,
+        mut msg: Out<[u8]>,
+        msg_prio: Option<Out<c_uint>>,
+        abstime: Option<&timespec>,
+    ) -> Result<usize> {
+        let (queue, flags) = descriptor(mqdes)?;
+        if flags & O_ACCMODE == O_WRONLY {
+            return Err(Errno(EBADF));
+        }
+
+        let mut deadline = None;
+        loop {
+            let mut locked = queue.lock();
+            if msg.len() < locked.header().msgsize {
+                return Err(Errno(EMSGSIZE));
+            }
+
+            if locked.header().curmsgs > 0 {
+                let (len, prio) = locked.receive(&mut msg);
+                locked.changed();
+                drop(locked);
+
+                if let Some(mut msg_prio) = msg_prio {
+                    msg_prio.write(prio);
+                }
+                return Ok(len);
+            }
+
+            if flags & O_NONBLOCK == O_NONBLOCK {
+                return Err(Errno(EAGAIN));
+            }
+            if deadline.is_none()
+                && let Some(abstime) = abstime
+            {
+                deadline = Some(timespec_realtime_to_monotonic(abstime)?);
+            }
+
+            let seen = locked.header().changes.load(Ordering::Relaxed);
+            locked.header().receivers += 1;
+            drop(locked);
+
+            // Also run if the thread is cancelled while waiting.
+            extern "C" fn stop_receiving(queue: *mut c_void) {
+                unsafe { &*queue.cast::<Queue>() }.lock().header().receivers -= 1;
+            }
+            let mut entry = CleanupLinkedListEntry::new(
+                stop_receiving,
+                ptr::from_ref(&queue).cast_mut().cast(),
+            );
+            unsafe { __relibc_internal_pthread_cleanup_push(ptr::from_mut(&mut entry).cast()) };
+            let result = queue.wait(seen, deadline.as_ref());
+            unsafe { __relibc_internal_pthread_cleanup_pop(1) };
+            result?;
+        }
+    }
+
+    fn mq_timedsend(
+        mqdes: mqd_t,
+        msg: &[u8],
+        msg_prio: c_uint,
+        abstime: Option<&timespec>,
+    ) -> Result<()> {
+        let (queue, flags) = descriptor(mqdes)?;
+        if flags & O_ACCMODE == O_RDONLY {
+            return Err(Errno(EBADF));
+        }
+        if c_long::from(msg_prio) >= MQ_PRIO_MAX {
+            return Err(Errno(EINVAL));
+        }
+
+        let mut deadline = None;
+        loop {
+            let mut locked = queue.lock();
+            if msg.len() > locked.header().msgsize {
+                return Err(Errno(EMSGSIZE));
+            }
+
+            if locked.header().curmsgs < locked.header().maxmsg {
+                let was_empty = locked.header().curmsgs == 0;
+                locked.send(msg, msg_prio);
+
+                // Notifications are only sent if no receiver would have received the message.
+                if was_empty && locked.header().receivers == 0 {
+                    locked.notify();
+                }
+                locked.changed();
+                return Ok(());
+            }
+
+            if flags & O_NONBLOCK == O_NONBLOCK {
+                return Err(Errno(EAGAIN));
+            }
+            if deadline.is_none()
+                && let Some(abstime) = abstime
+            {
+                deadline = Some(timespec_realtime_to_monotonic(abstime)?);
+            }
+
+            let seen = locked.header().changes.load(Ordering::Relaxed);
+            drop(locked);
+
+            queue.wait(seen, deadline.as_ref())?;
+        }
+    }
+
+    fn mq_unlink(name: CStr) -> Result<()> {
+        let path = queue_path(name)?;
+        Sys::unlink(CStr::borrow(&path))
+    }
+}
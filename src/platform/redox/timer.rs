@@ -6,19 +6,19 @@ use crate::{
     header::{
         errno::EIO,
         signal::{SIGEV_SIGNAL, SIGEV_THREAD, sigevent},
-        time::{itimerspec, timespec},
+        time::{NANOSECONDS, itimerspec, timespec},
     },
     out::Out,
     platform::{
         Pal, PalSignal, Sys,
         sys::event,
-        types::{c_void, clockid_t, pid_t, pthread_t, timer_t},
+        types::{c_int, c_void, clockid_t, pid_t, pthread_t, timer_t},
     },
     sync::Mutex,
 };
 use alloc::collections::BTreeMap;
 use core::{
-    mem::{MaybeUninit, size_of},
+    mem::{self, MaybeUninit, size_of},
     ptr,
     sync::atomic::{AtomicUsize, Ordering},
 };
@@ -43,6 +43,10 @@ pub(crate) struct RlctTimer {
     // When non-zero, timer_routine delivers SIGALRM via kill(process_pid, sig)
     // instead of rlct_kill (thread-specific). Used by alarm().
     pub process_pid: pid_t,
+    /// Expirations missed before the last notification, for timer_getoverrun
+    pub overrun: c_int,
+    /// Expirations missed since the last notification
+    pub pending_overrun: c_int,
 }
 
 unsafe impl Send for RlctTimer {}
@@ -69,6 +73,8 @@ impl RlctTimer {
             next_wake_time: itimerspec::default(),
             next_wake_version: 0,
             process_pid: Sys::getpid(),
+            overrun: 0,
+            pending_overrun: 0,
         };
         assert!(TIMERS.lock().insert(id, timer_st).is_none());
         key
@@ -103,27 +109,35 @@ pub extern "C" fn timer_routine(arg: *mut c_void) -> *mut c_void {
             break;
         }
 
-        let mut timers = TIMERS.lock();
-        let Some(timer_st) = timers.get_mut(&arg.addr()) else {
-            return ptr::null_mut();
+        let (evp, process_pid) = {
+            let mut timers = TIMERS.lock();
+            let Some(timer_st) = timers.get_mut(&arg.addr()) else {
+                return ptr::null_mut();
+            };
+            let expired = timer_version == timer_st.next_wake_version;
+            if expired {
+                timer_st.overrun = mem::take(&mut timer_st.pending_overrun);
+            }
+            (expired.then(|| timer_st.evp.clone()), timer_st.process_pid)
         };
-        if timer_version == timer_st.next_wake_version {
-            if timer_st.evp.sigev_notify == SIGEV_THREAD {
-                if let Some(fun) = timer_st.evp.sigev_notify_function {
-                    fun(timer_st.evp.sigev_value);
+
+        // Notify without holding the lock, as the notification may use the timer.
+        if let Some(evp) = evp {
+            if evp.sigev_notify == SIGEV_THREAD {
+                if let Some(fun) = evp.sigev_notify_function {
+                    fun(evp.sigev_value);
                 }
-            } else if timer_st.evp.sigev_notify == SIGEV_SIGNAL
-                && Sys::sigqueue(
-                    timer_st.process_pid,
-                    timer_st.evp.sigev_signo as _,
-                    timer_st.evp.sigev_value,
-                )
-                .is_err()
+            } else if evp.sigev_notify == SIGEV_SIGNAL
+                && Sys::sigqueue(process_pid, evp.sigev_signo as _, evp.sigev_value).is_err()
             {
                 break;
             }
         }
 
+        let mut timers = TIMERS.lock();
+        let Some(timer_st) = timers.get_mut(&arg.addr()) else {
+            return ptr::null_mut();
+        };
         if timer_next_event(timer_st).is_err() {
             break;
         }
@@ -156,6 +170,9 @@ fn timer_next_event(timer_st: &mut RlctTimer) -> Result<()> {
 }
 
 /// Update next_wake_time.it_value from next_wake_time.it_interval
+///
+/// The next expiration is the first one after now, and the expirations that already passed are
+/// counted in pending_overrun.
 pub(crate) fn timer_update_wake_time(timer_st: &mut RlctTimer) -> Result<()> {
     let interval = &timer_st.next_wake_time.it_interval;
     timer_st.next_wake_time.it_value = if interval.is_zero() {
@@ -163,7 +180,16 @@ pub(crate) fn timer_update_wake_time(timer_st: &mut RlctTimer) -> Result<()> {
     } else {
         let mut now = timespec::default();
         Sys::clock_gettime(timer_st.clockid, Out::from_mut(&mut now))?;
-        timespec::add(&now, interval).unwrap_or_default()
+        let interval = nanoseconds(interval);
+        let mut next = nanoseconds(&timer_st.next_wake_time.it_value);
+        let now = nanoseconds(&now);
+        if next <= now {
+            let expirations = (now - next) / interval + 1;
+            next += expirations * interval;
+            let missed = c_int::try_from(expirations - 1).unwrap_or(c_int::MAX);
+            timer_st.pending_overrun = timer_st.pending_overrun.saturating_add(missed);
+        }
+        from_nanoseconds(next).unwrap_or_default()
     };
     if timer_st.next_wake_time.it_value.is_zero() {
         return Err(Errno(0));
@@ -171,3 +197,14 @@ pub(crate) fn timer_update_wake_time(timer_st: &mut RlctTimer) -> Result<()> {
     timer_st.next_wake_version += 1;
     Ok(())
 }
+
+fn nanoseconds(time: &timespec) -> i128 {
+    i128::from(time.tv_sec) * i128::from(NANOSECONDS) + i128::from(time.tv_nsec)
+}
+
+fn from_nanoseconds(nanoseconds: i128) -> Option<timespec> {
+    Some(timespec {
+        tv_sec: nanoseconds.div_euclid(NANOSECONDS.into()).try_into().ok()?,
+        tv_nsec: nanoseconds.rem_euclid(NANOSECONDS.into()).try_into().ok()?,
+    })
+}
@@ -2,8 +2,9 @@
 
 use core::{
     cell::UnsafeCell,
+    mem::{self, MaybeUninit},
     ptr,
-    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    sync::atomic::{AtomicBool, AtomicI32, AtomicUsize, Ordering},
 };
 
 use alloc::collections::BTreeMap;
@@ -15,12 +16,19 @@ use redox_rt::{
 
 use crate::{
     error::Errno,
-    header::{errno::*, pthread as header, sched::sched_param, sys_mman},
+    header::{
+        errno::*,
+        pthread as header,
+        sched::{self, SCHED_OTHER, sched_param},
+        signal::{SA_SIGINFO, sigaction, siginfo_t},
+        sys_mman,
+    },
     ld_so::tcb::Tcb,
-    platform::{Pal, Sys, types::*},
+    out::Out,
+    platform::{self, Pal, PalSignal, Sys, types::*},
 };
 
-use crate::sync::{Mutex, waitval::Waitval};
+use crate::sync::{Mutex, Once, waitval::Waitval};
 
 /// Called only by the main thread, as part of relibc_start.
 pub unsafe fn init() {
@@ -30,11 +38,22 @@ pub unsafe fn init() {
     #[cfg(target_os = "linux")]
     let (stack_base, stack_size) = (ptr::null_mut(), 0);
 
+    let sched_policy = Sys::sched_getscheduler(0).unwrap_or(SCHED_OTHER);
+    let mut param = MaybeUninit::<sched_param>::uninit();
+    let sched_priority = match Sys::sched_getparam(0, Out::from_uninit_mut(&mut param)) {
+        Ok(()) => unsafe { param.assume_init() }.sched_priority,
+        Err(_) => 0,
+    };
+
     let thread = Pthread {
         waitval: Waitval::new(),
-        has_enabled_cancelation: AtomicBool::new(false),
+        has_enabled_cancelation: AtomicBool::new(true),
         has_queued_cancelation: AtomicBool::new(false),
+        has_async_cancelation: AtomicBool::new(false),
+        in_cancelation_point: AtomicBool::new(false),
         flags: PthreadFlags::empty().bits().into(),
+        sched_policy: AtomicI32::new(sched_policy),
+        sched_priority: AtomicI32::new(sched_priority),
 
         //index: FIRST_THREAD_IDX,
 
@@ -52,8 +71,13 @@ pub unsafe fn init() {
     unsafe {
         current_tcb.pthread.tcb_selfref.get().write(current_tcb);
     }
+    THREADS_READY.store(true, Ordering::Release);
 }
 
+/// Whether [`init`] has set up the main thread, after which [`current_thread`] can be used on
+/// every path, including syscalls that also run while ld.so initializes.
+static THREADS_READY: AtomicBool = AtomicBool::new(false);
+
 //static NEXT_INDEX: AtomicU32 = AtomicU32::new(FIRST_THREAD_IDX + 1);
 //const FIRST_THREAD_IDX: usize = 1;
 
@@ -74,8 +98,15 @@ pub struct Pthread {
     pub(crate) waitval: Waitval<Retval>,
     pub(crate) has_queued_cancelation: AtomicBool,
     pub(crate) has_enabled_cancelation: AtomicBool,
+    pub(crate) has_async_cancelation: AtomicBool,
+    /// Set while the thread is blocked in a cancellation point, see [`cancellation_point`].
+    pub(crate) in_cancelation_point: AtomicBool,
     pub(crate) flags: AtomicUsize,
 
+    /// Scheduling policy and priority last applied to this thread.
+    pub(crate) sched_policy: AtomicI32,
+    pub(crate) sched_priority: AtomicI32,
+
     pub(crate) stack_base: *mut c_void,
     pub(crate) stack_size: usize,
 
@@ -98,6 +129,16 @@ unsafe impl Sync for Pthread {}
 #[derive(Clone, Copy, Debug)]
 pub struct Retval(pub *mut c_void);
 
+/// State shared between `create` and the new thread until it has started.
+struct StartInfo {
+    procmask: u64,
+    /// Scheduling to apply before running the start routine, for
+    /// `PTHREAD_EXPLICIT_SCHED`.
+    sched: Option<(c_int, sched_param)>,
+    /// Set by the new thread if `sched` could not be applied.
+    sched_error: Option<Errno>,
+}
+
 struct MmapGuard {
     page_start: *mut c_void,
     mmap_size: usize,
@@ -123,8 +164,23 @@ pub(crate) unsafe fn create(
     let current_sigmask =
         redox_rt::signal::get_sigmask().expect("failed to obtain sigprocmask for caller");
 
+    let sched = match i32::from(attrs.inheritsched) {
+        header::PTHREAD_EXPLICIT_SCHED => {
+            let policy = c_int::from(attrs.schedpolicy);
+            sched::check_param(policy, &attrs.param)?;
+            Some((policy, attrs.param))
+        }
+        _ => None,
+    };
+
     // Create a locked mutex, unlocked by the thread after it has started.
-    let synchronization_mutex = unsafe { Mutex::locked(current_sigmask) };
+    let synchronization_mutex = unsafe {
+        Mutex::locked(StartInfo {
+            procmask: current_sigmask,
+            sched,
+            sched_error: None,
+        })
+    };
     let synchronization_mutex = &synchronization_mutex;
 
     let stack_size = attrs.stacksize.next_multiple_of(Sys::getpagesize());
@@ -167,9 +223,22 @@ pub(crate) unsafe fn create(
     new_tcb.pthread.flags = flags.bits().into();
     new_tcb.pthread.stack_base = stack_base;
     new_tcb.pthread.stack_size = stack_size;
+    let (sched_policy, sched_priority) = match sched {
+        Some((policy, param)) => (policy, param.sched_priority),
+        None => {
+            let current = &current_tcb.pthread;
+            (
+                current.sched_policy.load(Ordering::Acquire),
+                current.sched_priority.load(Ordering::Acquire),
+            )
+        }
+    };
+    new_tcb.pthread.sched_policy = sched_policy.into();
+    new_tcb.pthread.sched_priority = sched_priority.into();
 
     new_tcb.masters_ptr = current_tcb.masters_ptr;
     new_tcb.masters_len = current_tcb.masters_len;
+    new_tcb.dtv_generation = current_tcb.dtv_generation;
     new_tcb.linker_ptr = current_tcb.linker_ptr;
 
     let stack_end = unsafe { stack_base.add(stack_size) };
@@ -203,7 +272,12 @@ pub(crate) unsafe fn create(
     };
     core::mem::forget(stack_raii);
 
-    let _ = synchronization_mutex.lock();
+    let start_info = synchronization_mutex.lock();
+    if let Some(error) = start_info.sched_error {
+        // The thread has already detached itself and exited.
+        return Err(error);
+    }
+    drop(start_info);
 
     OS_TID_TO_PTHREAD
         .lock()
@@ -217,7 +291,7 @@ unsafe extern "C" fn new_thread_shim(
     entry_point: unsafe extern "C" fn(*mut c_void) -> *mut c_void,
     arg: *mut c_void,
     tcb: *mut Tcb,
-    synchronization_mutex: *const Mutex<u64>,
+    synchronization_mutex: *const Mutex<StartInfo>,
 ) -> ! {
     let tcb = unsafe { tcb.as_mut() }.expect("non-null TLS is required");
 
@@ -235,12 +309,27 @@ unsafe extern "C" fn new_thread_shim(
         redox_rt::signal::setup_sighandler(&tcb.os_specific, false);
     }
 
+    let start_info = unsafe { &mut *(&*synchronization_mutex).as_ptr() };
     #[cfg(target_os = "redox")]
-    let procmask = unsafe { (&*synchronization_mutex).as_ptr().read() };
+    let procmask = start_info.procmask;
 
     unsafe { tcb.copy_masters() }.unwrap();
 
-    unsafe { tcb.pthread.os_tid.get().write(Sys::current_os_tid()) };
+    let os_tid = Sys::current_os_tid();
+    unsafe { tcb.pthread.os_tid.get().write(os_tid) };
+
+    // Apply explicit scheduling before any user code runs. On failure, pthread_create reports
+    // the error and nobody will join this thread.
+    if let Some((policy, param)) = start_info.sched
+        && let Err(error) = unsafe { Sys::rlct_setschedparam(os_tid, policy, &param) }
+    {
+        start_info.sched_error = Some(error);
+        tcb.pthread
+            .flags
+            .fetch_or(PthreadFlags::DETACHED.bits(), Ordering::AcqRel);
+        unsafe { (&*synchronization_mutex).manual_unlock() };
+        unsafe { exit_current_thread(Retval(ptr::null_mut())) }
+    }
 
     unsafe { (&*synchronization_mutex).manual_unlock() };
 
@@ -297,12 +386,97 @@ pub unsafe fn testcancel() {
     }
 }
 
+/// The result of a blocking operation run as a [`cancellation_point`].
+pub trait Interruptible {
+    /// Whether the operation was interrupted by a signal handler before completing.
+    fn interrupted(&self) -> bool;
+}
+impl<T> Interruptible for Result<T, Errno> {
+    fn interrupted(&self) -> bool {
+        matches!(self, Err(Errno(EINTR)))
+    }
+}
+impl Interruptible for Errno {
+    fn interrupted(&self) -> bool {
+        self.0 == EINTR
+    }
+}
+/// For C functions returning `-1` and setting errno.
+impl Interruptible for c_int {
+    fn interrupted(&self) -> bool {
+        *self == -1 && platform::ERRNO.get() == EINTR
+    }
+}
+
+/// Runs the blocking operation `f` as a cancellation point.
+///
+/// A cancellation request queued before `f` starts is acted upon immediately. While `f` is
+/// blocked, the cancellation signal interrupts it, and the request is acted upon once `f` returns
+/// with `EINTR`. If `f` completed instead, its result is returned and the request stays queued
+/// until the next cancellation point, so that no result or resource held by `f` is lost.
+///
+/// On Linux, a request that arrives after the check but before `f` has started blocking is acted
+/// upon by the cancellation signal handler, see `platform::sys::cancel`. On Redox, it is only
+/// acted upon once `f` returns by itself.
+pub fn cancellation_point<T: Interruptible>(f: impl FnOnce() -> T) -> T {
+    // Threads are not set up yet while relibc and ld.so initialize.
+    let Some(this_thread) = current_thread() else {
+        return f();
+    };
+
+    // Cancellation points may nest, e.g. sleep() calling nanosleep().
+    let was_in_cancelation_point = this_thread
+        .in_cancelation_point
+        .swap(true, Ordering::SeqCst);
+
+    // Checked after setting the flag, so that a request from pthread_cancel either is seen here
+    // or its signal finds the flag set.
+    unsafe { testcancel() };
+
+    let ret = f();
+
+    this_thread
+        .in_cancelation_point
+        .store(was_in_cancelation_point, Ordering::SeqCst);
+
+    if ret.interrupted() {
+        unsafe { testcancel() };
+    }
+
+    ret
+}
+
+/// Like [`current_thread`], but returns `None` until [`init`] has set up the main thread, for
+/// platform code that also runs while ld.so initializes.
+pub(crate) fn current_thread_if_ready() -> Option<&'static Pthread> {
+    if !THREADS_READY.load(Ordering::Acquire) {
+        return None;
+    }
+    current_thread()
+}
+
+/// Returns the cancellation request flag of the current thread, if it is inside a cancellation
+/// point with cancellation enabled, for the syscalls which act upon a request right before they
+/// block.
+#[cfg(target_os = "linux")]
+pub(crate) fn syscall_cancel_flag() -> Option<&'static AtomicBool> {
+    let this_thread = current_thread_if_ready()?;
+    (this_thread.in_cancelation_point.load(Ordering::SeqCst)
+        && this_thread.has_enabled_cancelation.load(Ordering::Acquire))
+    .then_some(&this_thread.has_queued_cancelation)
+}
+
 pub unsafe fn exit_current_thread(retval: Retval) -> ! {
     // Run pthread_cleanup_push/pthread_cleanup_pop destructors.
     unsafe { header::run_destructor_stack() };
 
     unsafe { header::tls::run_all_destructors() };
 
+    if let Some(tcb) = unsafe { Tcb::current() } {
+        unsafe { tcb.free_dynamic_tls() };
+    }
+    unsafe { platform::release_thread_cache() };
+
     let this = current_thread().expect("failed to obtain current thread when exiting");
     let stack_base = this.stack_base;
     let stack_size = this.stack_size;
@@ -354,36 +528,108 @@ unsafe fn dealloc_thread(thread: &Pthread) {
 pub const SIGRT_RLCT_CANCEL: usize = 33;
 pub const SIGRT_RLCT_TIMER: usize = 34;
 
-unsafe extern "C" fn cancel_sighandler(_: c_int) {
-    unsafe { cancel_current_thread() };
+extern "C" fn cancel_sighandler(_: c_int, _: *mut siginfo_t, context: *mut c_void) {
+    let Some(this_thread) = current_thread() else {
+        return;
+    };
+    if !this_thread.has_enabled_cancelation.load(Ordering::Acquire) {
+        return;
+    }
+
+    // Deferred requests stay queued. Inside a cancellation point, returning from the handler
+    // interrupts the blocking call, and cancellation_point acts upon the request.
+    if this_thread.has_async_cancelation.load(Ordering::Acquire) {
+        unsafe { cancel_current_thread() };
+    }
+
+    // If the blocking syscall has not been entered yet, it would not be interrupted anymore.
+    #[cfg(target_os = "linux")]
+    if this_thread.in_cancelation_point.load(Ordering::SeqCst) {
+        unsafe { platform::sys::cancel::cancel_before_syscall(context.cast()) };
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = context;
 }
 unsafe fn cancel_current_thread() {
+    let this_thread = current_thread().expect("current thread not present");
+
+    // Cleanup handlers and TLS destructors must not be cancelled themselves.
+    this_thread
+        .has_enabled_cancelation
+        .store(false, Ordering::Release);
+    this_thread
+        .in_cancelation_point
+        .store(false, Ordering::SeqCst);
+
     // Terminate the thread
     unsafe { exit_current_thread(Retval(header::PTHREAD_CANCELED)) };
 }
 
+static CANCEL_SIGHANDLER: Once<()> = Once::new();
+
+fn install_cancel_sighandler() {
+    CANCEL_SIGHANDLER.call_once(|| {
+        let handler: extern "C" fn(c_int, *mut siginfo_t, *mut c_void) = cancel_sighandler;
+        let action = sigaction {
+            // Takes the SA_SIGINFO arguments, see the sigaction docs.
+            sa_handler: Some(unsafe {
+                mem::transmute::<
+                    extern "C" fn(c_int, *mut siginfo_t, *mut c_void),
+                    extern "C" fn(c_int),
+                >(handler)
+            }),
+            // No SA_RESTART, so that the blocking call of a cancellation point fails with EINTR
+            // instead of resuming. SA_SIGINFO, so that the handler sees where it interrupted.
+            sa_flags: SA_SIGINFO as c_int,
+            sa_restorer: None, // set by platform if applicable
+            sa_mask: !0,
+        };
+        Sys::sigaction(SIGRT_RLCT_CANCEL as c_int, Some(&action), None)
+            .expect("failed to install the pthread cancellation signal handler");
+    });
+}
+
 pub unsafe fn cancel(thread: &Pthread) -> Result<(), Errno> {
-    // TODO: What order should these atomic bools be accessed in?
-    thread.has_queued_cancelation.store(true, Ordering::Release);
+    install_cancel_sighandler();
+
+    // Queued before signalling, so that a target entering a cancellation point either observes
+    // the request or receives the signal while inside it. Targets outside cancellation points are
+    // not signalled, as that would interrupt their syscalls.
+    thread.has_queued_cancelation.store(true, Ordering::SeqCst);
 
-    if thread.has_enabled_cancelation.load(Ordering::Acquire) {
+    if thread.has_enabled_cancelation.load(Ordering::Acquire)
+        && (thread.has_async_cancelation.load(Ordering::Acquire)
+            || thread.in_cancelation_point.load(Ordering::SeqCst))
+    {
         (unsafe { Sys::rlct_kill(thread.os_tid.get().read(), SIGRT_RLCT_CANCEL) })?;
     }
 
     Ok(())
 }
 
-pub fn set_sched_param(
-    _thread: &Pthread,
-    _policy: c_int,
-    _param: &sched_param,
-) -> Result<(), Errno> {
-    // TODO
+pub fn set_sched_param(thread: &Pthread, policy: c_int, param: &sched_param) -> Result<(), Errno> {
+    if !sched::is_valid_policy(policy) {
+        return Err(Errno(EINVAL));
+    }
+    sched::check_param(policy, param)?;
+
+    unsafe { Sys::rlct_setschedparam(thread.os_tid.get().read(), policy, param) }?;
+
+    thread.sched_policy.store(policy, Ordering::Release);
+    thread
+        .sched_priority
+        .store(param.sched_priority, Ordering::Release);
     Ok(())
 }
-pub fn set_sched_priority(_thread: &Pthread, _prio: c_int) -> Result<(), Errno> {
-    // TODO
-    Ok(())
+pub fn set_sched_priority(thread: &Pthread, prio: c_int) -> Result<(), Errno> {
+    let policy = thread.sched_policy.load(Ordering::Acquire);
+    set_sched_param(
+        thread,
+        policy,
+        &sched_param {
+            sched_priority: prio,
+        },
+    )
 }
 pub fn set_cancel_state(state: c_int) -> Result<c_int, Errno> {
     let this_thread = current_thread().expect("current thread not present");
@@ -394,7 +640,10 @@ pub fn set_cancel_state(state: c_int) -> Result<c_int, Errno> {
                 .has_enabled_cancelation
                 .swap(true, Ordering::Release);
 
-            if this_thread.has_queued_cancelation.load(Ordering::Acquire) {
+            // Deferred requests wait for the next cancellation point.
+            if this_thread.has_async_cancelation.load(Ordering::Acquire)
+                && this_thread.has_queued_cancelation.load(Ordering::Acquire)
+            {
                 unsafe {
                     cancel_current_thread();
                 }
@@ -414,27 +663,42 @@ pub fn set_cancel_state(state: c_int) -> Result<c_int, Errno> {
     })
 }
 pub fn set_cancel_type(ty: c_int) -> Result<c_int, Errno> {
-    #[expect(unused_variables, reason = "function not yet fully implemented")]
     let this_thread = current_thread().expect("current thread not present");
 
-    // TODO
-    match ty {
-        header::PTHREAD_CANCEL_DEFERRED => (),
-        header::PTHREAD_CANCEL_ASYNCHRONOUS => (),
+    let was_async = match ty {
+        header::PTHREAD_CANCEL_DEFERRED => this_thread
+            .has_async_cancelation
+            .swap(false, Ordering::Release),
+        header::PTHREAD_CANCEL_ASYNCHRONOUS => {
+            let old = this_thread
+                .has_async_cancelation
+                .swap(true, Ordering::Release);
+
+            // A request may already have been signalled while cancellation was deferred.
+            unsafe { testcancel() };
+            old
+        }
 
         _ => return Err(Errno(EINVAL)),
-    }
-    Ok(header::PTHREAD_CANCEL_DEFERRED)
+    };
+
+    Ok(match was_async {
+        true => header::PTHREAD_CANCEL_ASYNCHRONOUS,
+        false => header::PTHREAD_CANCEL_DEFERRED,
+    })
 }
 #[expect(unused_variables, reason = "function not yet fully implemented")]
 pub fn get_cpu_clkid(thread: &Pthread) -> Result<clockid_t, Errno> {
     // TODO
     Err(Errno(ENOENT))
 }
-#[expect(unused_variables, reason = "function not yet fully implemented")]
-pub fn get_sched_param(thread: &Pthread) -> Result<(clockid_t, sched_param), Errno> {
-    // TODO should be possible to return sched_param
-    Err(Errno(ENOSYS))
+pub fn get_sched_param(thread: &Pthread) -> Result<(c_int, sched_param), Errno> {
+    Ok((
+        thread.sched_policy.load(Ordering::Acquire),
+        sched_param {
+            sched_priority: thread.sched_priority.load(Ordering::Acquire),
+        },
+    ))
 }
 
 // TODO: Hash map?
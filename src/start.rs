@@ -101,7 +101,9 @@ extern "C" fn init_array() {
         if platform::environ.is_null() {
             platform::environ = __relibc_init_environ;
         }
+        platform::init_malloc_options();
     }
+    platform::start_alloc_trace();
 
     unsafe {
         crate::pthread::init();
@@ -129,6 +131,9 @@ pub unsafe extern "C" fn relibc_start_v1(
     // Ensure correct host system before executing more system calls
     unsafe { relibc_verify_host() };
 
+    // Must happen before anything is allocated
+    unsafe { platform::init_malloc_debug(sp.envp()) };
+
     #[cfg(target_os = "redox")]
     let thr_fd = redox_rt::proc::FdGuard::new(
         unsafe {
@@ -206,6 +211,10 @@ pub unsafe extern "C" fn relibc_start_v1(
 
     let auxvs = unsafe { get_auxvs(sp.auxv().cast()) };
     if !is_dynamically_linked {
+        // The program's indirect functions may be called by constructors.
+        ld_so::ifunc::init(&auxvs);
+        unsafe { ld_so::ifunc::apply_static_relocations() };
+
         unsafe { crate::platform::init(auxvs) };
         init_array();
         if unsafe { crate::platform::logger::init().is_err() } {
@@ -4,13 +4,19 @@ use crate::{
     error::Errno,
     header::{
         errno::{EINVAL, ETIMEDOUT},
-        pthread::{PTHREAD_PROCESS_PRIVATE, RlctMutex},
+        pthread::{
+            __relibc_internal_pthread_cleanup_pop, __relibc_internal_pthread_cleanup_push,
+            CleanupLinkedListEntry, PTHREAD_PROCESS_PRIVATE, RlctMutex,
+        },
         time::{CLOCK_MONOTONIC, CLOCK_REALTIME, timespec, timespec_realtime_to_monotonic},
     },
-    platform::types::clockid_t,
+    platform::types::{c_void, clockid_t},
 };
 
-use core::sync::atomic::{AtomicU32 as AtomicUint, Ordering};
+use core::{
+    ptr,
+    sync::atomic::{AtomicU32 as AtomicUint, Ordering},
+};
 
 #[derive(Clone, Copy)]
 pub struct CondAttr {
@@ -83,7 +89,18 @@ impl Cond {
         self.clockwait(mutex, timeout, CLOCK_REALTIME)
     }
     fn wait_inner(&self, mutex: &RlctMutex, timeout: Option<&timespec>) -> Result<(), Errno> {
-        self.wait_inner_generic(|| mutex.unlock(), || mutex.lock(), timeout)
+        // If the thread is cancelled while blocked, the mutex must be reacquired before the
+        // user's cleanup handlers run.
+        extern "C" fn relock(mutex: *mut c_void) {
+            let _ = unsafe { &*mutex.cast::<RlctMutex>() }.lock();
+        }
+        let mut entry = CleanupLinkedListEntry::new(relock, ptr::from_ref(mutex).cast_mut().cast());
+
+        unsafe { __relibc_internal_pthread_cleanup_push(ptr::from_mut(&mut entry).cast()) };
+        let result = self.wait_inner_generic(|| mutex.unlock(), || mutex.lock(), timeout, true);
+        unsafe { __relibc_internal_pthread_cleanup_pop(0) };
+
+        result
     }
     pub fn wait_inner_typedmutex<'lock, T>(
         &self,
@@ -101,6 +118,7 @@ impl Cond {
                 Ok(())
             },
             None,
+            false,
         )
         .unwrap();
         newguard.unwrap()
@@ -111,6 +129,7 @@ impl Cond {
         unlock: impl FnOnce() -> Result<()>,
         lock: impl FnOnce() -> Result<()>,
         deadline: Option<&timespec>,
+        cancelable: bool,
     ) -> Result<(), Errno> {
         // TODO: Error checking for certain types (i.e. robust and errorcheck) of mutexes, e.g. if the
         // mutex is not locked.
@@ -118,7 +137,17 @@ impl Cond {
         self.prev.store(current, Ordering::Relaxed);
 
         unlock()?;
-        let futex_r = crate::sync::futex_wait(&self.cur, current, deadline);
+        let futex_r = if cancelable {
+            // A cancellation request interrupting the wait acts inside cancellation_point, and
+            // the cleanup handler pushed by wait_inner relocks the mutex. Other signals only
+            // cause a spurious wakeup.
+            crate::pthread::cancellation_point(|| {
+                crate::sync::futex_wait_interruptible(&self.cur, current, deadline)
+            })
+            .unwrap_or(super::FutexWaitResult::Waited)
+        } else {
+            crate::sync::futex_wait(&self.cur, current, deadline)
+        };
         lock()?;
 
         match futex_r {
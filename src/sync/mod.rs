@@ -107,13 +107,24 @@ pub unsafe fn futex_wait_ptr<T: FutexTy>(
     value: T,
     deadline_opt: Option<&timespec>,
 ) -> FutexWaitResult {
+    unsafe { futex_wait_interruptible_ptr(ptr, value, deadline_opt) }
+        .unwrap_or(FutexWaitResult::Waited)
+}
+/// Like [`futex_wait_ptr`], but returns `EINTR` when interrupted by a signal handler, for waits
+/// that are cancellation points.
+pub unsafe fn futex_wait_interruptible_ptr<T: FutexTy>(
+    ptr: *mut T,
+    value: T,
+    deadline_opt: Option<&timespec>,
+) -> Result<FutexWaitResult, Errno> {
     match unsafe { Sys::futex_wait(ptr.cast(), value.conv(), deadline_opt) } {
-        Ok(()) | Err(Errno(EINTR)) => FutexWaitResult::Waited,
-        Err(Errno(EAGAIN)) => FutexWaitResult::Stale,
-        Err(Errno(ETIMEDOUT)) if deadline_opt.is_some() => FutexWaitResult::TimedOut,
+        Ok(()) => Ok(FutexWaitResult::Waited),
+        Err(Errno(EINTR)) => Err(Errno(EINTR)),
+        Err(Errno(EAGAIN)) => Ok(FutexWaitResult::Stale),
+        Err(Errno(ETIMEDOUT)) if deadline_opt.is_some() => Ok(FutexWaitResult::TimedOut),
         Err(err) => {
             todo_error!(0, err, "futex failed");
-            FutexWaitResult::Waited
+            Ok(FutexWaitResult::Waited)
         }
     }
 }
@@ -127,6 +138,13 @@ pub fn futex_wait<T: FutexAtomicTy>(
 ) -> FutexWaitResult {
     unsafe { futex_wait_ptr(atomic.ptr(), value, deadline_opt) }
 }
+pub fn futex_wait_interruptible<T: FutexAtomicTy>(
+    atomic: &T,
+    value: T::Ty,
+    deadline_opt: Option<&timespec>,
+) -> Result<FutexWaitResult, Errno> {
+    unsafe { futex_wait_interruptible_ptr(atomic.ptr(), value, deadline_opt) }
+}
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum FutexWaitResult {
@@ -1,6 +1,6 @@
 use core::{
     cell::Cell,
-    sync::atomic::{AtomicU32 as AtomicUint, Ordering},
+    sync::atomic::{AtomicU16, AtomicU32 as AtomicUint, Ordering},
 };
 
 use crate::{
@@ -16,6 +16,8 @@ pub struct RlctMutex {
     // Actual locking word.
     inner: AtomicUint,
     recursive_count: AtomicUint,
+    // Running average of how many spins it took to acquire the lock, see `lock_inner`.
+    spin_estimate: AtomicU16,
 
     ty: Ty,
     robust: bool,
@@ -27,9 +29,9 @@ const INDEX_MASK: u32 = !WAITING_BIT;
 
 // TODO: Lower limit is probably better.
 const RECURSIVE_COUNT_MAX_INCLUSIVE: u32 = u32::MAX;
-// TODO: How many spins should we do before it becomes more time-economical to enter kernel mode
-// via futexes?
-const SPIN_COUNT: usize = 0;
+// Upper bound on the adaptive number of spins before waiting on the futex. Beyond this, the owner
+// is most likely not about to release the lock, and entering the kernel is cheaper.
+const SPIN_COUNT_MAX: u16 = 100;
 
 impl RlctMutex {
     pub(crate) const fn new(attr: &RlctMutexAttr) -> Result<Self, Errno> {
@@ -44,6 +46,7 @@ impl RlctMutex {
         Ok(Self {
             inner: AtomicUint::new(STATE_UNLOCKED),
             recursive_count: AtomicUint::new(0),
+            spin_estimate: AtomicU16::new(0),
             robust: match robust {
                 PTHREAD_MUTEX_STALLED => false,
                 PTHREAD_MUTEX_ROBUST => true,
@@ -75,19 +78,36 @@ impl RlctMutex {
     fn lock_inner(&self, deadline: Option<&timespec>) -> Result<(), Errno> {
         let this_thread = os_tid_invalid_after_fork();
 
-        //let mut spins_left = SPIN_COUNT;
+        // Similar to glibc's adaptive mutexes, spin for a bit longer than it previously took to
+        // acquire the lock, capped at SPIN_COUNT_MAX.
+        let spin_estimate = self.spin_estimate.load(Ordering::Relaxed);
+        let max_spins = SPIN_COUNT_MAX.min(spin_estimate.saturating_mul(2).saturating_add(10));
+        let mut spins = 0;
+
+        // Set once this thread has waited on the futex. From then on, the lock is acquired with
+        // WAITING_BIT set, as other threads may still be waiting.
+        let mut contended = false;
+
+        let mut state = STATE_UNLOCKED;
 
         loop {
-            let result = self.inner.compare_exchange_weak(
-                STATE_UNLOCKED,
-                this_thread,
-                Ordering::Acquire,
-                Ordering::Relaxed,
-            );
+            let new = if contended {
+                this_thread | WAITING_BIT
+            } else {
+                this_thread
+            };
+            let result =
+                self.inner
+                    .compare_exchange_weak(state, new, Ordering::Acquire, Ordering::Relaxed);
 
             match result {
                 // CAS succeeded
                 Ok(_) => {
+                    if spins > 0 {
+                        let delta = (i32::from(spins) - i32::from(spin_estimate)) / 8;
+                        self.spin_estimate
+                            .store((i32::from(spin_estimate) + delta) as u16, Ordering::Relaxed);
+                    }
                     if self.ty == Ty::Recursive {
                         self.increment_recursive_count()?;
                     }
@@ -102,30 +122,58 @@ impl RlctMutex {
                 Err(thread) if thread & INDEX_MASK == this_thread && self.ty == Ty::Errck => {
                     return Err(Errno(EAGAIN));
                 }
-                // CAS spuriously failed, simply retry the CAS. TODO: Use core::hint::spin_loop()?
+                // CAS spuriously failed, or the lock was released in the meantime. Simply retry
+                // the CAS.
                 Err(thread) if thread & INDEX_MASK == 0 => {
+                    state = thread;
                     continue;
                 }
-                // CAS failed because some other thread owned the lock. We must now wait.
+                // CAS failed because some other thread owned the lock. Spin while the owner is
+                // likely to release it soon, otherwise wait.
                 Err(thread) => {
-                    /*if spins_left > 0 {
-                        // TODO: Faster to spin trying to load the flag, compared to CAS?
-                        spins_left -= 1;
-                        core::hint::spin_loop();
+                    // Spinning only pays off while the owner runs. Neither Linux nor Redox tell
+                    // userspace cheaply whether another thread is on a CPU, so WAITING_BIT
+                    // serves as the proxy: it is set once another thread stopped spinning on
+                    // this owner, because the owner was descheduled or holds the lock for longer
+                    // than a spin. Spinning stops as soon as it appears, and an owner that is
+                    // preempted before costs at most SPIN_COUNT_MAX spins, which is less than
+                    // the futex wait that follows.
+                    if thread & WAITING_BIT == 0 && spins < max_spins {
+                        // Spin on plain loads of the owner's TID, which unlike CAS do not need
+                        // exclusive access to the cache line, until it releases the lock, hands
+                        // it over, or another thread starts waiting.
+                        while spins < max_spins {
+                            spins += 1;
+                            core::hint::spin_loop();
+
+                            if self.inner.load(Ordering::Relaxed) != thread {
+                                break;
+                            }
+                        }
+                        state = STATE_UNLOCKED;
                         continue;
                     }
 
-                    spins_left = SPIN_COUNT;
-
-                    let inner = self.inner.fetch_or(WAITING_BIT, Ordering::Relaxed);
-
-                    if inner == STATE_UNLOCKED {
+                    // Announce that we are about to wait, so that the owner wakes us up.
+                    if thread & WAITING_BIT == 0
+                        && self
+                            .inner
+                            .compare_exchange_weak(
+                                thread,
+                                thread | WAITING_BIT,
+                                Ordering::Relaxed,
+                                Ordering::Relaxed,
+                            )
+                            .is_err()
+                    {
+                        state = STATE_UNLOCKED;
                         continue;
-                    }*/
+                    }
 
-                    // If the mutex is not robust, simply futex_wait until unblocked.
-                    //crate::sync::futex_wait(&self.inner, inner | WAITING_BIT, None);
-                    if crate::sync::futex_wait(&self.inner, thread, deadline)
+                    contended = true;
+                    state = STATE_UNLOCKED;
+
+                    if crate::sync::futex_wait(&self.inner, thread | WAITING_BIT, deadline)
                         == FutexWaitResult::TimedOut
                     {
                         return Err(Errno(ETIMEDOUT));
@@ -208,13 +256,13 @@ impl RlctMutex {
             }
         }
 
-        self.inner.store(STATE_UNLOCKED, Ordering::Release);
-        crate::sync::futex_wake(&self.inner, i32::MAX);
-        /*let was_waiting = self.inner.swap(STATE_UNLOCKED, Ordering::Release) & WAITING_BIT != 0;
+        let was_waiting = self.inner.swap(STATE_UNLOCKED, Ordering::Release) & WAITING_BIT != 0;
 
+        // Only enter the kernel if some thread is actually waiting. The woken thread acquires the
+        // lock with WAITING_BIT set, and thus wakes the next waiter, if any, when unlocking.
         if was_waiting {
             let _ = crate::sync::futex_wake(&self.inner, 1);
-        }*/
+        }
 
         Ok(())
     }
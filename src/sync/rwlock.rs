@@ -20,19 +20,38 @@ pub struct InnerRwLock {
 // PTHREAD_RWLOCK_INITIALIZER is defined as "all zeroes".
 
 const WAITING_WR: u32 = 1 << (u32::BITS - 1);
-const COUNT_MASK: u32 = WAITING_WR - 1;
+// Constant for the lifetime of the lock, and preserved by all operations.
+const PREFER_WR: u32 = 1 << (u32::BITS - 2);
+const COUNT_MASK: u32 = PREFER_WR - 1;
 const EXCLUSIVE: u32 = COUNT_MASK;
 
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Preference {
+    /// Readers can acquire the lock while writers are waiting, as long as it is not write-locked.
+    /// Writers may starve if readers keep the lock busy.
+    #[default]
+    Reader,
+    /// Readers block while a writer is waiting. A thread already holding a read lock therefore
+    /// deadlocks if it recursively read-locks while a writer waits.
+    Writer,
+}
+
 // TODO: Optimize for short waits and long waits, using AtomicLock::wait_until, but still
 // supporting timeouts.
 // TODO: Add futex ops that use bitmasks.
 
 impl InnerRwLock {
-    pub const fn new(_pshared: Pshared) -> Self {
+    pub const fn new(_pshared: Pshared, preference: Preference) -> Self {
         Self {
-            state: AtomicU32::new(0),
+            state: AtomicU32::new(match preference {
+                Preference::Reader => 0,
+                Preference::Writer => PREFER_WR,
+            }),
         }
     }
+    fn prefer_wr(&self) -> u32 {
+        self.state.load(Ordering::Relaxed) & PREFER_WR
+    }
     fn translate_timeout(deadline: Option<(&timespec, i32)>) -> Result<Option<timespec>, Errno> {
         let relative = match deadline {
             // FUTEX expect monotonic clock
@@ -50,20 +69,22 @@ impl InnerRwLock {
         deadline: Option<(&timespec, clockid_t)>,
     ) -> Result<(), Errno> {
         let relative = Self::translate_timeout(deadline)?;
+        let prefer_wr = self.prefer_wr();
         let mut waiting_wr = self.state.load(Ordering::Relaxed) & WAITING_WR;
 
         loop {
             match self.state.compare_exchange_weak(
-                waiting_wr,
-                EXCLUSIVE,
+                prefer_wr | waiting_wr,
+                prefer_wr | EXCLUSIVE,
                 Ordering::Acquire,
                 Ordering::Relaxed,
             ) {
                 Ok(_) => break,
                 Err(actual) => {
-                    let expected = if actual & COUNT_MASK != EXCLUSIVE {
-                        // Set the exclusive bit, but only if we're waiting for readers, to avoid
-                        // reader starvation by overprioritizing write locks.
+                    let expected = if actual & COUNT_MASK != EXCLUSIVE || prefer_wr != 0 {
+                        // Set the exclusive bit, but unless writers are preferred, only if we're
+                        // waiting for readers, to avoid reader starvation by overprioritizing
+                        // write locks.
                         self.state.fetch_or(WAITING_WR, Ordering::Relaxed);
 
                         actual | WAITING_WR
@@ -76,6 +97,12 @@ impl InnerRwLock {
                         if crate::sync::futex_wait(&self.state, expected, relative.as_ref())
                             == super::FutexWaitResult::TimedOut
                         {
+                            if prefer_wr != 0 {
+                                // Readers might be blocked only because of our WAITING_WR bit.
+                                // Other waiting writers set it again when they retry.
+                                self.state.fetch_and(!WAITING_WR, Ordering::Relaxed);
+                                let _ = crate::sync::futex_wake(&self.state, i32::MAX);
+                            }
                             return Err(Errno(ETIMEDOUT));
                         }
                     } else {
@@ -105,9 +132,16 @@ impl InnerRwLock {
     }
     pub fn try_acquire_read_lock(&self) -> Result<(), u32> {
         let mut cached = self.state.load(Ordering::Acquire);
+        let prefer_wr = cached & PREFER_WR;
 
         loop {
             let waiting_wr = cached & WAITING_WR;
+
+            // With writer preference, new readers queue behind waiting writers.
+            if prefer_wr != 0 && waiting_wr != 0 {
+                return Err(cached);
+            }
+
             let old = if cached & COUNT_MASK == EXCLUSIVE {
                 0
             } else {
@@ -123,8 +157,8 @@ impl InnerRwLock {
             );
 
             match self.state.compare_exchange_weak(
-                (old & COUNT_MASK) | waiting_wr,
-                new | waiting_wr,
+                (old & COUNT_MASK) | prefer_wr | waiting_wr,
+                new | prefer_wr | waiting_wr,
                 Ordering::Acquire,
                 Ordering::Relaxed,
             ) {
@@ -140,12 +174,13 @@ impl InnerRwLock {
         }
     }
     pub fn try_acquire_write_lock(&self) -> Result<(), u32> {
+        let prefer_wr = self.prefer_wr();
         let mut waiting_wr = self.state.load(Ordering::Relaxed) & WAITING_WR;
 
         loop {
             match self.state.compare_exchange_weak(
-                waiting_wr,
-                EXCLUSIVE,
+                prefer_wr | waiting_wr,
+                prefer_wr | EXCLUSIVE,
                 Ordering::Acquire,
                 Ordering::Relaxed,
             ) {
@@ -168,8 +203,9 @@ impl InnerRwLock {
             // Unlocking a write lock.
 
             // This discards the writer-waiting bit, in order to ensure some level of fairness
-            // between read and write locks.
-            self.state.store(0, Ordering::Release);
+            // between read and write locks. Writers that are still waiting set it again when
+            // they retry.
+            self.state.store(state & PREFER_WR, Ordering::Release);
 
             let _ = crate::sync::futex_wake(&self.state, i32::MAX);
         } else {
@@ -194,7 +230,7 @@ unsafe impl<T: ?Sized + Send + Sync> Sync for RwLock<T> {}
 impl<T> RwLock<T> {
     pub const fn new(val: T) -> Self {
         Self {
-            inner: InnerRwLock::new(Pshared::Private),
+            inner: InnerRwLock::new(Pshared::Private, Preference::Reader),
             data: UnsafeCell::new(val),
         }
     }
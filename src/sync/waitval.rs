@@ -32,9 +32,15 @@ impl<T> Waitval<T> {
         crate::sync::futex_wake(&self.state, i32::MAX);
     }
 
+    /// Waits for the value to be posted. Must be called within a cancellation point: a
+    /// cancellation request interrupting the wait acts unless the value has been posted by then.
     pub fn wait(&self) -> &T {
         while self.state.load(Ordering::Acquire) == 0 {
-            crate::sync::futex_wait(&self.state, 0, None);
+            if crate::sync::futex_wait_interruptible(&self.state, 0, None).is_err()
+                && self.state.load(Ordering::Acquire) == 0
+            {
+                unsafe { crate::pthread::testcancel() };
+            }
         }
 
         unsafe { (*self.value.get()).assume_init_ref() }
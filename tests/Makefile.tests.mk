@@ -67,15 +67,21 @@ EXPECT_NAMES=\
 	features \
 	fnmatch \
 	glob \
+	ifunc \
 	iso646 \
 	libgen \
 	locale/duplocale \
+	locale/lc_time \
 	locale/newlocale \
 	locale/setlocale \
+	malloc/debug \
+	malloc/mallinfo \
+	malloc/mtrace \
 	malloc/usable_size \
 	math \
 	regex \
 	semaphore/lock \
+	semaphore/named \
 	setjmp \
 	sigaction \
 	sigaltstack \
@@ -146,6 +152,8 @@ EXPECT_NAMES=\
 	sys_select/select \
 	sys_select/select_timed \
 	sys_select/pselect \
+	sys_msg/msg \
+	sys_sem/sem \
 	sys_shm/shm \
 	sys_socket/recv \
 	sys_socket/recvfrom \
@@ -160,17 +168,23 @@ EXPECT_NAMES=\
 	sys_stat/utimensat \
 	sys_syslog/syslog \
 	time/asctime \
+	time/clock_nanosleep \
 	time/constants \
+	time/getdate \
 	time/gmtime \
 	time/localtime \
 	time/localtime_r \
 	time/macros \
 	time/mktime \
 	time/strftime \
+	time/strftime_flags \
 	time/strptime \
+	time/strptime_extended \
 	time/time \
 	time/timegm \
 	time/timer \
+	time/timer_thread \
+	time/tzif \
 	time/tzset \
 	unistd/access \
 	unistd/alarm \
@@ -230,9 +244,12 @@ EXPECT_NAMES=\
 VARIED_NAMES=\
 	dirent/main \
 	dirent/posix_getdents \
+	dl_iterate_phdr \
 	includes \
 	kill-waitpid \
 	limits \
+	mqueue/mqueue \
+	mqueue/notify \
 	net/if \
 	netdb/getaddrinfo \
 	netdb/getaddrinfo_null \
@@ -327,6 +344,10 @@ VARIED_NAMES=\
 	pthread/mutex_recursive \
 	pthread/timeout \
 	pthread/tls \
+	pthread/sched \
+	pthread/cancel \
+	pthread/lock_bench \
+	malloc/thread_bench \
 	grp/getgrouplist \
 	grp/getgroups \
 	grp/getgrgid_r \
@@ -346,8 +367,18 @@ endif
 
 # Tests that only working with when ld.so exist
 DYNAMIC_ONLY_EXPECT_NAMES=\
+	dladdr \
+	dlclose \
 	dlfcn \
-	dlopen_scopes
+	dlopen_flags \
+	dlopen_scopes \
+	dlopen_tls \
+	dlvsym \
+	ifunc_lib \
+	ld_debug \
+	ld_so_run \
+	preload_audit \
+	search_paths
 
 # Tests that may produce different result when ld.so absent
 STATIC_CHECK_EXPECT_NAMES=\
@@ -0,0 +1,63 @@
+#define _GNU_SOURCE
+#include <assert.h>
+#include <link.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+
+struct search {
+    uintptr_t addr;
+    size_t calls;
+    int found;
+};
+
+static int callback(struct dl_phdr_info *info, size_t size, void *data) {
+    struct search *search = data;
+    search->calls++;
+
+    assert(size >= offsetof(struct dl_phdr_info, dlpi_phnum) + sizeof(info->dlpi_phnum));
+    assert(info->dlpi_name != NULL);
+    assert(info->dlpi_phdr != NULL && info->dlpi_phnum > 0);
+
+    for (size_t i = 0; i < info->dlpi_phnum; i++) {
+        const ElfW(Phdr) *phdr = &info->dlpi_phdr[i];
+        if (phdr->p_type != PT_LOAD) {
+            continue;
+        }
+
+        uintptr_t start = info->dlpi_addr + phdr->p_vaddr;
+        if (search->addr >= start && search->addr < start + phdr->p_memsz) {
+            // The program comes first.
+            assert(search->calls == 1);
+            search->found = 1;
+        }
+    }
+
+    return 0;
+}
+
+static int stop(struct dl_phdr_info *info, size_t size, void *data) {
+    (void)info;
+    (void)size;
+    (*(int *)data)++;
+    return 42;
+}
+
+int main(void) {
+    struct search search = {
+        .addr = (uintptr_t)main,
+        .calls = 0,
+        .found = 0,
+    };
+    assert(dl_iterate_phdr(callback, &search) == 0);
+    assert(search.calls >= 1);
+    assert(search.found);
+
+    // Iteration stops at the first nonzero return value.
+    int calls = 0;
+    assert(dl_iterate_phdr(stop, &calls) == 42);
+    assert(calls == 1);
+
+    return EXIT_SUCCESS;
+}
@@ -0,0 +1,100 @@
+#define _GNU_SOURCE
+#include <assert.h>
+#include <dlfcn.h>
+#include <link.h>
+#include <pthread.h>
+#include <stddef.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#define SHARED_LIB "sharedlib.so"
+
+static int in_program;
+
+// In a new thread, the TLS block of the library is only allocated once used.
+static void *new_thread(void *handle) {
+    void *tls_data;
+    assert(dlinfo(handle, RTLD_DI_TLS_DATA, &tls_data) == 0);
+    assert(tls_data == NULL);
+
+    int *tls_var = dlsym(handle, "tls_var");
+    assert(dlinfo(handle, RTLD_DI_TLS_DATA, &tls_data) == 0);
+    assert(tls_data == tls_var);
+    return NULL;
+}
+
+int main(void) {
+    void *handle = dlopen(SHARED_LIB, RTLD_NOW | RTLD_LOCAL);
+    if (!handle) {
+        printf("dlopen(%s): %s\n", SHARED_LIB, dlerror());
+        return EXIT_FAILURE;
+    }
+
+    void *print = dlsym(handle, "print");
+    assert(print != NULL);
+
+    // An address in the middle of a function resolves to that function.
+    Dl_info info;
+    assert(dladdr((char *)print + 1, &info) != 0);
+    assert(strstr(info.dli_fname, SHARED_LIB) != NULL);
+    assert(info.dli_fbase != NULL && info.dli_fbase <= print);
+    printf("dladdr: dli_sname = %s\n", info.dli_sname);
+    assert(info.dli_saddr == print);
+
+    // The program itself is found too.
+    assert(dladdr(&in_program, &info) != 0);
+    assert(info.dli_fbase != NULL);
+
+    // Addresses outside of any object are not.
+    int local;
+    assert(dladdr(&local, &info) == 0);
+
+    struct link_map *map;
+    assert(dlinfo(handle, RTLD_DI_LINKMAP, &map) == 0);
+    assert(strstr(map->l_name, SHARED_LIB) != NULL);
+
+    char origin[4096];
+    assert(dlinfo(handle, RTLD_DI_ORIGIN, origin) == 0);
+    assert(strncmp(map->l_name, origin, strlen(origin)) == 0);
+
+    size_t modid;
+    assert(dlinfo(handle, RTLD_DI_TLS_MODID, &modid) == 0);
+    assert(modid != 0);
+
+    int *tls_var = dlsym(handle, "tls_var");
+    void *tls_data;
+    assert(dlinfo(handle, RTLD_DI_TLS_DATA, &tls_data) == 0);
+    assert(tls_data == tls_var);
+    printf("dlinfo: tls_var = %d\n", *(int *)tls_data);
+
+    pthread_t thread;
+    assert(pthread_create(&thread, NULL, new_thread, handle) == 0);
+    assert(pthread_join(thread, NULL) == 0);
+
+    // The search directories of the program include its DT_RUNPATH, which
+    // names the directory of the library.
+    void *program = dlopen(NULL, RTLD_NOW);
+    assert(program != NULL);
+    Dl_serinfo size;
+    assert(dlinfo(program, RTLD_DI_SERINFOSIZE, &size) == 0);
+    assert(size.dls_cnt > 0);
+    Dl_serinfo *serinfo = malloc(size.dls_size);
+    assert(serinfo != NULL);
+    assert(dlinfo(program, RTLD_DI_SERINFOSIZE, serinfo) == 0);
+    assert(dlinfo(program, RTLD_DI_SERINFO, serinfo) == 0);
+    int searched = 0;
+    for (unsigned int i = 0; i < serinfo->dls_cnt; i++) {
+        if (strcmp(serinfo->dls_serpath[i].dls_name, origin) == 0) {
+            searched = 1;
+        }
+    }
+    printf("dlinfo: searches the origin: %d\n", searched);
+    free(serinfo);
+
+    assert(dlinfo(handle, -1, &modid) == -1);
+    assert(dlerror() != NULL);
+
+    assert(dlclose(handle) == 0);
+    return EXIT_SUCCESS;
+}
@@ -0,0 +1,110 @@
+#include <dlfcn.h>
+#include <stdio.h>
+
+#include "test_helpers.h"
+
+#define CYCLES 100
+
+static int verbose = 1;
+static int events;
+static void *reentrant_handle;
+
+static void close_lib(void *handle);
+
+// Called by the libraries as they are unloaded.
+void unload_event(const char *event) {
+    events++;
+    if (verbose) {
+        printf("%s\n", event);
+    }
+
+    // Finalizers may call back into the dynamic linker.
+    if (reentrant_handle) {
+        void *handle = reentrant_handle;
+        reentrant_handle = NULL;
+        printf("dlsym from fini: %s\n", dlsym(handle, "b_next") ? "found" : dlerror());
+        close_lib(handle);
+    }
+}
+
+static void *open_lib(const char *name, int flags) {
+    void *handle = dlopen(name, flags);
+    if (!handle) {
+        printf("dlopen(%s): %s\n", name, dlerror());
+        exit(EXIT_FAILURE);
+    }
+    return handle;
+}
+
+static void close_lib(void *handle) {
+    int status = dlclose(handle);
+    ERROR_IF(dlclose, status, != 0);
+}
+
+static int is_loaded(const char *name) {
+    void *handle = dlopen(name, RTLD_LAZY | RTLD_NOLOAD);
+    if (handle) {
+        close_lib(handle);
+    }
+    return handle != NULL;
+}
+
+static int call(void *handle, const char *name) {
+    int (*func)(void);
+    *(void **)(&func) = dlsym(handle, name);
+    if (!func) {
+        printf("dlsym(%s): %s\n", name, dlerror());
+        exit(EXIT_FAILURE);
+    }
+    return func();
+}
+
+int main(void) {
+    // A dependency shared by two libraries stays loaded until both are
+    // closed, and is finalized after them.
+    void *a = open_lib("libunloada.so", RTLD_NOW);
+    void *b = open_lib("libunloadb.so", RTLD_NOW);
+    printf("a: %d\n", call(a, "a_next"));
+    printf("b: %d\n", call(b, "b_next"));
+    puts("close a");
+    close_lib(a);
+    printf("dep loaded: %d\n", is_loaded("libunloaddep.so"));
+    puts("close b");
+    close_lib(b);
+    printf("dep loaded: %d\n", is_loaded("libunloaddep.so"));
+
+    // A library stays loaded while another one is bound to it, even if it
+    // is not a dependency of it.
+    void *dep = open_lib("libunloaddep.so", RTLD_LAZY | RTLD_GLOBAL);
+    void *c = open_lib("libunloadc.so", RTLD_LAZY);
+    printf("c: %d\n", call(c, "c_next"));
+    puts("close dep");
+    close_lib(dep);
+    printf("dep loaded: %d\n", is_loaded("libunloaddep.so"));
+    printf("c: %d\n", call(c, "c_next"));
+    puts("close c");
+    close_lib(c);
+    printf("dep loaded: %d\n", is_loaded("libunloaddep.so"));
+
+    // A finalizer looks up a symbol and closes another library.
+    a = open_lib("libunloada.so", RTLD_NOW);
+    reentrant_handle = open_lib("libunloadb.so", RTLD_NOW);
+    puts("close a");
+    close_lib(a);
+    printf("dep loaded: %d\n", is_loaded("libunloaddep.so"));
+
+    // Each cycle loads fresh copies of the libraries.
+    verbose = 0;
+    events = 0;
+    int fresh = 0;
+    for (int i = 0; i < CYCLES; i++) {
+        a = open_lib("libunloada.so", RTLD_NOW);
+        b = open_lib("libunloadb.so", RTLD_LAZY);
+        fresh += call(a, "a_next") == 1 && call(b, "b_next") == 2;
+        close_lib(b);
+        close_lib(a);
+    }
+    printf("fresh: %d/%d, events: %d\n", fresh, CYCLES, events);
+
+    return EXIT_SUCCESS;
+}
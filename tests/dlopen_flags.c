@@ -0,0 +1,83 @@
+#define _GNU_SOURCE
+#include <assert.h>
+#include <dlfcn.h>
+#include <stdio.h>
+#include <stdlib.h>
+
+// Interposes on the definition in libdeepbind.so, unless it is loaded with
+// RTLD_DEEPBIND.
+int shared_value(void) { return 1; }
+
+static void test_noload_nodelete(void) {
+    assert(dlopen("sharedlib.so", RTLD_LAZY | RTLD_NOLOAD) == NULL);
+
+    void *handle = dlopen("sharedlib.so", RTLD_LAZY | RTLD_NODELETE);
+    assert(handle != NULL);
+
+    int *global_var = dlsym(handle, "global_var");
+    assert(global_var != NULL);
+    *global_var = 1337;
+
+    // Reopening a loaded object returns the same handle.
+    assert(dlopen("sharedlib.so", RTLD_LAZY | RTLD_NOLOAD) == handle);
+    assert(dlclose(handle) == 0);
+    assert(dlclose(handle) == 0);
+
+    // The object stays loaded, along with its data.
+    handle = dlopen("sharedlib.so", RTLD_LAZY | RTLD_NOLOAD);
+    assert(handle != NULL);
+    global_var = dlsym(handle, "global_var");
+    printf("nodelete: global_var = %d\n", *global_var);
+}
+
+static void test_deepbind(void) {
+    int (*call)(void);
+
+    void *handle = dlopen("libdeepbind.so", RTLD_NOW | RTLD_LOCAL | RTLD_DEEPBIND);
+    assert(handle != NULL);
+    *(void **)(&call) = dlsym(handle, "call_shared_value");
+    assert(call != NULL);
+    printf("deepbind: call_shared_value() = %d\n", call());
+
+    // Upgrading the object to the global scope makes it visible to
+    // RTLD_NEXT lookups from the program.
+    assert(dlopen("libdeepbind.so", RTLD_NOW | RTLD_GLOBAL | RTLD_NOLOAD) == handle);
+
+    int (*next)(void);
+    *(void **)(&next) = dlsym(RTLD_NEXT, "shared_value");
+    assert(next != NULL);
+    printf("next: shared_value() = %d\n", next());
+}
+
+static void test_bind_now(void) {
+    void *target = dlopen("libbindtarget.so", RTLD_NOW | RTLD_GLOBAL);
+    assert(target != NULL);
+
+    // The call to bind_target is bound to the target once libbindnow.so is
+    // reopened with RTLD_NOW, which keeps the target loaded.
+    void *handle = dlopen("libbindnow.so", RTLD_LAZY);
+    assert(handle != NULL);
+    assert(dlopen("libbindnow.so", RTLD_NOW) == handle);
+    assert(dlclose(target) == 0);
+    printf("bind now: target loaded: %d\n",
+           dlopen("libbindtarget.so", RTLD_NOW | RTLD_NOLOAD) != NULL);
+
+    int (*call)(void);
+    *(void **)(&call) = dlsym(handle, "call_bind_target");
+    assert(call != NULL);
+    printf("bind now: call_bind_target() = %d\n", call());
+}
+
+static void test_dlmopen(void) {
+    void *handle = dlmopen(LM_ID_BASE, "sharedlib.so", RTLD_LAZY | RTLD_NOLOAD);
+    assert(handle != NULL);
+}
+
+int main(void) {
+    printf("shared_value() = %d\n", shared_value());
+    test_noload_nodelete();
+    test_deepbind();
+    test_bind_now();
+    test_dlmopen();
+    return EXIT_SUCCESS;
+}
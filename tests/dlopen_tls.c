@@ -0,0 +1,72 @@
+#include <dlfcn.h>
+#include <pthread.h>
+#include <stdint.h>
+#include <stdio.h>
+
+#include "test_helpers.h"
+
+_Alignas(128) static __thread char main_aligned[5] = {5};
+
+static char *(*tls_aligned_addr)(void);
+static int *(*tls_counter_addr)(void);
+
+static int is_aligned(void *ptr, uintptr_t align) {
+    return ((uintptr_t)ptr & (align - 1)) == 0;
+}
+
+static void *load(void) {
+    void *handle = dlopen("libtlsalign.so", RTLD_NOW | RTLD_LOCAL);
+    if (!handle) {
+        printf("dlopen(libtlsalign.so): %s\n", dlerror());
+        exit(EXIT_FAILURE);
+    }
+
+    *(void **)(&tls_aligned_addr) = dlsym(handle, "tls_aligned_addr");
+    *(void **)(&tls_counter_addr) = dlsym(handle, "tls_counter_addr");
+    if (!tls_aligned_addr || !tls_counter_addr) {
+        printf("dlsym: %s\n", dlerror());
+        exit(EXIT_FAILURE);
+    }
+
+    return handle;
+}
+
+static void *thread_main(void *arg) {
+    (void)arg;
+
+    char *aligned = tls_aligned_addr();
+    printf("thread: aligned = %d, tls_aligned = %d %d %d, tls_counter = %d\n",
+           is_aligned(aligned, 256), aligned[0], aligned[1], aligned[2],
+           *tls_counter_addr());
+    printf("thread: main_aligned = %d\n", is_aligned(main_aligned, 128));
+    return NULL;
+}
+
+int main(void) {
+    printf("main: main_aligned = %d, value = %d\n", is_aligned(main_aligned, 128),
+           main_aligned[0]);
+
+    void *handle = load();
+    char *aligned = tls_aligned_addr();
+    printf("main: aligned = %d, tls_aligned = %d %d %d, tls_counter = %d\n",
+           is_aligned(aligned, 256), aligned[0], aligned[1], aligned[2],
+           *tls_counter_addr());
+    *tls_counter_addr() = 7;
+
+    // Threads get a fresh copy of the TLS of the library.
+    pthread_t thread;
+    int status = pthread_create(&thread, NULL, thread_main, NULL);
+    ERROR_IF(pthread_create, status, != 0);
+    status = pthread_join(thread, NULL);
+    ERROR_IF(pthread_join, status, != 0);
+    printf("main: tls_counter = %d\n", *tls_counter_addr());
+
+    // Reloading the library gives a fresh copy of its TLS.
+    status = dlclose(handle);
+    ERROR_IF(dlclose, status, != 0);
+    handle = load();
+    printf("reload: tls_counter = %d\n", *tls_counter_addr());
+    dlclose(handle);
+
+    return EXIT_SUCCESS;
+}
@@ -0,0 +1,36 @@
+#include <assert.h>
+#include <dlfcn.h>
+#include <stdio.h>
+#include <stdlib.h>
+
+int value(void);
+
+int main(void) {
+    // Linked against the default version.
+    printf("value() = %d\n", value());
+
+    void *handle = dlopen("libversioned.so", RTLD_LAZY | RTLD_LOCAL);
+    if (!handle) {
+        printf("dlopen(libversioned.so): %s\n", dlerror());
+        return EXIT_FAILURE;
+    }
+
+    int (*f)(void);
+
+    *(void **)(&f) = dlsym(handle, "value");
+    assert(f != NULL);
+    printf("dlsym(value) = %d\n", f());
+
+    *(void **)(&f) = dlvsym(handle, "value", "VERS_1");
+    assert(f != NULL);
+    printf("dlvsym(value, VERS_1) = %d\n", f());
+
+    *(void **)(&f) = dlvsym(handle, "value", "VERS_2");
+    assert(f != NULL);
+    printf("dlvsym(value, VERS_2) = %d\n", f());
+
+    assert(dlvsym(handle, "value", "VERS_3") == NULL);
+
+    assert(dlclose(handle) == 0);
+    return EXIT_SUCCESS;
+}
@@ -0,0 +1,46 @@
+#include <stdio.h>
+#include <stdlib.h>
+
+#ifdef __aarch64__
+#include <stdint.h>
+
+#define IFUNC_ARG_HWCAP (1ULL << 62)
+
+struct ifunc_arg {
+    unsigned long size;
+    unsigned long hwcap;
+    unsigned long hwcap2;
+};
+#endif
+
+static int hwcap_ok;
+
+static int value_generic(void) { return 1; }
+static int value_selected(void) { return 2; }
+
+#ifdef __aarch64__
+static int (*resolve_value(uint64_t hwcap, const struct ifunc_arg *arg))(void) {
+    hwcap_ok = (hwcap & IFUNC_ARG_HWCAP) && arg && arg->size >= sizeof(*arg) &&
+               arg->hwcap == (hwcap & ~IFUNC_ARG_HWCAP);
+#else
+static int (*resolve_value(void))(void) {
+    hwcap_ok = 1;
+#endif
+    return hwcap_ok ? value_selected : value_generic;
+}
+
+int value(void) __attribute__((ifunc("resolve_value")));
+
+static int (*volatile value_ptr)(void) = value;
+
+__attribute__((constructor)) static void constructor(void) {
+    // Indirect functions are resolved before constructors run.
+    printf("constructor: %d\n", value());
+}
+
+int main(void) {
+    printf("main: %d\n", value());
+    printf("pointer: %d\n", value_ptr());
+    printf("hwcap: %d\n", hwcap_ok);
+    return EXIT_SUCCESS;
+}
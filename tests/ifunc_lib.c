@@ -0,0 +1,29 @@
+#include <dlfcn.h>
+#include <stdio.h>
+
+#include "test_helpers.h"
+
+int ifunc_add(int a, int b);
+int ifunc_resolved(void);
+int (*ifunc_add_ptr(void))(int, int);
+
+int main(void) {
+    // Bound through the PLT (`JUMP_SLOT`).
+    printf("call: %d\n", ifunc_add(1, 2));
+    printf("resolved: %d\n", ifunc_resolved());
+
+    // Bound through the GOT (`GLOB_DAT`).
+    int (*add)(int, int) = ifunc_add;
+    printf("pointer: %d\n", add(3, 4));
+    printf("library pointer: %d\n", ifunc_add_ptr()(5, 6));
+
+    void *handle = dlopen("libifunc.so", RTLD_NOW);
+    ERROR_IF(dlopen, handle, == NULL);
+    int (*sym)(int, int);
+    *(void **)(&sym) = dlsym(handle, "ifunc_add");
+    ERROR_IF(dlsym, sym, == NULL);
+    printf("dlsym: %d\n", sym(7, 8));
+    dlclose(handle);
+
+    return EXIT_SUCCESS;
+}
@@ -0,0 +1,109 @@
+#include <ctype.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+
+#include "test_helpers.h"
+
+// Defined by libversioned.so, which this program is linked against, in
+// versions VERS_1 and VERS_2.
+int value(void);
+
+// Runs this program again with LD_DEBUG set to `debug`, and LD_BIND_NOW set
+// if `bind_now` is set. Returns the trace, which is removed once closed.
+static FILE *trace(const char *self, const char *debug, int bind_now) {
+    char output[64];
+    snprintf(output, sizeof(output), "/tmp/ld_debug.%d", (int)getpid());
+
+    char debug_var[64];
+    snprintf(debug_var, sizeof(debug_var), "LD_DEBUG=%s", debug);
+    char output_var[96];
+    snprintf(output_var, sizeof(output_var), "LD_DEBUG_OUTPUT=%s", output);
+    char *env[] = {
+        debug_var,
+        output_var,
+        bind_now ? "LD_BIND_NOW=1" : "LD_BIND_NOW",
+        NULL,
+    };
+    pid_t pid = run_self_with_env(self, "child", env);
+
+    // The output file is suffixed with the process ID.
+    char path[96];
+    snprintf(path, sizeof(path), "%s.%d", output, (int)pid);
+    FILE *file = fopen(path, "r");
+    ERROR_IF(fopen, file, == NULL);
+    int status = unlink(path);
+    ERROR_IF(unlink, status, == -1);
+    return file;
+}
+
+// Runs this program again with LD_DEBUG=statistics, and LD_BIND_NOW set if
+// `bind_now` is set. Prints the statistics with the numbers left out, and
+// returns the number of relocations processed at load time.
+static long statistics(const char *self, int bind_now) {
+    FILE *file = trace(self, "statistics", bind_now);
+
+    long relocations = -1;
+    char line[256];
+    while (fgets(line, sizeof(line), file)) {
+        const char *count = strstr(line, "number of relocations: ");
+        if (count) {
+            relocations = strtol(count + strlen("number of relocations: "), NULL, 10);
+        }
+
+        for (char *c = line; *c; c++) {
+            if (isdigit((unsigned char)*c)) {
+                char *end = c;
+                while (isdigit((unsigned char)*end)) {
+                    end++;
+                }
+                *c = 'N';
+                memmove(c + 1, end, strlen(end) + 1);
+            }
+        }
+        printf("%s", line);
+    }
+
+    fclose(file);
+    return relocations;
+}
+
+// Runs this program again with LD_DEBUG set to `debug`, and fails unless the
+// trace has a line containing `expected`.
+static void expect_trace(const char *self, const char *debug, const char *expected) {
+    FILE *file = trace(self, debug, 0);
+
+    int found = 0;
+    char line[512];
+    while (fgets(line, sizeof(line), file)) {
+        if (strstr(line, expected)) {
+            found = 1;
+        }
+    }
+    fclose(file);
+
+    printf("%s: %s\n", debug, found ? "traced" : "missing");
+    if (!found) {
+        exit(EXIT_FAILURE);
+    }
+}
+
+int main(int argc, char **argv) {
+    if (argc > 1) {
+        return value() == 2 ? EXIT_SUCCESS : EXIT_FAILURE;
+    }
+
+    long lazy = statistics(argv[0], 0);
+    long now = statistics(argv[0], 1);
+
+    // PLT slots bound lazily are only counted once resolved.
+    printf("fewer relocations when binding lazily: %s\n", lazy < now ? "yes" : "no");
+
+    expect_trace(argv[0], "bindings", ": symbol 'value' [VERS_2]");
+    expect_trace(argv[0], "reloc", "[ld.so]: relocation processing: ");
+    expect_trace(argv[0], "symbols", "[ld.so]: symbol=value; lookup in file=");
+    expect_trace(argv[0], "versions", "[ld.so]: checking for version 'VERS_2' in file ");
+
+    return EXIT_SUCCESS;
+}
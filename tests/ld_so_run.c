@@ -0,0 +1,92 @@
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+
+#include "test_helpers.h"
+
+// Runs the dynamic linker as a command: `LD_SO` is its path, and this program
+// is linked against libfoo.so.
+extern char *FOO;
+
+static const char *basename_of(const char *path) {
+    const char *slash = strrchr(path, '/');
+    return slash ? slash + 1 : path;
+}
+
+// Runs `argv`, storing its standard output in `output`. Returns the exit
+// status.
+static int run(char *const argv[], char *output, size_t size) {
+    char *env[] = {NULL};
+    int status = run_with_env(argv, env, STDOUT_FILENO, output, size, NULL);
+    if (!WIFEXITED(status)) {
+        printf("%s: killed by signal %d\n", argv[1], WTERMSIG(status));
+        exit(EXIT_FAILURE);
+    }
+    return WEXITSTATUS(status);
+}
+
+int main(int argc, char **argv) {
+    if (argc > 1 && strcmp(argv[1], "child") == 0) {
+        printf("argc=%d argv[0]=%s", argc, basename_of(argv[0]));
+        for (int i = 2; i < argc; i++) {
+            printf(" argv[%d]=%s", i, argv[i]);
+        }
+        printf("\n");
+        return EXIT_SUCCESS;
+    }
+
+    if (FOO == NULL) {
+        return EXIT_FAILURE;
+    }
+
+    char *self = argv[0];
+    char output[4096];
+
+    // The arguments following the program are passed to it.
+    char *plain[] = {LD_SO, self, "child", "one", "two", NULL};
+    int status = run(plain, output, sizeof(output));
+    printf("run: %d %s", status, output);
+
+    char *argv0[] = {LD_SO, "--argv0", "renamed", self, "child", NULL};
+    status = run(argv0, output, sizeof(output));
+    printf("--argv0: %d %s", status, output);
+
+    char *inhibit[] = {LD_SO, "--inhibit-cache", self, "child", "--list", NULL};
+    status = run(inhibit, output, sizeof(output));
+    printf("--inhibit-cache: %d %s", status, output);
+
+    // Each dependency is listed with the path it was found at.
+    char *list[] = {LD_SO, "--list", self, NULL};
+    status = run(list, output, sizeof(output));
+    const char *foo = strstr(output, "\tlibfoo.so => ");
+    printf("--list: %d libfoo.so %s\n", status,
+           foo && strstr(foo, "/libfoo.so (0x") ? "found" : "missing");
+    if (strstr(output, "child")) {
+        printf("--list: the program was run\n");
+    }
+
+    // Dynamically linked programs, statically linked programs and files
+    // that are not programs.
+    char *verify_dynamic[] = {LD_SO, "--verify", self, NULL};
+    printf("--verify dynamic: %d\n", run(verify_dynamic, output, sizeof(output)));
+
+    char static_path[4096];
+    snprintf(static_path, sizeof(static_path), "%.*s/../bins_static/args",
+             (int)(basename_of(self) - self - 1), self);
+    char *verify_static[] = {LD_SO, "--verify", static_path, NULL};
+    printf("--verify static: %d\n", run(verify_static, output, sizeof(output)));
+
+    char text_path[] = "/tmp/ld_so_run.XXXXXX";
+    int fd = mkstemp(text_path);
+    ERROR_IF(mkstemp, fd, == -1);
+    status = write(fd, "not a program\n", 14);
+    ERROR_IF(write, status, == -1);
+    close(fd);
+    char *verify_text[] = {LD_SO, "--verify", text_path, NULL};
+    printf("--verify text: %d\n", run(verify_text, output, sizeof(output)));
+    unlink(text_path);
+
+    return EXIT_SUCCESS;
+}
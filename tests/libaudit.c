@@ -0,0 +1,65 @@
+#include <dlfcn.h>
+#include <elf.h>
+#include <link.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <string.h>
+
+// Auditing library for preload_audit.c, reporting the calls it receives once
+// the binding of `interposed_name` is audited.
+
+static unsigned int version;
+static int opened_interposed;
+static int opened_preload;
+
+static const char *basename_of(const char *path) {
+    const char *slash = strrchr(path, '/');
+    return slash ? slash + 1 : path;
+}
+
+unsigned int la_version(unsigned int current) {
+    version = current;
+    return LAV_CURRENT;
+}
+
+unsigned int la_objopen(struct link_map *map, Lmid_t lmid, uintptr_t *cookie) {
+    (void)lmid;
+    const char *name = basename_of(map->l_name);
+    if (strcmp(name, "libinterposed.so") == 0) {
+        opened_interposed = 1;
+    } else if (strcmp(name, "libpreload.so") == 0) {
+        opened_preload = 1;
+    }
+    *cookie = (uintptr_t)map;
+    return LA_FLG_BINDTO | LA_FLG_BINDFROM;
+}
+
+static uintptr_t symbind(uintptr_t value, uintptr_t *defcook, const char *symname) {
+    if (strcmp(symname, "interposed_name") == 0) {
+        const struct link_map *def = (const struct link_map *)*defcook;
+        printf("la_version: %s\n", version >= 1 ? "called" : "not called");
+        printf("la_objopen: libinterposed.so %s, libpreload.so %s\n",
+               opened_interposed ? "opened" : "not opened",
+               opened_preload ? "opened" : "not opened");
+        printf("la_symbind: %s bound to %s\n", symname, basename_of(def->l_name));
+        // The auditing library may have its own copy of the C library.
+        fflush(stdout);
+    }
+    return value;
+}
+
+uintptr_t la_symbind32(Elf32_Sym *sym, unsigned int ndx, uintptr_t *refcook, uintptr_t *defcook,
+                       unsigned int *flags, const char *symname) {
+    (void)ndx;
+    (void)refcook;
+    (void)flags;
+    return symbind(sym->st_value, defcook, symname);
+}
+
+uintptr_t la_symbind64(Elf64_Sym *sym, unsigned int ndx, uintptr_t *refcook, uintptr_t *defcook,
+                       unsigned int *flags, const char *symname) {
+    (void)ndx;
+    (void)refcook;
+    (void)flags;
+    return symbind(sym->st_value, defcook, symname);
+}
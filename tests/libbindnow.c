@@ -0,0 +1,4 @@
+// Defined by libbindtarget.so, which this library does not depend on.
+int bind_target(void);
+
+int call_bind_target(void) { return bind_target(); }
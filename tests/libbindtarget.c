@@ -0,0 +1 @@
+int bind_target(void) { return 3; }
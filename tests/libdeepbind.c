@@ -0,0 +1,3 @@
+int shared_value(void) { return 2; }
+
+int call_shared_value(void) { return shared_value(); }
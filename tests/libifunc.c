@@ -0,0 +1,15 @@
+static int resolved;
+
+static int add_impl(int a, int b) { return a + b; }
+
+static int (*resolve_add(void))(int, int) {
+    resolved = 1;
+    return add_impl;
+}
+
+int ifunc_add(int a, int b) __attribute__((ifunc("resolve_add")));
+
+int ifunc_resolved(void) { return resolved; }
+
+// Referenced from within the library, through the GOT.
+int (*ifunc_add_ptr(void))(int, int) { return ifunc_add; }
@@ -0,0 +1,2 @@
+// Overridden by libpreload.so when it is preloaded.
+const char *interposed_name(void) { return "needed"; }
@@ -0,0 +1,2 @@
+// Interposes the definition of libinterposed.so.
+const char *interposed_name(void) { return "preload"; }
@@ -0,0 +1,2 @@
+// Built into several directories, with `WHICH` naming the directory.
+const char *search_which(void) { return WHICH; }
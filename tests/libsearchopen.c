@@ -0,0 +1,15 @@
+#include <dlfcn.h>
+
+// Opens libsearch.so with the search paths of this library.
+const char *search_open(void) {
+    void *handle = dlopen("libsearch.so", RTLD_NOW | RTLD_LOCAL);
+    if (!handle) {
+        return dlerror();
+    }
+    const char *(*which)(void);
+    *(void **)(&which) = dlsym(handle, "search_which");
+    if (!which) {
+        return dlerror();
+    }
+    return which();
+}
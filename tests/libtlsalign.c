@@ -0,0 +1,6 @@
+_Alignas(256) __thread char tls_aligned[3] = {1, 2, 3};
+__thread int tls_counter = 42;
+
+char *tls_aligned_addr(void) { return tls_aligned; }
+
+int *tls_counter_addr(void) { return &tls_counter; }
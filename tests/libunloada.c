@@ -0,0 +1,8 @@
+int dep_next(void);
+
+// Defined by the program.
+void unload_event(const char *event);
+
+__attribute__((destructor)) static void fini(void) { unload_event("a: fini"); }
+
+int a_next(void) { return dep_next(); }
@@ -0,0 +1,9 @@
+// Not a dependency, found in the global scope.
+int dep_next(void);
+
+// Defined by the program.
+void unload_event(const char *event);
+
+__attribute__((destructor)) static void fini(void) { unload_event("c: fini"); }
+
+int c_next(void) { return dep_next(); }
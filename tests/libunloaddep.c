@@ -0,0 +1,19 @@
+int __cxa_atexit(void (*func)(void *), void *arg, void *dso);
+
+// Any address within this object identifies it to __cxa_atexit.
+static char dso_marker;
+
+// Defined by the program.
+void unload_event(const char *event);
+
+static int counter;
+
+static void atexit_handler(void *arg) { unload_event(arg); }
+
+__attribute__((constructor)) static void init(void) {
+    __cxa_atexit(atexit_handler, "dep: atexit handler", &dso_marker);
+}
+
+__attribute__((destructor)) static void fini(void) { unload_event("dep: fini"); }
+
+int dep_next(void) { return ++counter; }
@@ -0,0 +1,5 @@
+int value_v1(void) { return 1; }
+int value_v2(void) { return 2; }
+
+__asm__(".symver value_v1, value@VERS_1");
+__asm__(".symver value_v2, value@@VERS_2");
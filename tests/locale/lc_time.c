@@ -0,0 +1,72 @@
+#include <langinfo.h>
+#include <locale.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <time.h>
+
+#include "test_helpers.h"
+
+static void format(const char *fmt, const struct tm *tm, locale_t loc) {
+    char buf[128];
+    size_t n = strftime_l(buf, sizeof(buf), fmt, tm, loc);
+    printf("\"%s\": %zu \"%s\"\n", fmt, n, buf);
+}
+
+int main(void) {
+    // The fixture is in tests/locales
+    int status = setenv("LOCPATH", "locales", 1);
+    ERROR_IF(setenv, status, == -1);
+
+    locale_t loc = newlocale(LC_TIME_MASK, "xx_TEST", (locale_t)0);
+    ERROR_IF(newlocale, loc, == (locale_t)0);
+
+    struct tm tm = {0};
+    tm.tm_year = 2021 - 1900;
+    tm.tm_mon = 2;
+    tm.tm_mday = 5;
+    tm.tm_hour = 14;
+    tm.tm_min = 7;
+    tm.tm_sec = 9;
+    tm.tm_wday = 5;
+    tm.tm_yday = 63;
+
+    // Names
+    format("%a %A", &tm, loc);
+    format("%b %B", &tm, loc);
+    format("%^a %#B", &tm, loc);
+    format("%p %r", &tm, loc);
+
+    // Formats
+    format("%c", &tm, loc);
+    format("%x", &tm, loc);
+    format("%X", &tm, loc);
+
+    // Eras and alternative digits
+    format("%Ec", &tm, loc);
+    format("%Ex", &tm, loc);
+    format("%EX", &tm, loc);
+    format("%EC %Ey %EY", &tm, loc);
+    format("%Oe %Od %OH", &tm, loc);
+
+    tm.tm_year = 1995 - 1900;
+    tm.tm_hour = 9;
+    format("%EY", &tm, loc);
+    format("%p", &tm, loc);
+
+    // Items of other categories are the same as in the "C" locale
+    printf("ABDAY_1: \"%s\"\n", nl_langinfo_l(ABDAY_1, loc));
+    printf("ERA_T_FMT: \"%s\"\n", nl_langinfo_l(ERA_T_FMT, loc));
+    printf("RADIXCHAR: \"%s\"\n", nl_langinfo_l(RADIXCHAR, loc));
+    printf("THOUSEP: \"%s\"\n", nl_langinfo_l(THOUSEP, loc));
+    printf("C RADIXCHAR: \"%s\"\n", nl_langinfo(RADIXCHAR));
+
+    // Parsing names
+    memset(&tm, 0, sizeof(tm));
+    const char *rest = strptime_l("vendredi 5 mars 2021", "%A %d %B %Y", &tm, loc);
+    ERROR_IF(strptime_l, rest, == NULL);
+    printf("strptime_l: %d-%02d-%02d wday %d\n", tm.tm_year + 1900, tm.tm_mon + 1, tm.tm_mday,
+           tm.tm_wday);
+
+    freelocale(loc);
+}
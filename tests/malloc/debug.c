@@ -0,0 +1,125 @@
+#include <assert.h>
+#include <malloc.h>
+#include <signal.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+
+#include "../test_helpers.h"
+
+static void use_heap(void) {
+    char *ptr = malloc(24);
+    assert(ptr != NULL);
+    memset(ptr, 'A', 24);
+    assert(malloc_usable_size(ptr) >= 24);
+
+    ptr = realloc(ptr, 4096);
+    assert(ptr != NULL);
+    for (int i = 0; i < 24; i++) {
+        assert(ptr[i] == 'A');
+    }
+    free(ptr);
+
+    int *zeroed = calloc(64, sizeof(int));
+    assert(zeroed != NULL);
+    for (int i = 0; i < 64; i++) {
+        assert(zeroed[i] == 0);
+    }
+    free(zeroed);
+
+    void *aligned = memalign(256, 100);
+    assert(aligned != NULL);
+    assert((size_t)aligned % 256 == 0);
+    free(aligned);
+}
+
+static void double_free(void) {
+    char *ptr = malloc(24);
+    assert(ptr != NULL);
+    free(ptr);
+    free(ptr);
+}
+
+static void overflow(void) {
+    char *ptr = malloc(24);
+    assert(ptr != NULL);
+    ptr[24] = 'A';
+    free(ptr);
+}
+
+static void underflow(void) {
+    char *ptr = malloc(24);
+    assert(ptr != NULL);
+    ptr[-1] = 'A';
+    free(ptr);
+}
+
+static void invalid_free(void) {
+    char *ptr = calloc(1, 64);
+    assert(ptr != NULL);
+    free(ptr + 16);
+}
+
+static void write_after_free(void) {
+    char *ptr = malloc(24);
+    assert(ptr != NULL);
+    free(ptr);
+    ptr[0] = 'A';
+
+    // With a quarantine of one block, freeing another block releases the
+    // first one, which is checked then.
+    char *other = malloc(24);
+    assert(other != NULL);
+    free(other);
+}
+
+// Runs this program again with MALLOC_CHECK_ and `quarantine` set, and
+// returns its status. The diagnostic written to the standard error, without
+// the address, is printed.
+static int run(char *self, char *mode, char *quarantine) {
+    char *argv[] = {self, mode, NULL};
+    char *env[] = {"MALLOC_CHECK_=3", quarantine ? quarantine : "RELIBC_MALLOC_QUARANTINE", NULL};
+    char message[256];
+    int status = run_with_env(argv, env, STDERR_FILENO, message, sizeof(message), NULL);
+
+    char *address = strstr(message, ": 0x");
+    if (address) {
+        *address = '\0';
+        printf("%s: %s\n", mode, message);
+    }
+    return status;
+}
+
+int main(int argc, char **argv) {
+    if (argc > 1) {
+        if (strcmp(argv[1], "use") == 0) {
+            use_heap();
+        } else if (strcmp(argv[1], "double-free") == 0) {
+            double_free();
+        } else if (strcmp(argv[1], "overflow") == 0) {
+            overflow();
+        } else if (strcmp(argv[1], "underflow") == 0) {
+            underflow();
+        } else if (strcmp(argv[1], "invalid-free") == 0) {
+            invalid_free();
+        } else if (strcmp(argv[1], "write-after-free") == 0) {
+            write_after_free();
+        }
+        return EXIT_SUCCESS;
+    }
+
+    int status = run(argv[0], "use", NULL);
+    assert(WIFEXITED(status) && WEXITSTATUS(status) == EXIT_SUCCESS);
+    status = run(argv[0], "use", "RELIBC_MALLOC_QUARANTINE=16");
+    assert(WIFEXITED(status) && WEXITSTATUS(status) == EXIT_SUCCESS);
+
+    char *corruptions[] = {"double-free", "overflow", "underflow", "invalid-free"};
+    for (size_t i = 0; i < sizeof(corruptions) / sizeof(corruptions[0]); i++) {
+        status = run(argv[0], corruptions[i], NULL);
+        assert(WIFSIGNALED(status) && WTERMSIG(status) == SIGABRT);
+    }
+
+    status = run(argv[0], "write-after-free", "RELIBC_MALLOC_QUARANTINE=1");
+    assert(WIFSIGNALED(status) && WTERMSIG(status) == SIGABRT);
+}
@@ -0,0 +1,116 @@
+#include <assert.h>
+#include <malloc.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "../test_helpers.h"
+
+#define SIZE (64 * 1024)
+
+int main(void) {
+    struct mallinfo2 before = mallinfo2();
+
+    char *ptr = malloc(SIZE);
+    assert(ptr != NULL);
+    memset(ptr, 'A', SIZE);
+
+    struct mallinfo2 during = mallinfo2();
+    assert(during.uordblks >= before.uordblks + SIZE);
+    assert(during.arena >= during.uordblks);
+
+    free(ptr);
+
+    struct mallinfo2 after = mallinfo2();
+    assert(after.uordblks < during.uordblks);
+    assert(after.uordblks + after.fordblks <= after.arena);
+
+    // Releasing memory is not guaranteed, only that the heap remains usable.
+    int released = malloc_trim(0);
+    assert(released == 0 || released == 1);
+    ptr = malloc(SIZE);
+    assert(ptr != NULL);
+    free(ptr);
+
+    int status = mallopt(M_PERTURB, 0x55);
+    assert(status == 1);
+    unsigned char *perturbed = malloc(3000);
+    assert(perturbed != NULL);
+    for (size_t i = 0; i < 3000; i++) {
+        assert(perturbed[i] == 0xaa);
+    }
+    free(perturbed);
+    status = mallopt(M_PERTURB, 0);
+    assert(status == 1);
+
+    status = mallopt(M_TRIM_THRESHOLD, 256 * 1024);
+    assert(status == 1);
+    status = mallopt(M_TOP_PAD, 64 * 1024);
+    assert(status == 1);
+    status = mallopt(M_ARENA_MAX, 1);
+    assert(status == 1);
+    status = mallopt(M_ARENA_MAX, 8);
+    assert(status == 1);
+
+    // Large blocks are mapped on their own, and unmapped when freed.
+    status = mallopt(M_MMAP_THRESHOLD, 128 * 1024);
+    assert(status == 1);
+    status = mallopt(M_MMAP_MAX, 65536);
+    assert(status == 1);
+    before = mallinfo2();
+    char *large = malloc(1024 * 1024);
+    assert(large != NULL);
+    during = mallinfo2();
+    assert(during.hblks == before.hblks + 1);
+    assert(during.hblkhd >= before.hblkhd + 1024 * 1024);
+    memset(large, 'B', 1024 * 1024);
+    large = realloc(large, 2 * 1024 * 1024);
+    assert(large != NULL);
+    assert(large[1024 * 1024 - 1] == 'B');
+    assert(malloc_usable_size(large) >= 2 * 1024 * 1024);
+    free(large);
+    after = mallinfo2();
+    assert(after.hblks == before.hblks);
+    assert(after.hblkhd == before.hblkhd);
+
+    unsigned char *zeroed = calloc(1024, 1024);
+    assert(zeroed != NULL);
+    for (size_t i = 0; i < 1024 * 1024; i++) {
+        assert(zeroed[i] == 0);
+    }
+    free(zeroed);
+
+    // The allocator may still map large blocks on its own then, which are
+    // resized and freed like any other block.
+    status = mallopt(M_MMAP_MAX, 0);
+    assert(status == 1);
+    large = malloc(1024 * 1024);
+    assert(large != NULL);
+    assert(mallinfo2().hblks == before.hblks);
+    assert(malloc_usable_size(large) >= 1024 * 1024);
+    memset(large, 'C', 1024 * 1024);
+    large = realloc(large, 2 * 1024 * 1024);
+    assert(large != NULL);
+    assert(large[1024 * 1024 - 1] == 'C');
+    free(large);
+    assert(mallinfo2().hblks == before.hblks);
+
+    FILE *stream = tmpfile();
+    ERROR_IF(tmpfile, stream, == NULL);
+
+    status = malloc_info(1, stream);
+    assert(status == EINVAL);
+
+    status = malloc_info(0, stream);
+    ERROR_IF(malloc_info, status, == -1);
+    rewind(stream);
+    char line[64];
+    assert(fgets(line, sizeof(line), stream) != NULL);
+    assert(strcmp(line, "<malloc version=\"1\">\n") == 0);
+    fclose(stream);
+
+    // Statistics are printed to stderr.
+    malloc_stats();
+
+    return EXIT_SUCCESS;
+}
@@ -0,0 +1,94 @@
+#include <assert.h>
+#include <mcheck.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "../test_helpers.h"
+
+int main(void) {
+    char path[] = "/tmp/mtrace-XXXXXX";
+    int fd = mkstemp(path);
+    ERROR_IF(mkstemp, fd, == -1);
+    close(fd);
+
+    // Without MALLOC_TRACE, nothing is traced.
+    mtrace();
+    muntrace();
+
+    int status = setenv("MALLOC_TRACE", path, 1);
+    ERROR_IF(setenv, status, == -1);
+    mtrace();
+
+    char *freed = malloc(32);
+    assert(freed != NULL);
+    freed = realloc(freed, 64);
+    assert(freed != NULL);
+    free(freed);
+
+    char *leaked = calloc(4, 16);
+    assert(leaked != NULL);
+
+    // The events buffered before fork() are not written again by the child,
+    // which stops tracing on its own.
+    pid_t pid = fork();
+    ERROR_IF(fork, pid, == -1);
+    if (pid == 0) {
+        muntrace();
+        _exit(EXIT_SUCCESS);
+    }
+    int wstatus;
+    pid_t waited = waitpid(pid, &wstatus, 0);
+    ERROR_IF(waitpid, waited, == -1);
+    assert(WIFEXITED(wstatus) && WEXITSTATUS(wstatus) == 0);
+
+    muntrace();
+
+    FILE *trace = fopen(path, "r");
+    ERROR_IF(fopen, trace, == NULL);
+
+    char line[256];
+    int allocs = 0, frees = 0, reallocs = 0, start = 0, end = 0;
+    while (fgets(line, sizeof(line), trace) != NULL) {
+        if (strcmp(line, "= Start\n") == 0) {
+            start++;
+            continue;
+        }
+        if (strcmp(line, "= End\n") == 0) {
+            end++;
+            continue;
+        }
+        if (line[0] == '=') {
+            continue;
+        }
+
+        // Each event starts with the address of its caller.
+        assert(strncmp(line, "@ ", 2) == 0);
+        char *event = strstr(line, "] ");
+        assert(event != NULL);
+        event += 2;
+        switch (event[0]) {
+        case '+':
+            allocs++;
+            break;
+        case '-':
+            frees++;
+            break;
+        case '<':
+        case '>':
+            reallocs++;
+            break;
+        }
+    }
+    fclose(trace);
+    unlink(path);
+
+    assert(start == 1 && end == 2);
+    assert(allocs == 2);
+    assert(frees == 1);
+    assert(reallocs == 2);
+
+    free(leaked);
+}
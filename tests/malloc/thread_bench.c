@@ -0,0 +1,178 @@
+// Scaling benchmark for malloc() and free() in multithreaded programs.
+//
+// Prints the throughput with one thread and with several threads, so that it can be compared
+// between relibc builds. Only correctness is checked, as the numbers depend on the machine. Blocks
+// are also freed by other threads than the ones that allocated them, and allocations must keep
+// working in the child after a fork() while other threads allocate.
+
+#include <assert.h>
+#include <pthread.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+#include "../test_helpers.h"
+
+#define THREADS 8
+#define DURATION_MS 200
+#define BLOCKS 64
+
+static volatile bool stop = false;
+
+struct worker {
+    pthread_t thread;
+    uint64_t ops;
+};
+
+// Blocks handed over from the producer to the consumer thread.
+static void *handover[BLOCKS];
+static pthread_mutex_t handover_lock = PTHREAD_MUTEX_INITIALIZER;
+static pthread_cond_t handover_cond = PTHREAD_COND_INITIALIZER;
+static size_t handover_len = 0;
+
+static void *malloc_routine(void *arg) {
+    struct worker *worker = arg;
+    void *blocks[BLOCKS] = {0};
+    unsigned int seed = (unsigned int)(uintptr_t)worker;
+
+    while (!stop) {
+        size_t i = rand_r(&seed) % BLOCKS;
+        free(blocks[i]);
+
+        size_t size = 1 + rand_r(&seed) % 512;
+        blocks[i] = malloc(size);
+        assert(blocks[i] != NULL);
+        memset(blocks[i], (int)i, size);
+
+        worker->ops++;
+    }
+
+    for (size_t i = 0; i < BLOCKS; i++) {
+        free(blocks[i]);
+    }
+    return NULL;
+}
+
+static void *producer_routine(void *arg) {
+    (void)arg;
+
+    for (size_t round = 0; round < 1000; round++) {
+        pthread_mutex_lock(&handover_lock);
+        while (handover_len == BLOCKS) {
+            pthread_cond_wait(&handover_cond, &handover_lock);
+        }
+        while (handover_len < BLOCKS) {
+            size_t size = 8 + (round + handover_len) % 256;
+            char *block = malloc(size);
+            assert(block != NULL);
+            memset(block, 0x5a, size);
+            handover[handover_len++] = block;
+        }
+        pthread_cond_broadcast(&handover_cond);
+        pthread_mutex_unlock(&handover_lock);
+    }
+    return NULL;
+}
+
+static void *consumer_routine(void *arg) {
+    (void)arg;
+
+    for (size_t round = 0; round < 1000; round++) {
+        pthread_mutex_lock(&handover_lock);
+        while (handover_len < BLOCKS) {
+            pthread_cond_wait(&handover_cond, &handover_lock);
+        }
+        while (handover_len > 0) {
+            char *block = handover[--handover_len];
+            assert(block[0] == 0x5a);
+            free(block);
+        }
+        pthread_cond_broadcast(&handover_cond);
+        pthread_mutex_unlock(&handover_lock);
+    }
+    return NULL;
+}
+
+static void sleep_ms(long ms) {
+    struct timespec ts = {.tv_sec = ms / 1000, .tv_nsec = (ms % 1000) * 1000000};
+    int status = nanosleep(&ts, NULL);
+    ERROR_IF(nanosleep, status, == -1);
+}
+
+static void start(struct worker *workers, size_t threads) {
+    stop = false;
+    for (size_t i = 0; i < threads; i++) {
+        workers[i].ops = 0;
+        int status = pthread_create(&workers[i].thread, NULL, malloc_routine, &workers[i]);
+        ERROR_IF2(pthread_create, status, != 0);
+    }
+}
+
+static uint64_t finish(struct worker *workers, size_t threads) {
+    stop = true;
+
+    uint64_t total = 0;
+    for (size_t i = 0; i < threads; i++) {
+        int status = pthread_join(workers[i].thread, NULL);
+        ERROR_IF2(pthread_join, status, != 0);
+        total += workers[i].ops;
+    }
+    return total;
+}
+
+// Runs the benchmark and returns the number of operations per second.
+static uint64_t run(size_t threads) {
+    struct worker workers[THREADS];
+
+    start(workers, threads);
+    sleep_ms(DURATION_MS);
+    uint64_t ops = finish(workers, threads) * 1000 / DURATION_MS;
+
+    printf("%zu thread(s) %12llu ops/s\n", threads, (unsigned long long)ops);
+    return ops;
+}
+
+int main(void) {
+    uint64_t single = run(1);
+    uint64_t multi = run(THREADS);
+    assert(single > 0 && multi > 0);
+    printf("scaling: %.2fx\n", (double)multi / (double)single);
+
+    pthread_t producer, consumer;
+    int status = pthread_create(&producer, NULL, producer_routine, NULL);
+    ERROR_IF2(pthread_create, status, != 0);
+    status = pthread_create(&consumer, NULL, consumer_routine, NULL);
+    ERROR_IF2(pthread_create, status, != 0);
+    status = pthread_join(producer, NULL);
+    ERROR_IF2(pthread_join, status, != 0);
+    status = pthread_join(consumer, NULL);
+    ERROR_IF2(pthread_join, status, != 0);
+
+    struct worker workers[THREADS];
+    start(workers, THREADS);
+    pid_t pid = fork();
+    ERROR_IF(fork, pid, == -1);
+    if (pid == 0) {
+        for (size_t i = 0; i < 10000; i++) {
+            void *block = malloc(1 + i % 1024);
+            if (block == NULL) {
+                _exit(EXIT_FAILURE);
+            }
+            free(block);
+        }
+        _exit(EXIT_SUCCESS);
+    }
+    finish(workers, THREADS);
+
+    int wstatus;
+    status = waitpid(pid, &wstatus, 0);
+    ERROR_IF(waitpid, status, == -1);
+    assert(WIFEXITED(wstatus) && WEXITSTATUS(wstatus) == EXIT_SUCCESS);
+
+    return EXIT_SUCCESS;
+}
@@ -0,0 +1,123 @@
+#include <assert.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <mqueue.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <time.h>
+#include <unistd.h>
+
+#include "../test_helpers.h"
+
+#define NAME "/relibc_test_mqueue"
+#define MSGSIZE 64
+
+static void send(mqd_t mq, const char *msg, unsigned int prio) {
+    int status = mq_send(mq, msg, strlen(msg) + 1, prio);
+    ERROR_IF(mq_send, status, == -1);
+}
+
+static void receive(mqd_t mq, const char *expected, unsigned int expected_prio) {
+    char buf[MSGSIZE];
+    unsigned int prio;
+
+    ssize_t len = mq_receive(mq, buf, sizeof(buf), &prio);
+    ERROR_IF(mq_receive, len, == -1);
+    assert((size_t)len == strlen(expected) + 1);
+    assert(strcmp(buf, expected) == 0);
+    assert(prio == expected_prio);
+}
+
+int main(void) {
+    // Leftovers from an earlier, failed run.
+    mq_unlink(NAME);
+
+    struct mq_attr attr = {.mq_maxmsg = 4, .mq_msgsize = MSGSIZE};
+    mqd_t mq = mq_open(NAME, O_RDWR | O_CREAT | O_EXCL, 0600, &attr);
+    ERROR_IF(mq_open, mq, == (mqd_t)-1);
+
+    mqd_t again = mq_open(NAME, O_RDWR | O_CREAT | O_EXCL, 0600, &attr);
+    assert(again == (mqd_t)-1);
+    assert(errno == EEXIST);
+
+    mqd_t invalid = mq_open("/relibc/test/mqueue", O_RDWR | O_CREAT, 0600, NULL);
+    assert(invalid == (mqd_t)-1);
+
+    // Messages are received highest priority first, in FIFO order within a priority.
+    send(mq, "low", 1);
+    send(mq, "high", 10);
+    send(mq, "medium 1", 5);
+    send(mq, "medium 2", 5);
+
+    struct mq_attr current;
+    int status = mq_getattr(mq, &current);
+    ERROR_IF(mq_getattr, status, == -1);
+    assert(current.mq_maxmsg == 4);
+    assert(current.mq_msgsize == MSGSIZE);
+    assert(current.mq_curmsgs == 4);
+    assert(current.mq_flags == 0);
+
+    // The queue is full.
+    struct timespec abstime;
+    status = clock_gettime(CLOCK_REALTIME, &abstime);
+    ERROR_IF(clock_gettime, status, == -1);
+    abstime.tv_nsec += 10000000;
+    if (abstime.tv_nsec >= 1000000000) {
+        abstime.tv_sec += 1;
+        abstime.tv_nsec -= 1000000000;
+    }
+    status = mq_timedsend(mq, "full", 5, 0, &abstime);
+    assert(status == -1);
+    assert(errno == ETIMEDOUT);
+
+    status = mq_send(mq, "big", MSGSIZE + 1, 0);
+    assert(status == -1);
+    assert(errno == EMSGSIZE);
+
+    receive(mq, "high", 10);
+    receive(mq, "medium 1", 5);
+    receive(mq, "medium 2", 5);
+    receive(mq, "low", 1);
+
+    // Receiving buffers must be able to hold the largest message.
+    char small[MSGSIZE - 1];
+    ssize_t len = mq_receive(mq, small, sizeof(small), NULL);
+    assert(len == -1);
+    assert(errno == EMSGSIZE);
+
+    struct mq_attr nonblock = {.mq_flags = O_NONBLOCK};
+    struct mq_attr old;
+    status = mq_setattr(mq, &nonblock, &old);
+    ERROR_IF(mq_setattr, status, == -1);
+    assert(old.mq_flags == 0);
+    assert(old.mq_curmsgs == 0);
+
+    char buf[MSGSIZE];
+    len = mq_receive(mq, buf, sizeof(buf), NULL);
+    assert(len == -1);
+    assert(errno == EAGAIN);
+
+    // A second descriptor refers to the same queue.
+    mqd_t reader = mq_open(NAME, O_RDONLY);
+    ERROR_IF(mq_open, reader, == (mqd_t)-1);
+    send(mq, "shared", 3);
+    receive(reader, "shared", 3);
+
+    status = mq_send(reader, "read only", 10, 0);
+    assert(status == -1);
+    assert(errno == EBADF);
+
+    status = mq_close(reader);
+    ERROR_IF(mq_close, status, == -1);
+    status = mq_close(mq);
+    ERROR_IF(mq_close, status, == -1);
+
+    status = mq_unlink(NAME);
+    ERROR_IF(mq_unlink, status, == -1);
+    status = mq_unlink(NAME);
+    assert(status == -1);
+    assert(errno == ENOENT);
+
+    return EXIT_SUCCESS;
+}
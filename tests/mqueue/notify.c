@@ -0,0 +1,97 @@
+#include <assert.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <mqueue.h>
+#include <semaphore.h>
+#include <signal.h>
+#include <stdlib.h>
+#include <string.h>
+#include <time.h>
+#include <unistd.h>
+
+#include "../test_helpers.h"
+
+#define NAME "/relibc_test_mqueue_notify"
+
+static sem_t notified;
+static volatile int notified_value;
+
+static void notify_function(union sigval value) {
+    notified_value = value.sival_int;
+    int status = sem_post(&notified);
+    ERROR_IF(sem_post, status, == -1);
+}
+
+static int wait_notified(void) {
+    struct timespec deadline;
+    int status = clock_gettime(CLOCK_REALTIME, &deadline);
+    ERROR_IF(clock_gettime, status, == -1);
+    deadline.tv_sec += 5;
+    return sem_timedwait(&notified, &deadline);
+}
+
+static void register_thread(mqd_t mq, int value) {
+    struct sigevent event = {0};
+    event.sigev_notify = SIGEV_THREAD;
+    event.sigev_notify_function = notify_function;
+    event.sigev_value.sival_int = value;
+    int status = mq_notify(mq, &event);
+    ERROR_IF(mq_notify, status, == -1);
+}
+
+int main(void) {
+    int status = sem_init(&notified, 0, 0);
+    ERROR_IF(sem_init, status, == -1);
+
+    // Leftovers from an earlier, failed run.
+    mq_unlink(NAME);
+
+    struct mq_attr attr = {.mq_maxmsg = 4, .mq_msgsize = 16};
+    mqd_t mq = mq_open(NAME, O_RDWR | O_CREAT | O_EXCL, 0600, &attr);
+    ERROR_IF(mq_open, mq, == (mqd_t)-1);
+
+    // A message arriving in the empty queue calls the function from a new thread.
+    register_thread(mq, 42);
+
+    struct sigevent event = {0};
+    event.sigev_notify = SIGEV_NONE;
+    status = mq_notify(mq, &event);
+    assert(status == -1);
+    assert(errno == EBUSY);
+
+    status = mq_send(mq, "hello", 6, 0);
+    ERROR_IF(mq_send, status, == -1);
+    status = wait_notified();
+    ERROR_IF(sem_timedwait, status, == -1);
+    assert(notified_value == 42);
+
+    // The registration was removed by the notification.
+    char buf[16];
+    ssize_t len = mq_receive(mq, buf, sizeof(buf), NULL);
+    ERROR_IF(mq_receive, len, == -1);
+    status = mq_send(mq, "again", 6, 0);
+    ERROR_IF(mq_send, status, == -1);
+    len = mq_receive(mq, buf, sizeof(buf), NULL);
+    ERROR_IF(mq_receive, len, == -1);
+
+    // Nothing is called once the registration is removed.
+    register_thread(mq, 1);
+    status = mq_notify(mq, NULL);
+    ERROR_IF(mq_notify, status, == -1);
+    register_thread(mq, 2);
+    status = mq_send(mq, "third", 6, 0);
+    ERROR_IF(mq_send, status, == -1);
+    status = wait_notified();
+    ERROR_IF(sem_timedwait, status, == -1);
+    assert(notified_value == 2);
+    usleep(100000);
+    status = sem_trywait(&notified);
+    assert(status == -1);
+
+    status = mq_close(mq);
+    ERROR_IF(mq_close, status, == -1);
+    status = mq_unlink(NAME);
+    ERROR_IF(mq_unlink, status, == -1);
+
+    return EXIT_SUCCESS;
+}
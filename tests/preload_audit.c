@@ -0,0 +1,51 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "test_helpers.h"
+
+// Defined by libinterposed.so, which this program is linked against, and by
+// libpreload.so.
+const char *interposed_name(void);
+
+// Runs this program again, with LD_PRELOAD set to libpreload.so if `preload`
+// is set and LD_AUDIT set to libaudit.so if `audit` is set.
+static void run(const char *self, int preload, int audit) {
+    char dir[4096];
+    const char *slash = strrchr(self, '/');
+    if (slash) {
+        snprintf(dir, sizeof(dir), "%.*s", (int)(slash - self), self);
+    } else {
+        snprintf(dir, sizeof(dir), ".");
+    }
+
+    char preload_var[4200] = "LD_PRELOAD";
+    char audit_var[4200] = "LD_AUDIT";
+    if (preload) {
+        snprintf(preload_var, sizeof(preload_var), "LD_PRELOAD=%s/libpreload.so", dir);
+    }
+    if (audit) {
+        snprintf(audit_var, sizeof(audit_var), "LD_AUDIT=%s/libaudit.so", dir);
+    }
+    char *env[] = {preload_var, audit_var, NULL};
+    run_self_with_env(self, "child", env);
+}
+
+int main(int argc, char **argv) {
+    if (argc > 1) {
+        printf("%s%s: interposed_name() = %s\n",
+               getenv("LD_PRELOAD") ? "LD_PRELOAD" : "no LD_PRELOAD",
+               getenv("LD_AUDIT") ? " and LD_AUDIT" : "",
+               interposed_name());
+        return EXIT_SUCCESS;
+    }
+
+    run(argv[0], 0, 0);
+    // The preloaded definition takes precedence over the one of the library
+    // the program depends on.
+    run(argv[0], 1, 0);
+    // The auditing library observes the objects and the binding.
+    run(argv[0], 1, 1);
+
+    return EXIT_SUCCESS;
+}
@@ -0,0 +1,150 @@
+#include <assert.h>
+#include <pthread.h>
+#include <stdbool.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <time.h>
+#include <unistd.h>
+
+#include "../test_helpers.h"
+
+static int pipe_fds[2];
+static volatile bool cleanup_ran = false;
+
+static pthread_mutex_t mutex = PTHREAD_MUTEX_INITIALIZER;
+static pthread_cond_t cond = PTHREAD_COND_INITIALIZER;
+static volatile bool waiting = false;
+
+void mark_cleanup(void *arg) {
+    (void)arg;
+    cleanup_ran = true;
+}
+
+void unlock_mutex(void *arg) {
+    // The mutex has been reacquired before cleanup handlers run.
+    int status = pthread_mutex_unlock(arg);
+    assert(status == 0);
+    cleanup_ran = true;
+}
+
+void *blocked_in_read(void *arg) {
+    (void)arg;
+    char c;
+
+    pthread_cleanup_push(mark_cleanup, NULL);
+    // Never returns, nothing is ever written to the pipe.
+    read(pipe_fds[0], &c, 1);
+    pthread_cleanup_pop(false);
+
+    return NULL;
+}
+
+void *blocked_in_clock_nanosleep(void *arg) {
+    (void)arg;
+    struct timespec duration = {.tv_sec = 1000};
+
+    pthread_cleanup_push(mark_cleanup, NULL);
+    clock_nanosleep(CLOCK_MONOTONIC, 0, &duration, NULL);
+    pthread_cleanup_pop(false);
+
+    return NULL;
+}
+
+void *blocked_in_cond_wait(void *arg) {
+    (void)arg;
+
+    pthread_mutex_lock(&mutex);
+    pthread_cleanup_push(unlock_mutex, &mutex);
+    waiting = true;
+    while (true) {
+        pthread_cond_wait(&cond, &mutex);
+    }
+    pthread_cleanup_pop(true);
+
+    return NULL;
+}
+
+void *deferred_spin(void *arg) {
+    (void)arg;
+
+    int status = pthread_setcancelstate(PTHREAD_CANCEL_DISABLE, NULL);
+    assert(status == 0);
+    // Let the main thread queue the request while cancellation is disabled.
+    sleep(1);
+    status = pthread_setcancelstate(PTHREAD_CANCEL_ENABLE, NULL);
+    assert(status == 0);
+
+    // Still deferred, so this must be reached.
+    pthread_cleanup_push(mark_cleanup, NULL);
+    pthread_testcancel();
+    pthread_cleanup_pop(false);
+
+    return NULL;
+}
+
+void *async_spin(void *arg) {
+    (void)arg;
+    int old_type;
+
+    int status = pthread_setcanceltype(PTHREAD_CANCEL_ASYNCHRONOUS, &old_type);
+    assert(status == 0);
+    assert(old_type == PTHREAD_CANCEL_DEFERRED);
+
+    while (true) {
+        // No cancellation points here.
+    }
+
+    return NULL;
+}
+
+static void cancel_and_join(void *(*routine)(void *), bool expect_cleanup) {
+    pthread_t thread;
+    void *retval;
+
+    cleanup_ran = false;
+
+    int status = pthread_create(&thread, NULL, routine, NULL);
+    ERROR_IF2(pthread_create, status, != 0);
+
+    usleep(100000);
+
+    status = pthread_cancel(thread);
+    ERROR_IF2(pthread_cancel, status, != 0);
+
+    status = pthread_join(thread, &retval);
+    ERROR_IF2(pthread_join, status, != 0);
+
+    assert(retval == PTHREAD_CANCELED);
+    assert(cleanup_ran == expect_cleanup);
+}
+
+int main(void) {
+    int status = pipe(pipe_fds);
+    ERROR_IF(pipe, status, == -1);
+
+    cancel_and_join(blocked_in_read, true);
+    cancel_and_join(blocked_in_clock_nanosleep, true);
+    cancel_and_join(blocked_in_cond_wait, true);
+    assert(waiting);
+    // The cleanup handler released the mutex again.
+    status = pthread_mutex_trylock(&mutex);
+    ERROR_IF2(pthread_mutex_trylock, status, != 0);
+    pthread_mutex_unlock(&mutex);
+
+    cancel_and_join(deferred_spin, true);
+    cancel_and_join(async_spin, false);
+
+    int old_type;
+    status = pthread_setcanceltype(PTHREAD_CANCEL_DEFERRED, &old_type);
+    ERROR_IF2(pthread_setcanceltype, status, != 0);
+    assert(old_type == PTHREAD_CANCEL_DEFERRED);
+
+    status = pthread_setcanceltype(42, &old_type);
+    assert(status != 0);
+
+    close(pipe_fds[0]);
+    close(pipe_fds[1]);
+
+    return EXIT_SUCCESS;
+}
@@ -0,0 +1,189 @@
+// Contention benchmark for mutexes and read-write locks.
+//
+// Prints the throughput of each scenario, so that it can be compared between relibc builds, e.g.
+// before and after changes to the locking code. Absolute numbers depend on the machine, so only
+// the runs of the same scenario are compared with each other.
+
+#define _GNU_SOURCE
+#include <assert.h>
+#include <pthread.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <time.h>
+
+#include "../test_helpers.h"
+
+#define THREADS 8
+#define DURATION_MS 200
+
+static volatile bool stop = false;
+
+static pthread_mutex_t mutex = PTHREAD_MUTEX_INITIALIZER;
+static pthread_rwlock_t rwlock;
+
+static uint64_t counter = 0;
+
+struct worker {
+    pthread_t thread;
+    bool writer;
+    uint64_t ops;
+};
+
+static void short_critical_section(void) {
+    // Keep the lock held for a short, but nonzero, amount of time.
+    for (volatile int i = 0; i < 20; i++) {
+    }
+}
+
+static void *mutex_routine(void *arg) {
+    struct worker *worker = arg;
+
+    while (!stop) {
+        int status = pthread_mutex_lock(&mutex);
+        ERROR_IF2(pthread_mutex_lock, status, != 0);
+        counter++;
+        short_critical_section();
+        status = pthread_mutex_unlock(&mutex);
+        ERROR_IF2(pthread_mutex_unlock, status, != 0);
+
+        worker->ops++;
+    }
+
+    return NULL;
+}
+
+static void *rwlock_routine(void *arg) {
+    struct worker *worker = arg;
+
+    while (!stop) {
+        int status;
+        if (worker->writer) {
+            status = pthread_rwlock_wrlock(&rwlock);
+            ERROR_IF2(pthread_rwlock_wrlock, status, != 0);
+            counter++;
+        } else {
+            status = pthread_rwlock_rdlock(&rwlock);
+            ERROR_IF2(pthread_rwlock_rdlock, status, != 0);
+        }
+        short_critical_section();
+        status = pthread_rwlock_unlock(&rwlock);
+        ERROR_IF2(pthread_rwlock_unlock, status, != 0);
+
+        worker->ops++;
+    }
+
+    return NULL;
+}
+
+static void sleep_ms(long ms) {
+    struct timespec ts = {.tv_sec = ms / 1000, .tv_nsec = (ms % 1000) * 1000000};
+    int status = nanosleep(&ts, NULL);
+    ERROR_IF(nanosleep, status, == -1);
+}
+
+// Runs the scenario and returns the total number of operations performed by writers, or by all
+// threads if `writers` is 0. If `all_ops` is not NULL, it receives the number of operations
+// performed by all threads.
+static uint64_t run(const char *name, void *(*routine)(void *), size_t writers, uint64_t *all_ops) {
+    struct worker workers[THREADS] = {0};
+
+    stop = false;
+    counter = 0;
+
+    for (size_t i = 0; i < THREADS; i++) {
+        workers[i].writer = i < writers;
+        int status = pthread_create(&workers[i].thread, NULL, routine, &workers[i]);
+        ERROR_IF2(pthread_create, status, != 0);
+    }
+
+    sleep_ms(DURATION_MS);
+    stop = true;
+
+    uint64_t total = 0;
+    uint64_t writer_ops = 0;
+    for (size_t i = 0; i < THREADS; i++) {
+        int status = pthread_join(workers[i].thread, NULL);
+        ERROR_IF2(pthread_join, status, != 0);
+
+        total += workers[i].ops;
+        if (workers[i].writer) {
+            writer_ops += workers[i].ops;
+        }
+    }
+
+    printf(
+        "%-24s %10llu ops/s (writers: %llu ops/s)\n",
+        name,
+        (unsigned long long)(total * 1000 / DURATION_MS),
+        (unsigned long long)(writer_ops * 1000 / DURATION_MS)
+    );
+
+    if (all_ops) {
+        *all_ops = total;
+    }
+    return writers ? writer_ops : total;
+}
+
+static void init_rwlock(int kind) {
+    pthread_rwlockattr_t attr;
+
+    int status = pthread_rwlockattr_init(&attr);
+    ERROR_IF2(pthread_rwlockattr_init, status, != 0);
+    status = pthread_rwlockattr_setkind_np(&attr, kind);
+    ERROR_IF2(pthread_rwlockattr_setkind_np, status, != 0);
+
+    int out;
+    status = pthread_rwlockattr_getkind_np(&attr, &out);
+    ERROR_IF2(pthread_rwlockattr_getkind_np, status, != 0);
+    assert(out == kind);
+
+    status = pthread_rwlock_init(&rwlock, &attr);
+    ERROR_IF2(pthread_rwlock_init, status, != 0);
+    status = pthread_rwlockattr_destroy(&attr);
+    ERROR_IF2(pthread_rwlockattr_destroy, status, != 0);
+}
+
+int main(void) {
+    uint64_t ops = run("mutex", mutex_routine, 0, NULL);
+    assert(ops > 0);
+    assert(counter == ops);
+
+    uint64_t reader_pref_total;
+    init_rwlock(PTHREAD_RWLOCK_PREFER_READER_NP);
+    uint64_t reader_pref_writer_ops =
+        run("rwlock prefer reader", rwlock_routine, 1, &reader_pref_total);
+    assert(counter == reader_pref_writer_ops);
+    pthread_rwlock_destroy(&rwlock);
+
+    uint64_t writer_pref_total;
+    init_rwlock(PTHREAD_RWLOCK_PREFER_WRITER_NP);
+    uint64_t writer_ops = run("rwlock prefer writer", rwlock_routine, 1, &writer_pref_total);
+    // The single writer must not be starved by the readers.
+    assert(writer_ops > 0);
+    assert(counter == writer_ops);
+    pthread_rwlock_destroy(&rwlock);
+
+    // Preferring the writer must not give it a smaller share of the operations than preferring
+    // the readers, allowing for some noise between the runs.
+    double reader_pref_share = (double)reader_pref_writer_ops / (double)reader_pref_total;
+    double writer_pref_share = (double)writer_ops / (double)writer_pref_total;
+    printf(
+        "writer share: %.4f preferring readers, %.4f preferring the writer\n",
+        reader_pref_share,
+        writer_pref_share
+    );
+    if (writer_pref_share < reader_pref_share * 0.75) {
+        puts("FAILURE: preferring the writer gave it a smaller share of the lock");
+        exit(EXIT_FAILURE);
+    }
+
+    pthread_rwlockattr_t attr;
+    int status = pthread_rwlockattr_init(&attr);
+    ERROR_IF2(pthread_rwlockattr_init, status, != 0);
+    status = pthread_rwlockattr_setkind_np(&attr, 42);
+    assert(status != 0);
+
+    return EXIT_SUCCESS;
+}
@@ -0,0 +1,128 @@
+#include <assert.h>
+#include <errno.h>
+#include <pthread.h>
+#include <sched.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "../test_helpers.h"
+
+void *routine(void *arg) {
+    (void)arg;
+
+    int policy;
+    struct sched_param param;
+
+    int status = pthread_getschedparam(pthread_self(), &policy, &param);
+    ERROR_IF2(pthread_getschedparam, status, != 0);
+    assert(policy == SCHED_OTHER);
+    assert(param.sched_priority == 0);
+
+    return NULL;
+}
+
+int main(void) {
+    int status;
+
+    int fifo_min = sched_get_priority_min(SCHED_FIFO);
+    ERROR_IF(sched_get_priority_min, fifo_min, == -1);
+    int fifo_max = sched_get_priority_max(SCHED_FIFO);
+    ERROR_IF(sched_get_priority_max, fifo_max, == -1);
+    assert(fifo_min < fifo_max);
+
+    int rr_min = sched_get_priority_min(SCHED_RR);
+    ERROR_IF(sched_get_priority_min, rr_min, == -1);
+    int rr_max = sched_get_priority_max(SCHED_RR);
+    ERROR_IF(sched_get_priority_max, rr_max, == -1);
+    assert(rr_min < rr_max);
+
+    int other_min = sched_get_priority_min(SCHED_OTHER);
+    ERROR_IF(sched_get_priority_min, other_min, == -1);
+    int other_max = sched_get_priority_max(SCHED_OTHER);
+    ERROR_IF(sched_get_priority_max, other_max, == -1);
+    assert(other_min <= other_max);
+
+    status = sched_get_priority_max(-1);
+    UNEXP_IF(sched_get_priority_max, status, != -1);
+    assert(errno == EINVAL);
+
+    int policy = sched_getscheduler(0);
+    ERROR_IF(sched_getscheduler, policy, == -1);
+    printf("process policy: %d\n", policy);
+
+    struct sched_param param;
+    status = sched_getparam(0, &param);
+    ERROR_IF(sched_getparam, status, == -1);
+    printf("process priority: %d\n", param.sched_priority);
+
+    // Setting the default policy must always be permitted.
+    param.sched_priority = 0;
+    status = sched_setscheduler(0, SCHED_OTHER, &param);
+    ERROR_IF(sched_setscheduler, status, == -1);
+    assert(sched_getscheduler(0) == SCHED_OTHER);
+
+    param.sched_priority = other_max + 1;
+    status = sched_setscheduler(0, SCHED_OTHER, &param);
+    UNEXP_IF(sched_setscheduler, status, != -1);
+    assert(errno == EINVAL);
+
+    // Explicit scheduling is applied when the thread starts.
+    pthread_attr_t attr;
+    status = pthread_attr_init(&attr);
+    ERROR_IF2(pthread_attr_init, status, != 0);
+    status = pthread_attr_setinheritsched(&attr, PTHREAD_EXPLICIT_SCHED);
+    ERROR_IF2(pthread_attr_setinheritsched, status, != 0);
+    status = pthread_attr_setschedpolicy(&attr, SCHED_OTHER);
+    ERROR_IF2(pthread_attr_setschedpolicy, status, != 0);
+    param.sched_priority = 0;
+    status = pthread_attr_setschedparam(&attr, &param);
+    ERROR_IF2(pthread_attr_setschedparam, status, != 0);
+
+    pthread_t thread;
+    status = pthread_create(&thread, &attr, routine, NULL);
+    ERROR_IF2(pthread_create, status, != 0);
+    status = pthread_join(thread, NULL);
+    ERROR_IF2(pthread_join, status, != 0);
+
+    // Out of range priorities are rejected before the thread is created.
+    param.sched_priority = other_max + 1;
+    status = pthread_attr_setschedparam(&attr, &param);
+    ERROR_IF2(pthread_attr_setschedparam, status, != 0);
+    status = pthread_create(&thread, &attr, routine, NULL);
+    UNEXP_IF(pthread_create, status, != EINVAL);
+
+    status = pthread_attr_destroy(&attr);
+    ERROR_IF2(pthread_attr_destroy, status, != 0);
+
+    // Changing a running thread.
+    param.sched_priority = 0;
+    status = pthread_setschedparam(pthread_self(), SCHED_OTHER, &param);
+    ERROR_IF2(pthread_setschedparam, status, != 0);
+    status = pthread_getschedparam(pthread_self(), &policy, &param);
+    ERROR_IF2(pthread_getschedparam, status, != 0);
+    assert(policy == SCHED_OTHER);
+    assert(param.sched_priority == 0);
+
+    // The policy of the calling thread is also the one reported by sched_getscheduler. Real-time
+    // policies may require privileges.
+    param.sched_priority = rr_min;
+    status = pthread_setschedparam(pthread_self(), SCHED_RR, &param);
+    if (status != EPERM) {
+        ERROR_IF2(pthread_setschedparam, status, != 0);
+        assert(sched_getscheduler(0) == SCHED_RR);
+        status = sched_getparam(0, &param);
+        ERROR_IF(sched_getparam, status, == -1);
+        assert(param.sched_priority == rr_min);
+
+        param.sched_priority = 0;
+        status = sched_setscheduler(0, SCHED_OTHER, &param);
+        ERROR_IF(sched_setscheduler, status, == -1);
+        assert(sched_getscheduler(0) == SCHED_OTHER);
+    }
+
+    status = pthread_setschedparam(pthread_self(), -1, &param);
+    UNEXP_IF(pthread_setschedparam, status, != EINVAL);
+
+    return EXIT_SUCCESS;
+}
@@ -0,0 +1,75 @@
+#include <dlfcn.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "test_helpers.h"
+
+// libsearch.so is in several directories next to this program:
+// - search_a, in the DT_RPATH of libsearchrpath.so,
+// - search_b, in LD_LIBRARY_PATH for some of the runs,
+// - search_c, in the DT_RUNPATH of this program,
+// - search_$LIB/$PLATFORM, in the DT_RUNPATH of libsearchtokens.so.
+
+static const char *open_from(const char *library) {
+    if (library == NULL) {
+        void *handle = dlopen("libsearch.so", RTLD_NOW | RTLD_LOCAL);
+        if (!handle) {
+            return dlerror();
+        }
+        const char *(*which)(void);
+        *(void **)(&which) = dlsym(handle, "search_which");
+        return which ? which() : dlerror();
+    }
+
+    void *handle = dlopen(library, RTLD_NOW | RTLD_LOCAL);
+    if (!handle) {
+        return dlerror();
+    }
+    const char *(*search_open)(void);
+    *(void **)(&search_open) = dlsym(handle, "search_open");
+    return search_open ? search_open() : dlerror();
+}
+
+// Runs this program again to open libsearch.so from `library`, with
+// LD_LIBRARY_PATH set to search_b if `library_path` is set.
+static void run(const char *self, const char *library, int library_path) {
+    char path[4200] = "LD_LIBRARY_PATH";
+    if (library_path) {
+        const char *slash = strrchr(self, '/');
+        if (slash) {
+            snprintf(path, sizeof(path), "LD_LIBRARY_PATH=%.*s/search_b", (int)(slash - self), self);
+        } else {
+            snprintf(path, sizeof(path), "LD_LIBRARY_PATH=search_b");
+        }
+    }
+    char *env[] = {path, NULL};
+    run_self_with_env(self, library ? library : "program", env);
+}
+
+int main(int argc, char **argv) {
+    if (argc > 1) {
+        const char *library = strcmp(argv[1], "program") == 0 ? NULL : argv[1];
+        printf(
+            "%s%s: %s\n",
+            argv[1],
+            getenv("LD_LIBRARY_PATH") ? " with LD_LIBRARY_PATH" : "",
+            open_from(library)
+        );
+        return EXIT_SUCCESS;
+    }
+
+    // DT_RUNPATH comes after LD_LIBRARY_PATH.
+    run(argv[0], NULL, 0);
+    run(argv[0], NULL, 1);
+
+    // DT_RPATH comes before LD_LIBRARY_PATH, and dlopen uses the search paths
+    // of the calling library rather than those of the program.
+    run(argv[0], "libsearchrpath.so", 0);
+    run(argv[0], "libsearchrpath.so", 1);
+
+    // $ORIGIN, $LIB and $PLATFORM are expanded.
+    run(argv[0], "libsearchtokens.so", 0);
+
+    return EXIT_SUCCESS;
+}
@@ -0,0 +1,138 @@
+#include <assert.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <limits.h>
+#include <semaphore.h>
+#include <stdlib.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "../test_helpers.h"
+
+#define NAME "/relibc_test_semaphore"
+#define RACE "/relibc_test_semaphore_race"
+
+int main(void) {
+    int status;
+    int value;
+
+    // Leftovers from an earlier, failed run.
+    sem_unlink(NAME);
+
+    sem_t *sem = sem_open(NAME, O_CREAT | O_EXCL, 0600, 1);
+    ERROR_IF(sem_open, sem, == SEM_FAILED);
+
+    sem_t *again = sem_open(NAME, O_CREAT | O_EXCL, 0600, 1);
+    assert(again == SEM_FAILED);
+    assert(errno == EEXIST);
+
+    sem_t *invalid = sem_open("/relibc/test/semaphore", O_CREAT, 0600, 0);
+    assert(invalid == SEM_FAILED);
+    assert(errno == EINVAL);
+
+    sem_t *too_large =
+        sem_open("/relibc_test_semaphore_max", O_CREAT, 0600, (unsigned)SEM_VALUE_MAX + 1);
+    assert(too_large == SEM_FAILED);
+    assert(errno == EINVAL);
+
+    // Opening the semaphore again in the same process returns the same address.
+    again = sem_open(NAME, 0);
+    ERROR_IF(sem_open, again, == SEM_FAILED);
+    assert(again == sem);
+
+    // The initial value is ignored when the semaphore already exists.
+    again = sem_open(NAME, O_CREAT, 0600, 5);
+    ERROR_IF(sem_open, again, == SEM_FAILED);
+    assert(again == sem);
+
+    status = sem_getvalue(sem, &value);
+    ERROR_IF(sem_getvalue, status, == -1);
+    assert(value == 1);
+
+    status = sem_close(again);
+    ERROR_IF(sem_close, status, == -1);
+    status = sem_close(again);
+    ERROR_IF(sem_close, status, == -1);
+
+    status = sem_wait(sem);
+    ERROR_IF(sem_wait, status, == -1);
+
+    // The semaphore is shared with the child, which opens it by name.
+    pid_t pid = fork();
+    ERROR_IF(fork, pid, == -1);
+    if (pid == 0) {
+        sem_t *child = sem_open(NAME, 0);
+        if (child == SEM_FAILED) {
+            _exit(EXIT_FAILURE);
+        }
+        if (sem_post(child) == -1) {
+            _exit(EXIT_FAILURE);
+        }
+        _exit(sem_close(child) == -1 ? EXIT_FAILURE : EXIT_SUCCESS);
+    }
+
+    status = sem_wait(sem);
+    ERROR_IF(sem_wait, status, == -1);
+
+    int wstatus;
+    status = waitpid(pid, &wstatus, 0);
+    ERROR_IF(waitpid, status, == -1);
+    assert(WIFEXITED(wstatus) && WEXITSTATUS(wstatus) == EXIT_SUCCESS);
+
+    status = sem_getvalue(sem, &value);
+    ERROR_IF(sem_getvalue, status, == -1);
+    assert(value == 0);
+
+    // The semaphore remains usable after its name is removed.
+    status = sem_unlink(NAME);
+    ERROR_IF(sem_unlink, status, == -1);
+
+    again = sem_open(NAME, 0);
+    assert(again == SEM_FAILED);
+    assert(errno == ENOENT);
+
+    status = sem_unlink(NAME);
+    assert(status == -1);
+    assert(errno == ENOENT);
+
+    status = sem_post(sem);
+    ERROR_IF(sem_post, status, == -1);
+    status = sem_trywait(sem);
+    ERROR_IF(sem_trywait, status, == -1);
+
+    status = sem_close(sem);
+    ERROR_IF(sem_close, status, == -1);
+
+    // Processes racing to create the same semaphore all end up with the one that was
+    // initialized first.
+    sem_unlink(RACE);
+    enum { RACERS = 8 };
+    pid_t racers[RACERS];
+    for (int i = 0; i < RACERS; i++) {
+        racers[i] = fork();
+        ERROR_IF(fork, racers[i], == -1);
+        if (racers[i] == 0) {
+            sem_t *race = sem_open(RACE, O_CREAT, 0600, 0);
+            if (race == SEM_FAILED) {
+                _exit(EXIT_FAILURE);
+            }
+            _exit(sem_post(race) == -1 ? EXIT_FAILURE : EXIT_SUCCESS);
+        }
+    }
+    for (int i = 0; i < RACERS; i++) {
+        status = waitpid(racers[i], &wstatus, 0);
+        ERROR_IF(waitpid, status, == -1);
+        assert(WIFEXITED(wstatus) && WEXITSTATUS(wstatus) == EXIT_SUCCESS);
+    }
+
+    sem_t *race = sem_open(RACE, 0);
+    ERROR_IF(sem_open, race, == SEM_FAILED);
+    status = sem_getvalue(race, &value);
+    ERROR_IF(sem_getvalue, status, == -1);
+    assert(value == RACERS);
+
+    status = sem_close(race);
+    ERROR_IF(sem_close, status, == -1);
+    status = sem_unlink(RACE);
+    ERROR_IF(sem_unlink, status, == -1);
+}
@@ -0,0 +1,108 @@
+#include <errno.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/ipc.h>
+#include <sys/msg.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "test_helpers.h"
+
+struct message {
+    long mtype;
+    char mtext[32];
+};
+
+static void send(int msqid, long mtype, const char *text) {
+    struct message msg = {.mtype = mtype};
+    strcpy(msg.mtext, text);
+    int status = msgsnd(msqid, &msg, strlen(text) + 1, 0);
+    ERROR_IF(msgsnd, status, == -1);
+}
+
+static void receive(int msqid, long msgtyp) {
+    struct message msg;
+    ssize_t len = msgrcv(msqid, &msg, sizeof(msg.mtext), msgtyp, 0);
+    ERROR_IF(msgrcv, len, == -1);
+    printf("msgrcv(%ld): type %ld, %zd bytes, \"%s\"\n", msgtyp, msg.mtype, len, msg.mtext);
+}
+
+int main(void) {
+    int status;
+    int msqid = msgget(IPC_PRIVATE, IPC_CREAT | 0600);
+    ERROR_IF(msgget, msqid, == -1);
+
+    send(msqid, 3, "three");
+    send(msqid, 1, "one");
+    send(msqid, 2, "two");
+    send(msqid, 1, "another one");
+
+    struct msqid_ds ds;
+    status = msgctl(msqid, IPC_STAT, &ds);
+    ERROR_IF(msgctl, status, == -1);
+    printf("msg_qnum: %lu\n", (unsigned long)ds.msg_qnum);
+    UNEXP_IF(msgctl, ds.msg_lspid, != getpid());
+
+    // Specific type, lowest type up to 2, then the first remaining message.
+    receive(msqid, 2);
+    receive(msqid, -2);
+    receive(msqid, -2);
+    receive(msqid, 0);
+
+    struct message msg;
+    errno = 0;
+    ssize_t len = msgrcv(msqid, &msg, sizeof(msg.mtext), 0, IPC_NOWAIT);
+    UNEXP_IF(msgrcv, len, != -1);
+    CHECK_AND_PRINT_ERRNO(ENOMSG);
+    printf("\n");
+
+    // Messages that are too large are only truncated with MSG_NOERROR.
+    send(msqid, 7, "truncated message");
+    errno = 0;
+    len = msgrcv(msqid, &msg, 4, 0, 0);
+    UNEXP_IF(msgrcv, len, != -1);
+    CHECK_AND_PRINT_ERRNO(E2BIG);
+    printf("\n");
+    len = msgrcv(msqid, &msg, 4, 0, MSG_NOERROR);
+    ERROR_IF(msgrcv, len, == -1);
+    printf("truncated: %.*s\n", (int)len, msg.mtext);
+
+    errno = 0;
+    msg.mtype = 0;
+    status = msgsnd(msqid, &msg, 1, 0);
+    UNEXP_IF(msgsnd, status, != -1);
+    CHECK_AND_PRINT_ERRNO(EINVAL);
+    printf("\n");
+
+    // The child blocks until the parent sends a message.
+    pid_t pid = fork();
+    ERROR_IF(fork, pid, == -1);
+    if (pid == 0) {
+        len = msgrcv(msqid, &msg, sizeof(msg.mtext), 42, 0);
+        _exit(len == 6 && strcmp(msg.mtext, "hello") == 0 ? EXIT_SUCCESS : EXIT_FAILURE);
+    }
+    usleep(10000);
+    send(msqid, 42, "hello");
+
+    int wstatus;
+    status = waitpid(pid, &wstatus, 0);
+    ERROR_IF(waitpid, status, == -1);
+    UNEXP_IF(waitpid, (WIFEXITED(wstatus) && WEXITSTATUS(wstatus) == EXIT_SUCCESS), == 0);
+
+    status = msgctl(msqid, IPC_STAT, &ds);
+    ERROR_IF(msgctl, status, == -1);
+    printf("msg_qnum: %lu\n", (unsigned long)ds.msg_qnum);
+    UNEXP_IF(msgctl, ds.msg_lrpid, != pid);
+
+    status = msgctl(msqid, IPC_RMID, NULL);
+    ERROR_IF(msgctl, status, == -1);
+
+    errno = 0;
+    status = msgsnd(msqid, &msg, 1, IPC_NOWAIT);
+    UNEXP_IF(msgsnd, status, != -1);
+    CHECK_AND_PRINT_ERRNO(EINVAL);
+    printf("\n");
+
+    return EXIT_SUCCESS;
+}
@@ -0,0 +1,145 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <signal.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <sys/ipc.h>
+#include <sys/sem.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+#include "test_helpers.h"
+
+union semun {
+    int val;
+    struct semid_ds *buf;
+    unsigned short *array;
+};
+
+int main(void) {
+    int status;
+    key_t key = ftok("example_dir/1-never-gonna-give-you-up", 'S');
+    ERROR_IF(ftok, key, == -1);
+
+    int semid = semget(key, 2, IPC_CREAT | IPC_EXCL | 0600);
+    ERROR_IF(semget, semid, == -1);
+
+    errno = 0;
+    int dup = semget(key, 2, IPC_CREAT | IPC_EXCL | 0600);
+    UNEXP_IF(semget, dup, != -1);
+    CHECK_AND_PRINT_ERRNO(EEXIST);
+    printf("\n");
+
+    // Looking up the existing set returns the same identifier.
+    int same = semget(key, 0, 0);
+    ERROR_IF(semget, same, == -1);
+    UNEXP_IF(semget, same, != semid);
+
+    unsigned short init[2] = {1, 0};
+    status = semctl(semid, 0, SETALL, (union semun){.array = init});
+    ERROR_IF(semctl, status, == -1);
+
+    struct semid_ds ds;
+    status = semctl(semid, 0, IPC_STAT, (union semun){.buf = &ds});
+    ERROR_IF(semctl, status, == -1);
+    printf("sem_nsems: %d\n", (int)ds.sem_nsems);
+    printf("mode: %o\n", (unsigned)(ds.sem_perm.mode & 0777));
+
+    // Operations are applied atomically: the second one would block, so neither is applied.
+    struct sembuf nowait[2] = {
+        {.sem_num = 0, .sem_op = -1, .sem_flg = IPC_NOWAIT},
+        {.sem_num = 1, .sem_op = -1, .sem_flg = IPC_NOWAIT},
+    };
+    errno = 0;
+    status = semop(semid, nowait, 2);
+    UNEXP_IF(semop, status, != -1);
+    CHECK_AND_PRINT_ERRNO(EAGAIN);
+    printf("\n");
+    printf("semval[0]: %d\n", semctl(semid, 0, GETVAL));
+
+    struct timespec timeout = {.tv_sec = 0, .tv_nsec = 10000000};
+    struct sembuf wait_one = {.sem_num = 1, .sem_op = -1};
+    errno = 0;
+    status = semtimedop(semid, &wait_one, 1, &timeout);
+    UNEXP_IF(semtimedop, status, != -1);
+    CHECK_AND_PRINT_ERRNO(EAGAIN);
+    printf("\n");
+
+    struct sembuf bad = {.sem_num = 2, .sem_op = 1};
+    errno = 0;
+    status = semop(semid, &bad, 1);
+    UNEXP_IF(semop, status, != -1);
+    CHECK_AND_PRINT_ERRNO(EFBIG);
+    printf("\n");
+
+    // The child blocks until the parent posts semaphore 1.
+    pid_t pid = fork();
+    ERROR_IF(fork, pid, == -1);
+    if (pid == 0) {
+        status = semop(semid, &wait_one, 1);
+        if (status == -1) {
+            _exit(EXIT_FAILURE);
+        }
+        _exit(EXIT_SUCCESS);
+    }
+
+    // Wait for the child to block.
+    while (semctl(semid, 1, GETNCNT) != 1) {
+        usleep(1000);
+    }
+    printf("semncnt[1]: %d\n", semctl(semid, 1, GETNCNT));
+
+    struct sembuf post = {.sem_num = 1, .sem_op = 1};
+    status = semop(semid, &post, 1);
+    ERROR_IF(semop, status, == -1);
+
+    int wstatus;
+    status = waitpid(pid, &wstatus, 0);
+    ERROR_IF(waitpid, status, == -1);
+    UNEXP_IF(waitpid, (WIFEXITED(wstatus) && WEXITSTATUS(wstatus) == EXIT_SUCCESS), == 0);
+    printf("semval[1]: %d\n", semctl(semid, 1, GETVAL));
+    UNEXP_IF(semctl, semctl(semid, 1, GETPID), != pid);
+
+    status = semctl(semid, 0, SETVAL, (union semun){.val = 5});
+    ERROR_IF(semctl, status, == -1);
+    printf("semval[0]: %d\n", semctl(semid, 0, GETVAL));
+
+    // SEM_UNDO adjustments are reverted when the child exits, and when it is killed.
+    struct sembuf take_undo = {.sem_num = 0, .sem_op = -2, .sem_flg = SEM_UNDO};
+    for (int killed = 0; killed <= 1; killed++) {
+        pid = fork();
+        ERROR_IF(fork, pid, == -1);
+        if (pid == 0) {
+            status = semop(semid, &take_undo, 1);
+            if (status == -1 || semctl(semid, 0, GETVAL) != 3) {
+                _exit(EXIT_FAILURE);
+            }
+            if (killed) {
+                raise(SIGKILL);
+            }
+            _exit(EXIT_SUCCESS);
+        }
+
+        status = waitpid(pid, &wstatus, 0);
+        ERROR_IF(waitpid, status, == -1);
+        if (killed) {
+            UNEXP_IF(waitpid, (WIFSIGNALED(wstatus) && WTERMSIG(wstatus) == SIGKILL), == 0);
+        } else {
+            UNEXP_IF(waitpid, (WIFEXITED(wstatus) && WEXITSTATUS(wstatus) == EXIT_SUCCESS), == 0);
+        }
+        printf("semval[0] after the child %s: %d\n", killed ? "was killed" : "exited",
+               semctl(semid, 0, GETVAL));
+    }
+
+    status = semctl(semid, 0, IPC_RMID);
+    ERROR_IF(semctl, status, == -1);
+
+    errno = 0;
+    status = semctl(semid, 0, GETVAL);
+    UNEXP_IF(semctl, status, != -1);
+    CHECK_AND_PRINT_ERRNO(EINVAL);
+    printf("\n");
+
+    return EXIT_SUCCESS;
+}
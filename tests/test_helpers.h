@@ -6,6 +6,7 @@
 #include <stdio.h>
 #include <stdlib.h>
 #include <string.h>
+#include <sys/wait.h>
 #include <unistd.h>
 
 // Throws errors on a well-defined API error values.
@@ -129,4 +130,77 @@ void printf_errno(int errnoval, char *errnoname) {
         }                                   \
     } while(0);
 
+// Runs `argv[0]` with the arguments `argv` and waits for it to terminate.
+//
+// The entries of `env`, which ends with NULL, are applied to its environment
+// first: "NAME=value" sets a variable and "NAME" removes it. If `output` is not
+// NULL, what the program writes to the file descriptor `fd` is stored there,
+// up to `size - 1` bytes and null-terminated. If `pid` is not NULL, it receives
+// the process ID of the program.
+//
+// Returns the wait status.
+int run_with_env(char *const argv[], char *const env[], int fd, char *output,
+                 size_t size, pid_t *pid) {
+    int fds[2];
+    if (output) {
+        int status = pipe(fds);
+        ERROR_IF(pipe, status, == -1);
+    }
+
+    fflush(stdout);
+    pid_t child = fork();
+    ERROR_IF(fork, child, == -1);
+    if (child == 0) {
+        for (char *const *var = env; *var; var++) {
+            if (strchr(*var, '=')) {
+                putenv(*var);
+            } else {
+                unsetenv(*var);
+            }
+        }
+        if (output) {
+            close(fds[0]);
+            dup2(fds[1], fd);
+            close(fds[1]);
+        }
+        execv(argv[0], argv);
+        perror("execv");
+        _exit(127);
+    }
+
+    if (output) {
+        close(fds[1]);
+        size_t len = 0;
+        ssize_t count;
+        while (len + 1 < size &&
+               (count = read(fds[0], output + len, size - len - 1)) > 0) {
+            len += (size_t)count;
+        }
+        output[len] = '\0';
+        close(fds[0]);
+    }
+
+    int status;
+    pid_t waited = waitpid(child, &status, 0);
+    ERROR_IF(waitpid, waited, == -1);
+    if (pid) {
+        *pid = child;
+    }
+    return status;
+}
+
+// Runs this program, `self`, again with the single argument `arg` and the
+// environment changed by `env` as for run_with_env(). Fails the test unless
+// it exits successfully, and returns its process ID.
+pid_t run_self_with_env(const char *self, const char *arg, char *const env[]) {
+    char *argv[] = {(char *)self, (char *)arg, NULL};
+    pid_t pid;
+    int status = run_with_env(argv, env, -1, NULL, 0, &pid);
+    if (!WIFEXITED(status) || WEXITSTATUS(status) != 0) {
+        printf("child failed with status %d\n", status);
+        exit(EXIT_FAILURE);
+    }
+    return pid;
+}
+
 #endif /* _TEST_HELPERS */
@@ -0,0 +1,58 @@
+#include <errno.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <time.h>
+
+#include "test_helpers.h"
+
+static long elapsed_ms(const struct timespec *start) {
+    struct timespec now;
+    int status = clock_gettime(CLOCK_MONOTONIC, &now);
+    ERROR_IF(clock_gettime, status, == -1);
+    return (now.tv_sec - start->tv_sec) * 1000 + (now.tv_nsec - start->tv_nsec) / 1000000;
+}
+
+int main(void) {
+    struct timespec start;
+    int status = clock_gettime(CLOCK_MONOTONIC, &start);
+    ERROR_IF(clock_gettime, status, == -1);
+
+    // Relative sleep
+    struct timespec duration = {.tv_sec = 0, .tv_nsec = 100000000};
+    status = clock_nanosleep(CLOCK_MONOTONIC, 0, &duration, NULL);
+    ERROR_IF2(clock_nanosleep, status, != 0);
+    long elapsed = elapsed_ms(&start);
+    printf("relative: %d, slept enough: %d\n", status, elapsed >= 100);
+
+    // Absolute sleep until a deadline
+    struct timespec deadline;
+    status = clock_gettime(CLOCK_REALTIME, &deadline);
+    ERROR_IF(clock_gettime, status, == -1);
+    deadline.tv_nsec += 100000000;
+    if (deadline.tv_nsec >= 1000000000) {
+        deadline.tv_sec += 1;
+        deadline.tv_nsec -= 1000000000;
+    }
+    status = clock_gettime(CLOCK_MONOTONIC, &start);
+    ERROR_IF(clock_gettime, status, == -1);
+    status = clock_nanosleep(CLOCK_REALTIME, TIMER_ABSTIME, &deadline, NULL);
+    ERROR_IF2(clock_nanosleep, status, != 0);
+    elapsed = elapsed_ms(&start);
+    printf("absolute: %d, slept enough: %d\n", status, elapsed >= 99);
+
+    // A deadline in the past returns immediately
+    struct timespec past = {.tv_sec = 1, .tv_nsec = 0};
+    status = clock_nanosleep(CLOCK_MONOTONIC, TIMER_ABSTIME, &past, NULL);
+    printf("past deadline: %d\n", status);
+
+    // Errors are returned rather than stored in errno
+    errno = 0;
+    status = clock_nanosleep(CLOCK_THREAD_CPUTIME_ID, 0, &duration, NULL);
+    printf("thread CPU-time clock: %s, errno %d\n", status == EINVAL ? "EINVAL" : "other", errno);
+
+    struct timespec invalid = {.tv_sec = 0, .tv_nsec = 1000000000};
+    status = clock_nanosleep(CLOCK_MONOTONIC, 0, &invalid, NULL);
+    printf("invalid duration: %s\n", status == EINVAL ? "EINVAL" : "other");
+
+    return EXIT_SUCCESS;
+}
@@ -0,0 +1,167 @@
+#define _GNU_SOURCE
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <time.h>
+#include <unistd.h>
+
+#include "test_helpers.h"
+
+static const char templates[] =
+    "%m/%d/%Y %H:%M\n"
+    "%Y-%m-%d\n"
+    "%A\n"
+    "%B\n"
+    "%B %Y\n"
+    "%A %B\n"
+    "%H:%M\n";
+
+static void expect_error(const char *input, int expected) {
+    getdate_err = 0;
+    struct tm *tm = getdate(input);
+    if (tm != NULL || getdate_err != expected) {
+        printf("FAILURE: getdate(\"%s\") gave %p, getdate_err %d instead of %d\n", input,
+               (void *)tm, getdate_err, expected);
+        exit(EXIT_FAILURE);
+    }
+    printf("\"%s\": getdate_err %d\n", input, getdate_err);
+}
+
+static struct tm expect_date(const char *input) {
+    struct tm *tm = getdate(input);
+    if (tm == NULL) {
+        printf("FAILURE: getdate(\"%s\") failed with getdate_err %d\n", input, getdate_err);
+        exit(EXIT_FAILURE);
+    }
+    return *tm;
+}
+
+static int days_since_epoch(const struct tm *tm) {
+    struct tm copy = *tm;
+    copy.tm_hour = 12;
+    copy.tm_min = copy.tm_sec = 0;
+    copy.tm_isdst = -1;
+    return (int)(mktime(&copy) / (24 * 60 * 60));
+}
+
+int main(void) {
+    char path[] = "/tmp/getdate-XXXXXX";
+    int fd = mkstemp(path);
+    ERROR_IF(mkstemp, fd, == -1);
+    ssize_t written = write(fd, templates, strlen(templates));
+    ERROR_IF(write, written, == -1);
+    close(fd);
+
+    time_t now_time = time(NULL);
+    struct tm now;
+    localtime_r(&now_time, &now);
+
+    // 1: DATEMSK is undefined or empty
+    unsetenv("DATEMSK");
+    expect_error("2021-06-15", 1);
+    setenv("DATEMSK", "", 1);
+    expect_error("2021-06-15", 1);
+
+    // 2: The template file cannot be read. This cannot be observed as root,
+    // who can read any file.
+    int status = chmod(path, 0);
+    ERROR_IF(chmod, status, == -1);
+    setenv("DATEMSK", path, 1);
+    if (access(path, R_OK) == -1) {
+        getdate_err = 0;
+        UNEXP_IF(getdate, (getdate("2021-06-15") != NULL), != 0);
+        UNEXP_IF(getdate, getdate_err, != 2);
+    }
+    status = chmod(path, 0600);
+    ERROR_IF(chmod, status, == -1);
+
+    // 3: The template file does not exist
+    setenv("DATEMSK", "/nonexistent/getdate-templates", 1);
+    expect_error("2021-06-15", 3);
+
+    // 4: The template file is not a regular file
+    setenv("DATEMSK", "/tmp", 1);
+    expect_error("2021-06-15", 4);
+
+    // 5: Reading the template file fails. Reading this process' memory at
+    // address 0 gives EIO, which relies on Linux's /proc, so nothing is
+    // printed to keep the output the same elsewhere.
+#ifdef __linux__
+    setenv("DATEMSK", "/proc/self/mem", 1);
+    getdate_err = 0;
+    UNEXP_IF(getdate, (getdate("2021-06-15") != NULL), != 0);
+    UNEXP_IF(getdate, getdate_err, != 5);
+#endif
+
+    // 6 (memory allocation failure) cannot be caused.
+
+    setenv("DATEMSK", path, 1);
+
+    // 7: No template matches
+    expect_error("15.06.2021", 7);
+    expect_error("2021-06-15 trailing", 7);
+
+    // 8: Invalid date
+    expect_error("02/30/2021 10:00", 8);
+    expect_error("2021-02-29", 8);
+
+    // Full dates, with surrounding blanks
+    struct tm tm = expect_date("  12/25/2021 18:30 \n");
+    printf("%d-%02d-%02d %02d:%02d:%02d, wday %d, yday %d\n", tm.tm_year + 1900, tm.tm_mon + 1,
+           tm.tm_mday, tm.tm_hour, tm.tm_min, tm.tm_sec, tm.tm_wday, tm.tm_yday);
+    tm = expect_date("2020-02-29");
+    printf("%d-%02d-%02d, wday %d, yday %d\n", tm.tm_year + 1900, tm.tm_mon + 1, tm.tm_mday,
+           tm.tm_wday, tm.tm_yday);
+    UNEXP_IF(getdate, tm.tm_hour * 3600 + tm.tm_min * 60 + tm.tm_sec,
+             < now.tm_hour * 3600 + now.tm_min * 60 + now.tm_sec);
+
+    // The first day of a month, in a given year
+    tm = expect_date("March 2022");
+    printf("%d-%02d-%02d\n", tm.tm_year + 1900, tm.tm_mon + 1, tm.tm_mday);
+
+    // Only a weekday: today, or that day in the next week
+    tm = expect_date("Wednesday");
+    UNEXP_IF(getdate, tm.tm_wday, != 3);
+    int days = days_since_epoch(&tm) - days_since_epoch(&now);
+    UNEXP_IF(getdate, days, < 0);
+    UNEXP_IF(getdate, days, > 6);
+    printf("weekday: ok\n");
+
+    // Only a month: its first day, this year or the next one if it has passed
+    tm = expect_date("January");
+    UNEXP_IF(getdate, tm.tm_mon, != 0);
+    UNEXP_IF(getdate, tm.tm_mday, != 1);
+    UNEXP_IF(getdate, tm.tm_year, != now.tm_year + (now.tm_mon > 0));
+    printf("month: ok\n");
+
+    // A weekday and a month: the first such weekday in that month
+    tm = expect_date("Monday December");
+    UNEXP_IF(getdate, tm.tm_mon, != 11);
+    UNEXP_IF(getdate, tm.tm_wday, != 1);
+    UNEXP_IF(getdate, tm.tm_mday, > 7);
+    UNEXP_IF(getdate, tm.tm_year, != now.tm_year);
+    printf("weekday and month: ok\n");
+
+    // Only a time: today, or tomorrow if the hour has passed
+    char input[16];
+    int hour = (now.tm_hour + 23) % 24;
+    snprintf(input, sizeof(input), "%02d:15", hour);
+    tm = expect_date(input);
+    UNEXP_IF(getdate, tm.tm_hour, != hour);
+    UNEXP_IF(getdate, tm.tm_min, != 15);
+    UNEXP_IF(getdate, tm.tm_sec, != 0);
+    days = days_since_epoch(&tm) - days_since_epoch(&now);
+    UNEXP_IF(getdate, days, != (hour < now.tm_hour));
+    printf("time: ok\n");
+
+    // getdate_r returns the error instead.
+    struct tm result;
+    status = getdate_r("2021-06-15", &result);
+    UNEXP_IF(getdate_r, status, != 0);
+    printf("getdate_r: %d-%02d-%02d\n", result.tm_year + 1900, result.tm_mon + 1, result.tm_mday);
+    status = getdate_r("June 15th", &result);
+    printf("getdate_r: %d\n", status);
+
+    unlink(path);
+}
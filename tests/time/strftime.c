@@ -155,7 +155,7 @@ void test_v(void) {
 
 int main(void) {
     print(1531808742, "%a %A %b %B");
-    print(1531808742, "The %Cst century");
+    print(1531808742, "%C %y");
     print(1531808742, "%I:%M:%S %p");
     print(1531839600, "%r");
     print(1531839600, "%R");
@@ -0,0 +1,124 @@
+#include <assert.h>
+#include <locale.h>
+#include <stdio.h>
+#include <string.h>
+#include <time.h>
+#include <wchar.h>
+
+#include "test_helpers.h"
+
+static const char *formats[] = {
+    // Every conversion
+    "%a|%A|%b|%B|%h",
+    "%c",
+    "%C|%d|%D|%e|%F",
+    "%g|%G|%V",
+    "%H|%I|%j|%k|%l|%m|%M",
+    "%n|%t|%%",
+    "%p|%P|%r|%R|%S|%T",
+    "%u|%U|%w|%W",
+    "%x|%X|%y|%Y",
+    "%z|%Z",
+    // Flags and widths
+    "%_d|%-d|%0e|%-e|%_H|%-j|%_j",
+    "%10Y|%_10Y|%-10Y|%3y|%_3y|%1j",
+    "%10A|%-10A|%010A|%^A|%#A|%^#a",
+    "%#p|%^p|%^P|%#Z|%^10B",
+    "%10D|%010D|%^c|%12T",
+    "%10z|%_z|%-z|%06z",
+    // Modifiers, which the "C" locale ignores
+    "%Ec|%EC|%Ex|%EX|%Ey|%EY",
+    "%Od|%Oe|%OH|%OI|%Om|%OM|%OS|%Ou|%OU|%OV|%Ow|%OW|%Oy",
+    "%Ob|%OB|%Oh",
+    // Invalid conversions are copied
+    "%Q|%Ea|%OA|%Ed|%5Ed|%-E",
+    "trailing %",
+    "%5",
+    NULL,
+};
+
+static void print_all(const struct tm *tm) {
+    char buf[128];
+    for (int i = 0; formats[i] != NULL; i++) {
+        size_t n = strftime(buf, sizeof(buf), formats[i], tm);
+        printf("\"%s\": %zu \"%s\"\n", formats[i], n, buf);
+    }
+}
+
+int main(void) {
+    struct tm tm = {
+        .tm_year = 2020 - 1900,
+        .tm_mon = 11,
+        .tm_mday = 5,
+        .tm_hour = 3,
+        .tm_min = 4,
+        .tm_sec = 9,
+        .tm_wday = 6,
+        .tm_yday = 339,
+        .tm_isdst = 0,
+        .tm_gmtoff = -16200,
+        .tm_zone = "NST",
+    };
+    print_all(&tm);
+
+    // Afternoon, on the first day of a year that starts in the last ISO
+    // week of the previous one
+    tm = (struct tm) {
+        .tm_year = 2021 - 1900,
+        .tm_mon = 0,
+        .tm_mday = 1,
+        .tm_hour = 12,
+        .tm_min = 30,
+        .tm_wday = 5,
+        .tm_yday = 0,
+        .tm_isdst = 1,
+        .tm_gmtoff = 19800,
+        .tm_zone = "IST",
+    };
+    print_all(&tm);
+
+    // Years with less than 4 digits, and before year 0
+    char buf[128];
+    tm.tm_year = 5 - 1900;
+    strftime(buf, sizeof(buf), "%Y|%C|%y|%G|%g|%F", &tm);
+    printf("year 5: %s\n", buf);
+    tm.tm_year = -5 - 1900;
+    strftime(buf, sizeof(buf), "%Y|%C|%y|%10Y|%_10Y|%F", &tm);
+    printf("year -5: %s\n", buf);
+
+    // No offset when it is unknown whether daylight saving time is in effect
+    tm.tm_isdst = -1;
+    size_t n = strftime(buf, sizeof(buf), "%z|%Z", &tm);
+    printf("isdst -1: %zu \"%s\"\n", n, buf);
+
+    // The output must fit with its terminating NUL.
+    n = strftime(buf, 8, "%F", &tm);
+    printf("too long: %zu\n", n);
+    n = strftime(buf, 3, "%y", &tm);
+    printf("fits: %zu \"%s\"\n", n, buf);
+    n = strftime(buf, sizeof(buf), "%2000000000Y", &tm);
+    printf("width too large: %zu\n", n);
+
+    // Locales
+    locale_t c = newlocale(LC_ALL_MASK, "C", (locale_t)0);
+    ERROR_IF(newlocale, c, == (locale_t)0);
+    n = strftime_l(buf, sizeof(buf), "%A %B %p %c", &tm, c);
+    printf("strftime_l: %zu \"%s\"\n", n, buf);
+    freelocale(c);
+
+    // Locale files may not be installed.
+    tm.tm_year = 2021 - 1900;
+    locale_t en_us = newlocale(LC_TIME_MASK, "en_US", (locale_t)0);
+    if (en_us != (locale_t)0) {
+        strftime_l(buf, sizeof(buf), "%A %B %x", &tm, en_us);
+        assert(strcmp(buf, "Friday January 01/01/2021") == 0);
+        freelocale(en_us);
+    }
+
+    // Wide characters
+    wchar_t wbuf[64];
+    n = wcsftime(wbuf, 64, L"%a %d %b %Y %H:%M", &tm);
+    printf("wcsftime: %zu \"%ls\"\n", n, wbuf);
+    n = wcsftime(wbuf, 5, L"%Y%m", &tm);
+    printf("wcsftime too long: %zu\n", n);
+}
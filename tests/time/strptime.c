@@ -22,10 +22,8 @@ static void strptime_test(const char* restrict time,
     // then this will need to be modified to memcmp up to
     // sizeof(struct tm) - sizeof(char*) followed by a strcmp.
     //
-    // glibc and musl differ a bit which is why some calls to
-    // this function comment out tm_yday and tm_wday.
-    // glibc seems to set these fields even if they're not specified,
-    // which is a cool extra but implementation specific behavior.
+    // tm_wday and tm_yday are computed when the date is known, as
+    // glibc does, which musl does not.
     if(memcmp(&expected, &actual, sizeof(struct tm))) {
         puts("struct tm expected versus actual\n");
         printf("%-8s %d %4d\n", "tm_year", expected.tm_year, actual.tm_year);
@@ -62,8 +60,8 @@ int main(void) {
         .tm_year = 81,
         .tm_mon = 10,
         .tm_mday = 18,
-        /* .tm_wday = 3, */
-        /* .tm_yday = 321, */
+        .tm_wday = 3,
+        .tm_yday = 321,
     };
     const char* daydream_rem = &daydream[10];
     strptime_test(daydream,
@@ -79,7 +77,7 @@ int main(void) {
         .tm_year = 76,
         .tm_mon = 6,
         .tm_mday = 4,
-        /* .tm_yday = 185, */
+        .tm_yday = 185,
     };
     const char* america_rem = &america[9];
     strptime_test(america,
@@ -105,7 +103,7 @@ int main(void) {
         .tm_hour = 11,
         .tm_min = 19,
         .tm_sec = 57,
-        /* .tm_yday = 304, */
+        .tm_yday = 304,
         .tm_wday = 1,
     };
     strptime_test(redox,
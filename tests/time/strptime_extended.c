@@ -0,0 +1,88 @@
+#define _GNU_SOURCE
+#include <locale.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <time.h>
+
+#include "test_helpers.h"
+
+static void show(const char *input, const char *format, const struct tm *tm, const char *rest) {
+    if (rest == NULL) {
+        printf("\"%s\" \"%s\": NULL\n", input, format);
+        return;
+    }
+    printf("\"%s\" \"%s\": %d-%02d-%02d %02d:%02d:%02d wday %d yday %d gmtoff %ld rest \"%s\"\n",
+           input, format, tm->tm_year + 1900, tm->tm_mon + 1, tm->tm_mday, tm->tm_hour, tm->tm_min,
+           tm->tm_sec, tm->tm_wday, tm->tm_yday, tm->tm_gmtoff, rest);
+}
+
+static void parse(const char *input, const char *format) {
+    struct tm tm = {0};
+    show(input, format, &tm, strptime(input, format, &tm));
+}
+
+int main(void) {
+    setenv("TZ", "UTC", 1);
+    tzset();
+
+    // RFC 2822 and ISO 8601 timestamps
+    parse("Tue, 15 Jun 2021 14:30:00 +0200", "%a, %d %b %Y %H:%M:%S %z");
+    parse("2021-06-15T14:30:00Z", "%Y-%m-%dT%H:%M:%S%z");
+    parse("2021-06-15T14:30:00-05:30", "%FT%T%z");
+    parse("20210615 143000", "%Y%m%d %H%M%S");
+
+    // Offsets from UTC
+    parse("+01", "%z");
+    parse("-0945", "%z");
+    parse("+12345", "%z");
+    parse("+0160", "%z");
+    parse("+123", "%z");
+
+    // Seconds since the Epoch
+    parse("1623767400", "%s");
+    parse("-86400", "%s");
+
+    // Week numbers with a weekday, and days of the year
+    parse("2021 00 Fri", "%Y %U %a");
+    parse("2021 01 Mon", "%Y %W %a");
+    parse("2021 23 2", "%Y %U %w");
+    parse("2021 24 2", "%Y %W %u");
+    parse("2021 166", "%Y %j");
+    parse("2020 366", "%Y %j");
+
+    // ISO 8601 week dates
+    parse("2020-W53-7", "%G-W%V-%u");
+    parse("2021-W01", "%G-W%V");
+    parse("21 24 2", "%g %V %u");
+    parse("2021-W00", "%G-W%V");
+
+    // Centuries and 12-hour clocks
+    parse("20 05", "%C %y");
+    parse("21", "%C");
+    parse("12:15 am", "%I:%M %p");
+    parse("02:30:05 PM", "%r");
+
+    // Modifiers, which the C locale has no alternatives for
+    parse("15/06/21", "%Od/%Om/%Ey");
+    parse("Tue Jun 15 14:30:00 2021", "%Ec");
+    parse("2021", "%EY");
+    parse("15", "%Ed");
+
+    // Time zone abbreviations
+    struct tm tm = {0};
+    tm.tm_isdst = 1;
+    const char *rest = strptime("12:00 UTC", "%H:%M %Z", &tm);
+    show("12:00 UTC", "%H:%M %Z", &tm, rest);
+    printf("tm_isdst %d, tm_zone %s\n", tm.tm_isdst, tm.tm_zone);
+    parse("12:00 XYZ", "%H:%M %Z");
+
+    // Names from the given locale
+    locale_t c_locale = newlocale(LC_ALL_MASK, "C", (locale_t)0);
+    ERROR_IF(newlocale, c_locale, == (locale_t)0);
+    memset(&tm, 0, sizeof(tm));
+    const char *input = "thursday, DECEMBER 24 2020";
+    rest = strptime_l(input, "%A, %B %d %Y", &tm, c_locale);
+    show(input, "%A, %B %d %Y", &tm, rest);
+    freelocale(c_locale);
+}
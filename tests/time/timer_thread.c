@@ -0,0 +1,159 @@
+#include <errno.h>
+#include <pthread.h>
+#include <semaphore.h>
+#include <signal.h>
+#include <stdatomic.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <time.h>
+#include <unistd.h>
+
+#include "test_helpers.h"
+
+#define STACK_SIZE (1024 * 1024)
+
+static pthread_t main_thread;
+static sem_t notified;
+static atomic_int calls;
+static atomic_int bad_calls;
+static timer_t self_deleting;
+static timer_t overrunning;
+static atomic_int overrun_calls;
+static atomic_int overrun = -1;
+
+static void notify(union sigval value) {
+    if (value.sival_int != 42 || pthread_equal(pthread_self(), main_thread)) {
+        atomic_fetch_add(&bad_calls, 1);
+    }
+
+    // The thread is created with the requested attributes.
+    pthread_attr_t attr;
+    size_t stacksize = 0;
+    if (pthread_getattr_np(pthread_self(), &attr) == 0) {
+        pthread_attr_getstacksize(&attr, &stacksize);
+        pthread_attr_destroy(&attr);
+    }
+    if (stacksize < STACK_SIZE) {
+        atomic_fetch_add(&bad_calls, 1);
+    }
+
+    atomic_fetch_add(&calls, 1);
+    sem_post(&notified);
+}
+
+static void delete_self(union sigval value) {
+    int status = timer_delete(self_deleting);
+    ERROR_IF(timer_delete, status, == -1);
+    atomic_fetch_add(&calls, 1);
+    sem_post((sem_t *)value.sival_ptr);
+}
+
+static void sleep_ms(long ms) {
+    struct timespec ts = {.tv_sec = 0, .tv_nsec = ms * 1000000};
+    while (nanosleep(&ts, &ts) == -1 && errno == EINTR) {
+    }
+}
+
+// Blocks the first notification for several intervals, and records the
+// overrun count seen by the next one.
+static void block_first(union sigval value) {
+    if (atomic_fetch_add(&overrun_calls, 1) == 0) {
+        sleep_ms(50);
+        return;
+    }
+    if (atomic_load(&overrun) == -1) {
+        atomic_store(&overrun, timer_getoverrun(overrunning));
+        sem_post((sem_t *)value.sival_ptr);
+    }
+}
+
+int main(void) {
+    main_thread = pthread_self();
+    int status = sem_init(&notified, 0, 0);
+    ERROR_IF(sem_init, status, == -1);
+
+    // A thread notification requires a function.
+    timer_t timerid;
+    struct sigevent sev = {0};
+    sev.sigev_notify = SIGEV_THREAD;
+    sev.sigev_value.sival_int = 42;
+    sev.sigev_notify_function = notify;
+
+    pthread_attr_t attr;
+    status = pthread_attr_init(&attr);
+    ERROR_IF(pthread_attr_init, status, != 0);
+    status = pthread_attr_setstacksize(&attr, STACK_SIZE);
+    ERROR_IF(pthread_attr_setstacksize, status, != 0);
+    sev.sigev_notify_attributes = &attr;
+
+    status = timer_create(CLOCK_MONOTONIC, &sev, &timerid);
+    ERROR_IF(timer_create, status, == -1);
+    pthread_attr_destroy(&attr);
+
+    // Not armed yet
+    sleep_ms(20);
+    UNEXP_IF(notify, atomic_load(&calls), != 0);
+
+    // Periodic notifications
+    struct itimerspec its = {0};
+    its.it_value.tv_nsec = 10 * 1000000;
+    its.it_interval.tv_nsec = 10 * 1000000;
+    status = timer_settime(timerid, 0, &its, NULL);
+    ERROR_IF(timer_settime, status, == -1);
+    for (int i = 0; i < 3; i++) {
+        status = sem_wait(&notified);
+        ERROR_IF(sem_wait, status, == -1);
+    }
+    UNEXP_IF(notify, atomic_load(&bad_calls), != 0);
+    printf("periodic: ok\n");
+
+    // No notification after the timer is deleted
+    status = timer_delete(timerid);
+    ERROR_IF(timer_delete, status, == -1);
+    sleep_ms(30);
+    int after_delete = atomic_load(&calls);
+    sleep_ms(50);
+    UNEXP_IF(timer_delete, atomic_load(&calls), != after_delete);
+    printf("timer_delete: ok\n");
+
+    // Expirations while a notification is blocked are counted as overruns.
+    sem_t overrun_seen;
+    status = sem_init(&overrun_seen, 0, 0);
+    ERROR_IF(sem_init, status, == -1);
+    sev.sigev_value.sival_ptr = &overrun_seen;
+    sev.sigev_notify_function = block_first;
+    sev.sigev_notify_attributes = NULL;
+    status = timer_create(CLOCK_MONOTONIC, &sev, &overrunning);
+    ERROR_IF(timer_create, status, == -1);
+    status = timer_settime(overrunning, 0, &its, NULL);
+    ERROR_IF(timer_settime, status, == -1);
+    status = sem_wait(&overrun_seen);
+    ERROR_IF(sem_wait, status, == -1);
+    status = timer_delete(overrunning);
+    ERROR_IF(timer_delete, status, == -1);
+    ERROR_IF(timer_getoverrun, atomic_load(&overrun), == -1);
+    UNEXP_IF(timer_getoverrun, atomic_load(&overrun), <= 0);
+    sem_destroy(&overrun_seen);
+    printf("timer_getoverrun: ok\n");
+
+    // A timer can be deleted from its own notification.
+    sem_t deleted;
+    status = sem_init(&deleted, 0, 0);
+    ERROR_IF(sem_init, status, == -1);
+    sev.sigev_value.sival_ptr = &deleted;
+    sev.sigev_notify_function = delete_self;
+    sev.sigev_notify_attributes = NULL;
+    status = timer_create(CLOCK_REALTIME, &sev, &self_deleting);
+    ERROR_IF(timer_create, status, == -1);
+    status = timer_settime(self_deleting, 0, &its, NULL);
+    ERROR_IF(timer_settime, status, == -1);
+    status = sem_wait(&deleted);
+    ERROR_IF(sem_wait, status, == -1);
+    after_delete = atomic_load(&calls);
+    sleep_ms(50);
+    UNEXP_IF(delete_self, atomic_load(&calls), != after_delete);
+    printf("delete from notification: ok\n");
+
+    sem_destroy(&deleted);
+    sem_destroy(&notified);
+}
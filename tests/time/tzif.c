@@ -0,0 +1,78 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include <time.h>
+
+#include "test_helpers.h"
+
+static void print_local(const char *zone, time_t time) {
+    int status = setenv("TZ", zone, 1);
+    ERROR_IF(setenv, status, == -1);
+    tzset();
+
+    struct tm tm;
+    struct tm *result = localtime_r(&time, &tm);
+    ERROR_IF(localtime_r, result, == NULL);
+
+    char buf[64];
+    strftime(buf, sizeof(buf), "%Y-%m-%d %H:%M:%S %Z %z", &tm);
+    printf("%s: %s, isdst %d, gmtoff %ld\n", zone, buf, tm.tm_isdst, tm.tm_gmtoff);
+
+    // Converting back gives the same time.
+    time_t back = mktime(&tm);
+    if (back != time) {
+        printf("FAILURE: mktime gave %lld instead of %lld\n", (long long)back, (long long)time);
+        exit(EXIT_FAILURE);
+    }
+}
+
+int main(void) {
+    // 2021-01-15 12:00:00 and 2021-07-15 12:00:00 UTC
+    time_t winter = 1610712000;
+    time_t summer = 1626350400;
+
+    const char *zones[] = {
+        // TZif files, by name and by absolute path
+        "America/New_York",
+        ":Europe/London",
+        "/usr/share/zoneinfo/Asia/Tokyo",
+        "Australia/Sydney",
+        // POSIX TZ strings, with and without rules
+        "EST5EDT,M3.2.0,M11.1.0",
+        "CET-1CEST,M3.5.0,M10.5.0/3",
+        "<+0330>-3:30",
+        "NZST-12NZDT,M9.5.0,M4.1.0/3",
+        "AAA3BBB,J60/1,J300",
+        "UTC0",
+    };
+    for (size_t i = 0; i < sizeof(zones) / sizeof(zones[0]); i++) {
+        print_local(zones[i], winter);
+        print_local(zones[i], summer);
+    }
+
+    // Names escaping the zoneinfo directory are not loaded.
+    setenv("TZ", "../zoneinfo/Asia/Tokyo", 1);
+    tzset();
+    struct tm escaped;
+    struct tm *result = localtime_r(&winter, &escaped);
+    ERROR_IF(localtime_r, result, == NULL);
+    if (escaped.tm_gmtoff == 32400) {
+        puts("FAILURE: loaded a time zone outside of the zoneinfo directory");
+        exit(EXIT_FAILURE);
+    }
+
+    // 2021-03-14 02:30 does not exist in New York, and 2021-11-07 01:30
+    // happens twice.
+    setenv("TZ", "America/New_York", 1);
+    tzset();
+    struct tm gap = { .tm_year = 121, .tm_mon = 2, .tm_mday = 14, .tm_hour = 2, .tm_min = 30, .tm_isdst = -1 };
+    time_t t = mktime(&gap);
+    printf("gap: %lld, %02d:%02d, isdst %d\n", (long long)t, gap.tm_hour, gap.tm_min, gap.tm_isdst);
+
+    struct tm dst = { .tm_year = 121, .tm_mon = 10, .tm_mday = 7, .tm_hour = 1, .tm_min = 30, .tm_isdst = 1 };
+    t = mktime(&dst);
+    printf("repeated as DST: %lld, isdst %d\n", (long long)t, dst.tm_isdst);
+
+    struct tm std = { .tm_year = 121, .tm_mon = 10, .tm_mday = 7, .tm_hour = 1, .tm_min = 30, .tm_isdst = 0 };
+    t = mktime(&std);
+    printf("repeated as standard time: %lld, isdst %d\n", (long long)t, std.tm_isdst);
+}